@@ -0,0 +1,153 @@
+#ifndef TORRENTINFO_H
+#define TORRENTINFO_H
+
+/* Generated with cbindgen from src/ffi.rs, do not edit. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// An iterator over the files of a torrent.
+typedef struct TiFileIter TiFileIter;
+
+// A parsed torrent.
+typedef struct TiTorrent TiTorrent;
+
+// An iterator over the tracker urls of a torrent.
+typedef struct TiTrackerIter TiTrackerIter;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+// Parses the torrent in the `len` bytes at `buf`, returning NULL on errors.
+//
+// # Safety
+//
+// `buf` must point to `len` readable bytes.
+struct TiTorrent *ti_parse(const uint8_t *buf, size_t len);
+
+// Reads and parses the torrent file at the UTF-8 `path`, returning NULL on errors.
+//
+// # Safety
+//
+// `path` must be a NUL-terminated string.
+struct TiTorrent *ti_parse_file(const char *path);
+
+// Returns the message of the last error of the calling thread, or NULL if the last call
+// succeeded.
+//
+// The message is valid until the next parse call on the thread.
+const char *ti_last_error(void);
+
+// Frees a torrent, NULL is ignored.
+//
+// # Safety
+//
+// `torrent` must be returned by `ti_parse` or `ti_parse_file` and not be freed before.
+void ti_free(struct TiTorrent *torrent);
+
+// Returns the hex info hash, or NULL if the info dict can't be hashed.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+const char *ti_infohash_hex(const struct TiTorrent *torrent);
+
+// Returns the name, or NULL if the torrent has none.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+const char *ti_name(const struct TiTorrent *torrent);
+
+// Returns the total size of the files in bytes.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+uint64_t ti_total_size(const struct TiTorrent *torrent);
+
+// Returns the piece length in bytes.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+uint64_t ti_piece_length(const struct TiTorrent *torrent);
+
+// Returns the number of pieces.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+size_t ti_piece_count(const struct TiTorrent *torrent);
+
+// Returns 1 for private torrents, 0 otherwise.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+int ti_is_private(const struct TiTorrent *torrent);
+
+// Returns the number of files, leaving out pad files.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+size_t ti_file_count(const struct TiTorrent *torrent);
+
+// Returns an iterator over the files, to be freed with `ti_file_iter_free` before the
+// torrent.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+struct TiFileIter *ti_file_iter(const struct TiTorrent *torrent);
+
+// Stores the `/`-separated path, which starts with the torrent name for multi-file
+// torrents, and the length of the next file in the out-params, which may be NULL.
+//
+// Returns 1 if there was a next file and 0 at the end.
+//
+// # Safety
+//
+// `iter` must be a valid iterator of a valid torrent.
+int ti_file_iter_next(struct TiFileIter *iter, const char **path, uint64_t *length);
+
+// Frees a file iterator, NULL is ignored.
+//
+// # Safety
+//
+// `iter` must be returned by `ti_file_iter` and not be freed before.
+void ti_file_iter_free(struct TiFileIter *iter);
+
+// Returns an iterator over the tracker urls of all tiers, to be freed with
+// `ti_tracker_iter_free` before the torrent.
+//
+// # Safety
+//
+// `torrent` must be a valid torrent.
+struct TiTrackerIter *ti_tracker_iter(const struct TiTorrent *torrent);
+
+// Stores the next tracker url in `url`, returning 1 if there was a next url and 0 at
+// the end.
+//
+// # Safety
+//
+// `iter` must be a valid iterator of a valid torrent and `url` must be writable.
+int ti_tracker_iter_next(struct TiTrackerIter *iter, const char **url);
+
+// Frees a tracker iterator, NULL is ignored.
+//
+// # Safety
+//
+// `iter` must be returned by `ti_tracker_iter` and not be freed before.
+void ti_tracker_iter_free(struct TiTrackerIter *iter);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* TORRENTINFO_H */
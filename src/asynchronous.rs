@@ -0,0 +1,240 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Futures to use the crate from tokio runtimes (`async` feature).
+//!
+//! Blocking work like verification and the tracker and DHT clients runs on the blocking
+//! thread pool of tokio.
+
+#[cfg(feature = "net")]
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::mem;
+#[cfg(feature = "fs")]
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::task::{self, JoinHandle};
+
+#[cfg(feature = "net")]
+use dht::{self, Lookup, LookupOptions};
+use error::{Error, Result};
+#[cfg(feature = "net")]
+use tracker::{self, AnnounceRequest, AnnounceResponse, ScrapeResult};
+#[cfg(feature = "net")]
+use InfoHash;
+use {decompress, Torrent, MAX_TORRENT_SIZE};
+#[cfg(feature = "fs")]
+use {verify_with, Verification, VerifyOptions};
+
+/// A future reading and parsing a torrent, see `Torrent::from_async_reader`.
+#[derive(Debug)]
+pub struct ReadTorrent<R> {
+    reader: R,
+    buf: Vec<u8>,
+}
+
+impl<R: AsyncRead + Unpin> ReadTorrent<R> {
+    pub(crate) fn new(reader: R) -> Self {
+        ReadTorrent {
+            reader,
+            buf: vec![],
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> Future for ReadTorrent<R> {
+    type Output = Result<Torrent>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<Torrent>> {
+        let this = &mut *self;
+        let mut chunk = [0u8; 8192];
+        loop {
+            let mut chunk = ReadBuf::new(&mut chunk);
+            match Pin::new(&mut this.reader).poll_read(cx, &mut chunk) {
+                Poll::Pending => return Poll::Pending,
+                Poll::Ready(Err(e)) => return Poll::Ready(Err(e.into())),
+                Poll::Ready(Ok(())) if chunk.filled().is_empty() => break,
+                Poll::Ready(Ok(())) => {
+                    this.buf.extend_from_slice(chunk.filled());
+                    if this.buf.len() as u64 > MAX_TORRENT_SIZE {
+                        return Poll::Ready(Err(Error::TooLarge(MAX_TORRENT_SIZE)));
+                    }
+                }
+            }
+        }
+
+        Poll::Ready(
+            decompress(mem::take(&mut this.buf), MAX_TORRENT_SIZE)
+                .and_then(|buf| Torrent::from_buf(&buf)),
+        )
+    }
+}
+
+type Work<T> = Box<dyn FnOnce() -> Result<T> + Send>;
+
+/// A future of blocking work, which runs on the blocking thread pool of tokio once the
+/// future is first polled.
+///
+/// The future must be polled from within a tokio runtime.
+pub struct Blocking<T> {
+    work: Option<Work<T>>,
+    handle: Option<JoinHandle<Result<T>>>,
+}
+
+impl<T: Send + 'static> Blocking<T> {
+    pub(crate) fn spawn<F: FnOnce() -> Result<T> + Send + 'static>(f: F) -> Self {
+        Blocking {
+            work: Some(Box::new(f)),
+            handle: None,
+        }
+    }
+}
+
+impl<T> fmt::Debug for Blocking<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Blocking")
+            .field("started", &self.handle.is_some())
+            .finish()
+    }
+}
+
+impl<T: Send + 'static> Future for Blocking<T> {
+    type Output = Result<T>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<T>> {
+        if let Some(work) = self.work.take() {
+            self.handle = Some(task::spawn_blocking(work));
+        }
+        let handle = self
+            .handle
+            .as_mut()
+            .expect("Blocking polled after completion");
+        match Pin::new(handle).poll(cx) {
+            Poll::Pending => Poll::Pending,
+            Poll::Ready(Ok(result)) => Poll::Ready(result),
+            Poll::Ready(Err(e)) => Poll::Ready(Err(Error::Io(io::Error::other(e)))),
+        }
+    }
+}
+
+/// Checks the data of a torrent like `verify_with` on the blocking thread pool (`fs`
+/// feature).
+#[cfg(feature = "fs")]
+pub fn verify_async<P: Into<PathBuf>>(
+    torrent: Torrent,
+    root: P,
+    options: VerifyOptions,
+) -> Blocking<Verification> {
+    let root = root.into();
+    Blocking::spawn(move || verify_with(&torrent, root, &options))
+}
+
+/// Scrapes a tracker like `tracker::scrape` on the blocking thread pool (`net` feature).
+#[cfg(feature = "net")]
+pub fn scrape_async(
+    announce: &str,
+    info_hashes: &[InfoHash],
+) -> Blocking<HashMap<InfoHash, ScrapeResult>> {
+    let announce = announce.to_owned();
+    let info_hashes = info_hashes.to_vec();
+    Blocking::spawn(move || tracker::scrape(&announce, &info_hashes))
+}
+
+/// Announces to a tracker like `tracker::announce` on the blocking thread pool (`net`
+/// feature).
+#[cfg(feature = "net")]
+pub fn announce_async(announce: &str, request: AnnounceRequest) -> Blocking<AnnounceResponse> {
+    let announce = announce.to_owned();
+    Blocking::spawn(move || tracker::announce(&announce, &request))
+}
+
+/// Looks up the peers of a torrent like `dht::get_peers` on the blocking thread pool
+/// (`net` feature).
+#[cfg(feature = "net")]
+pub fn get_peers_async(info_hash: InfoHash, options: LookupOptions) -> Blocking<Lookup> {
+    Blocking::spawn(move || dht::get_peers(&info_hash, &options))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use std::fs;
+    use tokio::runtime;
+
+    #[test]
+    pub fn test_async() {
+        let runtime = runtime::Builder::new_current_thread().build().unwrap();
+        let buf = &b"d4:infod6:lengthi3e4:name1:a12:piece lengthi16384e6:pieces20:\
+\xa9\x99\x3e\x36\x47\x06\x81\x6a\xba\x3e\x25\x71\x78\x50\xc2\x6c\x9c\xd0\xd8\x9dee"[..];
+
+        let torrent = runtime.block_on(Torrent::from_async_reader(buf)).unwrap();
+        assert_eq!(torrent, Torrent::from_buf(buf).unwrap());
+
+        let root = env::temp_dir().join(format!("torrentinfo-async-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(&root).unwrap();
+        fs::write(root.join("t.torrent"), buf).unwrap();
+        fs::write(root.join("a"), b"abc").unwrap();
+
+        let torrent = runtime
+            .block_on(Torrent::from_file_async(root.join("t.torrent")))
+            .unwrap();
+        let verification = runtime
+            .block_on(verify_async(torrent, &root, VerifyOptions::default()))
+            .unwrap();
+        assert!(verification.is_complete());
+
+        match runtime.block_on(Torrent::from_file_async(root.join("missing"))) {
+            Err(Error::ReadTorrent { .. }) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[cfg(feature = "net")]
+    #[test]
+    pub fn test_net_async() {
+        let runtime = runtime::Builder::new_current_thread().build().unwrap();
+        let info_hash = InfoHash::from_bytes(&[1; 20]).unwrap();
+
+        match runtime.block_on(scrape_async("wss://tracker.example/announce", &[info_hash])) {
+            Err(Error::Unsupported(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let request = AnnounceRequest::new(info_hash, 6881);
+        match runtime.block_on(announce_async("wss://tracker.example/announce", request)) {
+            Err(Error::Unsupported(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let options = LookupOptions {
+            bootstrap: vec![],
+            ..LookupOptions::default()
+        };
+        match runtime.block_on(get_peers_async(info_hash, options)) {
+            Err(Error::Dht(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
@@ -0,0 +1,263 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Minimal bencode scanner working on raw byte spans.
+
+use std::collections::HashSet;
+use std::convert::TryFrom;
+use std::str;
+
+/// Returns the raw bytes of the value stored under `key` in the bencoded dict `buf`.
+pub fn dict_value<'a>(buf: &'a [u8], key: &[u8]) -> Option<&'a [u8]> {
+    if buf.first() != Some(&b'd') {
+        return None;
+    }
+    let mut pos = 1;
+    while *buf.get(pos)? != b'e' {
+        let (k, key_end) = string_at(buf, pos)?;
+        let value_end = value_end(buf, key_end)?;
+        if k == key {
+            return Some(&buf[key_end..value_end]);
+        }
+        pos = value_end;
+    }
+
+    None
+}
+
+/// Returns the content of the byte string `value`.
+pub fn as_bytes(value: &[u8]) -> Option<&[u8]> {
+    match string_at(value, 0) {
+        Some((bytes, end)) if end == value.len() => Some(bytes),
+        _ => None,
+    }
+}
+
+/// Returns the content of the UTF-8 string `value`.
+pub fn as_str(value: &[u8]) -> Option<&str> {
+    as_bytes(value).and_then(|bytes| str::from_utf8(bytes).ok())
+}
+
+/// Returns the integer `value`.
+pub fn as_int(value: &[u8]) -> Option<i64> {
+    if value.first() != Some(&b'i') || value.last() != Some(&b'e') || value.len() < 3 {
+        return None;
+    }
+    str::from_utf8(&value[1..value.len() - 1])
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Returns the non-negative integer `value`.
+pub fn as_uint(value: &[u8]) -> Option<u64> {
+    as_int(value).and_then(|i| u64::try_from(i).ok())
+}
+
+/// Interprets the content of a byte string as a flag, accepting integers and words like
+/// `true` or `yes` as written by some clients.
+pub fn string_flag(bytes: &[u8]) -> bool {
+    let s = str::from_utf8(bytes).unwrap_or_default().trim();
+    match s.parse::<i64>() {
+        Ok(n) => n != 0,
+        Err(_) => ["true", "yes", "y"]
+            .iter()
+            .any(|w| s.eq_ignore_ascii_case(w)),
+    }
+}
+
+/// Returns the integer or string `value` as a flag, with non-zero integers being `true`.
+pub fn as_flag(value: &[u8]) -> Option<bool> {
+    match as_int(value) {
+        Some(n) => Some(n != 0),
+        None => as_bytes(value).map(string_flag),
+    }
+}
+
+/// Iterates over the raw items of the bencoded list `value`, stopping at invalid data.
+pub fn list_items(value: &[u8]) -> ListItems<'_> {
+    ListItems {
+        buf: value,
+        pos: if value.first() == Some(&b'l') {
+            1
+        } else {
+            value.len()
+        },
+    }
+}
+
+pub struct ListItems<'a> {
+    buf: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Iterator for ListItems<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<&'a [u8]> {
+        let end = match self.buf.get(self.pos) {
+            Some(b'e') | None => None,
+            Some(_) => value_end(self.buf, self.pos),
+        };
+        match end {
+            Some(end) => {
+                let item = &self.buf[self.pos..end];
+                self.pos = end;
+                Some(item)
+            }
+            None => {
+                self.pos = self.buf.len();
+                None
+            }
+        }
+    }
+}
+
+/// Returns the offset of `part`, a subslice of `buf`, within `buf`.
+pub fn offset_of(buf: &[u8], part: &[u8]) -> usize {
+    part.as_ptr() as usize - buf.as_ptr() as usize
+}
+
+/// Parses the byte string starting at `pos`, returning its content and end offset.
+pub fn string_at(buf: &[u8], pos: usize) -> Option<(&[u8], usize)> {
+    let colon = pos + buf.get(pos..)?.iter().position(|&b| b == b':')?;
+    let len: usize = str::from_utf8(&buf[pos..colon]).ok()?.parse().ok()?;
+    let end = colon.checked_add(1)?.checked_add(len)?;
+    if end > buf.len() {
+        return None;
+    }
+
+    Some((&buf[colon + 1..end], end))
+}
+
+/// Returns the end offset of the value starting at `pos`.
+pub fn value_end(buf: &[u8], mut pos: usize) -> Option<usize> {
+    // Iterative, so deeply nested input can't exhaust the stack
+    let mut depth = 0usize;
+    loop {
+        match *buf.get(pos)? {
+            b'i' => pos += buf[pos..].iter().position(|&b| b == b'e')? + 1,
+            b'0'..=b'9' => pos = string_at(buf, pos)?.1,
+            b'l' | b'd' => {
+                depth += 1;
+                pos += 1;
+                continue;
+            }
+            b'e' if depth > 0 => {
+                depth -= 1;
+                pos += 1;
+            }
+            _ => return None,
+        }
+        if depth == 0 {
+            return Some(pos);
+        }
+    }
+}
+
+/// Returns the dotted paths of keys which appear more than once in the same dict, with
+/// list items named by their index.
+pub fn duplicate_keys(buf: &[u8]) -> Vec<String> {
+    let mut duplicates = vec![];
+    collect_duplicates(buf, 0, "", &mut duplicates);
+    duplicates
+}
+
+fn collect_duplicates(
+    buf: &[u8],
+    pos: usize,
+    path: &str,
+    duplicates: &mut Vec<String>,
+) -> Option<usize> {
+    let child = |name: &str| {
+        if path.is_empty() {
+            name.to_owned()
+        } else {
+            format!("{}.{}", path, name)
+        }
+    };
+    match *buf.get(pos)? {
+        b'd' => {
+            let mut keys = HashSet::new();
+            let mut pos = pos + 1;
+            while *buf.get(pos)? != b'e' {
+                let (key, key_end) = string_at(buf, pos)?;
+                let key_path = child(&String::from_utf8_lossy(key));
+                pos = collect_duplicates(buf, key_end, &key_path, duplicates)?;
+                if !keys.insert(key) {
+                    duplicates.push(key_path);
+                }
+            }
+            Some(pos + 1)
+        }
+        b'l' => {
+            let mut pos = pos + 1;
+            let mut index = 0;
+            while *buf.get(pos)? != b'e' {
+                pos = collect_duplicates(buf, pos, &child(&index.to_string()), duplicates)?;
+                index += 1;
+            }
+            Some(pos + 1)
+        }
+        _ => value_end(buf, pos),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_dict_value() {
+        let buf = b"d1:ai1e4:infod1:xl1:yi-2eee1:z0:e";
+        assert_eq!(dict_value(buf, b"a"), Some(&b"i1e"[..]));
+        assert_eq!(dict_value(buf, b"info"), Some(&b"d1:xl1:yi-2eee"[..]));
+        assert_eq!(dict_value(buf, b"z"), Some(&b"0:"[..]));
+        assert_eq!(dict_value(buf, b"missing"), None);
+        assert_eq!(dict_value(b"d4:info5:abce", b"info"), None);
+        assert_eq!(dict_value(b"li1ee", b"info"), None);
+    }
+
+    #[test]
+    pub fn test_values() {
+        assert_eq!(as_int(b"i-12e"), Some(-12));
+        assert_eq!(as_int(b"ie"), None);
+        assert_eq!(as_str(b"3:abc"), Some("abc"));
+        assert_eq!(as_bytes(b"3:abcd"), None);
+        let items: Vec<&[u8]> = list_items(b"li1e2:abl1:xee").collect();
+        assert_eq!(items, vec![&b"i1e"[..], &b"2:ab"[..], &b"l1:xe"[..]]);
+        assert_eq!(list_items(b"i1e").count(), 0);
+        assert_eq!(as_flag(b"i1e"), Some(true));
+        assert_eq!(as_flag(b"i2e"), Some(true));
+        assert_eq!(as_flag(b"i0e"), Some(false));
+        assert_eq!(as_flag(b"1:1"), Some(true));
+        assert_eq!(as_flag(b"4:True"), Some(true));
+        assert_eq!(as_flag(b"1:0"), Some(false));
+        assert_eq!(as_flag(b"0:"), Some(false));
+        assert_eq!(as_flag(b"le"), None);
+    }
+
+    #[test]
+    pub fn test_duplicate_keys() {
+        assert!(duplicate_keys(b"d1:ai1e1:bi2ee").is_empty());
+        assert_eq!(
+            duplicate_keys(b"d1:ai1e1:ai2e1:bld1:xi1e1:xi2eeee"),
+            vec!["a", "b.0.x"]
+        );
+    }
+}
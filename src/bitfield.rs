@@ -0,0 +1,184 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Piece bitfields as used by the BitTorrent `bitfield` message and resume files.
+
+use std::iter::FromIterator;
+
+use error::{Error, Result};
+use to_hex;
+
+/// One bit per piece, with the high bit of the first byte for the first piece.
+///
+/// The spare bits of the last byte are always zero.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct Bitfield {
+    bytes: Vec<u8>,
+    len: usize,
+}
+
+impl Bitfield {
+    /// Creates a bitfield of `len` cleared bits.
+    pub fn new(len: usize) -> Self {
+        Bitfield {
+            bytes: vec![0; len.div_ceil(8)],
+            len,
+        }
+    }
+
+    /// Parses the bytes of a bitfield with `len` bits, rejecting wrong lengths and set
+    /// spare bits.
+    pub fn from_bytes(bytes: &[u8], len: usize) -> Result<Self> {
+        if bytes.len() != len.div_ceil(8) {
+            bail!(Error::InvalidBitfield(format!(
+                "expected {} bytes for {} pieces, got {}",
+                len.div_ceil(8),
+                len,
+                bytes.len()
+            )));
+        }
+        let bitfield = Bitfield {
+            bytes: bytes.to_vec(),
+            len,
+        };
+        if !len.is_multiple_of(8) && bytes[len / 8] << (len % 8) != 0 {
+            bail!(Error::InvalidBitfield("spare bits are set".to_owned()));
+        }
+
+        Ok(bitfield)
+    }
+
+    /// Parses the hex representation of a bitfield with `len` bits.
+    pub fn from_hex(hex: &str, len: usize) -> Result<Self> {
+        let invalid = || Error::InvalidBitfield(format!("invalid hex: '{}'", hex));
+        if !hex.len().is_multiple_of(2) {
+            bail!(invalid());
+        }
+        let bytes = hex
+            .as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                let hi = (pair[0] as char).to_digit(16)?;
+                let lo = (pair[1] as char).to_digit(16)?;
+                Some((hi << 4 | lo) as u8)
+            })
+            .collect::<Option<Vec<u8>>>()
+            .ok_or_else(invalid)?;
+
+        Bitfield::from_bytes(&bytes, len)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    /// Returns the lowercase hex representation.
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.bytes)
+    }
+
+    /// Returns the number of bits.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Returns bit `index`, or `false` if it's out of range.
+    pub fn get(&self, index: usize) -> bool {
+        index < self.len && self.bytes[index / 8] & (0x80 >> (index % 8)) != 0
+    }
+
+    /// Sets bit `index` to `value`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of range.
+    pub fn set(&mut self, index: usize, value: bool) {
+        assert!(index < self.len, "bit {} out of range", index);
+        if value {
+            self.bytes[index / 8] |= 0x80 >> (index % 8);
+        } else {
+            self.bytes[index / 8] &= !(0x80 >> (index % 8));
+        }
+    }
+
+    /// Returns the number of set bits.
+    pub fn count_ones(&self) -> usize {
+        self.bytes.iter().map(|b| b.count_ones() as usize).sum()
+    }
+
+    /// Returns whether all bits are set.
+    pub fn is_full(&self) -> bool {
+        self.count_ones() == self.len
+    }
+
+    /// Returns the bits in order.
+    pub fn iter(&self) -> impl Iterator<Item = bool> + '_ {
+        (0..self.len).map(move |i| self.get(i))
+    }
+}
+
+impl FromIterator<bool> for Bitfield {
+    fn from_iter<I: IntoIterator<Item = bool>>(iter: I) -> Self {
+        let mut bitfield = Bitfield::default();
+        for bit in iter {
+            if bitfield.len.is_multiple_of(8) {
+                bitfield.bytes.push(0);
+            }
+            bitfield.len += 1;
+            let index = bitfield.len - 1;
+            bitfield.set(index, bit);
+        }
+        bitfield
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_bitfield() {
+        let mut bitfield = Bitfield::new(10);
+        assert_eq!(bitfield.as_bytes(), &[0, 0]);
+        bitfield.set(0, true);
+        bitfield.set(9, true);
+        assert_eq!(bitfield.as_bytes(), &[0x80, 0x40]);
+        assert_eq!(bitfield.to_hex(), "8040");
+        assert!(bitfield.get(9) && !bitfield.get(8) && !bitfield.get(10));
+        assert_eq!(bitfield.count_ones(), 2);
+        bitfield.set(9, false);
+        assert_eq!(bitfield.count_ones(), 1);
+
+        let bits = [true, false, true, true, false, false, false, false, true];
+        let bitfield: Bitfield = bits.iter().cloned().collect();
+        assert_eq!(bitfield.as_bytes(), &[0xb0, 0x80]);
+        assert_eq!(bitfield.iter().collect::<Vec<_>>(), bits);
+        assert_eq!(Bitfield::from_hex("B080", 9).unwrap(), bitfield);
+        assert!(Bitfield::from_bytes(&[0xff], 8).unwrap().is_full());
+        assert!(Bitfield::from_bytes(&[], 0).unwrap().is_full());
+
+        assert!(Bitfield::from_bytes(&[0xb0, 0xc0], 9).is_err());
+        assert!(Bitfield::from_bytes(&[0xb0], 9).is_err());
+        assert!(Bitfield::from_hex("b08", 9).is_err());
+        assert!(Bitfield::from_hex("b0xx", 9).is_err());
+    }
+}
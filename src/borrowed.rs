@@ -0,0 +1,255 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Zero-copy views of torrents.
+
+use bencode;
+use error::{Error, Result};
+#[cfg(feature = "hash")]
+use hash;
+#[cfg(feature = "hash")]
+use InfoHash;
+use PieceHashes;
+
+/// A read-only view of a torrent, borrowing all strings from the parsed buffer.
+///
+/// Values are looked up on access, so creating a view doesn't allocate. This makes it
+/// suited for listing large torrent collections, `Torrent` is better for repeated access.
+///
+/// ```
+/// use torrentinfo::TorrentRef;
+///
+/// let buf = b"d8:announce9:http://a/4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee";
+/// let torrent = TorrentRef::new(buf).unwrap();
+/// assert_eq!(torrent.announce(), Some("http://a/"));
+/// assert_eq!(torrent.info().name(), Some("a"));
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct TorrentRef<'a> {
+    buf: &'a [u8],
+    info: InfoRef<'a>,
+}
+
+/// The info dict of a `TorrentRef`.
+#[derive(Debug, Clone, Copy)]
+pub struct InfoRef<'a> {
+    buf: &'a [u8],
+}
+
+/// A file of a multi-file `InfoRef`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRef<'a> {
+    length: u64,
+    path: Vec<&'a str>,
+}
+
+impl<'a> TorrentRef<'a> {
+    /// Creates a view of the torrent in `buf`, checking that it is a well-formed dict with
+    /// an info dict.
+    pub fn new(buf: &'a [u8]) -> Result<Self> {
+        if buf.first() != Some(&b'd') || bencode::value_end(buf, 0) != Some(buf.len()) {
+            bail!(Error::InvalidBencode(0));
+        }
+        let info = match bencode::dict_value(buf, b"info") {
+            Some(info) if info.first() == Some(&b'd') => info,
+            _ => bail!(Error::InvalidBencode(0)),
+        };
+
+        Ok(TorrentRef {
+            buf,
+            info: InfoRef { buf: info },
+        })
+    }
+
+    fn str(&self, key: &[u8]) -> Option<&'a str> {
+        bencode::dict_value(self.buf, key).and_then(bencode::as_str)
+    }
+
+    pub fn announce(&self) -> Option<&'a str> {
+        self.str(b"announce")
+    }
+
+    /// Returns the tracker tiers of the `announce-list` (BEP 12).
+    pub fn announce_list(&self) -> Vec<Vec<&'a str>> {
+        match bencode::dict_value(self.buf, b"announce-list") {
+            Some(list) => bencode::list_items(list)
+                .map(|tier| {
+                    bencode::list_items(tier)
+                        .filter_map(bencode::as_str)
+                        .collect()
+                })
+                .collect(),
+            None => vec![],
+        }
+    }
+
+    pub fn comment(&self) -> Option<&'a str> {
+        self.str(b"comment")
+    }
+
+    pub fn created_by(&self) -> Option<&'a str> {
+        self.str(b"created by")
+    }
+
+    pub fn creation_date(&self) -> Option<i64> {
+        bencode::dict_value(self.buf, b"creation date").and_then(bencode::as_int)
+    }
+
+    pub fn encoding(&self) -> Option<&'a str> {
+        self.str(b"encoding")
+    }
+
+    pub fn info(&self) -> InfoRef<'a> {
+        self.info
+    }
+
+    /// Calculates the SHA-1 hash of the info dict (`hash` feature).
+    #[cfg(feature = "hash")]
+    pub fn info_hash(&self) -> InfoHash {
+        InfoHash::from(hash::backend().sha1(self.info.buf))
+    }
+
+    /// Returns the raw bytes of the value under `key`.
+    pub fn raw(&self, key: &str) -> Option<&'a [u8]> {
+        bencode::dict_value(self.buf, key.as_bytes())
+    }
+}
+
+impl<'a> InfoRef<'a> {
+    pub fn name(&self) -> Option<&'a str> {
+        bencode::dict_value(self.buf, b"name").and_then(bencode::as_str)
+    }
+
+    /// Returns the `source` tag of private trackers.
+    pub fn source(&self) -> Option<&'a str> {
+        bencode::dict_value(self.buf, b"source").and_then(bencode::as_str)
+    }
+
+    pub fn piece_length(&self) -> Option<u64> {
+        bencode::dict_value(self.buf, b"piece length").and_then(bencode::as_uint)
+    }
+
+    /// Returns the SHA-1 piece hashes, which are empty for v2 torrents.
+    pub fn pieces(&self) -> PieceHashes<'a> {
+        PieceHashes::new(
+            bencode::dict_value(self.buf, b"pieces")
+                .and_then(bencode::as_bytes)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns whether the torrent is private (BEP 27), see `Info::private`.
+    pub fn private(&self) -> bool {
+        bencode::dict_value(self.buf, b"private")
+            .and_then(bencode::as_flag)
+            .unwrap_or(false)
+    }
+
+    /// Returns the length of a single-file torrent.
+    pub fn length(&self) -> Option<u64> {
+        bencode::dict_value(self.buf, b"length").and_then(bencode::as_uint)
+    }
+
+    /// Returns the files of a multi-file torrent, or `None` for single-file torrents.
+    pub fn files(&self) -> Option<Vec<FileRef<'a>>> {
+        let files = bencode::dict_value(self.buf, b"files")?;
+        Some(
+            bencode::list_items(files)
+                .map(|file| FileRef {
+                    length: bencode::dict_value(file, b"length")
+                        .and_then(bencode::as_uint)
+                        .unwrap_or_default(),
+                    path: bencode::dict_value(file, b"path")
+                        .map(|path| {
+                            bencode::list_items(path)
+                                .filter_map(bencode::as_str)
+                                .collect()
+                        })
+                        .unwrap_or_default(),
+                })
+                .collect(),
+        )
+    }
+
+    /// Returns the total size of the files, failing if it overflows.
+    pub fn total_size(&self) -> Result<u64> {
+        match self.files() {
+            Some(files) => files
+                .iter()
+                .try_fold(0u64, |sum, f| sum.checked_add(f.length))
+                .ok_or(Error::SizeOverflow),
+            None => Ok(self.length().unwrap_or_default()),
+        }
+    }
+
+    /// Returns the raw bencoded info dict.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.buf
+    }
+}
+
+impl<'a> FileRef<'a> {
+    /// Returns the length, which is 0 if it is negative or missing.
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn path(&self) -> &[&'a str] {
+        &self.path
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "hash")]
+    use Torrent;
+
+    #[test]
+    pub fn test_torrent_ref() {
+        let buf = &b"d8:announce9:http://a/13:announce-listll9:http://a/el9:http://b/ee\
+13:creation datei7e4:infod5:filesld6:lengthi1e4:pathl1:aeed6:lengthi2e4:pathl1:b1:ceee\
+4:name1:d12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa7:privatei1e6:source3:TRKee"[..];
+        let torrent = TorrentRef::new(buf).unwrap();
+        assert_eq!(torrent.announce(), Some("http://a/"));
+        assert_eq!(
+            torrent.announce_list(),
+            vec![vec!["http://a/"], vec!["http://b/"]]
+        );
+        assert_eq!(torrent.creation_date(), Some(7));
+        assert_eq!(torrent.comment(), None);
+
+        let info = torrent.info();
+        assert_eq!(info.name(), Some("d"));
+        assert_eq!(info.piece_length(), Some(16384));
+        assert_eq!(info.pieces().len(), 1);
+        assert!(info.private());
+        assert_eq!(info.source(), Some("TRK"));
+        let files = info.files().unwrap();
+        assert_eq!(files[1].path(), &["b", "c"]);
+        assert_eq!(info.total_size().unwrap(), 3);
+        #[cfg(feature = "hash")]
+        assert_eq!(
+            torrent.info_hash(),
+            Torrent::from_buf(buf).unwrap().info_hash().unwrap()
+        );
+
+        assert!(TorrentRef::new(&buf[..buf.len() - 1]).is_err());
+        assert!(TorrentRef::new(b"d8:announce9:http://a/e").is_err());
+    }
+}
@@ -0,0 +1,1168 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+use std::collections::HashMap;
+use std::fs;
+use std::io::Read;
+use std::mem;
+use std::path::{Path, PathBuf};
+use std::sync::mpsc;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_bencode::value::Value;
+use serde_bytes::ByteBuf;
+
+use error::{Error, Result};
+use file_tree::{self, FileTreeEntry};
+use filter::Filter;
+use merkle;
+use progress::{NoProgress, Progress};
+use {File, FileMode, Info, Torrent, TorrentVersion};
+
+const MIN_PIECE_LENGTH: usize = 16 * 1024;
+const MAX_PIECE_LENGTH: usize = 64 * 1024 * 1024;
+const MAX_AUTO_PIECE_LENGTH: usize = 16 * 1024 * 1024;
+/// The piece count `PieceLength::Auto` aims to stay below
+const TARGET_PIECES: u64 = 1500;
+
+/// The piece length of a torrent to create.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PieceLength {
+    /// Select a piece length based on the total size of the content
+    #[default]
+    Auto,
+    /// Use the given piece length, which must be a power of two between 16 KiB and 64 MiB
+    Fixed(usize),
+}
+
+impl PieceLength {
+    /// Returns the smallest power of two piece length which keeps the piece count at or
+    /// below 1500, limited to the range of 16 KiB to 16 MiB.
+    pub fn auto(total_size: u64) -> usize {
+        let mut piece_length = MIN_PIECE_LENGTH;
+        while piece_length < MAX_AUTO_PIECE_LENGTH
+            && total_size.div_ceil(piece_length as u64) > TARGET_PIECES
+        {
+            piece_length *= 2;
+        }
+
+        piece_length
+    }
+
+    /// Resolves the piece length for content of `total_size` bytes.
+    pub fn resolve(self, total_size: u64) -> Result<usize> {
+        match self {
+            PieceLength::Auto => Ok(PieceLength::auto(total_size)),
+            PieceLength::Fixed(piece_length) => {
+                if !piece_length.is_power_of_two()
+                    || !(MIN_PIECE_LENGTH..=MAX_PIECE_LENGTH).contains(&piece_length)
+                {
+                    bail!(Error::InvalidPieceLength(piece_length));
+                }
+                Ok(piece_length)
+            }
+        }
+    }
+}
+
+impl From<usize> for PieceLength {
+    fn from(piece_length: usize) -> Self {
+        PieceLength::Fixed(piece_length)
+    }
+}
+
+/// How `TorrentBuilder` treats symlinks inside the content directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SymlinkPolicy {
+    /// Include the files and directories the links point to
+    #[default]
+    Follow,
+    /// Leave links out of the torrent
+    Skip,
+    /// Store links as symlinks (BEP 47), following links whose targets lie outside the
+    /// content directory. Only supported for v1 torrents.
+    Store,
+}
+
+/// Creates a torrent from a file or directory.
+///
+/// ```no_run
+/// use torrentinfo::TorrentBuilder;
+///
+/// let torrent = TorrentBuilder::new("/path/to/content")
+///     .announce("http://tracker.example/announce")
+///     .private(true)
+///     .build()
+///     .unwrap();
+/// ```
+#[derive(Debug, Clone)]
+pub struct TorrentBuilder {
+    path: PathBuf,
+    name: Option<String>,
+    announce: Option<String>,
+    announce_list: Option<Vec<Vec<String>>>,
+    comment: Option<String>,
+    created_by: Option<String>,
+    creation_date: Option<i64>,
+    piece_length: PieceLength,
+    private: bool,
+    source: Option<String>,
+    threads: usize,
+    version: TorrentVersion,
+    pad_files: bool,
+    deterministic: bool,
+    include: Vec<String>,
+    exclude: Vec<String>,
+    hidden_files: bool,
+    junk_files: bool,
+    symlinks: SymlinkPolicy,
+}
+
+impl TorrentBuilder {
+    pub fn new<P: AsRef<Path>>(path: P) -> Self {
+        TorrentBuilder {
+            path: path.as_ref().to_path_buf(),
+            name: None,
+            announce: None,
+            announce_list: None,
+            comment: None,
+            created_by: None,
+            creation_date: None,
+            piece_length: PieceLength::Auto,
+            private: false,
+            source: None,
+            threads: thread::available_parallelism().map_or(1, |n| n.get()),
+            version: TorrentVersion::V1,
+            pad_files: false,
+            deterministic: false,
+            include: vec![],
+            exclude: vec![],
+            hidden_files: false,
+            junk_files: false,
+            symlinks: SymlinkPolicy::Follow,
+        }
+    }
+
+    /// Overrides the torrent name, which defaults to the file or directory name.
+    pub fn name(mut self, name: &str) -> Self {
+        self.name = Some(name.to_owned());
+        self
+    }
+
+    pub fn announce(mut self, url: &str) -> Self {
+        self.announce = Some(url.to_owned());
+        self
+    }
+
+    /// Sets the tracker tiers (BEP 12).
+    pub fn announce_list(mut self, tiers: Vec<Vec<String>>) -> Self {
+        self.announce_list = Some(tiers);
+        self
+    }
+
+    pub fn comment(mut self, comment: &str) -> Self {
+        self.comment = Some(comment.to_owned());
+        self
+    }
+
+    /// Sets the `created by` field, which defaults to `torrentinfo/<version>`.
+    pub fn created_by(mut self, created_by: &str) -> Self {
+        self.created_by = Some(created_by.to_owned());
+        self
+    }
+
+    /// Sets the creation date as unix timestamp, which defaults to the current time.
+    pub fn creation_date(mut self, timestamp: i64) -> Self {
+        self.creation_date = Some(timestamp);
+        self
+    }
+
+    /// Sets the piece length, which defaults to `PieceLength::Auto`.
+    ///
+    /// Accepts a `PieceLength` or a fixed length in bytes.
+    pub fn piece_length<L: Into<PieceLength>>(mut self, piece_length: L) -> Self {
+        self.piece_length = piece_length.into();
+        self
+    }
+
+    pub fn private(mut self, private: bool) -> Self {
+        self.private = private;
+        self
+    }
+
+    /// Sets the `source` tag, which private trackers use to make the info hash unique.
+    pub fn source(mut self, source: &str) -> Self {
+        self.source = Some(source.to_owned());
+        self
+    }
+
+    /// Sets the number of hashing threads, which defaults to the number of CPUs.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Sets the protocol version of the torrent, which defaults to `TorrentVersion::V1`.
+    ///
+    /// Hybrid torrents get pad files (BEP 47) so every file starts at a piece boundary.
+    pub fn version(mut self, version: TorrentVersion) -> Self {
+        self.version = version;
+        self
+    }
+
+    /// Inserts pad files (BEP 47) so every file starts at a piece boundary.
+    ///
+    /// Hybrid torrents always get pad files.
+    pub fn pad_files(mut self, pad_files: bool) -> Self {
+        self.pad_files = pad_files;
+        self
+    }
+
+    /// Creates byte-identical torrents from identical content, regardless of the time and
+    /// platform.
+    ///
+    /// The creation date and `created by` are omitted unless they are set explicitly.
+    pub fn deterministic(mut self, deterministic: bool) -> Self {
+        self.deterministic = deterministic;
+        self
+    }
+
+    /// Only includes files matching the glob `pattern`, may be given multiple times.
+    ///
+    /// Patterns containing a `/` are matched against the path relative to the content
+    /// directory, other patterns against every path component.
+    pub fn include(mut self, pattern: &str) -> Self {
+        self.include.push(pattern.to_owned());
+        self
+    }
+
+    /// Excludes files matching the glob `pattern`, may be given multiple times.
+    ///
+    /// Excludes take precedence over includes.
+    pub fn exclude(mut self, pattern: &str) -> Self {
+        self.exclude.push(pattern.to_owned());
+        self
+    }
+
+    /// Includes hidden files and directories, whose names start with a dot.
+    pub fn hidden_files(mut self, hidden_files: bool) -> Self {
+        self.hidden_files = hidden_files;
+        self
+    }
+
+    /// Includes junk files like `Thumbs.db` and `.DS_Store`.
+    pub fn junk_files(mut self, junk_files: bool) -> Self {
+        self.junk_files = junk_files;
+        self
+    }
+
+    /// Sets how symlinks inside the content directory are treated, which defaults to
+    /// `SymlinkPolicy::Follow`.
+    ///
+    /// Directory links which would lead into a loop are never followed.
+    pub fn symlinks(mut self, symlinks: SymlinkPolicy) -> Self {
+        self.symlinks = symlinks;
+        self
+    }
+
+    /// Walks the content, hashes all pieces and creates the torrent.
+    pub fn build(self) -> Result<Torrent> {
+        self.build_with_progress(&mut NoProgress)
+    }
+
+    /// Like `build`, reporting the hashing progress to `progress`.
+    pub fn build_with_progress(self, progress: &mut dyn Progress) -> Result<Torrent> {
+        let metadata = fs::metadata(&self.path)?;
+        let name = match self.name {
+            Some(ref name) => name.clone(),
+            None => self
+                .path
+                .file_name()
+                .map(|n| n.to_string_lossy().into_owned())
+                .ok_or_else(|| Error::NoFiles(self.path.display().to_string()))?,
+        };
+
+        let mut symlinks: Vec<(Vec<String>, Vec<String>)> = vec![];
+        let mut files: Vec<(Vec<String>, PathBuf)> = if metadata.is_dir() {
+            let filter = Filter::new(
+                &self.include,
+                &self.exclude,
+                self.hidden_files,
+                self.junk_files,
+            )?;
+            let mut entries = vec![];
+            walk(&self.path, self.symlinks, &mut vec![], &mut entries)?;
+            let mut files = vec![];
+            for entry in entries {
+                match entry {
+                    Entry::File(p) => files.push((relative_path(&self.path, &p), p)),
+                    Entry::Symlink(p, target) => {
+                        symlinks.push((relative_path(&self.path, &p), target))
+                    }
+                }
+            }
+            symlinks.retain(|(path, _)| filter.matches(path));
+            files.retain(|(path, _)| filter.matches(path));
+            files
+        } else {
+            vec![(vec![name.clone()], self.path.clone())]
+        };
+        if !symlinks.is_empty() && self.version != TorrentVersion::V1 {
+            bail!(Error::Unsupported(
+                "storing symlinks in v2 torrents".to_owned()
+            ));
+        }
+        symlinks.sort();
+        if files.is_empty() {
+            bail!(Error::NoFiles(self.path.display().to_string()));
+        }
+        // Order by the torrent paths, which is the order of v2 file trees and doesn't
+        // depend on how the platform compares paths
+        files.sort();
+        let (file_paths, paths): (Vec<Vec<String>>, Vec<PathBuf>) = files.into_iter().unzip();
+
+        let mut sizes = Vec::with_capacity(paths.len());
+        for path in &paths {
+            sizes.push(fs::metadata(path)?.len());
+        }
+        let total_size = sizes.iter().sum();
+        let piece_length = self.piece_length.resolve(total_size)?;
+        let version = self.version;
+        let pad_files = self.pad_files || version == TorrentVersion::Hybrid;
+        let hasher: Box<PieceHasher> = match version {
+            TorrentVersion::V1 if pad_files => {
+                Box::new(move |data: &[u8], last| padded_sha1(data, piece_length, last).to_vec())
+            }
+            TorrentVersion::V1 => Box::new(|data: &[u8], _| merkle::sha1(data).to_vec()),
+            TorrentVersion::V2 => Box::new(|data: &[u8], _| merkle::blocks_root(data).to_vec()),
+            TorrentVersion::Hybrid => Box::new(move |data: &[u8], last| {
+                let mut hashes = merkle::blocks_root(data).to_vec();
+                hashes.extend_from_slice(&padded_sha1(data, piece_length, last));
+                hashes
+            }),
+        };
+        let aligned = pad_files || version != TorrentVersion::V1;
+
+        let num_pieces = if aligned {
+            sizes
+                .iter()
+                .map(|size| size.div_ceil(piece_length as u64))
+                .sum::<u64>()
+        } else {
+            total_size.div_ceil(piece_length as u64)
+        };
+        progress.start(total_size, num_pieces as usize);
+        let (hashes, lengths) = hash_pieces(
+            &paths,
+            piece_length,
+            aligned,
+            self.threads,
+            &*hasher,
+            progress,
+        )?;
+        progress.finish();
+
+        let mut info = Info {
+            name: Some(ByteBuf::from(name.into_bytes())),
+            piece_length: piece_length as u64,
+            private: if self.private {
+                Some(Value::Int(1))
+            } else {
+                None
+            },
+            source: self.source.clone(),
+            ..Default::default()
+        };
+        let mut piece_layers = None;
+
+        if version != TorrentVersion::V2 {
+            let offset = if version == TorrentVersion::Hybrid {
+                32
+            } else {
+                0
+            };
+            let pieces: Vec<u8> = hashes.iter().flat_map(|h| h[offset..].to_vec()).collect();
+            info.pieces = Some(ByteBuf::from(pieces));
+
+            if metadata.is_dir() {
+                let mut files = vec![];
+                let mut symlinks = symlinks.into_iter().peekable();
+                for (i, (path, &length)) in file_paths.iter().zip(&lengths).enumerate() {
+                    while let Some((link, target)) = symlinks.next_if(|(link, _)| link < path) {
+                        files.push(File::symlink(link, target));
+                    }
+                    files.push(File::new(length, path.clone()));
+                    let remainder = length % piece_length as u64;
+                    if pad_files && remainder > 0 && i + 1 < lengths.len() {
+                        files.push(File::padding(piece_length as u64 - remainder));
+                    }
+                }
+                files.extend(symlinks.map(|(link, target)| File::symlink(link, target)));
+                info.mode = Some(FileMode::Multi { files });
+                info.update_offsets();
+            } else {
+                info.mode = Some(FileMode::Single {
+                    length: lengths[0],
+                    md5sum: None,
+                });
+            }
+        }
+
+        if version != TorrentVersion::V1 {
+            let (entries, layers) = v2_files(&file_paths, &lengths, &hashes, piece_length);
+            info.meta_version = Some(2);
+            info.file_tree = Some(file_tree::to_value(&entries));
+            piece_layers = Some(Value::Dict(layers));
+        }
+
+        let (created_by, creation_date) = if self.deterministic {
+            (self.created_by, self.creation_date)
+        } else {
+            let now = SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .ok();
+            (
+                self.created_by
+                    .or_else(|| Some(format!("torrentinfo/{}", env!("CARGO_PKG_VERSION")))),
+                self.creation_date.or(now),
+            )
+        };
+
+        Ok(Torrent {
+            announce: self.announce,
+            announce_list: self.announce_list,
+            comment: self.comment.map(|c| ByteBuf::from(c.into_bytes())),
+            created_by,
+            creation_date,
+            info,
+            piece_layers,
+            ..Default::default()
+        })
+    }
+}
+
+/// Hashes a v1 piece which ends at a file boundary, padding it with zeros unless it is the
+/// last piece of the content.
+fn padded_sha1(data: &[u8], piece_length: usize, last: bool) -> [u8; 20] {
+    if data.len() < piece_length && !last {
+        let mut padded = data.to_vec();
+        padded.resize(piece_length, 0);
+        merkle::sha1(&padded)
+    } else {
+        merkle::sha1(data)
+    }
+}
+
+/// Groups the piece hashes by file, returning the file tree entries and the piece layers.
+///
+/// Each file starts at a piece boundary, so a file has `ceil(length / piece_length)` pieces.
+fn v2_files(
+    paths: &[Vec<String>],
+    lengths: &[u64],
+    hashes: &[Vec<u8>],
+    piece_length: usize,
+) -> (Vec<FileTreeEntry>, HashMap<Vec<u8>, Value>) {
+    let blocks_per_piece = piece_length / merkle::BLOCK_SIZE;
+    let mut entries = vec![];
+    let mut layers = HashMap::new();
+    let mut hashes = hashes.iter();
+
+    for (path, &length) in paths.iter().zip(lengths) {
+        let num_pieces = length.div_ceil(piece_length as u64) as usize;
+        let mut layer: Vec<merkle::Hash> = hashes
+            .by_ref()
+            .take(num_pieces)
+            .map(|h| {
+                let mut hash = [0u8; 32];
+                hash.copy_from_slice(&h[..32]);
+                hash
+            })
+            .collect();
+
+        let root = match num_pieces {
+            0 => None,
+            1 => Some(layer[0]),
+            _ => {
+                let last_blocks = (length - (num_pieces as u64 - 1) * piece_length as u64)
+                    .div_ceil(merkle::BLOCK_SIZE as u64) as usize;
+                let last = layer[num_pieces - 1];
+                layer[num_pieces - 1] = merkle::extend_root(last, last_blocks, blocks_per_piece);
+                let root = merkle::file_root(&layer, piece_length);
+                layers.insert(root.to_vec(), Value::Bytes(layer.concat()));
+                Some(root)
+            }
+        };
+        entries.push(FileTreeEntry::new(
+            path.clone(),
+            length,
+            root.map(|r| r.to_vec()),
+        ));
+    }
+
+    (entries, layers)
+}
+
+/// A file found by `walk`.
+enum Entry {
+    File(PathBuf),
+    /// A symlink to store, with its target relative to the content directory
+    Symlink(PathBuf, Vec<String>),
+}
+
+/// Collects all files below `dir`, sorted by path.
+///
+/// `ancestors` holds the canonical paths of the directories being walked, the first being
+/// the content directory.
+fn walk(
+    dir: &Path,
+    symlinks: SymlinkPolicy,
+    ancestors: &mut Vec<PathBuf>,
+    entries: &mut Vec<Entry>,
+) -> Result<()> {
+    let canonical = fs::canonicalize(dir)?;
+    if ancestors.contains(&canonical) {
+        // A link back to a directory being walked
+        return Ok(());
+    }
+    ancestors.push(canonical);
+
+    let mut paths = fs::read_dir(dir)?
+        .map(|entry| entry.map(|e| e.path()))
+        .collect::<::std::io::Result<Vec<PathBuf>>>()?;
+    paths.sort();
+
+    for path in paths {
+        if fs::symlink_metadata(&path)?.file_type().is_symlink() {
+            match symlinks {
+                SymlinkPolicy::Follow => {}
+                SymlinkPolicy::Skip => continue,
+                SymlinkPolicy::Store => {
+                    if let Some(target) = link_target(&ancestors[0], &path) {
+                        entries.push(Entry::Symlink(path, target));
+                        continue;
+                    }
+                }
+            }
+        }
+
+        if path.is_dir() {
+            walk(&path, symlinks, ancestors, entries)?;
+        } else {
+            entries.push(Entry::File(path));
+        }
+    }
+
+    ancestors.pop();
+    Ok(())
+}
+
+/// Returns the target of the link at `path` relative to `root`, or `None` if it lies
+/// outside of `root` or doesn't exist.
+fn link_target(root: &Path, path: &Path) -> Option<Vec<String>> {
+    let target = fs::canonicalize(path).ok()?;
+    target
+        .strip_prefix(root)
+        .ok()
+        .map(|target| relative_path(Path::new(""), target))
+}
+
+fn relative_path(root: &Path, path: &Path) -> Vec<String> {
+    path.strip_prefix(root)
+        .unwrap_or(path)
+        .components()
+        .map(|c| c.as_os_str().to_string_lossy().into_owned())
+        .collect()
+}
+
+/// Hashes a piece, given whether it is the last piece of the content.
+type PieceHasher = dyn Fn(&[u8], bool) -> Vec<u8> + Send + Sync;
+
+/// Hashes the concatenated content of `paths`, returning the piece hashes and the file lengths.
+///
+/// The pieces are read by one thread and hashed by `threads` workers, so reading overlaps
+/// with hashing. At most two pieces per worker are buffered. The progress is reported from
+/// the calling thread.
+fn hash_pieces(
+    paths: &[PathBuf],
+    piece_length: usize,
+    aligned: bool,
+    threads: usize,
+    hasher: &PieceHasher,
+    progress: &mut dyn Progress,
+) -> Result<(Vec<Vec<u8>>, Vec<u64>)> {
+    enum Event {
+        File(usize),
+        Piece(usize, usize, Vec<u8>),
+    }
+
+    let threads = threads.max(1);
+    let (piece_tx, piece_rx) = mpsc::sync_channel::<(usize, Vec<u8>, bool)>(threads * 2);
+    let piece_rx = Mutex::new(piece_rx);
+    let (event_tx, event_rx) = mpsc::channel();
+
+    thread::scope(|scope| {
+        for _ in 0..threads {
+            let piece_rx = &piece_rx;
+            let event_tx = event_tx.clone();
+            scope.spawn(move || loop {
+                let piece = piece_rx.lock().unwrap().recv();
+                match piece {
+                    Ok((index, data, last)) => {
+                        let hash = hasher(&data, last);
+                        if event_tx
+                            .send(Event::Piece(index, data.len(), hash))
+                            .is_err()
+                        {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            });
+        }
+
+        let file_tx = event_tx;
+        let reader = scope.spawn(move || {
+            read_pieces(
+                paths,
+                piece_length,
+                aligned,
+                |index| {
+                    let _ = file_tx.send(Event::File(index));
+                },
+                |index, piece, last| {
+                    // The workers only hang up on panic, which surfaces when the scope ends
+                    let _ = piece_tx.send((index, piece, last));
+                },
+            )
+        });
+
+        let mut hashes = Vec::new();
+        let mut pieces_done = 0;
+        let mut bytes_done = 0;
+        for event in event_rx {
+            match event {
+                Event::File(index) => progress.file(&paths[index]),
+                Event::Piece(index, length, hash) => {
+                    if hashes.len() <= index {
+                        hashes.resize(index + 1, vec![]);
+                    }
+                    hashes[index] = hash;
+                    pieces_done += 1;
+                    bytes_done += length as u64;
+                    progress.piece(pieces_done, bytes_done);
+                }
+            }
+        }
+
+        let lengths = reader.join().expect("piece reading thread panicked")?;
+        Ok((hashes, lengths))
+    })
+}
+
+/// Reads the concatenated content of `paths` piece by piece, returning the file lengths.
+///
+/// With `aligned`, every file starts a new piece, so the last piece of a file may be short.
+///
+/// `on_file` is called with the index of each file before it is read.
+fn read_pieces<F, G>(
+    paths: &[PathBuf],
+    piece_length: usize,
+    aligned: bool,
+    mut on_file: G,
+    mut f: F,
+) -> Result<Vec<u64>>
+where
+    F: FnMut(usize, Vec<u8>, bool),
+    G: FnMut(usize),
+{
+    let mut lengths = Vec::with_capacity(paths.len());
+    let mut index = 0;
+    let mut buf = vec![0u8; piece_length];
+    let mut filled = 0;
+
+    for (i, path) in paths.iter().enumerate() {
+        on_file(i);
+        let mut file = fs::File::open(path)?;
+        let mut length = 0;
+        loop {
+            let n = file.read(&mut buf[filled..])?;
+            if n == 0 {
+                break;
+            }
+            length += n as u64;
+            filled += n;
+            if filled == piece_length {
+                f(
+                    index,
+                    mem::replace(&mut buf, vec![0u8; piece_length]),
+                    false,
+                );
+                index += 1;
+                filled = 0;
+            }
+        }
+        lengths.push(length);
+
+        if filled > 0 && (aligned || i + 1 == paths.len()) {
+            let mut piece = mem::replace(&mut buf, vec![0u8; piece_length]);
+            piece.truncate(filled);
+            f(index, piece, i + 1 == paths.len());
+            index += 1;
+            filled = 0;
+        }
+    }
+
+    Ok(lengths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_bencode::ser;
+    use std::env;
+    use std::io::Write;
+
+    pub fn temp_dir(name: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("torrentinfo-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    pub fn write_file(path: &Path, len: usize) -> Vec<u8> {
+        let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::File::create(path).unwrap().write_all(&data).unwrap();
+        data
+    }
+
+    #[test]
+    pub fn test_build_multi_file() {
+        let dir = temp_dir("build-multi");
+        let mut data = write_file(&dir.join("b").join("c.bin"), 40000);
+        data.extend(write_file(&dir.join("z.bin"), 30000));
+        data = [write_file(&dir.join("a.bin"), 100), data].concat();
+
+        let torrent = TorrentBuilder::new(&dir)
+            .announce("http://tracker/announce")
+            .comment("comment")
+            .creation_date(1234)
+            .piece_length(16384)
+            .private(true)
+            .source("TRK")
+            .build()
+            .unwrap();
+
+        let files = torrent.files().unwrap();
+        let paths: Vec<String> = files.iter().map(|f| f.path().join("/")).collect();
+        assert_eq!(paths, vec!["a.bin", "b/c.bin", "z.bin"]);
+        assert_eq!(torrent.total_size(), 70100);
+        assert_eq!(torrent.creation_date(), Some(1234));
+        assert!(torrent.info().private());
+        assert_eq!(torrent.info().source(), Some("TRK"));
+
+        let expected: Vec<u8> = data
+            .chunks(16384)
+            .flat_map(|chunk| merkle::sha1(chunk).to_vec())
+            .collect();
+        assert_eq!(torrent.info().pieces().as_bytes(), expected.as_slice());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_threads() {
+        let dir = temp_dir("build-threads");
+        for i in 0..5 {
+            write_file(&dir.join(format!("{}.bin", i)), 10000 + i * 3333);
+        }
+
+        let build = |threads| {
+            TorrentBuilder::new(&dir)
+                .creation_date(0)
+                .piece_length(16384)
+                .threads(threads)
+                .build()
+                .unwrap()
+        };
+        let single = build(1);
+        let multi = build(4);
+        assert_eq!(single.info().pieces().len(), 6);
+        assert_eq!(single.info().pieces(), multi.info().pieces());
+        assert_eq!(single.info_hash().unwrap(), multi.info_hash().unwrap());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_progress() {
+        #[derive(Default)]
+        struct Recorder {
+            start: Option<(u64, usize)>,
+            files: Vec<PathBuf>,
+            pieces: Vec<(usize, u64)>,
+            finished: bool,
+        }
+
+        impl Progress for Recorder {
+            fn start(&mut self, total_bytes: u64, total_pieces: usize) {
+                self.start = Some((total_bytes, total_pieces));
+            }
+
+            fn file(&mut self, path: &Path) {
+                self.files.push(path.to_path_buf());
+            }
+
+            fn piece(&mut self, pieces_done: usize, bytes_done: u64) {
+                self.pieces.push((pieces_done, bytes_done));
+            }
+
+            fn finish(&mut self) {
+                self.finished = true;
+            }
+        }
+
+        let dir = temp_dir("build-progress");
+        write_file(&dir.join("a.bin"), 20000);
+        write_file(&dir.join("b.bin"), 30000);
+
+        let mut recorder = Recorder::default();
+        TorrentBuilder::new(&dir)
+            .piece_length(16384)
+            .threads(2)
+            .build_with_progress(&mut recorder)
+            .unwrap();
+        assert_eq!(recorder.start, Some((50000, 4)));
+        assert_eq!(recorder.files, vec![dir.join("a.bin"), dir.join("b.bin")]);
+        assert_eq!(recorder.pieces.len(), 4);
+        assert_eq!(recorder.pieces.last(), Some(&(4, 50000)));
+        assert!(recorder.finished);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_v2() {
+        let dir = temp_dir("build-v2");
+        let small = write_file(&dir.join("a").join("small.bin"), 20000);
+        let large = write_file(&dir.join("large.bin"), 100_000);
+        write_file(&dir.join("empty.bin"), 0);
+
+        let torrent = TorrentBuilder::new(&dir)
+            .piece_length(32768)
+            .version(TorrentVersion::V2)
+            .build()
+            .unwrap();
+        assert_eq!(torrent.version(), TorrentVersion::V2);
+        assert!(torrent.info().pieces().is_empty());
+        assert!(torrent.files().is_none());
+        assert_eq!(torrent.total_size(), 120_000);
+        assert_eq!(torrent.num_files(), 3);
+
+        let files = torrent.info().file_tree();
+        let paths: Vec<String> = files.iter().map(|f| f.path().join("/")).collect();
+        assert_eq!(paths, vec!["a/small.bin", "empty.bin", "large.bin"]);
+        assert_eq!(
+            files[0].pieces_root(),
+            Some(&merkle::blocks_root(&small)[..])
+        );
+        assert_eq!(files[1].pieces_root(), None);
+        let root = merkle::blocks_root(&large);
+        assert_eq!(files[2].pieces_root(), Some(&root[..]));
+
+        let layers = torrent.piece_layers();
+        assert_eq!(layers.len(), 1);
+        assert_eq!(layers[&root[..]].len(), 4 * 32);
+
+        let bytes = ser::to_bytes(&torrent).unwrap();
+        let parsed = Torrent::from_buf(&bytes).unwrap();
+        assert_eq!(parsed.version(), TorrentVersion::V2);
+        assert_eq!(parsed.info().file_tree(), files);
+        assert!(parsed.info_hash_v2().unwrap().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_hybrid() {
+        let dir = temp_dir("build-hybrid");
+        let a = write_file(&dir.join("a.bin"), 20000);
+        let b = write_file(&dir.join("b.bin"), 40000);
+
+        let torrent = TorrentBuilder::new(&dir)
+            .piece_length(16384)
+            .version(TorrentVersion::Hybrid)
+            .threads(3)
+            .build()
+            .unwrap();
+        assert_eq!(torrent.version(), TorrentVersion::Hybrid);
+
+        let files = torrent.files().unwrap();
+        let paths: Vec<String> = files.iter().map(|f| f.path().join("/")).collect();
+        assert_eq!(paths, vec!["a.bin", ".pad/12768", "b.bin"]);
+
+        let mut data = a.clone();
+        data.resize(32768, 0);
+        data.extend_from_slice(&b);
+        let expected: Vec<u8> = data
+            .chunks(16384)
+            .flat_map(|chunk| merkle::sha1(chunk).to_vec())
+            .collect();
+        assert_eq!(torrent.info().pieces().as_bytes(), expected.as_slice());
+
+        let tree = torrent.info().file_tree();
+        assert_eq!(tree.len(), 2);
+        assert_eq!(tree[1].pieces_root(), Some(&merkle::blocks_root(&b)[..]));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_pad_files() {
+        let dir = temp_dir("build-pad-files");
+        let a = write_file(&dir.join("a.bin"), 20000);
+        let b = write_file(&dir.join("b.bin"), 16384);
+        let c = write_file(&dir.join("c.bin"), 100);
+
+        let torrent = TorrentBuilder::new(&dir)
+            .piece_length(16384)
+            .pad_files(true)
+            .build()
+            .unwrap();
+        assert_eq!(torrent.version(), TorrentVersion::V1);
+
+        let files = torrent.files().unwrap();
+        let paths: Vec<String> = files.iter().map(|f| f.path().join("/")).collect();
+        assert_eq!(paths, vec!["a.bin", ".pad/12768", "b.bin", "c.bin"]);
+        assert!(files[1].is_padding());
+        assert_eq!(files[2].offset(), 32768);
+        assert_eq!(torrent.total_size(), 49252);
+        assert_eq!(torrent.content_size(), 36484);
+        assert_eq!(torrent.content_files().len(), 3);
+
+        let mut data = a.clone();
+        data.resize(32768, 0);
+        data.extend_from_slice(&b);
+        data.extend_from_slice(&c);
+        let expected: Vec<u8> = data
+            .chunks(16384)
+            .flat_map(|chunk| merkle::sha1(chunk).to_vec())
+            .collect();
+        assert_eq!(torrent.info().pieces().as_bytes(), expected.as_slice());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_deterministic() {
+        let dir = temp_dir("build-deterministic");
+        write_file(&dir.join("B.bin"), 100);
+        write_file(&dir.join("a").join("c.bin"), 200);
+        write_file(&dir.join("a.bin"), 300);
+
+        let build = |threads| {
+            let torrent = TorrentBuilder::new(&dir)
+                .deterministic(true)
+                .threads(threads)
+                .build()
+                .unwrap();
+            ser::to_bytes(&torrent).unwrap()
+        };
+        let bytes = build(1);
+        assert_eq!(bytes, build(4));
+
+        let torrent = Torrent::from_buf(&bytes).unwrap();
+        assert_eq!(torrent.creation_date(), None);
+        assert_eq!(torrent.created_by(), None);
+        let files = torrent.files().unwrap();
+        let paths: Vec<String> = files.iter().map(|f| f.path().join("/")).collect();
+        assert_eq!(paths, vec!["B.bin", "a/c.bin", "a.bin"]);
+
+        let pinned = TorrentBuilder::new(&dir)
+            .deterministic(true)
+            .creation_date(1234)
+            .build()
+            .unwrap();
+        assert_eq!(pinned.creation_date(), Some(1234));
+
+        let default = TorrentBuilder::new(&dir).build().unwrap();
+        assert!(default.creation_date().is_some());
+        assert!(default.created_by().is_some());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_filter() {
+        let dir = temp_dir("build-filter");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        write_file(&dir.join("a.bin"), 100);
+        write_file(&dir.join("a.tmp"), 100);
+        write_file(&dir.join(".hidden"), 100);
+        write_file(&dir.join("sub/Thumbs.db"), 100);
+        write_file(&dir.join("sub/b.bin"), 100);
+
+        let paths = |builder: TorrentBuilder| -> Vec<Vec<String>> {
+            let torrent = builder.build().unwrap();
+            torrent
+                .files()
+                .unwrap()
+                .iter()
+                .map(|f| f.path().iter().map(|c| c.to_string()).collect())
+                .collect()
+        };
+        assert_eq!(
+            paths(TorrentBuilder::new(&dir).exclude("*.tmp")),
+            vec![
+                vec!["a.bin".to_owned()],
+                vec!["sub".to_owned(), "b.bin".to_owned()]
+            ]
+        );
+        assert_eq!(
+            paths(TorrentBuilder::new(&dir).include("sub/*")),
+            vec![vec!["sub".to_owned(), "b.bin".to_owned()]]
+        );
+        assert_eq!(
+            paths(
+                TorrentBuilder::new(&dir)
+                    .include("*.bin")
+                    .include(".*")
+                    .hidden_files(true)
+            ),
+            vec![
+                vec![".hidden".to_owned()],
+                vec!["a.bin".to_owned()],
+                vec!["sub".to_owned(), "b.bin".to_owned()]
+            ]
+        );
+        assert!(TorrentBuilder::new(&dir).exclude("[").build().is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    pub fn test_build_symlinks() {
+        use std::os::unix::fs::symlink;
+
+        let dir = temp_dir("build-symlinks");
+        fs::create_dir_all(dir.join("sub")).unwrap();
+        write_file(&dir.join("a.bin"), 100);
+        write_file(&dir.join("sub/b.bin"), 100);
+        symlink("a.bin", dir.join("c.bin")).unwrap();
+        symlink("../sub", dir.join("sub/loop")).unwrap();
+
+        let build = |symlinks| {
+            TorrentBuilder::new(&dir)
+                .symlinks(symlinks)
+                .build()
+                .unwrap()
+        };
+        let paths = |torrent: &Torrent| -> Vec<String> {
+            torrent
+                .files()
+                .unwrap()
+                .iter()
+                .map(|f| f.path().join("/"))
+                .collect()
+        };
+
+        let torrent = build(SymlinkPolicy::Follow);
+        assert_eq!(paths(&torrent), vec!["a.bin", "c.bin", "sub/b.bin"]);
+        assert_eq!(torrent.total_size(), 300);
+
+        let torrent = build(SymlinkPolicy::Skip);
+        assert_eq!(paths(&torrent), vec!["a.bin", "sub/b.bin"]);
+
+        let torrent = build(SymlinkPolicy::Store);
+        assert_eq!(
+            paths(&torrent),
+            vec!["a.bin", "c.bin", "sub/b.bin", "sub/loop"]
+        );
+        assert_eq!(torrent.total_size(), 200);
+        let files = torrent.files().unwrap();
+        assert_eq!(files[0].symlink_path(), None);
+        assert_eq!(files[1].symlink_path(), Some(vec!["a.bin".into()]));
+        assert_eq!(files[3].symlink_path(), Some(vec!["sub".into()]));
+
+        assert!(TorrentBuilder::new(&dir)
+            .symlinks(SymlinkPolicy::Store)
+            .version(TorrentVersion::V2)
+            .build()
+            .is_err());
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_single_file() {
+        let dir = temp_dir("build-single");
+        let data = write_file(&dir.join("file.bin"), 1000);
+
+        let torrent = TorrentBuilder::new(dir.join("file.bin")).build().unwrap();
+        assert!(torrent.files().is_none());
+        assert_eq!(torrent.info().name().as_deref(), Some("file.bin"));
+        assert_eq!(torrent.total_size(), 1000);
+        assert_eq!(torrent.info().pieces().as_bytes(), &merkle::sha1(&data)[..]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_piece_length_auto() {
+        assert_eq!(PieceLength::auto(0), 16 * 1024);
+        assert_eq!(PieceLength::auto(1500 * 16 * 1024), 16 * 1024);
+        assert_eq!(PieceLength::auto(1500 * 16 * 1024 + 1), 32 * 1024);
+        assert_eq!(PieceLength::auto(700 * 1024 * 1024), 512 * 1024);
+        assert_eq!(PieceLength::auto(1 << 50), 16 * 1024 * 1024);
+    }
+
+    #[test]
+    pub fn test_piece_length_validation() {
+        assert_eq!(PieceLength::Fixed(32768).resolve(1).unwrap(), 32768);
+        assert!(PieceLength::Fixed(8192).resolve(1).is_err());
+        assert!(PieceLength::Fixed(20000).resolve(1).is_err());
+        assert!(PieceLength::Fixed(128 * 1024 * 1024).resolve(1).is_err());
+
+        let dir = temp_dir("build-piece-length");
+        write_file(&dir.join("file.bin"), 10);
+        let build = |piece_length| {
+            TorrentBuilder::new(dir.join("file.bin"))
+                .piece_length(piece_length)
+                .build()
+        };
+        assert!(build(PieceLength::Fixed(1000)).is_err());
+        assert_eq!(
+            build(PieceLength::Auto).unwrap().info().piece_length(),
+            16384
+        );
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    pub fn test_build_empty_dir() {
+        let dir = temp_dir("build-empty");
+        assert!(TorrentBuilder::new(&dir).build().is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,33 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Helpers shared by the parsers of the uTorrent, rtorrent and Transmission state files.
+
+use std::borrow::Cow;
+
+use serde_bytes::ByteBuf;
+
+/// Decodes an optional string of a client's state file, replacing invalid UTF-8.
+pub(crate) fn lossy(bytes: &Option<ByteBuf>) -> Option<Cow<'_, str>> {
+    bytes.as_ref().map(|b| String::from_utf8_lossy(b))
+}
+
+/// Returns a timestamp of a client's state file, in which 0 means unset.
+pub(crate) fn timestamp(timestamp: Option<i64>) -> Option<i64> {
+    timestamp.filter(|&t| t != 0)
+}
@@ -0,0 +1,129 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Transparent decompression of gzip (`gzip` feature) and zstd (`zstd` feature)
+//! compressed torrent files.
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use std::io::Read;
+
+#[cfg(feature = "gzip")]
+use flate2::read::MultiGzDecoder;
+#[cfg(feature = "zstd")]
+use ruzstd::decoding::StreamingDecoder;
+
+use error::{Error, Result};
+
+const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// Decompresses `buf` if it starts with the magic bytes of gzip or zstd, returning other
+/// input unchanged.
+///
+/// The decompressed torrent must be at most `limit` bytes, compressed input fails if
+/// the feature of its format is disabled.
+pub fn decompress(buf: Vec<u8>, limit: u64) -> Result<Vec<u8>> {
+    if buf.starts_with(GZIP_MAGIC) {
+        gunzip(&buf, limit)
+    } else if buf.starts_with(ZSTD_MAGIC) {
+        unzstd(&buf, limit)
+    } else {
+        Ok(buf)
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn read_limited<R: Read>(reader: R, limit: u64) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    reader.take(limit + 1).read_to_end(&mut buf)?;
+    if buf.len() as u64 > limit {
+        bail!(Error::TooLarge(limit));
+    }
+
+    Ok(buf)
+}
+
+#[cfg(feature = "gzip")]
+fn gunzip(buf: &[u8], limit: u64) -> Result<Vec<u8>> {
+    read_limited(MultiGzDecoder::new(buf), limit)
+}
+
+#[cfg(not(feature = "gzip"))]
+fn gunzip(_: &[u8], _: u64) -> Result<Vec<u8>> {
+    bail!(Error::Unsupported(
+        "gzip compression without the `gzip` feature".to_owned()
+    ))
+}
+
+#[cfg(feature = "zstd")]
+fn unzstd(mut buf: &[u8], limit: u64) -> Result<Vec<u8>> {
+    let decoder = StreamingDecoder::new(&mut buf)
+        .map_err(|e| Error::Unsupported(format!("zstd frame: {}", e)))?;
+    read_limited(decoder, limit)
+}
+
+#[cfg(not(feature = "zstd"))]
+fn unzstd(_: &[u8], _: u64) -> Result<Vec<u8>> {
+    bail!(Error::Unsupported(
+        "zstd compression without the `zstd` feature".to_owned()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TORRENT: &[u8] = b"d4:infod6:lengthi5e4:name1:a12:piece lengthi16384e\
+6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+
+    #[test]
+    pub fn test_uncompressed() {
+        assert_eq!(decompress(TORRENT.to_vec(), 1000).unwrap(), TORRENT);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    pub fn test_gzip() {
+        use flate2::write::GzEncoder;
+        use flate2::Compression;
+        use std::io::Write;
+
+        let mut encoder = GzEncoder::new(vec![], Compression::default());
+        encoder.write_all(TORRENT).unwrap();
+        let compressed = encoder.finish().unwrap();
+        assert_eq!(decompress(compressed.clone(), 1000).unwrap(), TORRENT);
+        match decompress(compressed, 10) {
+            Err(Error::TooLarge(10)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    pub fn test_zstd() {
+        use ruzstd::encoding::{compress_to_vec, CompressionLevel};
+
+        let compressed = compress_to_vec(TORRENT, CompressionLevel::Fastest);
+        assert_eq!(decompress(compressed.clone(), 1000).unwrap(), TORRENT);
+        match decompress(compressed, 10) {
+            Err(Error::TooLarge(10)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(decompress(ZSTD_MAGIC.to_vec(), 1000).is_err());
+    }
+}
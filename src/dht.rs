@@ -0,0 +1,388 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! A minimal mainline DHT client (BEP 5) looking up the peers of a torrent (`net` feature).
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use serde_bencode::value::Value;
+use serde_bencode::{de, ser};
+
+use error::{Error, Result};
+use limits::Limits;
+use tracker::{compact_peers, random_u32};
+use InfoHash;
+
+/// Well-known nodes to join the DHT.
+pub const BOOTSTRAP_NODES: &[&str] = &[
+    "router.bittorrent.com:6881",
+    "dht.transmissionbt.com:6881",
+    "router.utorrent.com:6881",
+    "dht.libtorrent.org:25401",
+];
+
+/// The number of queries in flight.
+const ALPHA: usize = 8;
+/// The number of closest nodes which must have answered to end the lookup.
+const K: usize = 8;
+
+/// Options of a DHT lookup.
+#[derive(Debug, Clone)]
+pub struct LookupOptions {
+    /// The nodes to start from as `host:port`
+    pub bootstrap: Vec<String>,
+    /// The timeout of a single query
+    pub timeout: Duration,
+    /// The maximum duration of the lookup
+    pub duration: Duration,
+    /// The maximum number of queried nodes
+    pub max_queries: usize,
+}
+
+impl Default for LookupOptions {
+    fn default() -> Self {
+        LookupOptions {
+            bootstrap: BOOTSTRAP_NODES
+                .iter()
+                .map(|node| node.to_string())
+                .collect(),
+            timeout: Duration::from_secs(2),
+            duration: Duration::from_secs(30),
+            max_queries: 200,
+        }
+    }
+}
+
+/// The result of a DHT lookup.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Lookup {
+    /// The peers announced for the torrent, without duplicates
+    pub peers: Vec<SocketAddr>,
+    /// The number of nodes queried
+    pub queried: usize,
+    /// The number of nodes which answered
+    pub responded: usize,
+}
+
+type NodeId = [u8; 20];
+
+fn distance(a: &NodeId, b: &NodeId) -> NodeId {
+    let mut distance = [0u8; 20];
+    for (d, (a, b)) in distance.iter_mut().zip(a.iter().zip(b.iter())) {
+        *d = a ^ b;
+    }
+    distance
+}
+
+fn bytes<B: Into<Vec<u8>>>(bytes: B) -> Value {
+    Value::Bytes(bytes.into())
+}
+
+/// Encodes a `get_peers` query.
+fn get_peers_query(transaction: &[u8], id: &NodeId, info_hash: &InfoHash) -> Result<Vec<u8>> {
+    let mut args = HashMap::new();
+    args.insert(b"id".to_vec(), bytes(&id[..]));
+    args.insert(b"info_hash".to_vec(), bytes(&info_hash.as_bytes()[..]));
+    let mut query = HashMap::new();
+    query.insert(b"a".to_vec(), Value::Dict(args));
+    query.insert(b"q".to_vec(), bytes("get_peers"));
+    query.insert(b"t".to_vec(), bytes(transaction));
+    query.insert(b"y".to_vec(), bytes("q"));
+
+    Ok(ser::to_bytes(&Value::Dict(query))?)
+}
+
+/// The peers and closer nodes of a `get_peers` response.
+#[derive(Debug, Default, PartialEq, Eq)]
+struct Response {
+    transaction: Vec<u8>,
+    /// The id of the answering node, `None` if it is missing or invalid
+    id: Option<NodeId>,
+    peers: Vec<SocketAddr>,
+    nodes: Vec<(NodeId, SocketAddr)>,
+}
+
+/// Parses a `get_peers` response, `None` for errors and invalid messages.
+fn parse_response(buf: &[u8]) -> Option<Response> {
+    Limits::default().check(buf).ok()?;
+    let dict = match de::from_bytes(buf) {
+        Ok(Value::Dict(dict)) => dict,
+        _ => return None,
+    };
+    let (transaction, r) = match (
+        dict.get(&b"t"[..]),
+        dict.get(&b"y"[..]),
+        dict.get(&b"r"[..]),
+    ) {
+        (Some(Value::Bytes(t)), Some(Value::Bytes(y)), Some(Value::Dict(r))) if y == b"r" => (t, r),
+        _ => return None,
+    };
+
+    let mut response = Response {
+        transaction: transaction.clone(),
+        ..Response::default()
+    };
+    if let Some(Value::Bytes(id)) = r.get(&b"id"[..]) {
+        if id.len() == 20 {
+            let mut node_id = [0u8; 20];
+            node_id.copy_from_slice(id);
+            response.id = Some(node_id);
+        }
+    }
+    if let Some(Value::List(values)) = r.get(&b"values"[..]) {
+        for value in values {
+            if let Value::Bytes(peer) = value {
+                response.peers.extend(compact_peers(peer));
+            }
+        }
+    }
+    if let Some(Value::Bytes(nodes)) = r.get(&b"nodes"[..]) {
+        for node in nodes.chunks_exact(26) {
+            let mut id = [0u8; 20];
+            id.copy_from_slice(&node[..20]);
+            response.nodes.extend(
+                compact_peers(&node[20..])
+                    .into_iter()
+                    .map(|addr| (id, addr)),
+            );
+        }
+    }
+
+    Some(response)
+}
+
+/// Looks up the peers of a torrent in the DHT.
+///
+/// Starting from the bootstrap nodes, the closest nodes to the info hash are queried
+/// iteratively until the closest nodes have answered, or the query or time limits of the
+/// `options` are reached. Only IPv4 nodes are queried.
+pub fn get_peers(info_hash: &InfoHash, options: &LookupOptions) -> Result<Lookup> {
+    let socket = UdpSocket::bind("0.0.0.0:0")?;
+    let target = *info_hash.as_bytes();
+    let mut id = [0u8; 20];
+    for chunk in id.chunks_mut(4) {
+        chunk.copy_from_slice(&random_u32().to_be_bytes());
+    }
+
+    // Bootstrap nodes have unknown ids and are queried first
+    let mut candidates: BTreeSet<(NodeId, SocketAddr)> = BTreeSet::new();
+    let mut seen = HashSet::new();
+    for node in &options.bootstrap {
+        if let Ok(addrs) = node.to_socket_addrs() {
+            for addr in addrs.filter(SocketAddr::is_ipv4) {
+                if seen.insert(addr) {
+                    candidates.insert(([0; 20], addr));
+                }
+            }
+        }
+    }
+    if candidates.is_empty() {
+        bail!(Error::Dht(
+            "could not resolve any bootstrap node".to_owned()
+        ));
+    }
+
+    let start = Instant::now();
+    let mut lookup = Lookup::default();
+    let mut peers = HashSet::new();
+    let mut responded: BTreeSet<(NodeId, SocketAddr)> = BTreeSet::new();
+    let mut in_flight: HashMap<Vec<u8>, (SocketAddr, Instant)> = HashMap::new();
+    let mut next_transaction = 0u16;
+    let mut buf = vec![0u8; 65536];
+    while start.elapsed() < options.duration {
+        let now = Instant::now();
+        in_flight.retain(|_, &mut (_, sent)| now.duration_since(sent) < options.timeout);
+
+        while in_flight.len() < ALPHA && lookup.queried < options.max_queries {
+            let (node_distance, addr) = match candidates.iter().next() {
+                Some(&candidate) => candidate,
+                None => break,
+            };
+            // Nodes farther than the closest K which answered cannot get closer
+            if responded.len() >= K
+                && responded
+                    .iter()
+                    .nth(K - 1)
+                    .is_some_and(|n| node_distance >= n.0)
+            {
+                candidates.clear();
+                break;
+            }
+            candidates.remove(&(node_distance, addr));
+
+            let transaction = next_transaction.to_be_bytes().to_vec();
+            next_transaction = next_transaction.wrapping_add(1);
+            if socket
+                .send_to(&get_peers_query(&transaction, &id, info_hash)?, addr)
+                .is_ok()
+            {
+                lookup.queried += 1;
+                in_flight.insert(transaction, (addr, Instant::now()));
+            }
+        }
+        if in_flight.is_empty() {
+            break;
+        }
+
+        socket.set_read_timeout(Some(Duration::from_millis(100)))?;
+        let (n, from) = match socket.recv_from(&mut buf) {
+            Ok(received) => received,
+            Err(ref e)
+                if e.kind() == io::ErrorKind::WouldBlock || e.kind() == io::ErrorKind::TimedOut =>
+            {
+                continue
+            }
+            Err(e) => bail!(e),
+        };
+        let response = match parse_response(&buf[..n]) {
+            Some(response) => response,
+            None => continue,
+        };
+        match in_flight.get(&response.transaction) {
+            Some(&(addr, _)) if addr == from => {
+                in_flight.remove(&response.transaction);
+                lookup.responded += 1;
+                // Bootstrap nodes are queried before their id is known, so nodes are ranked
+                // by the id they answer with
+                if let Some(node_id) = response.id {
+                    responded.insert((distance(&node_id, &target), addr));
+                }
+            }
+            _ => continue,
+        }
+
+        for peer in response.peers {
+            if peers.insert(peer) {
+                lookup.peers.push(peer);
+            }
+        }
+        for (node_id, addr) in response.nodes {
+            if seen.insert(addr) {
+                candidates.insert((distance(&node_id, &target), addr));
+            }
+        }
+    }
+
+    Ok(lookup)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    #[test]
+    pub fn test_get_peers_query() {
+        let query = get_peers_query(b"aa", &[b'i'; 20], &InfoHash::from([b'h'; 20])).unwrap();
+        assert_eq!(
+            &query[..],
+            &b"d1:ad2:id20:iiiiiiiiiiiiiiiiiiii9:info_hash20:hhhhhhhhhhhhhhhhhhhhe\
+1:q9:get_peers1:t2:aa1:y1:qe"[..]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_response() {
+        let buf = b"d1:rd2:id20:iiiiiiiiiiiiiiiiiiii5:nodes26:nnnnnnnnnnnnnnnnnnnn\
+\x0a\x00\x00\x01\x1a\xe15:token2:tk6:valuesl6:\x7f\x00\x00\x01\x1a\xe1ee1:t2:aa1:y1:re";
+        assert_eq!(
+            parse_response(buf),
+            Some(Response {
+                transaction: b"aa".to_vec(),
+                id: Some([b'i'; 20]),
+                peers: vec!["127.0.0.1:6881".parse().unwrap()],
+                nodes: vec![([b'n'; 20], "10.0.0.1:6881".parse().unwrap())],
+            })
+        );
+        assert_eq!(parse_response(b"d1:eli201e7:Generice1:t2:aa1:y1:ee"), None);
+        assert_eq!(parse_response(b"garbage"), None);
+
+        let nested = format!("{}{}", "l".repeat(100_000), "e".repeat(100_000));
+        assert_eq!(parse_response(nested.as_bytes()), None);
+    }
+
+    /// Runs a node answering `get_peers` with `peers` and the compact `nodes`.
+    fn fake_node(peers: Vec<u8>, nodes: Vec<u8>) -> SocketAddr {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let addr = socket.local_addr().unwrap();
+        thread::spawn(move || {
+            let mut buf = [0u8; 1500];
+            while let Ok((n, from)) = socket.recv_from(&mut buf) {
+                if Limits::default().check(&buf[..n]).is_err() {
+                    continue;
+                }
+                let query = match de::from_bytes(&buf[..n]) {
+                    Ok(Value::Dict(query)) => query,
+                    _ => continue,
+                };
+                let mut r = HashMap::new();
+                r.insert(b"id".to_vec(), bytes(&[b'n'; 20][..]));
+                r.insert(b"nodes".to_vec(), bytes(nodes.clone()));
+                if !peers.is_empty() {
+                    r.insert(b"values".to_vec(), Value::List(vec![bytes(peers.clone())]));
+                }
+                let mut response = HashMap::new();
+                response.insert(b"t".to_vec(), query[&b"t"[..]].clone());
+                response.insert(b"y".to_vec(), bytes("r"));
+                response.insert(b"r".to_vec(), Value::Dict(r));
+                let response = ser::to_bytes(&Value::Dict(response)).unwrap();
+                socket.send_to(&response, from).unwrap();
+            }
+        });
+        addr
+    }
+
+    fn compact(addr: SocketAddr) -> Vec<u8> {
+        match addr {
+            SocketAddr::V4(addr) => {
+                let mut compact = addr.ip().octets().to_vec();
+                compact.extend_from_slice(&addr.port().to_be_bytes());
+                compact
+            }
+            SocketAddr::V6(_) => unreachable!(),
+        }
+    }
+
+    #[test]
+    pub fn test_get_peers() {
+        let peer: SocketAddr = "10.1.2.3:51413".parse().unwrap();
+        let close = fake_node(compact(peer), vec![]);
+        let mut nodes = vec![b'x'; 20];
+        nodes.extend(compact(close));
+        let bootstrap = fake_node(vec![], nodes);
+
+        let options = LookupOptions {
+            bootstrap: vec![bootstrap.to_string(), "invalid host:1".to_owned()],
+            timeout: Duration::from_millis(500),
+            ..LookupOptions::default()
+        };
+        let lookup = get_peers(&InfoHash::from([b'x'; 20]), &options).unwrap();
+        assert_eq!(lookup.peers, vec![peer]);
+        assert_eq!(lookup.queried, 2);
+        assert_eq!(lookup.responded, 2);
+
+        let options = LookupOptions {
+            bootstrap: vec![],
+            ..LookupOptions::default()
+        };
+        assert!(get_peers(&InfoHash::from([b'x'; 20]), &options).is_err());
+    }
+}
@@ -0,0 +1,239 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Comparison of torrents.
+
+use std::collections::HashMap;
+use std::fmt;
+
+use {FileMode, Torrent};
+
+/// A file which differs between two torrents, see `TorrentDiff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FileChange {
+    /// The file only exists in the second torrent
+    Added { path: Vec<String>, length: u64 },
+    /// The file only exists in the first torrent
+    Removed { path: Vec<String>, length: u64 },
+    /// The file exists in both torrents with different lengths
+    Resized {
+        path: Vec<String>,
+        old_length: u64,
+        new_length: u64,
+    },
+}
+
+impl FileChange {
+    pub fn path(&self) -> &[String] {
+        match *self {
+            FileChange::Added { ref path, .. }
+            | FileChange::Removed { ref path, .. }
+            | FileChange::Resized { ref path, .. } => path,
+        }
+    }
+}
+
+impl fmt::Display for FileChange {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let path = self.path().join("/");
+        match *self {
+            FileChange::Added { length, .. } => write!(f, "+ {} ({} bytes)", path, length),
+            FileChange::Removed { length, .. } => write!(f, "- {} ({} bytes)", path, length),
+            FileChange::Resized {
+                old_length,
+                new_length,
+                ..
+            } => write!(f, "~ {} ({} -> {} bytes)", path, old_length, new_length),
+        }
+    }
+}
+
+/// The differences between two torrents, created by `diff`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TorrentDiff {
+    /// Whether the info hashes are equal, so both torrents describe the same content
+    pub same_info_hash: bool,
+    /// The trackers only the second torrent announces to
+    pub added_trackers: Vec<String>,
+    /// The trackers only the first torrent announces to
+    pub removed_trackers: Vec<String>,
+    /// The old and new comment, if they differ
+    pub comment: Option<(Option<String>, Option<String>)>,
+    /// The old and new piece length, if they differ
+    pub piece_length: Option<(u64, u64)>,
+    /// The added, removed and resized files, compared by their decoded paths and ignoring
+    /// pad files
+    pub files: Vec<FileChange>,
+}
+
+impl TorrentDiff {
+    /// Returns whether no differences were found.
+    pub fn is_empty(&self) -> bool {
+        self.same_info_hash
+            && self.added_trackers.is_empty()
+            && self.removed_trackers.is_empty()
+            && self.comment.is_none()
+            && self.piece_length.is_none()
+            && self.files.is_empty()
+    }
+}
+
+/// Compares torrent `a` with torrent `b`, e.g. a re-upload with its original.
+pub fn diff(a: &Torrent, b: &Torrent) -> TorrentDiff {
+    let (trackers_a, trackers_b) = (a.trackers(), b.trackers());
+    let comment_a = a.decoded_comment().map(|c| c.into_owned());
+    let comment_b = b.decoded_comment().map(|c| c.into_owned());
+    let (piece_length_a, piece_length_b) = (a.info().piece_length(), b.info().piece_length());
+
+    TorrentDiff {
+        same_info_hash: match (a.info_hash(), b.info_hash()) {
+            (Ok(a), Ok(b)) => a == b,
+            _ => false,
+        },
+        added_trackers: trackers_b
+            .iter()
+            .filter(|url| !trackers_a.contains(url))
+            .map(|url| url.to_string())
+            .collect(),
+        removed_trackers: trackers_a
+            .iter()
+            .filter(|url| !trackers_b.contains(url))
+            .map(|url| url.to_string())
+            .collect(),
+        comment: if comment_a != comment_b {
+            Some((comment_a, comment_b))
+        } else {
+            None
+        },
+        piece_length: if piece_length_a != piece_length_b {
+            Some((piece_length_a, piece_length_b))
+        } else {
+            None
+        },
+        files: file_changes(&files(a), &files(b)),
+    }
+}
+
+/// Returns the decoded paths and lengths of the content files.
+fn files(torrent: &Torrent) -> Vec<(Vec<String>, u64)> {
+    match torrent.info().mode() {
+        Some(FileMode::Single { length, .. }) => torrent
+            .decoded_name()
+            .map(|name| (vec![name.into_owned()], *length))
+            .into_iter()
+            .collect(),
+        Some(FileMode::Multi { files }) => files
+            .iter()
+            .filter(|file| !file.is_padding())
+            .map(|file| {
+                let path = torrent.decoded_path(file);
+                (
+                    path.into_iter().map(|c| c.into_owned()).collect(),
+                    file.length(),
+                )
+            })
+            .collect(),
+        None => torrent
+            .info()
+            .file_tree()
+            .into_iter()
+            .map(|entry| (entry.path().to_vec(), entry.length()))
+            .collect(),
+    }
+}
+
+fn file_changes(a: &[(Vec<String>, u64)], b: &[(Vec<String>, u64)]) -> Vec<FileChange> {
+    let lengths_a: HashMap<_, _> = a.iter().map(|(path, length)| (path, *length)).collect();
+    let lengths_b: HashMap<_, _> = b.iter().map(|(path, length)| (path, *length)).collect();
+
+    let mut changes = vec![];
+    for (path, length) in a {
+        match lengths_b.get(path) {
+            None => changes.push(FileChange::Removed {
+                path: path.clone(),
+                length: *length,
+            }),
+            Some(&new_length) if new_length != *length => changes.push(FileChange::Resized {
+                path: path.clone(),
+                old_length: *length,
+                new_length,
+            }),
+            Some(_) => {}
+        }
+    }
+    for (path, length) in b {
+        if !lengths_a.contains_key(path) {
+            changes.push(FileChange::Added {
+                path: path.clone(),
+                length: *length,
+            });
+        }
+    }
+
+    changes
+}
+
+#[cfg(all(test, feature = "hash"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_diff() {
+        let a = Torrent::from_buf(
+            b"d8:announce5:http17:comment1:a4:infod5:filesld6:lengthi1e4:pathl1:aee\
+d6:lengthi2e4:pathl1:beed4:attr1:p6:lengthi3e4:pathl4:.pad1:3eee4:name1:d\
+12:piece lengthi16384e6:pieces0:ee",
+        )
+        .unwrap();
+        let b = Torrent::from_buf(
+            b"d8:announce5:http213:announce-listll5:http2el5:http3ee4:infod5:filesl\
+d6:lengthi5e4:pathl1:beed6:lengthi1e4:pathl1:ceee4:name1:d12:piece lengthi32768e\
+6:pieces0:ee",
+        )
+        .unwrap();
+
+        let changes = diff(&a, &b);
+        assert!(!changes.same_info_hash);
+        assert_eq!(changes.added_trackers, vec!["http2", "http3"]);
+        assert_eq!(changes.removed_trackers, vec!["http1"]);
+        assert_eq!(changes.comment, Some((Some("a".to_owned()), None)));
+        assert_eq!(changes.piece_length, Some((16384, 32768)));
+        assert_eq!(
+            changes.files,
+            vec![
+                FileChange::Removed {
+                    path: vec!["a".to_owned()],
+                    length: 1
+                },
+                FileChange::Resized {
+                    path: vec!["b".to_owned()],
+                    old_length: 2,
+                    new_length: 5
+                },
+                FileChange::Added {
+                    path: vec!["c".to_owned()],
+                    length: 1
+                },
+            ]
+        );
+        assert_eq!(changes.files[1].to_string(), "~ b (2 -> 5 bytes)");
+        assert!(!changes.is_empty());
+
+        assert!(diff(&a, &a.clone()).is_empty());
+    }
+}
@@ -0,0 +1,408 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Lossless editing of bencoded documents.
+
+use std::collections::BTreeMap;
+use std::ops::Range;
+use std::str;
+
+use serde_bencode::value::Value;
+
+use bencode;
+use error::{Error, Result};
+use limits::Limits;
+
+/// A bencoded document which can be edited without touching the rest of its bytes.
+///
+/// Values which are not modified are written exactly as they were parsed, including their
+/// key order and non-canonical encodings, so editing e.g. the `announce` url of a torrent
+/// never changes its info hash.
+///
+/// ```
+/// extern crate serde_bencode;
+/// extern crate torrentinfo;
+///
+/// use serde_bencode::value::Value;
+/// use torrentinfo::BencodeDocument;
+///
+/// # fn main() {
+///
+/// let mut doc = BencodeDocument::parse(b"d8:announce9:http://a/4:infod1:zi1e1:ai2eee").unwrap();
+/// doc.set(&["announce"], Value::Bytes(b"http://b/".to_vec())).unwrap();
+/// assert_eq!(doc.to_bytes(), b"d8:announce9:http://b/4:infod1:zi1e1:ai2eee".to_vec());
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct BencodeDocument {
+    buf: Vec<u8>,
+    root: Node,
+}
+
+#[derive(Debug, Clone)]
+struct Node {
+    kind: Kind,
+    /// The span of the original bytes, `None` once the node was modified
+    span: Option<Range<usize>>,
+}
+
+#[derive(Debug, Clone)]
+enum Kind {
+    Int(i64),
+    Bytes(Vec<u8>),
+    List(Vec<Node>),
+    /// The entries in their original order
+    Dict(Vec<(Vec<u8>, Node)>),
+}
+
+impl BencodeDocument {
+    /// Parses `buf` within the default `Limits`.
+    pub fn parse(buf: &[u8]) -> Result<Self> {
+        Limits::default().check(buf)?;
+        let (root, end) = parse_node(buf, 0)?;
+        if end != buf.len() {
+            bail!(Error::InvalidBencode(end));
+        }
+
+        Ok(BencodeDocument {
+            buf: buf.to_vec(),
+            root,
+        })
+    }
+
+    /// Returns the value at `path`, a list of dict keys.
+    pub fn get(&self, path: &[&str]) -> Option<Value> {
+        let mut node = &self.root;
+        for key in path {
+            node = match node.kind {
+                Kind::Dict(ref entries) => &entries.iter().find(|(k, _)| k == key.as_bytes())?.1,
+                _ => return None,
+            };
+        }
+
+        Some(node.to_value())
+    }
+
+    /// Returns the original bytes of the value at `path`, unless it was modified.
+    pub fn get_raw(&self, path: &[&str]) -> Option<&[u8]> {
+        let mut node = &self.root;
+        for key in path {
+            node = match node.kind {
+                Kind::Dict(ref entries) => &entries.iter().find(|(k, _)| k == key.as_bytes())?.1,
+                _ => return None,
+            };
+        }
+
+        node.span.clone().map(|span| &self.buf[span])
+    }
+
+    /// Sets the value at `path`, creating missing dicts on the way.
+    ///
+    /// New keys are inserted at their sorted position.
+    pub fn set(&mut self, path: &[&str], value: Value) -> Result<()> {
+        let (key, parents) = match path.split_last() {
+            Some(split) => split,
+            None => {
+                self.root = Node::from_value(value);
+                return Ok(());
+            }
+        };
+
+        let mut node = &mut self.root;
+        for parent in parents {
+            node = match node.entry(parent) {
+                Some(node) => node,
+                None => bail!(Error::InvalidPath(path.join("."))),
+            };
+        }
+        match node.entry(key) {
+            Some(node) => *node = Node::from_value(value),
+            None => bail!(Error::InvalidPath(path.join("."))),
+        }
+
+        Ok(())
+    }
+
+    /// Removes the value at `path`, returning it.
+    pub fn remove(&mut self, path: &[&str]) -> Option<Value> {
+        let (key, parents) = path.split_last()?;
+        self.get(path)?;
+
+        let mut node = &mut self.root;
+        for parent in parents {
+            node.span = None;
+            node = match node.kind {
+                Kind::Dict(ref mut entries) => {
+                    &mut entries.iter_mut().find(|(k, _)| k == parent.as_bytes())?.1
+                }
+                _ => return None,
+            };
+        }
+        node.span = None;
+        match node.kind {
+            Kind::Dict(ref mut entries) => {
+                let index = entries.iter().position(|(k, _)| k == key.as_bytes())?;
+                Some(entries.remove(index).1.to_value())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the bencoded document.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.buf.len());
+        self.root.write(&self.buf, &mut out);
+        out
+    }
+
+    /// Returns the document in canonical bencode, with dict keys sorted by their bytes and
+    /// integers without leading zeros.
+    ///
+    /// Of keys appearing more than once in a dict only the last is kept.
+    pub fn to_canonical_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::with_capacity(self.buf.len());
+        self.root.write_canonical(&mut out);
+        out
+    }
+}
+
+impl Node {
+    fn new(kind: Kind) -> Self {
+        Node { kind, span: None }
+    }
+
+    fn from_value(value: Value) -> Self {
+        Node::new(match value {
+            Value::Int(i) => Kind::Int(i),
+            Value::Bytes(b) => Kind::Bytes(b),
+            Value::List(list) => Kind::List(list.into_iter().map(Node::from_value).collect()),
+            Value::Dict(dict) => {
+                let mut entries: Vec<_> = dict
+                    .into_iter()
+                    .map(|(k, v)| (k, Node::from_value(v)))
+                    .collect();
+                entries.sort_by(|a, b| a.0.cmp(&b.0));
+                Kind::Dict(entries)
+            }
+        })
+    }
+
+    fn to_value(&self) -> Value {
+        match self.kind {
+            Kind::Int(i) => Value::Int(i),
+            Kind::Bytes(ref b) => Value::Bytes(b.clone()),
+            Kind::List(ref list) => Value::List(list.iter().map(Node::to_value).collect()),
+            Kind::Dict(ref entries) => Value::Dict(
+                entries
+                    .iter()
+                    .map(|(k, v)| (k.clone(), v.to_value()))
+                    .collect(),
+            ),
+        }
+    }
+
+    /// Marks this dict as modified and returns the value of `key`, inserting an empty dict
+    /// if it is missing. Returns `None` if this is no dict.
+    fn entry(&mut self, key: &str) -> Option<&mut Node> {
+        let entries = match self.kind {
+            Kind::Dict(ref mut entries) => entries,
+            _ => return None,
+        };
+        self.span = None;
+
+        let key = key.as_bytes();
+        let index = match entries.iter().position(|(k, _)| k == key) {
+            Some(index) => index,
+            None => {
+                let index = entries
+                    .iter()
+                    .position(|(k, _)| k.as_slice() > key)
+                    .unwrap_or(entries.len());
+                entries.insert(index, (key.to_vec(), Node::new(Kind::Dict(vec![]))));
+                index
+            }
+        };
+
+        Some(&mut entries[index].1)
+    }
+
+    fn write(&self, buf: &[u8], out: &mut Vec<u8>) {
+        if let Some(ref span) = self.span {
+            out.extend_from_slice(&buf[span.clone()]);
+            return;
+        }
+
+        match self.kind {
+            Kind::Int(i) => out.extend_from_slice(format!("i{}e", i).as_bytes()),
+            Kind::Bytes(ref b) => write_bytes(b, out),
+            Kind::List(ref list) => {
+                out.push(b'l');
+                for node in list {
+                    node.write(buf, out);
+                }
+                out.push(b'e');
+            }
+            Kind::Dict(ref entries) => {
+                out.push(b'd');
+                for (key, node) in entries {
+                    write_bytes(key, out);
+                    node.write(buf, out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+
+    fn write_canonical(&self, out: &mut Vec<u8>) {
+        match self.kind {
+            Kind::Int(i) => out.extend_from_slice(format!("i{}e", i).as_bytes()),
+            Kind::Bytes(ref b) => write_bytes(b, out),
+            Kind::List(ref list) => {
+                out.push(b'l');
+                for node in list {
+                    node.write_canonical(out);
+                }
+                out.push(b'e');
+            }
+            Kind::Dict(ref entries) => {
+                let entries: BTreeMap<_, _> = entries.iter().map(|(k, v)| (k, v)).collect();
+                out.push(b'd');
+                for (key, node) in entries {
+                    write_bytes(key, out);
+                    node.write_canonical(out);
+                }
+                out.push(b'e');
+            }
+        }
+    }
+}
+
+fn write_bytes(bytes: &[u8], out: &mut Vec<u8>) {
+    out.extend_from_slice(bytes.len().to_string().as_bytes());
+    out.push(b':');
+    out.extend_from_slice(bytes);
+}
+
+/// Parses the value starting at `pos`, returning it and its end offset.
+fn parse_node(buf: &[u8], pos: usize) -> Result<(Node, usize)> {
+    let invalid = || Error::InvalidBencode(pos);
+    let kind = match buf.get(pos) {
+        Some(b'i') => {
+            let end = bencode::value_end(buf, pos).ok_or_else(invalid)?;
+            let i = str::from_utf8(&buf[pos + 1..end - 1])
+                .ok()
+                .and_then(|i| i.parse().ok())
+                .ok_or_else(invalid)?;
+            (Kind::Int(i), end)
+        }
+        Some(b'0'..=b'9') => {
+            let (bytes, end) = bencode::string_at(buf, pos).ok_or_else(invalid)?;
+            (Kind::Bytes(bytes.to_vec()), end)
+        }
+        Some(b'l') => {
+            let mut list = vec![];
+            let mut end = pos + 1;
+            while *buf.get(end).ok_or_else(invalid)? != b'e' {
+                let (node, next) = parse_node(buf, end)?;
+                list.push(node);
+                end = next;
+            }
+            (Kind::List(list), end + 1)
+        }
+        Some(b'd') => {
+            let mut entries = vec![];
+            let mut end = pos + 1;
+            while *buf.get(end).ok_or_else(invalid)? != b'e' {
+                let (key, key_end) =
+                    bencode::string_at(buf, end).ok_or(Error::InvalidBencode(end))?;
+                let (node, next) = parse_node(buf, key_end)?;
+                entries.push((key.to_vec(), node));
+                end = next;
+            }
+            (Kind::Dict(entries), end + 1)
+        }
+        _ => bail!(invalid()),
+    };
+    let (kind, end) = kind;
+
+    Ok((
+        Node {
+            kind,
+            span: Some(pos..end),
+        },
+        end,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BUF: &[u8] = b"d8:announce9:http://a/4:infod4:name1:a1:xi01e6:lengthi1ee1:zli1eee";
+
+    #[test]
+    pub fn test_untouched() {
+        let doc = BencodeDocument::parse(BUF).unwrap();
+        assert_eq!(doc.to_bytes(), BUF.to_vec());
+        assert_eq!(doc.get(&["info", "length"]), Some(Value::Int(1)));
+        assert_eq!(doc.get_raw(&["info", "x"]), Some(&b"i01e"[..]));
+        assert_eq!(doc.get(&["info", "missing"]), None);
+        assert!(BencodeDocument::parse(b"d1:ai1e").is_err());
+        assert!(BencodeDocument::parse(b"i1ei2e").is_err());
+    }
+
+    #[test]
+    pub fn test_edit() {
+        let mut doc = BencodeDocument::parse(BUF).unwrap();
+        doc.set(&["announce"], Value::Bytes(b"http://b/".to_vec()))
+            .unwrap();
+        doc.set(&["comment"], Value::Bytes(b"c".to_vec())).unwrap();
+        assert_eq!(
+            doc.to_bytes(),
+            b"d8:announce9:http://b/7:comment1:c4:infod4:name1:a1:xi01e6:lengthi1ee1:zli1eee"
+                .to_vec()
+        );
+        assert_eq!(doc.get_raw(&["info"]).map(|r| r.len()), Some(29));
+        assert_eq!(doc.get_raw(&["announce"]), None);
+
+        assert_eq!(
+            doc.remove(&["info", "name"]),
+            Some(Value::Bytes(b"a".to_vec()))
+        );
+        assert_eq!(doc.remove(&["info", "name"]), None);
+        doc.set(&["a", "b"], Value::Int(2)).unwrap();
+        assert_eq!(
+            doc.to_bytes(),
+            b"d1:ad1:bi2ee8:announce9:http://b/7:comment1:c4:infod1:xi01e6:lengthi1ee1:zli1eee"
+                .to_vec()
+        );
+        assert!(doc.set(&["z", "b"], Value::Int(2)).is_err());
+    }
+
+    #[test]
+    pub fn test_canonical() {
+        let doc = BencodeDocument::parse(BUF).unwrap();
+        let canonical = b"d8:announce9:http://a/4:infod6:lengthi1e4:name1:a1:xi1ee1:zli1eee";
+        assert_eq!(doc.to_canonical_bytes(), canonical.to_vec());
+        let doc = BencodeDocument::parse(canonical).unwrap();
+        assert_eq!(doc.to_canonical_bytes(), doc.to_bytes());
+
+        let doc = BencodeDocument::parse(b"d1:bi-0e1:ai1e1:bi002ee").unwrap();
+        assert_eq!(doc.to_canonical_bytes(), b"d1:ai1e1:bi2ee".to_vec());
+    }
+}
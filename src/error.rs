@@ -16,8 +16,176 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>.
  */
 
-error_chain! {
-    foreign_links {
-        SerdeBencode(::serde_bencode::Error);
+use std::error;
+use std::fmt;
+use std::io;
+use std::result;
+
+use serde_bencode;
+#[cfg(feature = "json")]
+use serde_json;
+#[cfg(feature = "yaml")]
+use serde_yaml;
+
+/// The errors of this crate.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum Error {
+    /// Reading or writing failed
+    Io(io::Error),
+    /// The bencode is malformed at the byte offset
+    InvalidBencode(usize),
+    /// A required key of a dict is missing
+    MissingField(String),
+    /// The bencode is well-formed, but doesn't describe a torrent
+    Decode(serde_bencode::Error),
+    /// The `pieces` of the info dict are not a byte string
+    InvalidPieces,
+    InvalidInfoHash(String),
+    InvalidMagnet(String),
+    InvalidTrackerUrl(String),
+    InvalidBitfield(String),
+    /// There are no files to create a torrent from at the path
+    NoFiles(String),
+    InvalidPieceLength(usize),
+    /// The piece layer of the file with the hex `pieces root` is missing or doesn't match
+    InvalidPieceLayer(String),
+    InvalidPattern(String),
+    /// A path into a `BencodeDocument` doesn't exist
+    InvalidPath(String),
+    /// Reading the torrent file at `path` failed
+    ReadTorrent {
+        path: String,
+        source: Box<Error>,
+    },
+    /// Reading the content file at `path` failed
+    ReadData {
+        path: String,
+        source: io::Error,
+    },
+    /// The total size of the files of a torrent overflows a `u64`
+    SizeOverflow,
+    /// The input exceeds the size limit in bytes
+    TooLarge(u64),
+    /// The input exceeds one of the `Limits`
+    LimitExceeded {
+        what: String,
+        limit: usize,
+    },
+    InvalidPublicKey,
+    /// The operation is not supported for this torrent
+    Unsupported(String),
+    /// An HTTP request failed
+    Http(String),
+    /// A tracker returned an error or an invalid response
+    Tracker(String),
+    /// A DHT lookup failed
+    Dht(String),
+    /// A peer sent an invalid message or refused a request
+    Peer(String),
+    /// Serializing to JSON failed
+    #[cfg(feature = "json")]
+    Json(serde_json::Error),
+    /// Serializing to YAML failed
+    #[cfg(feature = "yaml")]
+    Yaml(serde_yaml::Error),
+}
+
+pub type Result<T> = result::Result<T, Error>;
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Error::Io(ref e) => write!(f, "{}", e),
+            Error::InvalidBencode(offset) => write!(f, "invalid bencode at offset {}", offset),
+            Error::MissingField(ref field) => write!(f, "missing field '{}'", field),
+            Error::Decode(ref e) => write!(f, "invalid torrent: {}", e),
+            Error::InvalidPieces => write!(f, "invalid pieces"),
+            Error::InvalidInfoHash(ref s) => write!(f, "invalid info hash: '{}'", s),
+            Error::InvalidMagnet(ref s) => write!(f, "invalid magnet link: '{}'", s),
+            Error::InvalidTrackerUrl(ref s) => write!(f, "invalid tracker url: '{}'", s),
+            Error::InvalidBitfield(ref s) => write!(f, "invalid bitfield: {}", s),
+            Error::NoFiles(ref path) => {
+                write!(f, "no files to create a torrent from in '{}'", path)
+            }
+            Error::InvalidPieceLength(piece_length) => write!(
+                f,
+                "invalid piece length {}, must be a power of two between 16 KiB and 64 MiB",
+                piece_length
+            ),
+            Error::InvalidPieceLayer(ref root) => {
+                write!(f, "invalid piece layer for pieces root {}", root)
+            }
+            Error::InvalidPattern(ref pattern) => write!(f, "invalid glob pattern: '{}'", pattern),
+            Error::InvalidPath(ref path) => write!(f, "invalid path: '{}'", path),
+            Error::ReadTorrent { ref path, .. } => write!(f, "could not read torrent '{}'", path),
+            Error::ReadData { ref path, .. } => write!(f, "could not read '{}'", path),
+            Error::SizeOverflow => write!(f, "the total size of the files overflows"),
+            Error::TooLarge(limit) => {
+                write!(f, "torrent exceeds the size limit of {} bytes", limit)
+            }
+            Error::LimitExceeded { ref what, limit } => {
+                write!(f, "bencode exceeds the {} limit of {}", what, limit)
+            }
+            Error::InvalidPublicKey => write!(f, "invalid public key"),
+            Error::Unsupported(ref what) => write!(f, "{} is not supported", what),
+            Error::Http(ref e) => write!(f, "HTTP request failed: {}", e),
+            Error::Tracker(ref e) => write!(f, "tracker error: {}", e),
+            Error::Dht(ref e) => write!(f, "DHT lookup failed: {}", e),
+            Error::Peer(ref e) => write!(f, "peer error: {}", e),
+            #[cfg(feature = "json")]
+            Error::Json(ref e) => write!(f, "{}", e),
+            #[cfg(feature = "yaml")]
+            Error::Yaml(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+impl error::Error for Error {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            Error::Io(ref e) => Some(e),
+            Error::Decode(ref e) => Some(e),
+            Error::ReadTorrent { ref source, .. } => Some(source.as_ref()),
+            Error::ReadData { ref source, .. } => Some(source),
+            #[cfg(feature = "json")]
+            Error::Json(ref e) => Some(e),
+            #[cfg(feature = "yaml")]
+            Error::Yaml(ref e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for Error {
+    fn from(e: io::Error) -> Self {
+        Error::Io(e)
+    }
+}
+
+#[cfg(feature = "json")]
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::Json(e)
+    }
+}
+
+#[cfg(feature = "yaml")]
+impl From<serde_yaml::Error> for Error {
+    fn from(e: serde_yaml::Error) -> Self {
+        Error::Yaml(e)
+    }
+}
+
+impl From<serde_bencode::Error> for Error {
+    fn from(e: serde_bencode::Error) -> Self {
+        match e {
+            serde_bencode::Error::IoError(e) => Error::Io(e),
+            // The message looks like "Missing Field: `name`"
+            serde_bencode::Error::MissingField(msg) => {
+                Error::MissingField(msg.split('`').nth(1).unwrap_or(&msg).to_owned())
+            }
+            e => Error::Decode(e),
+        }
     }
-}
\ No newline at end of file
+}
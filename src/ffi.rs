@@ -0,0 +1,401 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! C API (`ffi` feature), declared in `include/torrentinfo.h`.
+//!
+//! Strings returned by the API are NUL-terminated UTF-8 owned by the torrent and valid
+//! until `ti_free`. Functions failing with NULL store the error for `ti_last_error`, and
+//! clear it on success.
+
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::{c_char, c_int};
+use std::ptr;
+use std::slice;
+
+use error::Error;
+use Torrent;
+
+/// A parsed torrent.
+pub struct TiTorrent {
+    torrent: Torrent,
+    info_hash: Option<CString>,
+    name: Option<CString>,
+    files: Vec<(CString, u64)>,
+    trackers: Vec<CString>,
+}
+
+/// An iterator over the files of a torrent.
+pub struct TiFileIter {
+    torrent: *const TiTorrent,
+    next: usize,
+}
+
+/// An iterator over the tracker urls of a torrent.
+pub struct TiTrackerIter {
+    torrent: *const TiTorrent,
+    next: usize,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+/// Converts a string for C, dropping the interior NUL bytes C can't represent.
+fn c_string(s: &str) -> CString {
+    CString::new(s.replace('\0', "")).unwrap_or_default()
+}
+
+fn set_error(e: &Error) {
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(c_string(&e.to_string())));
+}
+
+fn clear_error() {
+    LAST_ERROR.with(|last| *last.borrow_mut() = None);
+}
+
+fn wrap(result: ::error::Result<Torrent>) -> *mut TiTorrent {
+    let torrent = match result {
+        Ok(torrent) => torrent,
+        Err(e) => {
+            set_error(&e);
+            return ptr::null_mut();
+        }
+    };
+    clear_error();
+    // An info dict which can't be hashed gives a NULL info hash rather than an error
+    let info_hash = torrent
+        .info_hash()
+        .ok()
+        .map(|info_hash| c_string(&info_hash.to_string()));
+    let name = torrent.decoded_name().map(|name| c_string(&name));
+    let files = torrent
+        .manifest()
+        .iter()
+        .map(|entry| (c_string(&entry.path_string()), entry.length))
+        .collect();
+    let trackers = torrent.trackers().into_iter().map(c_string).collect();
+
+    Box::into_raw(Box::new(TiTorrent {
+        torrent,
+        info_hash,
+        name,
+        files,
+        trackers,
+    }))
+}
+
+/// Parses the torrent in the `len` bytes at `buf`, returning NULL on errors.
+///
+/// # Safety
+///
+/// `buf` must point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn ti_parse(buf: *const u8, len: usize) -> *mut TiTorrent {
+    if buf.is_null() {
+        set_error(&Error::InvalidBencode(0));
+        return ptr::null_mut();
+    }
+    wrap(Torrent::from_buf(slice::from_raw_parts(buf, len)))
+}
+
+/// Reads and parses the torrent file at the UTF-8 `path`, returning NULL on errors.
+///
+/// # Safety
+///
+/// `path` must be a NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn ti_parse_file(path: *const c_char) -> *mut TiTorrent {
+    if path.is_null() {
+        set_error(&Error::InvalidPath(String::new()));
+        return ptr::null_mut();
+    }
+    match CStr::from_ptr(path).to_str() {
+        Ok(path) => wrap(Torrent::from_file(path)),
+        Err(_) => {
+            let path = CStr::from_ptr(path).to_string_lossy().into_owned();
+            set_error(&Error::InvalidPath(path));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Returns the message of the last error of the calling thread, or NULL if the last call
+/// succeeded.
+///
+/// The message is valid until the next parse call on the thread.
+#[no_mangle]
+pub extern "C" fn ti_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Frees a torrent, NULL is ignored.
+///
+/// # Safety
+///
+/// `torrent` must be returned by `ti_parse` or `ti_parse_file` and not be freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ti_free(torrent: *mut TiTorrent) {
+    if !torrent.is_null() {
+        drop(Box::from_raw(torrent));
+    }
+}
+
+/// Returns the hex info hash, or NULL if the info dict can't be hashed.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_infohash_hex(torrent: *const TiTorrent) -> *const c_char {
+    (*torrent)
+        .info_hash
+        .as_ref()
+        .map_or(ptr::null(), |hash| hash.as_ptr())
+}
+
+/// Returns the name, or NULL if the torrent has none.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_name(torrent: *const TiTorrent) -> *const c_char {
+    (*torrent)
+        .name
+        .as_ref()
+        .map_or(ptr::null(), |name| name.as_ptr())
+}
+
+/// Returns the total size of the files in bytes.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_total_size(torrent: *const TiTorrent) -> u64 {
+    (*torrent).torrent.total_size()
+}
+
+/// Returns the piece length in bytes.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_piece_length(torrent: *const TiTorrent) -> u64 {
+    (*torrent).torrent.info().piece_length()
+}
+
+/// Returns the number of pieces.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_piece_count(torrent: *const TiTorrent) -> usize {
+    (*torrent).torrent.num_pieces()
+}
+
+/// Returns 1 for private torrents, 0 otherwise.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_is_private(torrent: *const TiTorrent) -> c_int {
+    (*torrent).torrent.is_private() as c_int
+}
+
+/// Returns the number of files, leaving out pad files.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_file_count(torrent: *const TiTorrent) -> usize {
+    (*torrent).files.len()
+}
+
+/// Returns an iterator over the files, to be freed with `ti_file_iter_free` before the
+/// torrent.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_file_iter(torrent: *const TiTorrent) -> *mut TiFileIter {
+    Box::into_raw(Box::new(TiFileIter { torrent, next: 0 }))
+}
+
+/// Stores the `/`-separated path, which starts with the torrent name for multi-file
+/// torrents, and the length of the next file in the out-params, which may be NULL.
+///
+/// Returns 1 if there was a next file and 0 at the end.
+///
+/// # Safety
+///
+/// `iter` must be a valid iterator of a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_file_iter_next(
+    iter: *mut TiFileIter,
+    path: *mut *const c_char,
+    length: *mut u64,
+) -> c_int {
+    let iter = &mut *iter;
+    let torrent = &*iter.torrent;
+    let (ref file_path, file_length) = match torrent.files.get(iter.next) {
+        Some(file) => file,
+        None => return 0,
+    };
+    iter.next += 1;
+    if !path.is_null() {
+        *path = file_path.as_ptr();
+    }
+    if !length.is_null() {
+        *length = *file_length;
+    }
+    1
+}
+
+/// Frees a file iterator, NULL is ignored.
+///
+/// # Safety
+///
+/// `iter` must be returned by `ti_file_iter` and not be freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ti_file_iter_free(iter: *mut TiFileIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+/// Returns an iterator over the tracker urls of all tiers, to be freed with
+/// `ti_tracker_iter_free` before the torrent.
+///
+/// # Safety
+///
+/// `torrent` must be a valid torrent.
+#[no_mangle]
+pub unsafe extern "C" fn ti_tracker_iter(torrent: *const TiTorrent) -> *mut TiTrackerIter {
+    Box::into_raw(Box::new(TiTrackerIter { torrent, next: 0 }))
+}
+
+/// Stores the next tracker url in `url`, returning 1 if there was a next url and 0 at
+/// the end.
+///
+/// # Safety
+///
+/// `iter` must be a valid iterator of a valid torrent and `url` must be writable.
+#[no_mangle]
+pub unsafe extern "C" fn ti_tracker_iter_next(
+    iter: *mut TiTrackerIter,
+    url: *mut *const c_char,
+) -> c_int {
+    let iter = &mut *iter;
+    let torrent = &*iter.torrent;
+    match torrent.trackers.get(iter.next) {
+        Some(tracker) => {
+            iter.next += 1;
+            *url = tracker.as_ptr();
+            1
+        }
+        None => 0,
+    }
+}
+
+/// Frees a tracker iterator, NULL is ignored.
+///
+/// # Safety
+///
+/// `iter` must be returned by `ti_tracker_iter` and not be freed before.
+#[no_mangle]
+pub unsafe extern "C" fn ti_tracker_iter_free(iter: *mut TiTrackerIter) {
+    if !iter.is_null() {
+        drop(Box::from_raw(iter));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TORRENT: &[u8] = b"d8:announce9:udp://t/a4:infod5:filesld6:lengthi3e4:pathl1:aee\
+d6:lengthi4e4:pathl1:beee4:name1:t12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+
+    unsafe fn string(s: *const c_char) -> String {
+        CStr::from_ptr(s).to_str().unwrap().to_owned()
+    }
+
+    #[test]
+    pub fn test_ffi() {
+        unsafe {
+            let torrent = ti_parse(TORRENT.as_ptr(), TORRENT.len());
+            assert!(!torrent.is_null());
+            assert_eq!(
+                string(ti_infohash_hex(torrent)),
+                Torrent::from_buf(TORRENT)
+                    .unwrap()
+                    .info_hash()
+                    .unwrap()
+                    .to_string()
+            );
+            assert_eq!(string(ti_name(torrent)), "t");
+            assert_eq!(ti_total_size(torrent), 7);
+            assert_eq!(ti_piece_length(torrent), 16384);
+            assert_eq!(ti_piece_count(torrent), 1);
+            assert_eq!(ti_is_private(torrent), 0);
+            assert_eq!(ti_file_count(torrent), 2);
+
+            let iter = ti_file_iter(torrent);
+            let mut path = ptr::null();
+            let mut length = 0;
+            assert_eq!(ti_file_iter_next(iter, &mut path, &mut length), 1);
+            assert_eq!((string(path), length), ("t/a".to_owned(), 3));
+            assert_eq!(ti_file_iter_next(iter, ptr::null_mut(), &mut length), 1);
+            assert_eq!(length, 4);
+            assert_eq!(ti_file_iter_next(iter, &mut path, &mut length), 0);
+            ti_file_iter_free(iter);
+
+            let iter = ti_tracker_iter(torrent);
+            let mut url = ptr::null();
+            assert_eq!(ti_tracker_iter_next(iter, &mut url), 1);
+            assert_eq!(string(url), "udp://t/a");
+            assert_eq!(ti_tracker_iter_next(iter, &mut url), 0);
+            ti_tracker_iter_free(iter);
+            ti_free(torrent);
+        }
+    }
+
+    #[test]
+    pub fn test_errors() {
+        unsafe {
+            assert!(ti_parse(b"le".as_ptr(), 2).is_null());
+            assert!(!ti_last_error().is_null());
+            assert!(ti_parse(ptr::null(), 0).is_null());
+            let path = CString::new("/nonexistent.torrent").unwrap();
+            assert!(ti_parse_file(path.as_ptr()).is_null());
+            assert!(string(ti_last_error()).contains("/nonexistent.torrent"));
+            let torrent = ti_parse(TORRENT.as_ptr(), TORRENT.len());
+            assert!(!torrent.is_null());
+            assert!(ti_last_error().is_null());
+            ti_free(torrent);
+            ti_free(ptr::null_mut());
+        }
+    }
+}
@@ -0,0 +1,81 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+use std::fmt;
+
+/// The attributes of a file (BEP 47), parsed from the characters of its `attr` string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct FileAttributes {
+    /// `x`, the file should be executable
+    pub executable: bool,
+    /// `h`, the file should be hidden
+    pub hidden: bool,
+    /// `p`, the file is a pad file and not part of the content
+    pub padding: bool,
+    /// `l`, the file is a symlink, see `File::symlink_path`
+    pub symlink: bool,
+}
+
+impl FileAttributes {
+    /// Parses an `attr` string, ignoring unknown characters.
+    pub fn parse(attr: &str) -> Self {
+        FileAttributes {
+            executable: attr.contains('x'),
+            hidden: attr.contains('h'),
+            padding: attr.contains('p'),
+            symlink: attr.contains('l'),
+        }
+    }
+
+    /// Returns whether no attribute is set.
+    pub fn is_empty(&self) -> bool {
+        *self == FileAttributes::default()
+    }
+}
+
+impl fmt::Display for FileAttributes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let flags = [
+            (self.executable, 'x'),
+            (self.hidden, 'h'),
+            (self.padding, 'p'),
+            (self.symlink, 'l'),
+        ];
+        for &(set, c) in flags.iter() {
+            if set {
+                write!(f, "{}", c)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_parse() {
+        let attr = FileAttributes::parse("lxq");
+        assert!(attr.executable && attr.symlink);
+        assert!(!attr.hidden && !attr.padding);
+        assert_eq!(attr.to_string(), "xl");
+        assert!(FileAttributes::parse("").is_empty());
+    }
+}
@@ -0,0 +1,159 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! The `file tree` of BitTorrent v2 torrents (BEP 52).
+
+#[cfg(feature = "fs")]
+use std::collections::HashMap;
+use std::convert::TryFrom;
+
+use serde_bencode;
+use serde_bencode::value::Value;
+
+use error::Result;
+
+/// A file of a v2 file tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileTreeEntry {
+    path: Vec<String>,
+    length: u64,
+    pieces_root: Option<Vec<u8>>,
+}
+
+impl FileTreeEntry {
+    pub fn new(path: Vec<String>, length: u64, pieces_root: Option<Vec<u8>>) -> Self {
+        FileTreeEntry {
+            path,
+            length,
+            pieces_root,
+        }
+    }
+
+    pub fn path(&self) -> &[String] {
+        &self.path
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    /// Returns the merkle root of the file, which is absent for empty files.
+    pub fn pieces_root(&self) -> Option<&[u8]> {
+        self.pieces_root.as_deref()
+    }
+}
+
+/// Flattens a file tree into its files, in tree order, or no files if a file has a
+/// negative length.
+pub fn entries(tree: &Value) -> Vec<FileTreeEntry> {
+    checked_entries(tree).unwrap_or_default()
+}
+
+/// Flattens a file tree like `entries`, failing on negative lengths.
+pub fn checked_entries(tree: &Value) -> Result<Vec<FileTreeEntry>> {
+    let mut entries = vec![];
+    collect(tree, &mut vec![], &mut entries)?;
+    Ok(entries)
+}
+
+fn collect(node: &Value, path: &mut Vec<String>, entries: &mut Vec<FileTreeEntry>) -> Result<()> {
+    let dict = match node {
+        Value::Dict(dict) => dict,
+        _ => return Ok(()),
+    };
+    let mut keys: Vec<&Vec<u8>> = dict.keys().collect();
+    keys.sort();
+
+    for key in keys {
+        let value = &dict[key];
+        if key.is_empty() {
+            if let Value::Dict(file) = value {
+                let length = match file.get(&b"length"[..]) {
+                    Some(Value::Int(length)) => u64::try_from(*length).map_err(|_| {
+                        serde_bencode::Error::InvalidValue(format!("negative length {}", length))
+                    })?,
+                    _ => 0,
+                };
+                let pieces_root = match file.get(&b"pieces root"[..]) {
+                    Some(Value::Bytes(root)) => Some(root.clone()),
+                    _ => None,
+                };
+                entries.push(FileTreeEntry::new(path.clone(), length, pieces_root));
+            }
+        } else {
+            path.push(String::from_utf8_lossy(key).into_owned());
+            collect(value, path, entries)?;
+            path.pop();
+        }
+    }
+
+    Ok(())
+}
+
+/// Builds a file tree from its files.
+#[cfg(feature = "fs")]
+pub fn to_value(entries: &[FileTreeEntry]) -> Value {
+    let mut root = HashMap::new();
+    for entry in entries {
+        let mut dict = &mut root;
+        for component in &entry.path {
+            let node = dict
+                .entry(component.as_bytes().to_vec())
+                .or_insert_with(|| Value::Dict(HashMap::new()));
+            dict = match node {
+                Value::Dict(d) => d,
+                _ => unreachable!(),
+            };
+        }
+
+        let mut file = HashMap::new();
+        file.insert(
+            b"length".to_vec(),
+            Value::Int(i64::try_from(entry.length).unwrap_or(i64::MAX)),
+        );
+        if let Some(ref root) = entry.pieces_root {
+            file.insert(b"pieces root".to_vec(), Value::Bytes(root.clone()));
+        }
+        dict.insert(vec![], Value::Dict(file));
+    }
+
+    Value::Dict(root)
+}
+
+#[cfg(all(test, feature = "fs"))]
+mod tests {
+    use super::*;
+    use serde_bencode::{de, ser};
+
+    #[test]
+    pub fn test_roundtrip() {
+        let buf = b"d1:ad1:bd0:d6:lengthi5e11:pieces root2:xxee1:cd0:d6:lengthi0eeee\
+                    1:zd0:d6:lengthi7e11:pieces root2:yyeee";
+        let tree: Value = de::from_bytes(buf).unwrap();
+        let entries = entries(&tree);
+        assert_eq!(
+            entries,
+            vec![
+                FileTreeEntry::new(vec!["a".into(), "b".into()], 5, Some(b"xx".to_vec())),
+                FileTreeEntry::new(vec!["a".into(), "c".into()], 0, None),
+                FileTreeEntry::new(vec!["z".into()], 7, Some(b"yy".to_vec())),
+            ]
+        );
+        assert_eq!(ser::to_bytes(&to_value(&entries)).unwrap(), buf.to_vec());
+    }
+}
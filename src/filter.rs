@@ -0,0 +1,210 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Selection of the files to include when creating torrents, and of torrent files in
+//! directory trees.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use glob::{MatchOptions, Pattern};
+
+use error::{Error, Result};
+
+/// Files which are created by file managers and never belong into a torrent.
+const JUNK_FILES: &[&str] = &["Thumbs.db", "desktop.ini", ".DS_Store"];
+
+const MATCH_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+/// Decides which files of a directory are part of a torrent.
+///
+/// Patterns containing a `/` are matched against the whole path relative to the content
+/// directory, other patterns against every path component, so `*.tmp` excludes all
+/// temporary files and `cache` excludes a directory.
+#[derive(Debug, Clone)]
+pub struct Filter {
+    include: Vec<Pattern>,
+    exclude: Vec<Pattern>,
+    hidden: bool,
+    junk: bool,
+}
+
+impl Filter {
+    pub fn new(include: &[String], exclude: &[String], hidden: bool, junk: bool) -> Result<Self> {
+        Ok(Filter {
+            include: compile(include)?,
+            exclude: compile(exclude)?,
+            hidden,
+            junk,
+        })
+    }
+
+    /// Returns whether the file at `path` is included.
+    pub fn matches(&self, path: &[String]) -> bool {
+        if !self.hidden && path.iter().any(|c| c.starts_with('.')) {
+            return false;
+        }
+        if !self.junk
+            && path
+                .last()
+                .is_some_and(|name| JUNK_FILES.contains(&name.as_str()))
+        {
+            return false;
+        }
+        if self.exclude.iter().any(|p| pattern_matches(p, path)) {
+            return false;
+        }
+
+        self.include.is_empty() || self.include.iter().any(|p| pattern_matches(p, path))
+    }
+}
+
+fn compile(patterns: &[String]) -> Result<Vec<Pattern>> {
+    patterns
+        .iter()
+        .map(|p| Pattern::new(p).map_err(|_| Error::InvalidPattern(p.clone())))
+        .collect()
+}
+
+fn pattern_matches(pattern: &Pattern, path: &[String]) -> bool {
+    if pattern.as_str().contains('/') {
+        pattern.matches_with(&path.join("/"), MATCH_OPTIONS)
+    } else {
+        path.iter().any(|c| pattern.matches_with(c, MATCH_OPTIONS))
+    }
+}
+
+/// Returns the `.torrent` files below `dir` which match the `include` and `exclude`
+/// patterns like in `Filter`, sorted by path.
+///
+/// Hidden files and directories are skipped, symlinks to directories aren't followed.
+pub fn find_torrents<P: AsRef<Path>>(
+    dir: P,
+    include: &[String],
+    exclude: &[String],
+) -> Result<Vec<PathBuf>> {
+    let filter = Filter::new(include, exclude, false, true)?;
+    let mut torrents = vec![];
+    let mut dirs = vec![(dir.as_ref().to_path_buf(), vec![])];
+    while let Some((dir, components)) = dirs.pop() {
+        let read_error = |e| Error::ReadData {
+            path: dir.display().to_string(),
+            source: e,
+        };
+        for entry in fs::read_dir(&dir).map_err(read_error)? {
+            let entry = entry.map_err(read_error)?;
+            let mut path = components.clone();
+            path.push(entry.file_name().to_string_lossy().into_owned());
+            if entry.file_type().map_err(read_error)?.is_dir() {
+                if !path[path.len() - 1].starts_with('.') {
+                    dirs.push((entry.path(), path));
+                }
+            } else if is_torrent(&path[path.len() - 1]) && filter.matches(&path) {
+                torrents.push(entry.path());
+            }
+        }
+    }
+
+    torrents.sort();
+    Ok(torrents)
+}
+
+fn is_torrent(name: &str) -> bool {
+    let name = name.as_bytes();
+    name.len() > 8 && name[name.len() - 8..].eq_ignore_ascii_case(b".torrent")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    fn path(path: &str) -> Vec<String> {
+        path.split('/').map(String::from).collect()
+    }
+
+    fn patterns(patterns: &[&str]) -> Vec<String> {
+        patterns.iter().map(|p| p.to_string()).collect()
+    }
+
+    #[test]
+    pub fn test_filter() {
+        let filter = Filter::new(
+            &[],
+            &patterns(&["*.tmp", "cache", "docs/*.pdf"]),
+            false,
+            false,
+        )
+        .unwrap();
+        assert!(filter.matches(&path("a/b.bin")));
+        assert!(!filter.matches(&path("a/b.tmp")));
+        assert!(!filter.matches(&path("cache/b.bin")));
+        assert!(!filter.matches(&path("docs/x.pdf")));
+        assert!(filter.matches(&path("docs/sub/x.pdf")));
+        assert!(!filter.matches(&path(".git/config")));
+        assert!(!filter.matches(&path("a/Thumbs.db")));
+
+        let filter = Filter::new(&patterns(&["*.mkv"]), &[], true, true).unwrap();
+        assert!(filter.matches(&path("a/b.mkv")));
+        assert!(filter.matches(&path(".hidden/b.mkv")));
+        assert!(!filter.matches(&path("a/b.nfo")));
+
+        assert!(Filter::new(&patterns(&["[a"]), &[], false, false).is_err());
+    }
+
+    #[test]
+    pub fn test_find_torrents() {
+        let dir = env::temp_dir().join(format!("torrentinfo-find-{}", ::std::process::id()));
+        for sub in &["a/b", "c", ".hidden"] {
+            fs::create_dir_all(dir.join(sub)).unwrap();
+        }
+        for file in &[
+            "x.torrent",
+            "a/b/y.TORRENT",
+            "a/z.txt",
+            "a/x€€€",
+            "c/w.torrent",
+            ".hidden/v.torrent",
+        ] {
+            fs::write(dir.join(file), b"").unwrap();
+        }
+
+        assert_eq!(
+            find_torrents(&dir, &[], &[]).unwrap(),
+            [
+                dir.join("a/b/y.TORRENT"),
+                dir.join("c/w.torrent"),
+                dir.join("x.torrent")
+            ]
+        );
+        assert_eq!(
+            find_torrents(&dir, &patterns(&["a"]), &[]).unwrap(),
+            [dir.join("a/b/y.TORRENT")]
+        );
+        assert_eq!(
+            find_torrents(&dir, &[], &patterns(&["c", "x.*"])).unwrap(),
+            [dir.join("a/b/y.TORRENT")]
+        );
+        assert!(find_torrents(dir.join("missing"), &[], &[]).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
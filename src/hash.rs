@@ -0,0 +1,191 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! SHA-1 and SHA-256 hashes of info dicts and pieces (`hash` feature).
+//!
+//! Hashing goes through a `HashBackend`, which defaults to the first enabled of the
+//! `openssl`, `ring` and RustCrypto backends. The `asm` feature switches the RustCrypto
+//! crates to their assembly implementations.
+
+#[cfg(feature = "hash")]
+use std::sync::OnceLock;
+
+#[cfg(feature = "hash")]
+use sha1::Sha1;
+#[cfg(feature = "hash")]
+use sha2::{Digest, Sha256};
+
+#[cfg(not(feature = "hash"))]
+use error::Error;
+use error::Result;
+
+/// An implementation of the hashes of torrents.
+pub trait HashBackend: Send + Sync {
+    fn sha1(&self, data: &[u8]) -> [u8; 20];
+    fn sha256(&self, data: &[u8]) -> [u8; 32];
+}
+
+/// The `sha-1` and `sha2` crates.
+#[cfg(feature = "hash")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RustCryptoBackend;
+
+#[cfg(feature = "hash")]
+impl HashBackend for RustCryptoBackend {
+    fn sha1(&self, data: &[u8]) -> [u8; 20] {
+        let mut hash = [0; 20];
+        hash.copy_from_slice(&Sha1::digest(data));
+        hash
+    }
+
+    fn sha256(&self, data: &[u8]) -> [u8; 32] {
+        let mut hash = [0; 32];
+        hash.copy_from_slice(&Sha256::digest(data));
+        hash
+    }
+}
+
+/// The `ring` crate (`ring` feature).
+#[cfg(feature = "ring")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RingBackend;
+
+#[cfg(feature = "ring")]
+impl HashBackend for RingBackend {
+    fn sha1(&self, data: &[u8]) -> [u8; 20] {
+        let mut hash = [0; 20];
+        hash.copy_from_slice(
+            ring::digest::digest(&ring::digest::SHA1_FOR_LEGACY_USE_ONLY, data).as_ref(),
+        );
+        hash
+    }
+
+    fn sha256(&self, data: &[u8]) -> [u8; 32] {
+        let mut hash = [0; 32];
+        hash.copy_from_slice(ring::digest::digest(&ring::digest::SHA256, data).as_ref());
+        hash
+    }
+}
+
+/// The system OpenSSL library (`openssl` feature).
+#[cfg(feature = "openssl")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpensslBackend;
+
+#[cfg(feature = "openssl")]
+impl HashBackend for OpensslBackend {
+    fn sha1(&self, data: &[u8]) -> [u8; 20] {
+        openssl::sha::sha1(data)
+    }
+
+    fn sha256(&self, data: &[u8]) -> [u8; 32] {
+        openssl::sha::sha256(data)
+    }
+}
+
+#[cfg(feature = "hash")]
+static BACKEND: OnceLock<Box<dyn HashBackend>> = OnceLock::new();
+
+/// Sets the hash backend of the process, returning false if it is already set or some
+/// hash was computed with the default backend.
+#[cfg(feature = "hash")]
+pub fn set_hash_backend(backend: Box<dyn HashBackend>) -> bool {
+    BACKEND.set(backend).is_ok()
+}
+
+#[cfg(feature = "hash")]
+pub(crate) fn backend() -> &'static dyn HashBackend {
+    &**BACKEND.get_or_init(|| {
+        #[cfg(feature = "openssl")]
+        return Box::new(OpensslBackend);
+        #[cfg(all(feature = "ring", not(feature = "openssl")))]
+        return Box::new(RingBackend);
+        #[cfg(not(any(feature = "ring", feature = "openssl")))]
+        Box::new(RustCryptoBackend)
+    })
+}
+
+/// Returns the SHA-1 hash of `data`, failing without the `hash` feature.
+#[cfg(feature = "hash")]
+pub fn sha1(data: &[u8]) -> Result<[u8; 20]> {
+    Ok(backend().sha1(data))
+}
+
+#[cfg(not(feature = "hash"))]
+pub fn sha1(_: &[u8]) -> Result<[u8; 20]> {
+    bail!(Error::Unsupported(
+        "SHA-1 hashes without the `hash` feature".to_owned()
+    ))
+}
+
+/// Returns the SHA-256 hash of `data`, failing without the `hash` feature.
+#[cfg(feature = "hash")]
+pub fn sha256(data: &[u8]) -> Result<[u8; 32]> {
+    Ok(backend().sha256(data))
+}
+
+#[cfg(not(feature = "hash"))]
+pub fn sha256(_: &[u8]) -> Result<[u8; 32]> {
+    bail!(Error::Unsupported(
+        "SHA-256 hashes without the `hash` feature".to_owned()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_hashes() {
+        use to_hex;
+
+        assert_eq!(
+            to_hex(&sha1(b"abc").unwrap()),
+            "a9993e364706816aba3e25717850c26c9cd0d89d"
+        );
+        assert_eq!(
+            to_hex(&sha256(b"abc").unwrap()),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+        assert!(!set_hash_backend(Box::new(RustCryptoBackend)));
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_backends() {
+        #[allow(unused_mut)]
+        let mut backends: Vec<Box<dyn HashBackend>> = vec![Box::new(RustCryptoBackend)];
+        #[cfg(feature = "ring")]
+        backends.push(Box::new(RingBackend));
+        #[cfg(feature = "openssl")]
+        backends.push(Box::new(OpensslBackend));
+        let data = vec![7; 100_000];
+        for backend in backends {
+            assert_eq!(backend.sha1(&data), sha1(&data).unwrap());
+            assert_eq!(backend.sha256(&data), sha256(&data).unwrap());
+        }
+    }
+
+    #[cfg(not(feature = "hash"))]
+    #[test]
+    pub fn test_unsupported() {
+        assert!(sha1(b"abc").is_err());
+        assert!(sha256(b"abc").is_err());
+    }
+}
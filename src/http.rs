@@ -0,0 +1,159 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! A minimal HTTP client for trackers and webseeds (`net` feature).
+
+use std::io::Read;
+use std::time::Duration;
+
+use ureq;
+
+use error::{Error, Result};
+use {redact_url, MAX_TORRENT_SIZE};
+
+/// The content types of error pages, which are never torrents.
+const PAGE_TYPES: &[&str] = &["text/html", "application/xhtml+xml", "application/json"];
+
+/// The timeout of HTTP requests.
+pub const TIMEOUT: Duration = Duration::from_secs(15);
+
+pub fn agent() -> ureq::Agent {
+    ureq::AgentBuilder::new()
+        .timeout(TIMEOUT)
+        .user_agent(concat!("torrentinfo/", env!("CARGO_PKG_VERSION")))
+        .build()
+}
+
+/// Converts errors, redacting secrets in the url.
+pub fn error(url: &str, e: ureq::Error) -> Error {
+    match e {
+        ureq::Error::Status(status, _) => {
+            Error::Http(format!("{} returned status {}", redact_url(url), status))
+        }
+        ureq::Error::Transport(e) => Error::Http(format!("{}: {}", redact_url(url), e.kind())),
+    }
+}
+
+/// Reads the body of a response of at most `limit` bytes.
+pub fn read_body(response: ureq::Response, limit: u64) -> Result<Vec<u8>> {
+    let mut buf = vec![];
+    response
+        .into_reader()
+        .take(limit + 1)
+        .read_to_end(&mut buf)?;
+    if buf.len() as u64 > limit {
+        bail!(Error::TooLarge(limit));
+    }
+
+    Ok(buf)
+}
+
+/// Fetches `url`, whose body must be at most `limit` bytes.
+pub fn get(url: &str, limit: u64) -> Result<Vec<u8>> {
+    let response = agent().get(url).call().map_err(|e| error(url, e))?;
+    read_body(response, limit)
+}
+
+/// Downloads the torrent file at an http or https `url` of at most `MAX_TORRENT_SIZE`
+/// bytes, failing for HTML and JSON responses like the login pages of private trackers.
+pub fn download_torrent(url: &str) -> Result<Vec<u8>> {
+    let lowercase = url.to_ascii_lowercase();
+    if !lowercase.starts_with("http://") && !lowercase.starts_with("https://") {
+        bail!(Error::Unsupported(format!(
+            "downloading torrents from '{}'",
+            redact_url(url)
+        )));
+    }
+
+    let response = agent()
+        .get(url)
+        .set("Accept", "application/x-bittorrent, */*;q=0.8")
+        .call()
+        .map_err(|e| error(url, e))?;
+    let content_type = response.content_type().to_ascii_lowercase();
+    if PAGE_TYPES.contains(&content_type.as_str()) {
+        bail!(Error::Http(format!(
+            "{} returned {} instead of a torrent",
+            redact_url(url),
+            content_type
+        )));
+    }
+    let length = response
+        .header("Content-Length")
+        .and_then(|l| l.parse::<u64>().ok());
+    if length.is_some_and(|length| length > MAX_TORRENT_SIZE) {
+        bail!(Error::TooLarge(MAX_TORRENT_SIZE));
+    }
+
+    read_body(response, MAX_TORRENT_SIZE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Runs a server answering every request with `response`.
+    fn server(response: &'static str) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}/t.torrent?passkey=secret", addr)
+    }
+
+    #[test]
+    pub fn test_download_torrent() {
+        let url = server(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/x-bittorrent\r\n\
+Content-Length: 2\r\n\r\nde",
+        );
+        assert_eq!(download_torrent(&url).unwrap(), b"de");
+
+        let url = server(
+            "HTTP/1.1 200 OK\r\nContent-Type: text/html; charset=utf-8\r\n\
+Content-Length: 6\r\n\r\n<html>",
+        );
+        match download_torrent(&url) {
+            Err(Error::Http(ref e)) => {
+                assert!(e.ends_with("returned text/html instead of a torrent"));
+                assert!(!e.contains("secret"));
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let url = server("HTTP/1.1 200 OK\r\nContent-Length: 100000000\r\n\r\n");
+        match download_torrent(&url) {
+            Err(Error::TooLarge(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        assert!(download_torrent("ftp://example.com/t.torrent").is_err());
+    }
+}
@@ -0,0 +1,177 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+use std::fmt;
+use std::str::FromStr;
+
+use error::{Error, Result};
+use to_hex;
+
+const BASE32_CHARS: &[u8] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+
+/// The 20 byte SHA-1 hash identifying a torrent.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct InfoHash([u8; 20]);
+
+impl InfoHash {
+    pub const LEN: usize = 20;
+
+    /// Creates an info hash from a 20 byte slice.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() != Self::LEN {
+            bail!(Error::InvalidInfoHash(to_hex(bytes)));
+        }
+        let mut hash = [0u8; 20];
+        hash.copy_from_slice(bytes);
+
+        Ok(InfoHash(hash))
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 20] {
+        &self.0
+    }
+
+    pub fn to_vec(&self) -> Vec<u8> {
+        self.0.to_vec()
+    }
+
+    /// Returns the lowercase hex representation.
+    pub fn to_hex(&self) -> String {
+        to_hex(&self.0)
+    }
+
+    /// Returns the RFC 4648 base32 representation, as used by older magnet links.
+    pub fn to_base32(&self) -> String {
+        let mut s = String::with_capacity(32);
+        for chunk in self.0.chunks(5) {
+            let mut buf = 0u64;
+            for &byte in chunk {
+                buf = (buf << 8) | u64::from(byte);
+            }
+            for i in (0..8).rev() {
+                s.push(BASE32_CHARS[((buf >> (i * 5)) & 0x1f) as usize] as char);
+            }
+        }
+
+        s
+    }
+
+    fn from_hex(s: &str) -> Option<Self> {
+        let mut hash = [0u8; 20];
+        for (i, pair) in s.as_bytes().chunks(2).enumerate() {
+            let hi = (pair[0] as char).to_digit(16)?;
+            let lo = (pair[1] as char).to_digit(16)?;
+            hash[i] = (hi << 4 | lo) as u8;
+        }
+
+        Some(InfoHash(hash))
+    }
+
+    fn from_base32(s: &str) -> Option<Self> {
+        let mut hash = [0u8; 20];
+        for (i, chunk) in s.as_bytes().chunks(8).enumerate() {
+            let mut buf = 0u64;
+            for &c in chunk {
+                let c = c.to_ascii_uppercase();
+                let value = BASE32_CHARS.iter().position(|&b| b == c)?;
+                buf = (buf << 5) | value as u64;
+            }
+            for j in 0..5 {
+                hash[i * 5 + j] = (buf >> ((4 - j) * 8)) as u8;
+            }
+        }
+
+        Some(InfoHash(hash))
+    }
+}
+
+impl FromStr for InfoHash {
+    type Err = Error;
+
+    /// Parses a hex (40 characters) or base32 (32 characters) encoded info hash.
+    fn from_str(s: &str) -> Result<Self> {
+        let hash = match s.len() {
+            40 if s.is_ascii() => InfoHash::from_hex(s),
+            32 if s.is_ascii() => InfoHash::from_base32(s),
+            _ => None,
+        };
+
+        hash.ok_or_else(|| Error::InvalidInfoHash(s.to_owned()))
+    }
+}
+
+impl fmt::Display for InfoHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(&self.to_hex())
+    }
+}
+
+impl fmt::Debug for InfoHash {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "InfoHash({})", self.to_hex())
+    }
+}
+
+impl From<[u8; 20]> for InfoHash {
+    fn from(hash: [u8; 20]) -> Self {
+        InfoHash(hash)
+    }
+}
+
+impl From<InfoHash> for [u8; 20] {
+    fn from(hash: InfoHash) -> Self {
+        hash.0
+    }
+}
+
+impl AsRef<[u8]> for InfoHash {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HEX: &str = "b43fd6d3a310c79acb7f4c915a12b9f6b9cc5bbf";
+
+    #[test]
+    pub fn test_hex_roundtrip() {
+        let hash: InfoHash = HEX.parse().unwrap();
+        assert_eq!(hash.to_string(), HEX);
+        assert_eq!(HEX.to_uppercase().parse::<InfoHash>().unwrap(), hash);
+    }
+
+    #[test]
+    pub fn test_base32_roundtrip() {
+        let hash: InfoHash = HEX.parse().unwrap();
+        let base32 = hash.to_base32();
+        assert_eq!(base32, "WQ75NU5DCDDZVS37JSIVUEVZ6244YW57");
+        assert_eq!(base32.parse::<InfoHash>().unwrap(), hash);
+        assert_eq!(base32.to_lowercase().parse::<InfoHash>().unwrap(), hash);
+    }
+
+    #[test]
+    pub fn test_invalid() {
+        assert!("abc".parse::<InfoHash>().is_err());
+        assert!(HEX.replace('b', "x").parse::<InfoHash>().is_err());
+        assert!("WQ75NU5DCDDZVS37JSIVUEVZ6247YW51".parse::<InfoHash>().is_err());
+        assert!(InfoHash::from_bytes(&[0u8; 19]).is_err());
+    }
+}
@@ -0,0 +1,265 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! A JSON-friendly view of torrents.
+
+use std::collections::BTreeMap;
+use std::result;
+use std::str;
+
+use serde::{Serialize, Serializer};
+use serde_bencode::value::Value;
+
+use {to_hex, File, FileMode, Torrent, TorrentVersion};
+
+/// A serializable view of a torrent for formats like JSON, created by `Torrent::json_view`.
+///
+/// Hashes and pieces are hex strings, names and paths are decoded with the `encoding` of the
+/// torrent and absent keys are omitted. Every file of the torrent is listed in `files`, also
+/// for single-file and v2 torrents.
+#[derive(Debug, Clone, Serialize)]
+pub struct TorrentJson<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    info_hash: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    info_hash_v2: Option<String>,
+    version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    announce: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    announce_list: Option<&'a [Vec<String>]>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    comment: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    created_by: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    creation_date: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    encoding: Option<&'a str>,
+    private: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    source: Option<&'a str>,
+    piece_length: u64,
+    num_pieces: usize,
+    total_size: u64,
+    files: Vec<FileJson>,
+    pieces: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    url_list: Vec<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    httpseeds: Option<&'a [String]>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    extra: BTreeMap<&'a str, ValueJson<'a>>,
+}
+
+/// A file of `TorrentJson`.
+#[derive(Debug, Clone, Serialize)]
+pub struct FileJson {
+    path: Vec<String>,
+    length: u64,
+    offset: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    md5sum: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attr: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sha1: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pieces_root: Option<String>,
+}
+
+/// Renders a bencode value, with byte strings as text if they are valid UTF-8 and as hex
+/// otherwise.
+#[derive(Debug, Clone)]
+pub struct ValueJson<'a>(&'a Value);
+
+impl<'a> Serialize for ValueJson<'a> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        match *self.0 {
+            Value::Int(n) => serializer.serialize_i64(n),
+            Value::Bytes(ref bytes) => match str::from_utf8(bytes) {
+                Ok(s) => serializer.serialize_str(s),
+                Err(_) => serializer.serialize_str(&to_hex(bytes)),
+            },
+            Value::List(ref list) => serializer.collect_seq(list.iter().map(ValueJson)),
+            Value::Dict(ref dict) => {
+                // Sort the keys, like bencode does
+                let dict: BTreeMap<_, _> = dict
+                    .iter()
+                    .map(|(k, v)| (String::from_utf8_lossy(k), ValueJson(v)))
+                    .collect();
+                serializer.collect_map(dict)
+            }
+        }
+    }
+}
+
+impl<'a> TorrentJson<'a> {
+    pub fn new(torrent: &'a Torrent) -> Self {
+        let info = torrent.info();
+        TorrentJson {
+            info_hash: torrent.info_hash().ok().map(|hash| hash.to_hex()),
+            info_hash_v2: torrent
+                .info_hash_v2()
+                .ok()
+                .and_then(|hash| hash)
+                .map(|hash| to_hex(&hash)),
+            version: match torrent.version() {
+                TorrentVersion::V1 => "v1",
+                TorrentVersion::V2 => "v2",
+                TorrentVersion::Hybrid => "hybrid",
+            },
+            name: torrent.decoded_name().map(|name| name.into_owned()),
+            announce: torrent.announce(),
+            announce_list: torrent.announce_list(),
+            comment: torrent
+                .decoded_comment()
+                .map(|comment| comment.into_owned()),
+            created_by: torrent.created_by(),
+            creation_date: torrent.creation_date(),
+            encoding: torrent.encoding(),
+            private: torrent.is_private(),
+            source: info.source(),
+            piece_length: info.piece_length(),
+            num_pieces: torrent.num_pieces(),
+            total_size: torrent.total_size(),
+            files: files(torrent),
+            pieces: info.pieces().iter().map(|hash| to_hex(hash)).collect(),
+            url_list: torrent.url_list(),
+            httpseeds: torrent.httpseeds(),
+            extra: torrent
+                .extra()
+                .iter()
+                .map(|(k, v)| (k.as_str(), ValueJson(v)))
+                .collect(),
+        }
+    }
+}
+
+fn files(torrent: &Torrent) -> Vec<FileJson> {
+    let info = torrent.info();
+    match info.mode() {
+        Some(FileMode::Single { length, md5sum }) => vec![FileJson {
+            path: torrent
+                .decoded_name()
+                .into_iter()
+                .map(|n| n.into_owned())
+                .collect(),
+            length: *length,
+            offset: 0,
+            md5sum: md5sum.clone(),
+            attr: None,
+            sha1: None,
+            pieces_root: None,
+        }],
+        Some(FileMode::Multi { files }) => files.iter().map(|f| file(torrent, f)).collect(),
+        None => {
+            let mut offset = 0;
+            info.file_tree()
+                .into_iter()
+                .map(|entry| {
+                    let file = FileJson {
+                        path: entry.path().to_vec(),
+                        length: entry.length(),
+                        offset,
+                        md5sum: None,
+                        attr: None,
+                        sha1: None,
+                        pieces_root: entry.pieces_root().map(to_hex),
+                    };
+                    offset += entry.length();
+                    file
+                })
+                .collect()
+        }
+    }
+}
+
+fn file(torrent: &Torrent, file: &File) -> FileJson {
+    let attributes = file.attributes();
+    FileJson {
+        path: torrent
+            .decoded_path(file)
+            .into_iter()
+            .map(|c| c.into_owned())
+            .collect(),
+        length: file.length(),
+        offset: file.offset(),
+        md5sum: file.md5sum().map(|s| s.to_owned()),
+        attr: if attributes.is_empty() {
+            None
+        } else {
+            Some(attributes.to_string())
+        },
+        sha1: file.sha1().map(|hash| to_hex(hash)),
+        pieces_root: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    #[allow(unused_imports)]
+    use super::*;
+
+    #[cfg(feature = "json")]
+    #[test]
+    pub fn test_to_json() {
+        let buf = &b"d8:announce3:url4:infod5:filesld4:attr1:x6:lengthi1e4:pathl1:ae\
+4:sha120:aaaaaaaaaaaaaaaaaaaaed6:lengthi2e4:pathl1:b1:ceee4:name1:d12:piece lengthi16384e\
+6:pieces20:bbbbbbbbbbbbbbbbbbbbe7:unknownli1e2:\xff\xfeee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let json = torrent.to_json().unwrap();
+        assert_eq!(
+            json,
+            format!(
+                "{{\"info_hash\":\"{}\",\"version\":\"v1\",\"name\":\"d\",\"announce\":\"url\",\
+\"private\":false,\"piece_length\":16384,\"num_pieces\":1,\"total_size\":3,\
+\"files\":[{{\"path\":[\"a\"],\"length\":1,\"offset\":0,\"attr\":\"x\",\"sha1\":\"{}\"}},\
+{{\"path\":[\"b\",\"c\"],\"length\":2,\"offset\":1}}],\"pieces\":[\"{}\"],\
+\"extra\":{{\"unknown\":[1,\"fffe\"]}}}}",
+                torrent.info_hash().unwrap(),
+                to_hex(&[b'a'; 20]),
+                to_hex(&[b'b'; 20])
+            )
+        );
+
+        let buf = &b"d4:infod6:lengthi5e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let json = Torrent::from_buf(buf).unwrap().to_json().unwrap();
+        assert!(json.contains("\"files\":[{\"path\":[\"a\"],\"length\":5,\"offset\":0}]"));
+    }
+
+    #[cfg(feature = "yaml")]
+    #[test]
+    pub fn test_to_yaml() {
+        let buf = &b"d8:announce3:url4:infod6:lengthi5e4:name1:a12:piece lengthi16384e\
+6:pieces20:bbbbbbbbbbbbbbbbbbbbee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            torrent.to_yaml().unwrap(),
+            format!(
+                "---\ninfo_hash: {}\nversion: v1\nname: a\nannounce: url\nprivate: false\n\
+piece_length: 16384\nnum_pieces: 1\ntotal_size: 5\nfiles:\n  - path:\n      - a\n    \
+length: 5\n    offset: 0\npieces:\n  - \"{}\"",
+                torrent.info_hash().unwrap(),
+                to_hex(&[b'b'; 20])
+            )
+        );
+    }
+}
@@ -16,32 +16,222 @@
  * along with this program.  If not, see <http://www.gnu.org/licenses/>
  */
 
+#[cfg(feature = "chrono")]
+extern crate chrono;
 extern crate serde;
 #[macro_use]
 extern crate serde_derive;
+#[cfg(feature = "fs")]
+extern crate crc32fast;
+#[cfg(feature = "encoding")]
+extern crate encoding_rs;
+#[cfg(feature = "gzip")]
+extern crate flate2;
+#[cfg(feature = "fs")]
+extern crate glob;
+#[cfg(feature = "wasm")]
+extern crate js_sys;
+#[cfg(feature = "fs")]
+extern crate md5;
+#[cfg(feature = "mmap")]
+extern crate memmap2;
+// The pyo3 macros refer to `::core`, which 2015 edition crates have to declare
+#[cfg(feature = "python")]
+extern crate core;
+#[cfg(feature = "openssl")]
+extern crate openssl;
+#[cfg(feature = "python")]
+extern crate pyo3;
+#[cfg(feature = "ring")]
+extern crate ring;
+#[cfg(feature = "signatures")]
+extern crate rsa;
+#[cfg(feature = "zstd")]
+extern crate ruzstd;
 extern crate serde_bencode;
 extern crate serde_bytes;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
+#[cfg(feature = "hash")]
 extern crate sha1;
-#[macro_use]
-extern crate error_chain;
+#[cfg(feature = "hash")]
+extern crate sha2;
+#[cfg(feature = "async")]
+extern crate tokio;
+#[cfg(feature = "normalization")]
+extern crate unicode_normalization;
+#[cfg(feature = "net")]
+extern crate ureq;
+#[cfg(feature = "wasm")]
+extern crate wasm_bindgen;
+
+/// Returns early with an error converted into `Error`.
+macro_rules! bail {
+    ($e:expr) => {
+        return Err(::std::convert::From::from($e))
+    };
+}
+
+use std::borrow::Cow;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::convert::TryFrom;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::io::{Read, Write};
+use std::ops::Range;
+use std::path::Path;
+#[cfg(feature = "async")]
+use std::path::PathBuf;
+use std::result;
+use std::slice;
+use std::str;
 
+#[cfg(feature = "chrono")]
+use chrono::{DateTime, TimeZone, Utc};
+use serde::de::{self as serde_de, Deserialize, Deserializer};
+use serde::{Serialize, Serializer};
+use serde_bencode::value::Value;
 use serde_bencode::{de, ser};
 use serde_bytes::ByteBuf;
-use sha1::{Digest, Sha1};
 
+#[cfg(all(feature = "async", feature = "fs"))]
+pub use asynchronous::verify_async;
+#[cfg(all(feature = "async", feature = "net"))]
+pub use asynchronous::{announce_async, get_peers_async, scrape_async};
+#[cfg(feature = "async")]
+pub use asynchronous::{Blocking, ReadTorrent};
+pub use bitfield::Bitfield;
+pub use borrowed::{FileRef, InfoRef, TorrentRef};
+#[cfg(feature = "fs")]
+pub use builder::{PieceLength, SymlinkPolicy, TorrentBuilder};
+pub use compression::decompress;
+pub use diff::{diff, FileChange, TorrentDiff};
+pub use document::BencodeDocument;
 pub use error::{Error, Result};
+pub use file_attr::FileAttributes;
+pub use file_tree::FileTreeEntry;
+#[cfg(feature = "fs")]
+pub use filter::find_torrents;
+pub use hash::HashBackend;
+#[cfg(feature = "openssl")]
+pub use hash::OpensslBackend;
+#[cfg(feature = "ring")]
+pub use hash::RingBackend;
+#[cfg(feature = "hash")]
+pub use hash::{set_hash_backend, RustCryptoBackend};
+#[cfg(feature = "net")]
+pub use http::download_torrent;
+pub use info_hash::InfoHash;
+pub use json::{FileJson, TorrentJson, ValueJson};
+pub use limits::Limits;
+pub use magnet::{Magnet, MagnetOptions, TrackerSelection};
+pub use manifest::ManifestEntry;
+#[cfg(feature = "normalization")]
+pub use normalize::Normalization;
+pub use piece_hashes::{PieceHashes, PIECE_HASH_LEN};
+pub use piece_map::FileSlice;
+pub use progress::{NoProgress, Progress};
+pub use redact::{contains_secrets, redact_url, REDACTED};
+#[cfg(feature = "fs")]
+pub use resume::libtorrent_resume;
+pub use rtorrent::{
+    rtorrent_session, RtorrentResume, RtorrentResumeFile, RtorrentSession, RtorrentState,
+};
+pub use sanitize::PathIssue;
+pub use signature::Signature;
+pub use stats::{SizeStats, TorrentStats};
+pub use tracker_url::TrackerUrl;
+pub use transmission::TransmissionResume;
+pub use utorrent::{parse_utorrent_resume, utorrent_resume, UtorrentResume, UtorrentTorrent};
+#[cfg(feature = "fs")]
+pub use verify::{
+    verify, verify_with, verify_with_reader, FailedPiece, FileRange, FileStatus, FsReader,
+    PieceReader, PieceStatus, Sampling, Verification, VerifyOptions,
+};
+pub use warning::{ParseWarning, Warning};
 
+#[cfg(feature = "async")]
+mod asynchronous;
+mod bencode;
+mod bitfield;
+mod borrowed;
+#[cfg(feature = "fs")]
+mod builder;
+mod client_state;
+mod compression;
+#[cfg(feature = "net")]
+pub mod dht;
+mod diff;
+mod document;
 pub mod error;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+mod file_attr;
+mod file_tree;
+#[cfg(feature = "fs")]
+mod filter;
+mod hash;
+#[cfg(feature = "net")]
+mod http;
+mod info_hash;
+mod json;
+mod limits;
+pub mod magnet;
+mod manifest;
+#[cfg(feature = "fs")]
+mod merkle;
+#[cfg(feature = "net")]
+pub mod metadata;
+#[cfg(feature = "normalization")]
+mod normalize;
+mod piece_hashes;
+mod piece_map;
+mod progress;
+#[cfg(feature = "python")]
+pub mod python;
+mod redact;
+#[cfg(feature = "fs")]
+mod resume;
+mod rtorrent;
+mod sanitize;
+#[cfg(feature = "fs")]
+mod sidecar;
+mod signature;
+mod stats;
+#[cfg(feature = "net")]
+pub mod tracker;
+mod tracker_url;
+mod transmission;
+#[cfg(feature = "net")]
+mod udp_tracker;
+mod utorrent;
+#[cfg(feature = "fs")]
+mod verify;
+mod warning;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "net")]
+pub mod webseed;
 
-#[derive(Debug, Default, Deserialize, Serialize)]
+/// The maximum size of torrent files read by `Torrent::from_file` and `Torrent::from_reader`.
+pub const MAX_TORRENT_SIZE: u64 = 64 * 1024 * 1024;
+
+/// A parsed torrent.
+///
+/// Torrents compare and hash by their info hash, so torrents differing only in the keys
+/// outside of the info dict, like trackers or comments, are equal.
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+#[non_exhaustive]
 pub struct Torrent {
     #[serde(default)]
     announce: Option<String>,
     #[serde(default)]
     #[serde(rename = "announce-list")]
-    announce_list: Option<Vec<String>>,
+    announce_list: Option<Vec<Vec<String>>>,
     #[serde(rename = "comment")]
-    comment: Option<String>,
+    comment: Option<ByteBuf>,
     #[serde(default)]
     #[serde(rename = "created by")]
     created_by: Option<String>,
@@ -52,164 +242,2511 @@ pub struct Torrent {
     encoding: Option<String>,
     info: Info,
     #[serde(default)]
-    nodes: Option<Vec<Node>>,
+    nodes: Option<Value>,
     #[serde(default)]
     httpseeds: Option<Vec<String>>,
+    #[serde(default)]
+    #[serde(rename = "url-list")]
+    url_list: Option<Value>,
+    #[serde(default)]
+    #[serde(rename = "piece layers")]
+    piece_layers: Option<Value>,
+    #[serde(default)]
+    signatures: Option<BTreeMap<String, Signature>>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
+    #[serde(skip)]
+    raw_info: Option<Vec<u8>>,
+    #[serde(skip)]
+    info_modified: bool,
+    /// The SHA-1 and SHA-256 info hashes, calculated while parsing without the pieces
+    #[serde(skip)]
+    info_hashes: Option<(InfoHash, [u8; 32])>,
+}
+
+impl PartialEq for Torrent {
+    fn eq(&self, other: &Self) -> bool {
+        self.identity() == other.identity()
+    }
+}
+
+impl Eq for Torrent {}
+
+impl Hash for Torrent {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.identity().hash(state);
+    }
+}
+
+impl TryFrom<Value> for Torrent {
+    type Error = Error;
+
+    /// Parses a torrent from a bencode value, whose dicts are encoded with sorted keys.
+    fn try_from(value: Value) -> Result<Self> {
+        Torrent::from_buf(&ser::to_bytes(&value)?)
+    }
+}
+
+/// The BitTorrent protocol version of a torrent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum TorrentVersion {
+    /// BitTorrent v1 (BEP 3)
+    #[default]
+    V1,
+    /// BitTorrent v2 (BEP 52)
+    V2,
+    /// Both v1 and v2 metadata, describing the same content
+    Hybrid,
 }
 
 impl Torrent {
+    /// Parses a torrent within the default `Limits`.
     pub fn from_buf(buf: &[u8]) -> Result<Self> {
-        de::from_bytes(buf).map_err(|e| e.into())
+        Torrent::from_buf_with_limits(buf, &Limits::default())
+    }
+
+    /// Parses a torrent, rejecting input which exceeds `limits` before decoding it.
+    pub fn from_buf_with_limits(buf: &[u8], limits: &Limits) -> Result<Self> {
+        limits.check(buf)?;
+        let mut torrent: Torrent = de::from_bytes(buf)?;
+        torrent.check_sizes()?;
+        torrent.raw_info = bencode::dict_value(buf, b"info").map(|info| info.to_vec());
+
+        Ok(torrent)
+    }
+
+    /// Parses a torrent like `from_buf`, also returning the oddities found in it.
+    pub fn from_buf_with_warnings(buf: &[u8]) -> Result<(Self, Vec<ParseWarning>)> {
+        let torrent = Torrent::from_buf(buf)?;
+        let mut warnings: Vec<ParseWarning> = bencode::duplicate_keys(buf)
+            .into_iter()
+            .map(ParseWarning::DuplicateKey)
+            .collect();
+
+        if let Some(date) = torrent.creation_date {
+            // 9999-12-31T23:59:59Z
+            if !(0..=253_402_300_799).contains(&date) {
+                warnings.push(ParseWarning::InvalidCreationDate(date));
+            }
+        }
+        let piece_length = torrent.info.piece_length;
+        if !piece_length.is_power_of_two() {
+            warnings.push(ParseWarning::PieceLengthNotPowerOfTwo(piece_length));
+        }
+        if let Some(Value::List(ref nodes)) = torrent.nodes {
+            warnings.extend(
+                nodes
+                    .iter()
+                    .enumerate()
+                    .filter(|&(_, node)| Node::from_value(node).is_none())
+                    .map(|(index, _)| ParseWarning::InvalidNode(index)),
+            );
+        }
+
+        let unknown_keys = torrent
+            .extra
+            .keys()
+            .cloned()
+            .chain(torrent.info.extra.keys().map(|key| format!("info.{}", key)))
+            .chain(
+                torrent
+                    .files()
+                    .unwrap_or_default()
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(index, file)| {
+                        file.extra
+                            .keys()
+                            .map(move |key| format!("info.files.{}.{}", index, key))
+                    }),
+            );
+        warnings.extend(unknown_keys.map(ParseWarning::UnknownKey));
+
+        Ok((torrent, warnings))
+    }
+
+    /// Parses a torrent without copying the `pieces` of the info dict, which make up most
+    /// of the size of large v1 torrents.
+    ///
+    /// Afterwards `Info::pieces` is empty and `Info::skipped_pieces` tells where the pieces
+    /// are in `buf`. With the `hash` feature the info hashes are calculated while parsing.
+    /// Torrents parsed this way can't be serialized again.
+    pub fn from_buf_skip_pieces(buf: &[u8]) -> Result<Self> {
+        Limits::default().check(buf)?;
+        let info = match bencode::dict_value(buf, b"info") {
+            Some(info) => info,
+            None => return Torrent::from_buf(buf),
+        };
+        let pieces = match bencode::dict_value(info, b"pieces") {
+            Some(pieces) => pieces,
+            None => return Torrent::from_buf(buf),
+        };
+        let content = match bencode::string_at(pieces, 0) {
+            Some((content, _)) => content,
+            None => bail!(Error::InvalidPieces),
+        };
+        let start = bencode::offset_of(buf, pieces);
+        let end = start + pieces.len();
+
+        let mut stripped = Vec::with_capacity(buf.len() - pieces.len() + 2);
+        stripped.extend_from_slice(&buf[..start]);
+        stripped.extend_from_slice(b"0:");
+        stripped.extend_from_slice(&buf[end..]);
+        let mut torrent: Torrent = de::from_bytes(&stripped)?;
+        torrent.check_sizes()?;
+
+        let offset = bencode::offset_of(buf, content);
+        torrent.info.skipped_pieces = Some(offset..offset + content.len());
+        #[cfg(feature = "hash")]
+        {
+            torrent.info_hashes = Some((InfoHash::from(hash::sha1(info)?), hash::sha256(info)?));
+        }
+
+        Ok(torrent)
+    }
+
+    /// Parses the torrent file at `path` from a memory map, without reading it into a
+    /// buffer first.
+    ///
+    /// The file must not be modified while it is parsed.
+    #[cfg(feature = "mmap")]
+    pub fn from_file_mmap<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        fs::File::open(path)
+            .and_then(|file| unsafe { memmap2::Mmap::map(&file) })
+            .map_err(Error::from)
+            .and_then(|map| Torrent::from_buf(&map))
+            .map_err(|e| Error::ReadTorrent {
+                path: path.display().to_string(),
+                source: Box::new(e),
+            })
+    }
+
+    /// Reads and parses a torrent of at most `MAX_TORRENT_SIZE` bytes.
+    ///
+    /// Gzip and zstd compressed torrents are decompressed with the `gzip` and `zstd`
+    /// features, see `decompress`.
+    pub fn from_reader<R: Read>(reader: R) -> Result<Self> {
+        let mut buf = vec![];
+        reader.take(MAX_TORRENT_SIZE + 1).read_to_end(&mut buf)?;
+        if buf.len() as u64 > MAX_TORRENT_SIZE {
+            bail!(Error::TooLarge(MAX_TORRENT_SIZE));
+        }
+
+        Torrent::from_buf(&decompress(buf, MAX_TORRENT_SIZE)?)
+    }
+
+    /// Reads and parses a torrent of at most `MAX_TORRENT_SIZE` bytes from an async
+    /// reader (`async` feature).
+    #[cfg(feature = "async")]
+    pub fn from_async_reader<R: tokio::io::AsyncRead + Unpin>(reader: R) -> ReadTorrent<R> {
+        ReadTorrent::new(reader)
+    }
+
+    /// Reads and parses the torrent file at `path` like `from_file` on the blocking thread
+    /// pool of tokio (`async` feature).
+    #[cfg(feature = "async")]
+    pub fn from_file_async<P: Into<PathBuf>>(path: P) -> Blocking<Self> {
+        let path = path.into();
+        Blocking::spawn(move || Torrent::from_file(path))
+    }
+
+    /// Reads and parses the torrent file at `path`, which must be at most
+    /// `MAX_TORRENT_SIZE` bytes.
+    pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        fs::File::open(path)
+            .map_err(Error::from)
+            .and_then(Torrent::from_reader)
+            .map_err(|e| Error::ReadTorrent {
+                path: path.display().to_string(),
+                source: Box::new(e),
+            })
+    }
+
+    /// Downloads and parses the torrent at an http or https `url`, which must be at most
+    /// `MAX_TORRENT_SIZE` bytes (`net` feature).
+    ///
+    /// HTML and JSON responses, like the login pages of private trackers, are rejected.
+    #[cfg(feature = "net")]
+    pub fn from_url(url: &str) -> Result<Self> {
+        download_torrent(url)
+            .and_then(|buf| decompress(buf, MAX_TORRENT_SIZE))
+            .and_then(|buf| Torrent::from_buf(&buf))
+            .map_err(|e| Error::ReadTorrent {
+                path: redact_url(url),
+                source: Box::new(e),
+            })
+    }
+
+    /// Serializes the torrent to bencode.
+    ///
+    /// The info dict of a parsed torrent is written as it appeared in the parsed file, so
+    /// the info hash doesn't change.
+    pub fn to_bytes(&self) -> Result<Vec<u8>> {
+        if self.info.skipped_pieces.is_some() {
+            bail!(Error::Unsupported(
+                "serializing torrents parsed without pieces".to_owned()
+            ));
+        }
+        let mut buf = ser::to_bytes(self)?;
+        if let Some(raw_info) = self.raw_info() {
+            if let Some(info) = bencode::dict_value(&buf, b"info") {
+                let start = bencode::offset_of(&buf, info);
+                let end = start + info.len();
+                buf.splice(start..end, raw_info.iter().cloned());
+            }
+        }
+
+        Ok(buf)
+    }
+
+    /// Serializes the torrent to canonical bencode, see `BencodeDocument::to_canonical_bytes`.
+    ///
+    /// Unlike `Torrent::to_bytes` this also re-encodes the info dict, which changes the info
+    /// hash if the parsed info dict was not canonical.
+    pub fn to_canonical_bytes(&self) -> Result<Vec<u8>> {
+        Ok(BencodeDocument::parse(&self.to_bytes()?)?.to_canonical_bytes())
+    }
+
+    /// Writes the bencoded torrent to `writer`.
+    pub fn write_to<W: Write>(&self, mut writer: W) -> Result<()> {
+        writer.write_all(&self.to_bytes()?)?;
+        Ok(())
+    }
+
+    /// Converts the torrent to a bencode value, to inspect or edit keys which are not
+    /// modeled by `Torrent`.
+    ///
+    /// Use `Torrent::try_from` to convert the value back.
+    pub fn to_value(&self) -> Result<Value> {
+        Ok(de::from_bytes(&self.to_bytes()?)?)
+    }
+
+    /// Returns a view of the torrent which serializes with hex-encoded hashes and pieces.
+    pub fn json_view(&self) -> TorrentJson<'_> {
+        TorrentJson::new(self)
+    }
+
+    /// Serializes the torrent to JSON, see `Torrent::json_view`.
+    #[cfg(feature = "json")]
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.json_view())?)
+    }
+
+    /// Serializes the torrent to indented JSON, see `Torrent::json_view`.
+    #[cfg(feature = "json")]
+    pub fn to_json_pretty(&self) -> Result<String> {
+        Ok(serde_json::to_string_pretty(&self.json_view())?)
+    }
+
+    /// Serializes the torrent to YAML, see `Torrent::json_view`.
+    #[cfg(feature = "yaml")]
+    pub fn to_yaml(&self) -> Result<String> {
+        Ok(serde_yaml::to_string(&self.json_view())?)
     }
 
-    pub fn files(&self) -> &Option<Vec<File>> {
-        &self.info.files
+    /// Returns the files of a multi-file torrent, see `Info::mode`.
+    pub fn files(&self) -> Option<&[File]> {
+        match self.info.mode {
+            Some(FileMode::Multi { ref files }) => Some(files),
+            _ => None,
+        }
     }
 
     pub fn num_files(&self) -> usize {
         match self.files() {
             Some(f) => f.len(),
+            None if self.version() == TorrentVersion::V2 => self.info.file_tree().len(),
             None => 1,
         }
     }
 
-    pub fn total_size(&self) -> i64 {
-        if self.files().is_none() {
-            return self.info.length.unwrap_or_default();
+    /// Returns the total size of the files.
+    ///
+    /// Parsing rejects torrents whose total size overflows, for edited torrents the sum
+    /// saturates at `u64::MAX`.
+    pub fn total_size(&self) -> u64 {
+        if self.version() == TorrentVersion::V2 {
+            return sum_lengths(self.info.file_tree().iter().map(|f| f.length()))
+                .unwrap_or(u64::MAX);
+        }
+        match self.info.mode {
+            Some(FileMode::Single { length, .. }) => length,
+            Some(FileMode::Multi { ref files }) => {
+                sum_lengths(files.iter().map(|f| f.length)).unwrap_or(u64::MAX)
+            }
+            None => 0,
         }
-        let mut total_size = 0;
+    }
 
+    /// Checks that the total sizes of the `files` and the `file tree` don't overflow.
+    fn check_sizes(&self) -> Result<()> {
         if let Some(files) = self.files() {
-            for file in files {
-                total_size += file.length;
+            sum_lengths(files.iter().map(|f| f.length)).ok_or(Error::SizeOverflow)?;
+        }
+        if let Some(ref tree) = self.info.file_tree {
+            let entries = file_tree::checked_entries(tree)?;
+            sum_lengths(entries.iter().map(|f| f.length())).ok_or(Error::SizeOverflow)?;
+        }
+
+        Ok(())
+    }
+
+    /// Returns the number of pieces the content is split into, based on the total size and
+    /// piece length.
+    ///
+    /// For v2 torrents every file starts a new piece.
+    pub fn num_pieces(&self) -> usize {
+        let piece_length = self.info.piece_length;
+        if piece_length == 0 {
+            return 0;
+        }
+        if self.version() == TorrentVersion::V2 {
+            return self
+                .info
+                .file_tree()
+                .iter()
+                .map(|f| f.length().div_ceil(piece_length) as usize)
+                .sum();
+        }
+
+        self.total_size().div_ceil(piece_length) as usize
+    }
+
+    /// Returns the file lengths and whether every file starts a new piece, the layout which
+    /// pieces are mapped to.
+    fn piece_layout(&self) -> (Vec<u64>, bool) {
+        match (self.version(), self.files()) {
+            (TorrentVersion::V2, _) => (
+                self.info.file_tree().iter().map(|f| f.length()).collect(),
+                true,
+            ),
+            (_, Some(files)) => (files.iter().map(|f| f.length).collect(), false),
+            (_, None) => (vec![self.total_size()], false),
+        }
+    }
+
+    /// Returns the content files with their offsets and checksums, leaving out pad files.
+    ///
+    /// The paths of multi-file torrents start with the torrent name, as the files are laid
+    /// out on disk.
+    pub fn manifest(&self) -> Vec<ManifestEntry> {
+        manifest::entries(self)
+    }
+
+    /// Returns statistics about the files, like their sizes by extension and directory.
+    pub fn stats(&self) -> TorrentStats {
+        stats::stats(self)
+    }
+
+    /// Creates an `.sfv` file with the CRC32 checksums of the content, reading the files
+    /// from the download directory `root` (`fs` feature).
+    #[cfg(feature = "fs")]
+    pub fn sfv<P: AsRef<Path>>(&self, root: P) -> Result<String> {
+        sidecar::sfv(self, root.as_ref())
+    }
+
+    /// Creates an `.md5` file which can be checked with `md5sum -c`, reading the files from
+    /// the download directory `root` if the torrent has no MD5 sums (`fs` feature).
+    #[cfg(feature = "fs")]
+    pub fn md5sums<P: AsRef<Path>>(&self, root: P) -> Result<String> {
+        sidecar::md5sums(self, root.as_ref())
+    }
+
+    /// Returns the byte ranges of the files covered by piece `index`, which are empty if
+    /// there is no such piece.
+    ///
+    /// The file indices refer to `files`, including pad files, or to the `file tree` of v2
+    /// torrents. Single-file torrents have the file index 0.
+    pub fn piece_files(&self, index: usize) -> Vec<FileSlice> {
+        let (lengths, aligned) = self.piece_layout();
+        piece_map::file_slices(&lengths, self.info.piece_length, aligned, index)
+    }
+
+    /// Returns the pieces covering file `file`, with file indices as in `piece_files`.
+    pub fn file_pieces(&self, file: usize) -> Range<usize> {
+        let (lengths, aligned) = self.piece_layout();
+        piece_map::file_pieces(&lengths, self.info.piece_length, aligned, file)
+    }
+
+    /// Returns which pieces are needed to download the files `files`, with file indices as
+    /// in `piece_files`.
+    pub fn pieces_for_files(&self, files: &[usize]) -> Bitfield {
+        let mut pieces = Bitfield::new(self.num_pieces());
+        for &file in files {
+            for index in self.file_pieces(file) {
+                if index < pieces.len() {
+                    pieces.set(index, true);
+                }
+            }
+        }
+
+        pieces
+    }
+
+    /// Checks the torrent for internal inconsistencies.
+    pub fn check(&self) -> Vec<Warning> {
+        let mut warnings = vec![];
+        if self.version() != TorrentVersion::V2 && !self.info.is_merkle() {
+            let len = match self.info.skipped_pieces {
+                Some(ref range) => range.len(),
+                None => self.info.pieces().as_bytes().len(),
+            };
+            if len % PIECE_HASH_LEN != 0 {
+                warnings.push(Warning::TruncatedPieces(len));
+            }
+            if len / PIECE_HASH_LEN != self.num_pieces() {
+                warnings.push(Warning::PieceCountMismatch {
+                    expected: self.num_pieces(),
+                    actual: len / PIECE_HASH_LEN,
+                });
+            }
+        }
+        if let Some(issue) = self.info.name().and_then(|n| sanitize::check_component(&n)) {
+            warnings.push(Warning::UnsafeName(issue));
+        }
+        for (file, f) in self.files().unwrap_or_default().iter().enumerate() {
+            if let Some(issue) = f.path_issue() {
+                warnings.push(Warning::UnsafePath { file, issue });
+            }
+        }
+
+        warnings
+    }
+
+    /// Returns the files without pad files, or for single-file torrents a file named after
+    /// the torrent.
+    ///
+    /// v2-only torrents have no `File`s, see `Info::file_tree` instead.
+    pub fn content_files(&self) -> Vec<Cow<'_, File>> {
+        match self.info.mode {
+            Some(FileMode::Multi { ref files }) => files
+                .iter()
+                .filter(|f| !f.is_padding())
+                .map(Cow::Borrowed)
+                .collect(),
+            Some(FileMode::Single { length, ref md5sum }) => {
+                let name = self.decoded_name().unwrap_or_default().into_owned();
+                let mut file = File::new(length, vec![name]);
+                file.md5sum = md5sum.clone();
+                vec![Cow::Owned(file)]
+            }
+            None => vec![],
+        }
+    }
+
+    /// Returns the total size without pad files.
+    pub fn content_size(&self) -> u64 {
+        match self.files() {
+            Some(_) => {
+                sum_lengths(self.content_files().iter().map(|f| f.length)).unwrap_or(u64::MAX)
+            }
+            None => self.total_size(),
+        }
+    }
+
+    /// Returns the parsed info dict bytes, unless the info dict was modified since.
+    fn raw_info(&self) -> Option<&[u8]> {
+        match self.raw_info {
+            Some(ref info) if !self.info_modified => Some(info),
+            _ => None,
+        }
+    }
+
+    /// Returns what torrents are compared by: the info hash, or without the `hash`
+    /// feature the info dict it is calculated from.
+    #[cfg(feature = "hash")]
+    fn identity(&self) -> Option<Vec<u8>> {
+        self.info_hash().ok().map(|info_hash| info_hash.to_vec())
+    }
+
+    #[cfg(not(feature = "hash"))]
+    fn identity(&self) -> Option<Vec<u8>> {
+        match self.raw_info() {
+            Some(info) => Some(info.to_vec()),
+            None => ser::to_bytes(&self.info).ok(),
+        }
+    }
+
+    /// Returns whether modifications through `info_mut` changed the info hash of a parsed
+    /// torrent.
+    ///
+    /// Changing the info hash turns the torrent into a different swarm.
+    pub fn info_hash_changed(&self) -> Result<bool> {
+        match self.raw_info {
+            Some(ref info) if self.info_modified => {
+                Ok(hash::sha1(info)? != hash::sha1(&ser::to_bytes(&self.info)?)?)
+            }
+            _ => Ok(false),
+        }
+    }
+
+    /// Calculates the SHA-1 hash of the bencoded info dict.
+    ///
+    /// The hash is taken over the `info` bytes as they appeared in the parsed file.
+    /// If those are unavailable, e.g. for a torrent that was not parsed from a buffer,
+    /// the info dict is serialized again instead.
+    ///
+    /// Fails with `Error::Unsupported` without the `hash` feature.
+    pub fn info_hash(&self) -> Result<InfoHash> {
+        let info_hash = match (self.raw_info(), self.info_hashes) {
+            (Some(info), _) => hash::sha1(info)?,
+            (None, Some((info_hash, _))) if !self.info_modified => return Ok(info_hash),
+            _ => hash::sha1(&ser::to_bytes(&self.info)?)?,
+        };
+
+        Ok(InfoHash::from(info_hash))
+    }
+
+    /// Calculates the SHA-256 hash of the info dict for v2 and hybrid torrents.
+    pub fn info_hash_v2(&self) -> Result<Option<[u8; 32]>> {
+        if self.version() == TorrentVersion::V1 {
+            return Ok(None);
+        }
+        let info_hash = match (self.raw_info(), self.info_hashes) {
+            (Some(info), _) => hash::sha256(info)?,
+            (None, Some((_, info_hash))) if !self.info_modified => info_hash,
+            _ => hash::sha256(&ser::to_bytes(&self.info)?)?,
+        };
+
+        Ok(Some(info_hash))
+    }
+
+    /// Returns the protocol version, based on the `meta version` and `pieces` of the info dict.
+    pub fn version(&self) -> TorrentVersion {
+        match (self.info.meta_version, &self.info.pieces) {
+            (Some(2), Some(_)) => TorrentVersion::Hybrid,
+            (Some(2), None) => TorrentVersion::V2,
+            _ => TorrentVersion::V1,
+        }
+    }
+
+    /// Returns the `piece layers` of a v2 torrent, mapping a file's `pieces root` to the
+    /// concatenated hashes of its pieces.
+    pub fn piece_layers(&self) -> HashMap<&[u8], &[u8]> {
+        let mut layers = HashMap::new();
+        if let Some(Value::Dict(ref dict)) = self.piece_layers {
+            for (root, layer) in dict {
+                if let Value::Bytes(ref layer) = *layer {
+                    layers.insert(root.as_slice(), layer.as_slice());
+                }
+            }
+        }
+
+        layers
+    }
+
+    /// Verifies the content of a merkle torrent (BEP 30) read from `reader` against the
+    /// root hash (`fs` feature).
+    #[cfg(feature = "fs")]
+    pub fn verify_merkle<R: Read>(&self, mut reader: R) -> Result<bool> {
+        let root_hash = match self.info.root_hash() {
+            Some(root_hash) if self.info.is_merkle() => root_hash,
+            _ => bail!(Error::Unsupported(
+                "verifying non-merkle torrents".to_owned()
+            )),
+        };
+        if self.info.piece_length == 0 {
+            bail!(Error::InvalidPieceLength(0));
+        }
+
+        let mut leaves = Vec::with_capacity(self.num_pieces());
+        // The piece length is untrusted, so the buffer grows with the data actually read
+        let mut piece = vec![];
+        loop {
+            piece.clear();
+            (&mut reader)
+                .take(self.info.piece_length)
+                .read_to_end(&mut piece)?;
+            if piece.is_empty() {
+                break;
+            }
+            leaves.push(merkle::sha1(&piece));
+        }
+
+        Ok(leaves.len() == self.num_pieces() && merkle::sha1_root(&leaves) == *root_hash)
+    }
+
+    /// Verifies piece `index` of a merkle torrent (BEP 30) with the hashes of its uncles in
+    /// the tree, ordered from the piece layer up as they are sent by peers (`fs` feature).
+    #[cfg(feature = "fs")]
+    pub fn verify_merkle_piece(&self, index: usize, data: &[u8], uncles: &[[u8; 20]]) -> bool {
+        let depth = self.num_pieces().next_power_of_two().trailing_zeros() as usize;
+        match self.info.root_hash() {
+            Some(root_hash) if index < self.num_pieces() && uncles.len() == depth => {
+                merkle::sha1_proof_root(index, merkle::sha1(data), uncles) == *root_hash
             }
+            _ => false,
         }
+    }
 
-        total_size
+    /// Returns the signatures of the info dict (BEP 35) by signer name.
+    pub fn signatures(&self) -> Option<&BTreeMap<String, Signature>> {
+        self.signatures.as_ref()
     }
 
-    pub fn info_hash(&self) -> Result<Vec<u8>> {
-        let info = ser::to_bytes(&self.info)?;
+    /// Returns whether the info dict is signed with `public_key` by any signer (BEP 35),
+    /// see `Signature::verify`.
+    #[cfg(feature = "signatures")]
+    pub fn verify_signature(&self, public_key: &[u8]) -> Result<bool> {
+        let signatures = match self.signatures {
+            Some(ref signatures) => signatures,
+            None => return Ok(false),
+        };
+        if self.info.skipped_pieces.is_some() {
+            bail!(Error::Unsupported(
+                "verifying torrents parsed without pieces".to_owned()
+            ));
+        }
+        let info = match self.raw_info() {
+            Some(info) => info.to_vec(),
+            None => ser::to_bytes(&self.info)?,
+        };
+        for signature in signatures.values() {
+            if signature.verify(&info, public_key)? {
+                return Ok(true);
+            }
+        }
 
-        let info_hash: Vec<u8> = Sha1::digest(&info).to_vec();
-        Ok(info_hash)
+        Ok(false)
     }
 
     pub fn info(&self) -> &Info {
         &self.info
     }
 
-    pub fn comment(&self) -> &Option<String> {
-        &self.comment
+    /// Returns the info dict for modification.
+    ///
+    /// Afterwards the info dict is serialized again instead of reusing the parsed bytes,
+    /// which may change the info hash, see `info_hash_changed`.
+    pub fn info_mut(&mut self) -> &mut Info {
+        self.info_modified = true;
+        &mut self.info
     }
 
-    pub fn announce(&self) -> &Option<String> {
-        &self.announce
+    /// Returns whether the torrent is private (BEP 27), see `Info::private`.
+    pub fn is_private(&self) -> bool {
+        self.info.private()
     }
 
-    pub fn announce_list(&self) -> &Option<Vec<String>> {
-        &self.announce_list
+    /// Returns the comment, replacing invalid UTF-8 sequences.
+    pub fn comment(&self) -> Option<Cow<'_, str>> {
+        self.comment
+            .as_ref()
+            .map(|comment| String::from_utf8_lossy(comment))
     }
 
-    pub fn created_by(&self) -> &Option<String> {
-        &self.created_by
+    /// Returns the comment as it is encoded in the torrent.
+    pub fn comment_bytes(&self) -> Option<&[u8]> {
+        self.comment.as_ref().map(|comment| &comment[..])
     }
 
-    pub fn creation_date(&self) -> &Option<i64> {
-        &self.creation_date
+    pub fn announce(&self) -> Option<&str> {
+        self.announce.as_deref()
     }
 
-    pub fn encoding(&self) -> &Option<String> {
-        &self.encoding
+    pub fn announce_list(&self) -> Option<&[Vec<String>]> {
+        self.announce_list.as_deref()
     }
-}
 
-#[derive(Debug, Deserialize, Serialize)]
-struct Node(String, i64);
+    /// Returns the tracker tiers as defined by BEP 12.
+    ///
+    /// If the torrent has no `announce-list`, the `announce` url forms the only tier.
+    /// Empty tiers are skipped.
+    pub fn tiers(&self) -> Vec<&[String]> {
+        match (&self.announce_list, &self.announce) {
+            (Some(list), _) if list.iter().any(|tier| !tier.is_empty()) => list
+                .iter()
+                .filter(|tier| !tier.is_empty())
+                .map(|tier| tier.as_slice())
+                .collect(),
+            (_, Some(announce)) => vec![slice::from_ref(announce)],
+            _ => vec![],
+        }
+    }
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-pub struct Info {
-    #[serde(default)]
-    files: Option<Vec<File>>,
-    #[serde(default)]
-    length: Option<i64>,
-    #[serde(default)]
-    md5sum: Option<String>,
-    name: Option<String>,
-    #[serde(default)]
-    path: Option<Vec<String>>,
-    #[serde(rename = "piece length")]
-    piece_length: i64,
-    pieces: ByteBuf,
-    #[serde(default)]
-    private: Option<u8>,
-    #[serde(default)]
-    #[serde(rename = "root hash")]
-    root_hash: Option<String>,
-}
+    /// Returns all tracker urls in tier order, without duplicates.
+    pub fn trackers(&self) -> Vec<&str> {
+        let mut trackers: Vec<&str> = Vec::new();
+        for url in self.tiers().into_iter().flat_map(|tier| tier.iter()) {
+            if !trackers.contains(&url.as_str()) {
+                trackers.push(url);
+            }
+        }
 
-impl Info {
-    pub fn name(&self) -> &Option<String> {
-        &self.name
+        trackers
     }
 
-    pub fn piece_length(&self) -> &i64 {
-        &self.piece_length
+    pub fn created_by(&self) -> Option<&str> {
+        self.created_by.as_deref()
     }
 
-    pub fn pieces(&self) -> &ByteBuf {
-        &self.pieces
+    pub fn creation_date(&self) -> Option<i64> {
+        self.creation_date
     }
 
-    pub fn private(&self) -> &Option<u8> {
-        &self.private
+    /// Returns the creation date, or `None` if it is missing or out of the range of
+    /// `DateTime`.
+    #[cfg(feature = "chrono")]
+    pub fn creation_datetime(&self) -> Option<DateTime<Utc>> {
+        self.creation_date
+            .and_then(|date| Utc.timestamp_opt(date, 0).single())
     }
-}
 
-#[derive(Debug, Default, Deserialize, Serialize)]
-pub struct File {
-    length: i64,
-    path: Vec<String>,
-    #[serde(default)]
-    md5sum: Option<String>,
-}
+    pub fn encoding(&self) -> Option<&str> {
+        self.encoding.as_deref()
+    }
 
-impl File {
-    pub fn new(length: i64, path: Vec<String>) -> Self {
-        Self {
-            length,
-            path,
-            ..Default::default()
+    /// Decodes a string of the torrent with its `encoding`, replacing invalid sequences.
+    ///
+    /// Legacy encodings like `cp1251` or `shift_jis` are only supported with the `encoding`
+    /// feature, otherwise strings are decoded as UTF-8.
+    pub fn decode<'a>(&self, bytes: &'a [u8]) -> Cow<'a, str> {
+        #[cfg(feature = "encoding")]
+        {
+            let encoding = self
+                .encoding
+                .as_ref()
+                .and_then(|label| encoding_rs::Encoding::for_label(label.trim().as_bytes()));
+            if let Some(encoding) = encoding {
+                return encoding.decode_without_bom_handling(bytes).0;
+            }
         }
+
+        String::from_utf8_lossy(bytes)
     }
 
-    pub fn length(&self) -> &i64 {
-        &self.length
+    /// Encodes `s` with the `encoding` of the torrent, `None` if it has characters the
+    /// encoding can't represent.
+    ///
+    /// Without the `encoding` feature, strings are encoded as UTF-8.
+    fn encode<'a>(&self, s: &'a str) -> Option<Cow<'a, [u8]>> {
+        #[cfg(feature = "encoding")]
+        {
+            let encoding = self
+                .encoding
+                .as_ref()
+                .and_then(|label| encoding_rs::Encoding::for_label(label.trim().as_bytes()));
+            if let Some(encoding) = encoding {
+                let (bytes, _, unmappable) = encoding.encode(s);
+                return if unmappable { None } else { Some(bytes) };
+            }
+        }
+
+        Some(Cow::Borrowed(s.as_bytes()))
     }
 
-    pub fn path(&self) -> &[String] {
-        &self.path
+    /// Returns the decoded name, preferring the UTF-8 `name.utf-8` key of some clients.
+    pub fn decoded_name(&self) -> Option<Cow<'_, str>> {
+        match self.info.extra.get("name.utf-8") {
+            Some(Value::Bytes(name)) => Some(String::from_utf8_lossy(name)),
+            _ => self.info.name_bytes().map(|name| self.decode(name)),
+        }
     }
-}
 
-const CHARS: &[u8] = b"0123456789abcdef";
+    /// Returns the decoded comment, preferring the UTF-8 `comment.utf-8` key of some
+    /// clients.
+    pub fn decoded_comment(&self) -> Option<Cow<'_, str>> {
+        match self.extra.get("comment.utf-8") {
+            Some(Value::Bytes(comment)) => Some(String::from_utf8_lossy(comment)),
+            _ => self.comment_bytes().map(|comment| self.decode(comment)),
+        }
+    }
 
-pub fn to_hex(bytes: &[u8]) -> String {
-    let mut v = Vec::with_capacity(bytes.len() * 2);
-    for &byte in bytes {
-        v.push(CHARS[(byte >> 4) as usize]);
-        v.push(CHARS[(byte & 0xf) as usize]);
+    /// Returns the decoded path components of `file`, preferring the UTF-8 `path.utf-8`
+    /// key of some clients.
+    pub fn decoded_path<'a>(&self, file: &'a File) -> Vec<Cow<'a, str>> {
+        match file.extra.get("path.utf-8") {
+            Some(Value::List(path)) => path
+                .iter()
+                .filter_map(|c| match c {
+                    Value::Bytes(c) => Some(String::from_utf8_lossy(c)),
+                    _ => None,
+                })
+                .collect(),
+            _ => file
+                .path_bytes()
+                .into_iter()
+                .map(|c| self.decode(c))
+                .collect(),
+        }
     }
 
-    unsafe { String::from_utf8_unchecked(v) }
-}
+    /// Returns the decoded target of `file` if it is a symlink (BEP 47).
+    pub fn decoded_symlink_path<'a>(&self, file: &'a File) -> Option<Vec<Cow<'a, str>>> {
+        file.symlink_path_bytes()
+            .map(|path| path.into_iter().map(|c| self.decode(c)).collect())
+    }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
+    pub fn set_announce(&mut self, url: Option<&str>) {
+        self.announce = url.map(str::to_owned);
+    }
 
-    #[test]
-    pub fn test_to_hex() {
-        assert_eq!(to_hex("foobar".as_bytes()), "666f6f626172");
+    /// Replaces the tracker tiers (BEP 12).
+    pub fn set_announce_list(&mut self, tiers: Option<Vec<Vec<String>>>) {
+        self.announce_list = tiers;
+    }
+
+    /// Adds `url` to the tracker tier with index `tier`, or to a new last tier if there is
+    /// no such tier, returning whether it was added.
+    ///
+    /// The `announce` url is set as well if it is missing. Urls which are already trackers
+    /// are not added again.
+    pub fn add_tracker(&mut self, tier: usize, url: &str) -> bool {
+        if self.trackers().contains(&url) {
+            return false;
+        }
+        let mut tiers: Vec<Vec<String>> = self.tiers().into_iter().map(|t| t.to_vec()).collect();
+        match tiers.get_mut(tier) {
+            Some(tier) => tier.push(url.to_owned()),
+            None => tiers.push(vec![url.to_owned()]),
+        }
+        if self.announce.is_none() {
+            self.announce = Some(url.to_owned());
+        }
+        self.announce_list = Some(tiers);
+        true
+    }
+
+    /// Removes `url` from all tracker tiers, returning whether it was present.
+    ///
+    /// If `url` is the `announce` url, it is replaced by the first remaining tracker.
+    pub fn remove_tracker(&mut self, url: &str) -> bool {
+        let mut removed = false;
+        if let Some(ref mut tiers) = self.announce_list {
+            for tier in tiers.iter_mut() {
+                let len = tier.len();
+                tier.retain(|u| u != url);
+                removed |= tier.len() != len;
+            }
+            tiers.retain(|tier| !tier.is_empty());
+        }
+        if self.announce_list.as_ref().is_some_and(|t| t.is_empty()) {
+            self.announce_list = None;
+        }
+        if self.announce.as_ref().is_some_and(|u| u == url) {
+            removed = true;
+            self.announce = self
+                .announce_list
+                .iter()
+                .flatten()
+                .flatten()
+                .next()
+                .cloned();
+        }
+
+        removed
+    }
+
+    /// Replaces the tracker `old` by `new` in all tiers and as `announce` url, returning
+    /// whether `old` was present.
+    ///
+    /// If `new` already was a tracker, only its first occurrence is kept.
+    pub fn replace_tracker(&mut self, old: &str, new: &str) -> bool {
+        let mut replaced = false;
+        let urls = self
+            .announce_list
+            .iter_mut()
+            .flatten()
+            .flatten()
+            .chain(self.announce.as_mut());
+        for url in urls.filter(|url| *url == old) {
+            *url = new.to_owned();
+            replaced = true;
+        }
+        if replaced {
+            self.dedupe_trackers();
+        }
+
+        replaced
+    }
+
+    /// Removes trackers which appear more than once, keeping the first occurrence in tier
+    /// order, and returns the number of removed urls.
+    ///
+    /// Urls are compared after normalizing them with `TrackerUrl`. Tiers which become empty
+    /// are removed. An `announce` url naming a tracker of the list is replaced by the url
+    /// in the list, so both spell it the same.
+    pub fn dedupe_trackers(&mut self) -> usize {
+        let normalize = |url: &String| match url.parse::<TrackerUrl>() {
+            Ok(url) => url.to_string(),
+            Err(_) => url.clone(),
+        };
+        let mut removed = 0;
+        if let Some(ref mut tiers) = self.announce_list {
+            let mut seen = HashSet::new();
+            for tier in tiers.iter_mut() {
+                let len = tier.len();
+                tier.retain(|url| seen.insert(normalize(url)));
+                removed += len - tier.len();
+            }
+            tiers.retain(|tier| !tier.is_empty());
+
+            if let Some(ref mut announce) = self.announce {
+                let normalized = normalize(announce);
+                let listed = tiers
+                    .iter()
+                    .flatten()
+                    .find(|url| normalize(url) == normalized);
+                if let Some(url) = listed {
+                    announce.clone_from(url);
+                }
+            }
+        }
+
+        removed
+    }
+
+    /// Removes the comment, `created by` and `creation date` and, with `remove_trackers`,
+    /// the `announce` url and `announce-list`, so a republished torrent doesn't reveal the
+    /// tools and trackers used to create it.
+    ///
+    /// All of these keys are outside of the info dict, so the info hash doesn't change.
+    /// Keys inside the info dict like `source` or `private` are kept, removing them would
+    /// make the torrent a different one.
+    pub fn anonymize(&mut self, remove_trackers: bool) {
+        self.comment = None;
+        self.extra.remove("comment.utf-8");
+        self.created_by = None;
+        self.creation_date = None;
+        if remove_trackers {
+            self.announce = None;
+            self.announce_list = None;
+        }
+    }
+
+    /// Masks the passkeys in the tracker and webseed urls with `redact_url`, returning
+    /// whether any url was changed.
+    ///
+    /// The info hash doesn't change, so the torrent can be shared without giving away the
+    /// passkeys.
+    pub fn redact_secrets(&mut self) -> bool {
+        let mut redacted = false;
+        {
+            let mut redact = |url: &mut String| {
+                let masked = redact_url(url);
+                if masked != *url {
+                    *url = masked;
+                    redacted = true;
+                }
+            };
+            let urls = self
+                .announce_list
+                .iter_mut()
+                .flatten()
+                .flatten()
+                .chain(self.httpseeds.iter_mut().flatten())
+                .chain(self.announce.as_mut());
+            for url in urls {
+                redact(url);
+            }
+        }
+
+        let webseeds = match self.url_list {
+            Some(Value::List(ref mut list)) => list.iter_mut().collect(),
+            Some(ref mut url) => vec![url],
+            None => vec![],
+        };
+        for url in webseeds {
+            if let Value::Bytes(ref mut bytes) = *url {
+                if let Ok(s) = str::from_utf8(bytes) {
+                    if contains_secrets(s) {
+                        *bytes = redact_url(s).into_bytes();
+                        redacted = true;
+                    }
+                }
+            }
+        }
+
+        redacted
+    }
+
+    pub fn set_comment(&mut self, comment: Option<&str>) {
+        self.comment = comment.map(|comment| ByteBuf::from(comment.as_bytes().to_vec()));
+    }
+
+    pub fn set_created_by(&mut self, created_by: Option<&str>) {
+        self.created_by = created_by.map(str::to_owned);
+    }
+
+    /// Sets the creation date as unix timestamp.
+    pub fn set_creation_date(&mut self, timestamp: Option<i64>) {
+        self.creation_date = timestamp;
+    }
+
+    /// Sets the name of the info dict, encoded with the `encoding` of the torrent.
+    ///
+    /// An existing `name.utf-8` key is updated as well, and one is added if the name can't
+    /// be encoded, so `decoded_name` returns `name` either way.
+    pub fn set_name(&mut self, name: &str) {
+        let encoded = self.encode(name).map(Cow::into_owned);
+        let info = self.info_mut();
+        info.set_name(name);
+        match encoded {
+            Some(encoded) => info.name = Some(ByteBuf::from(encoded)),
+            None => {
+                info.extra.insert(
+                    "name.utf-8".to_owned(),
+                    Value::Bytes(name.as_bytes().to_vec()),
+                );
+            }
+        }
+    }
+
+    /// Creates a `magnet:` link with the default `MagnetOptions`.
+    pub fn magnet_link(&self) -> Result<String> {
+        self.magnet_link_with(&MagnetOptions::default())
+    }
+
+    pub fn magnet_link_with(&self, options: &MagnetOptions) -> Result<String> {
+        magnet::magnet_link(self, options)
+    }
+
+    /// Returns the webseed urls from `url-list` (BEP 19), which may be a single url or a list.
+    ///
+    /// Empty urls and urls which are not valid UTF-8 are skipped.
+    pub fn url_list(&self) -> Vec<&str> {
+        let urls = match self.url_list {
+            Some(Value::Bytes(ref url)) => vec![url],
+            Some(Value::List(ref list)) => list
+                .iter()
+                .filter_map(|v| match v {
+                    Value::Bytes(url) => Some(url),
+                    _ => None,
+                })
+                .collect(),
+            _ => vec![],
+        };
+
+        urls.into_iter()
+            .filter_map(|url| str::from_utf8(url).ok())
+            .filter(|url| !url.is_empty())
+            .collect()
+    }
+
+    /// Returns the trackers in tier order as `TrackerUrl`s, skipping invalid urls and
+    /// duplicates.
+    pub fn tracker_urls(&self) -> Vec<TrackerUrl> {
+        let mut urls: Vec<TrackerUrl> = vec![];
+        for url in self.trackers() {
+            if let Ok(url) = url.parse() {
+                if !urls.contains(&url) {
+                    urls.push(url);
+                }
+            }
+        }
+
+        urls
+    }
+
+    /// Replaces the webseed urls (BEP 19), stored as a list.
+    pub fn set_url_list(&mut self, urls: Option<Vec<String>>) {
+        self.url_list = urls.map(|urls| {
+            Value::List(
+                urls.into_iter()
+                    .map(|url| Value::Bytes(url.into_bytes()))
+                    .collect(),
+            )
+        });
+    }
+
+    /// Returns the HTTP seeds (BEP 17).
+    pub fn httpseeds(&self) -> Option<&[String]> {
+        self.httpseeds.as_deref()
+    }
+
+    pub fn set_httpseeds(&mut self, urls: Option<Vec<String>>) {
+        self.httpseeds = urls;
+    }
+
+    /// Returns the DHT bootstrap nodes (BEP 5).
+    ///
+    /// Invalid nodes are skipped, see `ParseWarning::InvalidNode`.
+    pub fn nodes(&self) -> Vec<Node> {
+        match self.nodes {
+            Some(Value::List(ref nodes)) => nodes.iter().filter_map(Node::from_value).collect(),
+            _ => vec![],
+        }
+    }
+
+    /// Returns the keys of the torrent which are not modeled by this crate.
+    pub fn extra(&self) -> &BTreeMap<String, Value> {
+        &self.extra
+    }
+}
+
+/// A DHT node of a trackerless torrent (BEP 5).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Node {
+    host: String,
+    port: u16,
+}
+
+impl Node {
+    pub fn new(host: &str, port: u16) -> Self {
+        Node {
+            host: host.to_owned(),
+            port,
+        }
+    }
+
+    pub fn host(&self) -> &str {
+        &self.host
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Returns the node of a `[host, port]` list, `None` if it is invalid.
+    fn from_value(value: &Value) -> Option<Node> {
+        match value {
+            Value::List(items) => match items.as_slice() {
+                [Value::Bytes(host), Value::Int(port)] => Some(Node::new(
+                    str::from_utf8(host).ok()?,
+                    u16::try_from(*port).ok()?,
+                )),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Nodes are encoded as `[host, port]` lists.
+impl<'de> Deserialize<'de> for Node {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        // serde_bencode leaves the end of lists deserialized as tuples unread
+        let items = Vec::<Value>::deserialize(deserializer)?;
+        Node::from_value(&Value::List(items)).ok_or_else(|| serde_de::Error::custom("invalid node"))
+    }
+}
+
+impl Serialize for Node {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        (&self.host, i64::from(self.port)).serialize(serializer)
+    }
+}
+
+/// Whether a torrent contains a single file or a directory of files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum FileMode {
+    /// A single file named after the torrent
+    Single { length: u64, md5sum: Option<String> },
+    /// Files in a directory named after the torrent
+    Multi { files: Vec<File> },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub struct Info {
+    /// The files of v1 and hybrid torrents, `None` for v2 torrents
+    mode: Option<FileMode>,
+    name: Option<ByteBuf>,
+    path: Option<Vec<String>>,
+    piece_length: u64,
+    pieces: Option<ByteBuf>,
+    private: Option<Value>,
+    source: Option<String>,
+    similar: Option<Vec<ByteBuf>>,
+    collections: Option<Vec<String>>,
+    root_hash: Option<ByteBuf>,
+    meta_version: Option<i64>,
+    file_tree: Option<Value>,
+    extra: BTreeMap<String, Value>,
+    skipped_pieces: Option<Range<usize>>,
+}
+
+/// The info dict as it is encoded, with the overlapping single and multi-file keys.
+#[derive(Deserialize)]
+struct RawInfo {
+    #[serde(default)]
+    files: Option<Vec<File>>,
+    #[serde(default)]
+    length: Option<u64>,
+    #[serde(default)]
+    md5sum: Option<String>,
+    name: Option<ByteBuf>,
+    #[serde(default)]
+    path: Option<Vec<String>>,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    #[serde(default)]
+    pieces: Option<ByteBuf>,
+    #[serde(default)]
+    private: Option<Value>,
+    #[serde(default)]
+    source: Option<String>,
+    #[serde(default)]
+    similar: Option<Vec<ByteBuf>>,
+    #[serde(default)]
+    collections: Option<Vec<String>>,
+    #[serde(default)]
+    #[serde(rename = "root hash")]
+    root_hash: Option<ByteBuf>,
+    #[serde(default)]
+    #[serde(rename = "meta version")]
+    meta_version: Option<i64>,
+    #[serde(default)]
+    #[serde(rename = "file tree")]
+    file_tree: Option<Value>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
+}
+
+/// The borrowed counterpart of `RawInfo` for serialization.
+#[derive(Serialize)]
+struct RawInfoRef<'a> {
+    files: Option<&'a Vec<File>>,
+    length: Option<u64>,
+    md5sum: Option<&'a String>,
+    name: &'a Option<ByteBuf>,
+    path: &'a Option<Vec<String>>,
+    #[serde(rename = "piece length")]
+    piece_length: u64,
+    pieces: &'a Option<ByteBuf>,
+    private: &'a Option<Value>,
+    source: &'a Option<String>,
+    similar: &'a Option<Vec<ByteBuf>>,
+    collections: &'a Option<Vec<String>>,
+    #[serde(rename = "root hash")]
+    root_hash: &'a Option<ByteBuf>,
+    #[serde(rename = "meta version")]
+    meta_version: Option<i64>,
+    #[serde(rename = "file tree")]
+    file_tree: &'a Option<Value>,
+    #[serde(flatten)]
+    extra: &'a BTreeMap<String, Value>,
+}
+
+impl From<RawInfo> for Info {
+    fn from(raw: RawInfo) -> Self {
+        let mut extra = raw.extra;
+        let mode = match (raw.files, raw.length) {
+            (Some(files), length) => {
+                // Keep conflicting single-file keys, so they are written back
+                if let Some(length) = length {
+                    let length = i64::try_from(length).unwrap_or(i64::MAX);
+                    extra.insert("length".to_owned(), Value::Int(length));
+                }
+                if let Some(md5sum) = raw.md5sum {
+                    extra.insert("md5sum".to_owned(), Value::Bytes(md5sum.into_bytes()));
+                }
+                Some(FileMode::Multi { files })
+            }
+            (None, Some(length)) => Some(FileMode::Single {
+                length,
+                md5sum: raw.md5sum,
+            }),
+            (None, None) => {
+                if let Some(md5sum) = raw.md5sum {
+                    extra.insert("md5sum".to_owned(), Value::Bytes(md5sum.into_bytes()));
+                }
+                None
+            }
+        };
+
+        let mut info = Info {
+            mode,
+            name: raw.name,
+            path: raw.path,
+            piece_length: raw.piece_length,
+            pieces: raw.pieces,
+            private: raw.private,
+            source: raw.source,
+            similar: raw.similar,
+            collections: raw.collections,
+            root_hash: raw.root_hash,
+            meta_version: raw.meta_version,
+            file_tree: raw.file_tree,
+            extra,
+            skipped_pieces: None,
+        };
+        info.update_offsets();
+        info
+    }
+}
+
+impl<'de> Deserialize<'de> for Info {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> result::Result<Self, D::Error> {
+        RawInfo::deserialize(deserializer).map(Info::from)
+    }
+}
+
+impl Serialize for Info {
+    fn serialize<S: Serializer>(&self, serializer: S) -> result::Result<S::Ok, S::Error> {
+        let (files, length, md5sum) = match self.mode {
+            Some(FileMode::Single { length, ref md5sum }) => (None, Some(length), md5sum.as_ref()),
+            Some(FileMode::Multi { ref files }) => (Some(files), None, None),
+            None => (None, None, None),
+        };
+
+        RawInfoRef {
+            files,
+            length,
+            md5sum,
+            name: &self.name,
+            path: &self.path,
+            piece_length: self.piece_length,
+            pieces: &self.pieces,
+            private: &self.private,
+            source: &self.source,
+            similar: &self.similar,
+            collections: &self.collections,
+            root_hash: &self.root_hash,
+            meta_version: self.meta_version,
+            file_tree: &self.file_tree,
+            extra: &self.extra,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl Hash for Info {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        // Values contain `HashMap`s, so hash the bencode which has sorted keys
+        ser::to_bytes(self).unwrap_or_default().hash(state);
+    }
+}
+
+impl Info {
+    /// Returns the name, replacing invalid UTF-8 sequences.
+    pub fn name(&self) -> Option<Cow<'_, str>> {
+        self.name.as_ref().map(|name| String::from_utf8_lossy(name))
+    }
+
+    /// Returns the name as it is encoded in the torrent.
+    pub fn name_bytes(&self) -> Option<&[u8]> {
+        self.name.as_ref().map(|name| &name[..])
+    }
+
+    /// Returns whether the torrent contains a single file or multiple files, or `None` for
+    /// v2 torrents, which only have a `file tree`.
+    pub fn mode(&self) -> Option<&FileMode> {
+        self.mode.as_ref()
+    }
+
+    pub fn piece_length(&self) -> u64 {
+        self.piece_length
+    }
+
+    /// Returns the SHA-1 piece hashes, which are empty for v2 torrents and
+    /// torrents parsed with `Torrent::from_buf_skip_pieces`.
+    pub fn pieces(&self) -> PieceHashes<'_> {
+        match self.pieces {
+            Some(ref pieces) => PieceHashes::new(pieces),
+            None => PieceHashes::default(),
+        }
+    }
+
+    /// Sets the offsets of the files within the concatenated content.
+    pub(crate) fn update_offsets(&mut self) {
+        if let Some(FileMode::Multi { ref mut files }) = self.mode {
+            let mut offset = 0u64;
+            for file in files {
+                file.offset = offset;
+                offset = offset.saturating_add(file.length);
+            }
+        }
+    }
+
+    /// Returns the range of the pieces in the buffer the torrent was parsed from, if it was
+    /// parsed with `Torrent::from_buf_skip_pieces`.
+    pub fn skipped_pieces(&self) -> Option<Range<usize>> {
+        self.skipped_pieces.clone()
+    }
+
+    /// Returns the info hashes of torrents sharing files with this one (BEP 38), ignoring
+    /// malformed hashes.
+    pub fn similar(&self) -> Vec<InfoHash> {
+        self.similar
+            .iter()
+            .flatten()
+            .filter_map(|hash| InfoHash::from_bytes(hash).ok())
+            .collect()
+    }
+
+    /// Returns the names of the collections this torrent belongs to (BEP 38).
+    pub fn collections(&self) -> Option<&[String]> {
+        self.collections.as_deref()
+    }
+
+    /// Returns the root of the piece hash tree of a merkle torrent (BEP 30).
+    pub fn root_hash(&self) -> Option<&[u8; 20]> {
+        self.root_hash
+            .as_ref()
+            .and_then(|hash| <&[u8; 20]>::try_from(&hash[..]).ok())
+    }
+
+    /// Returns whether this is a merkle torrent (BEP 30), which has a `root hash` instead
+    /// of `pieces`.
+    pub fn is_merkle(&self) -> bool {
+        self.pieces.is_none() && self.root_hash().is_some()
+    }
+
+    pub fn meta_version(&self) -> Option<i64> {
+        self.meta_version
+    }
+
+    /// Returns the files of the v2 `file tree`.
+    pub fn file_tree(&self) -> Vec<FileTreeEntry> {
+        match self.file_tree {
+            Some(ref tree) => file_tree::entries(tree),
+            None => vec![],
+        }
+    }
+
+    /// Returns whether the torrent is private (BEP 27).
+    ///
+    /// Any non-zero integer counts as private, as do strings like `"1"` or `"true"` which
+    /// some clients write instead of an integer.
+    pub fn private(&self) -> bool {
+        match self.private {
+            Some(Value::Int(n)) => n != 0,
+            Some(Value::Bytes(ref bytes)) => bencode::string_flag(bytes),
+            _ => false,
+        }
+    }
+
+    /// Returns the `source` tag, which private trackers add to make the info hash unique.
+    pub fn source(&self) -> Option<&str> {
+        self.source.as_deref()
+    }
+
+    /// Sets the name as UTF-8, updating the `name.utf-8` key of some clients if present.
+    ///
+    /// See `Torrent::set_name` for torrents with a legacy `encoding`.
+    pub fn set_name(&mut self, name: &str) {
+        self.name = Some(ByteBuf::from(name.as_bytes().to_vec()));
+        if let Some(utf8) = self.extra.get_mut("name.utf-8") {
+            *utf8 = Value::Bytes(name.as_bytes().to_vec());
+        }
+    }
+
+    /// Sets the private flag (BEP 27), removing the key for public torrents.
+    pub fn set_private(&mut self, private: bool) {
+        self.private = if private { Some(Value::Int(1)) } else { None };
+    }
+
+    /// Sets or removes the `source` tag, which changes the info hash.
+    pub fn set_source(&mut self, source: Option<&str>) {
+        self.source = source.map(str::to_owned);
+    }
+
+    /// Returns the keys of the info dict which are not modeled by this crate.
+    pub fn extra(&self) -> &BTreeMap<String, Value> {
+        &self.extra
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct File {
+    length: u64,
+    path: Vec<ByteBuf>,
+    #[serde(default)]
+    md5sum: Option<String>,
+    #[serde(default)]
+    attr: Option<String>,
+    #[serde(default)]
+    #[serde(rename = "symlink path")]
+    symlink_path: Option<Vec<ByteBuf>>,
+    #[serde(default)]
+    sha1: Option<ByteBuf>,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
+    #[serde(skip)]
+    offset: u64,
+}
+
+impl Hash for File {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        ser::to_bytes(self).unwrap_or_default().hash(state);
+    }
+}
+
+impl File {
+    pub fn new(length: u64, path: Vec<String>) -> Self {
+        Self {
+            length,
+            path: path
+                .into_iter()
+                .map(|c| ByteBuf::from(c.into_bytes()))
+                .collect(),
+            ..Default::default()
+        }
+    }
+
+    /// Creates a BEP 47 pad file of `length` bytes.
+    #[cfg(feature = "fs")]
+    pub(crate) fn padding(length: u64) -> Self {
+        let mut file = File::new(length, vec![".pad".to_owned(), length.to_string()]);
+        file.attr = Some("p".to_owned());
+        file
+    }
+
+    /// Creates a BEP 47 symlink at `path`, pointing to `target` relative to the torrent root.
+    #[cfg(feature = "fs")]
+    pub(crate) fn symlink(path: Vec<String>, target: Vec<String>) -> Self {
+        let mut file = File::new(0, path);
+        file.attr = Some("l".to_owned());
+        file.symlink_path = Some(
+            target
+                .into_iter()
+                .map(|c| ByteBuf::from(c.into_bytes()))
+                .collect(),
+        );
+        file
+    }
+
+    pub fn length(&self) -> u64 {
+        self.length
+    }
+
+    pub fn md5sum(&self) -> Option<&str> {
+        self.md5sum.as_deref()
+    }
+
+    /// Returns the attributes of the file (BEP 47).
+    pub fn attributes(&self) -> FileAttributes {
+        self.attr
+            .as_ref()
+            .map_or_else(FileAttributes::default, |attr| FileAttributes::parse(attr))
+    }
+
+    /// Returns whether this is a pad file, marked by the `p` attribute (BEP 47) or named
+    /// `_____padding_file_...` like the pad files of older BitComet versions.
+    pub fn is_padding(&self) -> bool {
+        self.attributes().padding
+            || self
+                .path
+                .last()
+                .is_some_and(|name| name.starts_with(b"_____padding_file_"))
+    }
+
+    pub fn is_executable(&self) -> bool {
+        self.attributes().executable
+    }
+
+    pub fn is_hidden(&self) -> bool {
+        self.attributes().hidden
+    }
+
+    /// Returns the target of a symlink (BEP 47) relative to the torrent root, replacing
+    /// invalid UTF-8 sequences, or `None` if this is no symlink.
+    pub fn symlink_path(&self) -> Option<Vec<Cow<'_, str>>> {
+        self.symlink_path_bytes()
+            .map(|path| path.into_iter().map(String::from_utf8_lossy).collect())
+    }
+
+    /// Returns the target of a symlink as it is encoded in the torrent.
+    pub fn symlink_path_bytes(&self) -> Option<Vec<&[u8]>> {
+        match self.symlink_path {
+            Some(ref path) if self.attributes().symlink => {
+                Some(path.iter().map(|c| &c[..]).collect())
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the SHA-1 hash of the file content (BEP 47), if present and well-formed.
+    pub fn sha1(&self) -> Option<&[u8; 20]> {
+        self.sha1
+            .as_ref()
+            .and_then(|sha1| <&[u8; 20]>::try_from(&sha1[..]).ok())
+    }
+
+    /// Returns the path components, replacing invalid UTF-8 sequences.
+    pub fn path(&self) -> Vec<Cow<'_, str>> {
+        self.path
+            .iter()
+            .map(|c| String::from_utf8_lossy(c))
+            .collect()
+    }
+
+    /// Returns the path components as they are encoded in the torrent.
+    pub fn path_bytes(&self) -> Vec<&[u8]> {
+        self.path.iter().map(|c| &c[..]).collect()
+    }
+
+    /// Returns the first component of the path which is unsafe to use as a file name, like
+    /// `..` or an absolute path.
+    pub fn path_issue(&self) -> Option<PathIssue> {
+        sanitize::check_path(&self.path())
+    }
+
+    /// Returns the path with unsafe components dropped or escaped, so that it stays
+    /// within the download directory, or `_` if no component is left.
+    pub fn sanitized_path(&self) -> Vec<String> {
+        sanitize::sanitize_path(&self.path())
+    }
+
+    /// Returns the offset of the file within the concatenated content of the torrent,
+    /// including pad files.
+    pub fn offset(&self) -> u64 {
+        self.offset
+    }
+
+    /// Returns the keys of the file dict which are not modeled by this crate.
+    pub fn extra(&self) -> &BTreeMap<String, Value> {
+        &self.extra
+    }
+}
+
+/// Sums file lengths, returning `None` on overflow.
+fn sum_lengths<I: IntoIterator<Item = u64>>(lengths: I) -> Option<u64> {
+    lengths
+        .into_iter()
+        .try_fold(0u64, |sum, length| sum.checked_add(length))
+}
+
+const CHARS: &[u8] = b"0123456789abcdef";
+
+pub fn to_hex(bytes: &[u8]) -> String {
+    let mut v = Vec::with_capacity(bytes.len() * 2);
+    for &byte in bytes {
+        v.push(CHARS[(byte >> 4) as usize]);
+        v.push(CHARS[(byte & 0xf) as usize]);
+    }
+
+    unsafe { String::from_utf8_unchecked(v) }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_to_hex() {
+        assert_eq!(to_hex("foobar".as_bytes()), "666f6f626172");
+    }
+
+    #[test]
+    pub fn test_tiers() {
+        let torrent = Torrent {
+            announce: Some("http://a/announce".to_owned()),
+            announce_list: Some(vec![
+                vec!["http://a/announce".to_owned(), "http://b/announce".to_owned()],
+                vec![],
+                vec!["udp://c:80".to_owned(), "http://b/announce".to_owned()],
+            ]),
+            ..Default::default()
+        };
+        assert_eq!(torrent.tiers().len(), 2);
+        assert_eq!(torrent.tiers()[1], ["udp://c:80", "http://b/announce"]);
+        assert_eq!(
+            torrent.trackers(),
+            vec!["http://a/announce", "http://b/announce", "udp://c:80"]
+        );
+    }
+
+    #[test]
+    pub fn test_tiers_fallback() {
+        let torrent = Torrent {
+            announce: Some("http://a/announce".to_owned()),
+            ..Default::default()
+        };
+        assert_eq!(torrent.tiers(), vec![["http://a/announce".to_owned()]]);
+        assert_eq!(Torrent::default().trackers(), Vec::<&str>::new());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_info_hash_raw() {
+        let info = &b"d6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:6:source3:xyze"[..];
+        let mut buf = b"d8:announce9:http://a/4:info".to_vec();
+        buf.extend_from_slice(info);
+        buf.push(b'e');
+
+        let torrent = Torrent::from_buf(&buf).unwrap();
+        assert_eq!(torrent.info_hash().unwrap().to_vec(), hash::sha1(info).unwrap().to_vec());
+
+        let built = Torrent {
+            info: Info {
+                name: Some(ByteBuf::from(b"a".to_vec())),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let expected = hash::sha1(&ser::to_bytes(&built.info).unwrap())
+            .unwrap()
+            .to_vec();
+        assert_eq!(built.info_hash().unwrap().to_vec(), expected);
+    }
+
+    #[test]
+    pub fn test_check() {
+        let buf = &b"d4:infod6:lengthi20000e4:name1:a12:piece lengthi16384e\
+6:pieces40:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.num_pieces(), 2);
+        assert!(torrent.check().is_empty());
+
+        let buf = &b"d4:infod6:lengthi40000e4:name1:a12:piece lengthi16384e\
+6:pieces41:aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            torrent.check(),
+            vec![
+                Warning::TruncatedPieces(41),
+                Warning::PieceCountMismatch {
+                    expected: 3,
+                    actual: 2
+                }
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_unsafe_paths() {
+        let buf = &b"d4:infod5:filesld6:lengthi1e4:pathl2:..6:passwdeed6:lengthi1e4:pathl1:a1:bee\
+d6:lengthi1e4:pathl0:4:/tmpeee4:name1:.12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let files = torrent.files().unwrap();
+        assert_eq!(files[0].sanitized_path(), vec!["passwd"]);
+        assert_eq!(files[1].sanitized_path(), vec!["a", "b"]);
+        assert_eq!(files[2].sanitized_path(), vec!["_tmp"]);
+        let file = File::new(1, vec!["..".to_owned(), "".to_owned()]);
+        assert_eq!(file.sanitized_path(), vec!["_"]);
+        assert_eq!(
+            torrent.check()[1..].to_vec(),
+            vec![
+                Warning::UnsafeName(PathIssue::CurrentDir),
+                Warning::UnsafePath {
+                    file: 0,
+                    issue: PathIssue::ParentDir
+                },
+                Warning::UnsafePath {
+                    file: 2,
+                    issue: PathIssue::Empty
+                },
+            ]
+        );
+    }
+
+    #[test]
+    pub fn test_parse_warnings() {
+        let buf = &b"d13:creation datei1500000000000e1:xi1e1:xi2e4:infod5:filesld6:lengthi1e\
+4:pathl1:ae1:yi0eee4:name1:a12:piece lengthi1000e6:pieces0:1:zi0eee"[..];
+        let (torrent, warnings) = Torrent::from_buf_with_warnings(buf).unwrap();
+        assert_eq!(torrent.creation_date(), Some(1_500_000_000_000));
+        assert_eq!(
+            warnings,
+            vec![
+                ParseWarning::DuplicateKey("x".to_owned()),
+                ParseWarning::InvalidCreationDate(1_500_000_000_000),
+                ParseWarning::PieceLengthNotPowerOfTwo(1000),
+                ParseWarning::UnknownKey("x".to_owned()),
+                ParseWarning::UnknownKey("info.z".to_owned()),
+                ParseWarning::UnknownKey("info.files.0.y".to_owned()),
+            ]
+        );
+
+        let buf = &b"d4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        assert!(Torrent::from_buf_with_warnings(buf).unwrap().1.is_empty());
+    }
+
+    #[test]
+    pub fn test_errors() {
+        match Torrent::from_buf(b"d4:infod4:name1:aee") {
+            Err(Error::MissingField(ref field)) if field == "piece length" => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Torrent::from_buf(b"d4:infoi1e") {
+            Err(Error::InvalidBencode(10)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Torrent::from_buf_skip_pieces(b"d4:infod6:piecesi1eee") {
+            Err(Error::InvalidPieces) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Torrent::from_file("/nonexistent.torrent") {
+            Err(ref e @ Error::ReadTorrent { .. }) => {
+                let source = std::error::Error::source(e).unwrap();
+                assert!(source.downcast_ref::<Error>().is_some());
+            }
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    pub fn test_sizes() {
+        let negative = &b"d4:infod5:filesld6:lengthi-1e4:pathl1:aeee4:name1:d\
+12:piece lengthi16384e6:pieces0:ee"[..];
+        match Torrent::from_buf(negative) {
+            Err(Error::Decode(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let buf = &b"d4:infod6:lengthi-1e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        assert!(Torrent::from_buf(buf).is_err());
+
+        let overflow = &b"d4:infod5:filesld6:lengthi9223372036854775807e4:pathl1:aee\
+d6:lengthi9223372036854775807e4:pathl1:beed6:lengthi2e4:pathl1:ceee4:name1:d\
+12:piece lengthi16384e6:pieces0:ee"[..];
+        match Torrent::from_buf(overflow) {
+            Err(Error::SizeOverflow) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        match Torrent::from_buf_skip_pieces(overflow) {
+            Err(Error::SizeOverflow) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+        let torrent = TorrentRef::new(overflow).unwrap();
+        assert!(torrent.info().total_size().is_err());
+
+        let tree = &b"d4:infod9:file treed1:ad0:d6:lengthi-1eeee12:meta versioni2e4:name1:a\
+12:piece lengthi16384eee"[..];
+        match Torrent::from_buf(tree) {
+            Err(Error::Decode(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[cfg(feature = "chrono")]
+    #[test]
+    pub fn test_creation_datetime() {
+        let mut torrent = Torrent::default();
+        assert_eq!(torrent.creation_datetime(), None);
+        torrent.set_creation_date(Some(1_526_000_000));
+        assert_eq!(
+            torrent.creation_datetime().unwrap().to_rfc3339(),
+            "2018-05-11T00:53:20+00:00"
+        );
+        torrent.set_creation_date(Some(-86400));
+        assert_eq!(
+            torrent.creation_datetime().unwrap().to_rfc3339(),
+            "1969-12-31T00:00:00+00:00"
+        );
+        torrent.set_creation_date(Some(i64::MAX));
+        assert_eq!(torrent.creation_datetime(), None);
+    }
+
+    #[test]
+    pub fn test_eq_hash() {
+        use std::collections::HashSet;
+
+        let buf =
+            &b"d7:comment1:a4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let mut other = torrent.clone();
+        other.set_comment(Some("b"));
+        other.add_tracker(0, "http://a/");
+        assert_eq!(torrent, other);
+
+        let mut renamed = torrent.clone();
+        renamed.info_mut().set_name("b");
+        assert_ne!(torrent, renamed);
+        assert_ne!(torrent.info(), renamed.info());
+
+        let set: HashSet<Torrent> = vec![torrent.clone(), other, renamed].into_iter().collect();
+        assert_eq!(set.len(), 2);
+        let files: HashSet<File> = vec![File::new(1, vec!["a".to_owned()])]
+            .into_iter()
+            .collect();
+        assert!(files.contains(&File::new(1, vec!["a".to_owned()])));
+    }
+
+    #[test]
+    pub fn test_limits() {
+        let buf = &b"d4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let limits = Limits {
+            max_depth: 1,
+            ..Default::default()
+        };
+        assert!(Torrent::from_buf_with_limits(buf, &limits).is_err());
+        assert!(Torrent::from_buf(b"d4:info999999999999:").is_err());
+
+        let nested = format!("d1:x{}e", "l".repeat(1_000_000));
+        assert!(Torrent::from_buf(nested.as_bytes()).is_err());
+        assert!(Torrent::from_buf_skip_pieces(nested.as_bytes()).is_err());
+        assert!(TorrentRef::new(nested.as_bytes()).is_err());
+        assert!(BencodeDocument::parse(nested.as_bytes()).is_err());
+    }
+
+    #[test]
+    pub fn test_seeds_and_nodes() {
+        let buf = &b"d9:httpseedsl9:http://h/e4:infod6:lengthi1e4:name1:a12:piece lengthi16384e\
+6:pieces0:e5:nodesll4:host4:6881el5:otheri999999eel4:hosti6881eee8:url-list9:http://w/e"[..];
+        let (torrent, warnings) = Torrent::from_buf_with_warnings(buf).unwrap();
+        assert_eq!(torrent.nodes(), vec![Node::new("host", 6881)]);
+        assert_eq!(
+            warnings,
+            vec![ParseWarning::InvalidNode(0), ParseWarning::InvalidNode(1)]
+        );
+        assert_eq!(torrent.to_bytes().unwrap(), buf.to_vec());
+
+        let buf = &b"d9:httpseedsl9:http://h/e4:infod6:lengthi1e4:name1:a12:piece lengthi16384e\
+6:pieces0:e5:nodesll4:hosti6881eee8:url-list9:http://w/e"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.httpseeds(), Some(&["http://h/".to_owned()][..]));
+        assert_eq!(torrent.nodes(), vec![Node::new("host", 6881)]);
+        assert_eq!(torrent.url_list(), vec!["http://w/"]);
+        assert_eq!(torrent.to_bytes().unwrap(), buf.to_vec());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_file_mode() {
+        let buf = &b"d4:infod6:lengthi1e6:md5sum32:0123456789abcdef0123456789abcdef\
+4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            torrent.info().mode(),
+            Some(&FileMode::Single {
+                length: 1,
+                md5sum: Some("0123456789abcdef0123456789abcdef".to_owned())
+            })
+        );
+        assert!(torrent.files().is_none());
+        let files = torrent.content_files();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path(), ["a"]);
+        assert_eq!(files[0].length(), 1);
+        assert_eq!(files[0].md5sum(), Some("0123456789abcdef0123456789abcdef"));
+        assert_eq!(torrent.content_size(), 1);
+
+        // Both single and multi-file keys
+        let buf = &b"d4:infod5:filesld6:lengthi1e4:pathl1:aeee6:lengthi1e4:name1:d\
+12:piece lengthi16384e6:pieces0:ee"[..];
+        let mut torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.files().map(|f| f.len()), Some(1));
+        torrent.info_mut();
+        assert!(!torrent.info_hash_changed().unwrap());
+    }
+
+    #[test]
+    pub fn test_pieces_for_files() {
+        let mut torrent = Torrent::default();
+        torrent.info.piece_length = 16;
+        torrent.info.mode = Some(FileMode::Multi {
+            files: vec![
+                File::new(10, vec!["a".to_owned()]),
+                File::new(25, vec!["b".to_owned()]),
+                File::new(3, vec!["c".to_owned()]),
+            ],
+        });
+        assert_eq!(
+            torrent.piece_files(1),
+            vec![FileSlice {
+                file: 1,
+                offset: 6,
+                length: 16
+            }]
+        );
+        let bits = |files: &[usize]| torrent.pieces_for_files(files).iter().collect::<Vec<_>>();
+        assert_eq!(bits(&[0]), vec![true, false, false]);
+        assert_eq!(bits(&[2, 5]), vec![false, false, true]);
+        assert_eq!(bits(&[0, 1]), vec![true, true, true]);
+        assert_eq!(torrent.pieces_for_files(&[0]).to_hex(), "80");
+    }
+
+    #[test]
+    pub fn test_skip_pieces() {
+        let buf = &b"d8:announce9:http://a/4:infod6:lengthi1e4:name1:a12:piece lengthi16384e\
+6:pieces20:aaaaaaaaaaaaaaaaaaaa6:source3:xyzee"[..];
+        let full = Torrent::from_buf(buf).unwrap();
+        let torrent = Torrent::from_buf_skip_pieces(buf).unwrap();
+        assert!(torrent.info().pieces().is_empty());
+        let range = torrent.info().skipped_pieces().unwrap();
+        assert_eq!(&buf[range], full.info().pieces().as_bytes());
+        #[cfg(feature = "hash")]
+        assert_eq!(torrent.info_hash().unwrap(), full.info_hash().unwrap());
+        #[cfg(not(feature = "hash"))]
+        assert!(torrent.info_hash().is_err());
+        assert_eq!(torrent.version(), TorrentVersion::V1);
+        assert_eq!(torrent.info().extra(), full.info().extra());
+        assert!(torrent.to_bytes().is_err());
+    }
+
+    #[test]
+    pub fn test_from_reader() {
+        use std::io;
+
+        let buf = &b"d4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_reader(buf).unwrap();
+        assert_eq!(torrent.info().name().as_deref(), Some("a"));
+
+        let buf = &b"d4:infod5:filesld6:lengthi3e4:pathl1:aeed6:lengthi5e4:pathl1:beee\
+4:name1:d12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_reader(buf).unwrap();
+        let files = torrent.files().unwrap();
+        assert_eq!(files[0].offset(), 0);
+        assert_eq!(files[1].offset(), 3);
+
+        let large = io::repeat(b'd').take(MAX_TORRENT_SIZE + 1);
+        match Torrent::from_reader(large) {
+            Err(Error::TooLarge(_)) => {}
+            other => panic!("unexpected result {:?}", other),
+        }
+
+        let err = Torrent::from_file("/nonexistent/file.torrent").unwrap_err();
+        assert!(err.to_string().contains("/nonexistent/file.torrent"));
+    }
+
+    #[cfg(feature = "mmap")]
+    #[test]
+    pub fn test_from_file_mmap() {
+        let buf = &b"d4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let path = ::std::env::temp_dir().join("torrentinfo-test-mmap.torrent");
+        fs::write(&path, buf).unwrap();
+        let torrent = Torrent::from_file_mmap(&path).unwrap();
+        assert_eq!(
+            torrent.info_hash().unwrap(),
+            Torrent::from_buf(buf).unwrap().info_hash().unwrap()
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_to_bytes() {
+        // The info dict keys are out of order and must be kept that way
+        let buf = &b"d8:announce9:http://a/4:infod4:name1:a6:lengthi1e12:piece lengthi16384e\
+6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.to_bytes().unwrap(), buf.to_vec());
+
+        let mut out = vec![];
+        torrent.write_to(&mut out).unwrap();
+        let parsed = Torrent::from_buf(&out).unwrap();
+        assert_eq!(parsed.info_hash().unwrap(), torrent.info_hash().unwrap());
+
+        let built = Torrent {
+            info: Info {
+                name: Some(ByteBuf::from(b"a".to_vec())),
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let parsed = Torrent::from_buf(&built.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.info_hash().unwrap(), built.info_hash().unwrap());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_is_private() {
+        let parse = |private: &str| {
+            let buf = format!(
+                "d4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:{}ee",
+                private
+            );
+            Torrent::from_buf(buf.as_bytes()).unwrap()
+        };
+        assert!(!parse("").is_private());
+        assert!(parse("7:privatei1e").is_private());
+        assert!(parse("7:privatei2e").is_private());
+        assert!(!parse("7:privatei0e").is_private());
+        assert!(parse("7:private1:1").is_private());
+        assert!(parse("7:private4:true").is_private());
+        assert!(!parse("7:private1:0").is_private());
+        assert!(!parse("7:privatele").is_private());
+
+        let torrent = parse("7:private1:1");
+        let parsed = Torrent::from_buf(&torrent.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.info_hash().unwrap(), torrent.info_hash().unwrap());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_edit() {
+        let buf = &b"d8:announce9:http://a/4:infod6:lengthi1e4:name1:a12:piece lengthi16384e\
+6:pieces0:ee"[..];
+        let mut torrent = Torrent::from_buf(buf).unwrap();
+        let info_hash = torrent.info_hash().unwrap();
+
+        torrent.set_comment(Some("edited"));
+        torrent.add_tracker(0, "http://b/");
+        torrent.add_tracker(5, "http://c/");
+        assert_eq!(
+            torrent.tiers(),
+            vec![
+                &["http://a/".to_owned(), "http://b/".to_owned()][..],
+                &["http://c/".to_owned()][..]
+            ]
+        );
+        assert!(!torrent.add_tracker(1, "http://b/"));
+        assert!(torrent.remove_tracker("http://a/"));
+        assert!(!torrent.remove_tracker("http://a/"));
+        assert_eq!(torrent.announce(), Some("http://b/"));
+        assert_eq!(torrent.info_hash().unwrap(), info_hash);
+
+        // Without an announce list, removing the only tracker leaves none
+        let mut single = Torrent::from_buf(buf).unwrap();
+        assert!(single.remove_tracker("http://a/"));
+        assert_eq!(single.announce(), None);
+        assert!(single.trackers().is_empty());
+        assert!(!torrent.info_hash_changed().unwrap());
+
+        torrent.info_mut().set_name("a");
+        assert!(!torrent.info_hash_changed().unwrap());
+        torrent.info_mut().set_private(true);
+        torrent.info_mut().set_source(Some("TRK"));
+        assert!(torrent.info_hash_changed().unwrap());
+        assert_ne!(torrent.info_hash().unwrap(), info_hash);
+
+        torrent.set_url_list(Some(vec!["http://w/".to_owned()]));
+        torrent.set_httpseeds(Some(vec!["http://h/".to_owned()]));
+        let parsed = Torrent::from_buf(&torrent.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.url_list(), ["http://w/"]);
+        assert_eq!(parsed.httpseeds(), Some(&["http://h/".to_owned()][..]));
+        assert_eq!(parsed.comment().as_deref(), Some("edited"));
+        assert!(parsed.info().private());
+        assert_eq!(parsed.info().source(), Some("TRK"));
+        assert_eq!(parsed.info_hash().unwrap(), torrent.info_hash().unwrap());
+    }
+
+    #[test]
+    pub fn test_tracker_helpers() {
+        let mut torrent = Torrent::default();
+        torrent.set_announce(Some("http://a/"));
+        torrent.set_announce_list(Some(vec![
+            vec!["http://a/".to_owned(), "http://b/".to_owned()],
+            vec!["http://b/".to_owned()],
+            vec!["http://c/".to_owned(), "http://a/".to_owned()],
+        ]));
+        assert_eq!(torrent.dedupe_trackers(), 2);
+        assert_eq!(
+            torrent.trackers(),
+            vec!["http://a/", "http://b/", "http://c/"]
+        );
+        assert_eq!(torrent.tiers().len(), 2);
+        assert_eq!(torrent.dedupe_trackers(), 0);
+        torrent.add_tracker(1, "HTTP://B");
+        assert_eq!(torrent.tracker_urls().len(), 3);
+        assert_eq!(torrent.dedupe_trackers(), 1);
+
+        assert!(torrent.replace_tracker("http://a/", "http://c/"));
+        assert!(!torrent.replace_tracker("http://a/", "http://c/"));
+        assert_eq!(torrent.announce(), Some("http://c/"));
+        assert_eq!(
+            torrent.tiers(),
+            vec![&["http://c/".to_owned(), "http://b/".to_owned()][..]]
+        );
+
+        // Only `announce`
+        let mut torrent = Torrent::default();
+        torrent.set_announce(Some("http://a/"));
+        assert!(torrent.replace_tracker("http://a/", "http://b/"));
+        assert_eq!(torrent.trackers(), vec!["http://b/"]);
+        assert!(torrent.add_tracker(0, "http://c/"));
+        assert_eq!(
+            torrent.announce_list(),
+            Some(&[vec!["http://b/".to_owned(), "http://c/".to_owned()]][..])
+        );
+
+        // The announce url is spelled like its copy in the list
+        let mut torrent = Torrent::default();
+        torrent.set_announce(Some("HTTP://A:80/announce"));
+        torrent.set_announce_list(Some(vec![
+            vec!["http://b/".to_owned()],
+            vec![
+                "http://a/announce".to_owned(),
+                "http://a:80/announce".to_owned(),
+            ],
+        ]));
+        assert_eq!(torrent.dedupe_trackers(), 1);
+        assert_eq!(torrent.announce(), Some("http://a/announce"));
+        assert_eq!(torrent.trackers(), vec!["http://b/", "http://a/announce"]);
+        torrent.set_announce(Some("http://c/"));
+        assert_eq!(torrent.dedupe_trackers(), 0);
+        assert_eq!(torrent.announce(), Some("http://c/"));
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_anonymize() {
+        let buf = &b"d8:announce3:url7:comment1:c13:comment.utf-81:c10:created by4:tool\
+13:creation datei1e4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let mut torrent = Torrent::from_buf(buf).unwrap();
+        let info_hash = torrent.info_hash().unwrap();
+        torrent.anonymize(false);
+        assert_eq!(
+            torrent.to_bytes().unwrap(),
+            b"d8:announce3:url4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"
+                .to_vec()
+        );
+        torrent.anonymize(true);
+        assert!(torrent.trackers().is_empty());
+        assert_eq!(torrent.info_hash().unwrap(), info_hash);
+        assert!(!torrent.info_hash_changed().unwrap());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_redact_secrets() {
+        let buf = &b"d8:announce44:http://t/0123456789abcdef0123456789/announce\
+13:announce-listll44:http://t/0123456789abcdef0123456789/announce\
+16:udp://u/announceee4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:e\
+8:url-list22:http://w/?token=secrete"[..];
+        let mut torrent = Torrent::from_buf(buf).unwrap();
+        let info_hash = torrent.info_hash().unwrap();
+        assert!(torrent.redact_secrets());
+        assert_eq!(
+            torrent.trackers(),
+            vec!["http://t/REDACTED/announce", "udp://u/announce"]
+        );
+        assert_eq!(torrent.announce(), Some("http://t/REDACTED/announce"));
+        assert_eq!(torrent.url_list(), vec!["http://w/?token=REDACTED"]);
+        assert!(!torrent.redact_secrets());
+        assert_eq!(torrent.info_hash().unwrap(), info_hash);
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_canonical_bytes() {
+        // Already canonical, the info hash doesn't change
+        let buf = &b"d8:announce3:url4:infod6:lengthi1e4:name1:a12:piece lengthi16384e\
+6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.to_canonical_bytes().unwrap(), buf.to_vec());
+
+        // Unsorted keys and a leading zero
+        let buf = &b"d4:infod4:name1:a6:lengthi01e12:piece lengthi16384e6:pieces0:e\
+8:announce3:urle"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let canonical = torrent.to_canonical_bytes().unwrap();
+        assert_eq!(
+            canonical,
+            b"d8:announce3:url4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee"
+                .to_vec()
+        );
+        let reparsed = Torrent::from_buf(&canonical).unwrap();
+        assert_ne!(reparsed.info_hash().unwrap(), torrent.info_hash().unwrap());
+        assert_eq!(reparsed.to_canonical_bytes().unwrap(), canonical);
+        assert_eq!(reparsed.to_bytes().unwrap(), canonical);
+
+        // The hash of the canonical info dict is the one of the re-serialized info dict
+        let mut edited = torrent.clone();
+        edited.info_mut();
+        assert_eq!(edited.info_hash().unwrap(), reparsed.info_hash().unwrap());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_value() {
+        let buf = &b"d7:comment1:a4:infod6:lengthi1e4:name1:a12:piece lengthi16384e\
+6:pieces0:e7:unknowni1ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let mut value = torrent.to_value().unwrap();
+        if let Value::Dict(ref mut dict) = value {
+            dict.insert(b"unknown".to_vec(), Value::Int(2));
+            dict.remove(&b"comment"[..]);
+        }
+        let edited = Torrent::try_from(value).unwrap();
+        assert_eq!(edited.extra().get("unknown"), Some(&Value::Int(2)));
+        assert_eq!(edited.comment(), None);
+        assert_eq!(edited.info_hash().unwrap(), torrent.info_hash().unwrap());
+
+        assert!(Torrent::try_from(Value::Int(1)).is_err());
+    }
+
+    #[test]
+    pub fn test_extra_roundtrip() {
+        let buf = &b"d8:announce9:http://a/4:infod5:filesld6:lengthi1e4:pathl1:ae\
+4:sha14:abcdee4:name1:d12:piece lengthi16384e6:pieces0:6:source3:xyz7:unknowni1ee\
+8:url-listl9:http://w/ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.url_list(), vec!["http://w/"]);
+        assert_eq!(torrent.info().source(), Some("xyz"));
+        assert_eq!(torrent.info().extra().get("unknown"), Some(&Value::Int(1)));
+        let files = torrent.files().unwrap();
+        assert_eq!(files[0].sha1(), None);
+        assert!(files[0].extra().is_empty());
+        assert_eq!(ser::to_bytes(&torrent).unwrap(), buf);
+    }
+
+    #[test]
+    pub fn test_file_attributes() {
+        let buf =
+            &b"d4:infod5:filesld4:attr2:xh6:lengthi1e4:pathl1:ae4:sha120:aaaaaaaaaaaaaaaaaaaae\
+d4:attr1:p6:lengthi2e4:pathl4:.pad1:2eed4:attr1:l6:lengthi0e4:pathl1:be\
+12:symlink pathl1:aeee4:name1:d12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let files = torrent.files().unwrap();
+        assert!(files[0].is_executable() && files[0].is_hidden());
+        assert_eq!(files[0].sha1(), Some(&[b'a'; 20]));
+        assert_eq!(files[0].symlink_path(), None);
+        assert!(files[1].is_padding());
+        assert_eq!(files[1].attributes().to_string(), "p");
+        assert_eq!(torrent.content_size(), 1);
+        assert_eq!(files[2].symlink_path(), Some(vec!["a".into()]));
+        assert_eq!(
+            torrent.decoded_symlink_path(&files[2]),
+            Some(vec!["a".into()])
+        );
+        assert!(files[2].extra().is_empty());
+        assert_eq!(ser::to_bytes(&torrent).unwrap(), buf);
+
+        // Targets which aren't UTF-8 are decoded with the encoding of the torrent
+        let buf = &b"d8:encoding6:cp12514:infod5:filesld4:attr1:l6:lengthi0e4:pathl1:be\
+12:symlink pathl2:\xcf\xf0eee4:name1:d12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let files = torrent.files().unwrap();
+        assert_eq!(
+            files[0].symlink_path(),
+            Some(vec!["\u{fffd}\u{fffd}".into()])
+        );
+        #[cfg(feature = "encoding")]
+        assert_eq!(
+            torrent.decoded_symlink_path(&files[0]),
+            Some(vec!["Пр".into()])
+        );
+        assert_eq!(ser::to_bytes(&torrent).unwrap(), buf);
+
+        let name =
+            "_____padding_file_0_if you see this file, please update to BitComet 0.85 or above____";
+        assert!(File::new(100, vec![name.to_owned()]).is_padding());
+        assert!(!File::new(100, vec![name.to_owned(), "a".to_owned()]).is_padding());
+    }
+
+    #[cfg(feature = "fs")]
+    #[test]
+    pub fn test_merkle() {
+        let data: Vec<u8> = (0..40u8).collect();
+        let leaves: Vec<[u8; 20]> = data.chunks(16).map(merkle::sha1).collect();
+        let root = merkle::sha1_root(&leaves);
+        let mut buf = b"d4:infod6:lengthi40e4:name1:a12:piece lengthi16e9:root hash20:".to_vec();
+        buf.extend_from_slice(&root);
+        buf.extend_from_slice(b"ee");
+
+        let torrent = Torrent::from_buf(&buf).unwrap();
+        assert!(torrent.info().is_merkle());
+        assert_eq!(torrent.info().root_hash(), Some(&root));
+        assert!(torrent.check().is_empty());
+        assert!(torrent.verify_merkle(&data[..]).unwrap());
+        assert!(!torrent.verify_merkle(&data[..39]).unwrap());
+        assert!(!torrent.verify_merkle(&data[1..]).unwrap());
+
+        let uncles = [[0; 20], merkle::sha1_root(&leaves[..2])];
+        assert!(torrent.verify_merkle_piece(2, &data[32..], &uncles));
+        assert!(!torrent.verify_merkle_piece(2, &data[31..], &uncles));
+        assert!(!torrent.verify_merkle_piece(2, &data[32..], &uncles[..1]));
+        assert_eq!(ser::to_bytes(&torrent).unwrap(), buf);
+    }
+
+    #[test]
+    pub fn test_signatures() {
+        let mut buf = b"d4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:e\
+10:signaturesd6:signerd4:infod1:xi1ee9:signature128:"
+            .to_vec();
+        buf.extend(from_hex(
+            "02ca4f55f6a3f6f5435756ba7e797d0842c9b7066e34088780648aaa4ca35e0501a8d43a32a290c4\
+74106de4c2289ce948436e5ffaaef797d916c38df45db517f2e982f6da073c3be6f81e4f410f3403c2fcb553e3\
+fee1bd51452aa508a6883e232a01078f97c134ccb43bcc015ebd142c4a1fa9dab2b357ddc77248b8ff3cdd",
+        ));
+        buf.extend_from_slice(b"eee");
+
+        let torrent = Torrent::from_buf(&buf).unwrap();
+        let signature = &torrent.signatures().unwrap()["signer"];
+        assert_eq!(signature.signature().len(), 128);
+        assert_eq!(signature.certificate(), None);
+        assert_eq!(ser::to_bytes(&torrent).unwrap(), buf);
+
+        #[cfg(feature = "signatures")]
+        {
+            let public_key = from_hex(
+                "30819f300d06092a864886f70d010101050003818d0030818902818100a4cd98a99a741a0468b2\
+768b202fe5c4b82591283a25d9bfe94d5150941feb48fb5317222ba7f95bc254ff800c653b9c62c9b0ec1ab92b91\
+d2db7144c9384f14f0db163e5213e5be52daf387e9f0d9bfe9e67685a5b90b7de6395b4e989341e57683760cb661\
+98312c6d1cf838bd4e37a362b615e3dac38a026e6b675aea79ad0203010001",
+            );
+            assert!(torrent.verify_signature(&public_key).unwrap());
+            assert!(torrent.verify_signature(b"garbage").is_err());
+
+            let mut torrent = torrent;
+            torrent.info_mut().set_name("b");
+            assert!(!torrent.verify_signature(&public_key).unwrap());
+        }
+    }
+
+    fn from_hex(hex: &str) -> Vec<u8> {
+        (0..hex.len())
+            .step_by(2)
+            .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).unwrap())
+            .collect()
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_similar() {
+        let buf = &b"d4:infod11:collectionsl1:c1:de6:lengthi1e4:name1:a12:piece lengthi16384e\
+6:pieces0:7:similarl20:aaaaaaaaaaaaaaaaaaaa3:badeee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.info().similar(), vec![InfoHash::from([b'a'; 20])]);
+        assert_eq!(
+            torrent.info().collections(),
+            Some(&["c".to_owned(), "d".to_owned()][..])
+        );
+        torrent.info_hash().unwrap();
+        let mut edited = torrent.clone();
+        edited.info_mut();
+        assert!(!edited.info_hash_changed().unwrap());
+    }
+
+    #[test]
+    pub fn test_non_utf8_paths() {
+        // "файл" (file) and "папка" (folder) in CP1251
+        let buf = &b"d4:infod5:filesld6:lengthi1e4:pathl4:\xf4\xe0\xe9\xebeee\
+4:name5:\xef\xe0\xef\xea\xe012:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            torrent.info().name_bytes(),
+            Some(&b"\xef\xe0\xef\xea\xe0"[..])
+        );
+        assert_eq!(torrent.info().name().unwrap(), "\u{fffd}".repeat(5));
+        let file = &torrent.files().unwrap()[0];
+        assert_eq!(file.path_bytes(), vec![&b"\xf4\xe0\xe9\xeb"[..]]);
+        assert_eq!(file.path().join("/"), "\u{fffd}".repeat(4));
+        assert_eq!(torrent.to_bytes().unwrap(), buf);
+    }
+
+    #[test]
+    pub fn test_decode() {
+        let buf = &b"d7:comment2:\xef\xe013:comment.utf-84:\xd0\xbf\xd0\xb08:encoding6:cp1251\
+4:infod5:filesld6:lengthi1e4:pathl4:\xf4\xe0\xe9\xebeee4:name5:\xef\xe0\xef\xea\xe0\
+12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let file = &torrent.files().unwrap()[0];
+        assert_eq!(torrent.decoded_comment().unwrap(), "\u{43f}\u{430}");
+        #[cfg(feature = "encoding")]
+        {
+            assert_eq!(torrent.decoded_name().unwrap(), "папка");
+            assert_eq!(torrent.decoded_path(file), vec!["файл"]);
+        }
+        #[cfg(not(feature = "encoding"))]
+        assert_eq!(torrent.decoded_path(file), vec!["\u{fffd}".repeat(4)]);
+
+        let mut renamed = torrent.clone();
+        renamed.set_name("новая");
+        assert_eq!(renamed.decoded_name().unwrap(), "новая");
+        #[cfg(feature = "encoding")]
+        assert_eq!(
+            renamed.info().name_bytes(),
+            Some(&b"\xed\xee\xe2\xe0\xff"[..])
+        );
+        renamed.set_name("日本");
+        assert_eq!(renamed.decoded_name().unwrap(), "日本");
+
+        // A stale `name.utf-8` key is updated with the name
+        let buf = &b"d4:infod6:lengthi1e4:name3:old10:name.utf-83:old12:piece lengthi16384e\
+6:pieces0:ee"[..];
+        let mut torrent = Torrent::from_buf(buf).unwrap();
+        torrent.set_name("new");
+        assert_eq!(torrent.decoded_name().unwrap(), "new");
+        let parsed = Torrent::from_buf(&torrent.to_bytes().unwrap()).unwrap();
+        assert_eq!(parsed.decoded_name().unwrap(), "new");
+        assert_eq!(
+            parsed.info().extra()["name.utf-8"],
+            Value::Bytes(b"new".to_vec())
+        );
+    }
+
+    #[test]
+    pub fn test_parse_announce_list() {
+        let buf = b"d8:announce9:http://a/13:announce-listll9:http://a/9:http://b/el9:http://c/ee\
+4:infod6:lengthi1e4:name1:a12:piece lengthi16384e6:pieces0:ee";
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(torrent.trackers(), vec!["http://a/", "http://b/", "http://c/"]);
     }
 }
@@ -0,0 +1,185 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Resource limits for parsing untrusted bencode.
+
+use std::str;
+
+use error::{Error, Result};
+use MAX_TORRENT_SIZE;
+
+enum Container {
+    List,
+    Dict { key_next: bool },
+}
+
+/// Caps on the bencode accepted by the parsers, so crafted input can't cause huge
+/// allocations or exhaust the stack.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Limits {
+    /// The maximum size of the input in bytes
+    pub max_size: usize,
+    /// The maximum nesting depth of lists and dicts
+    pub max_depth: usize,
+    /// The maximum number of values, counting every list item and dict value
+    pub max_elements: usize,
+    /// The maximum length of a byte string
+    pub max_string_len: usize,
+}
+
+impl Default for Limits {
+    fn default() -> Self {
+        Limits {
+            max_size: MAX_TORRENT_SIZE as usize,
+            max_depth: 128,
+            max_elements: 2_000_000,
+            max_string_len: MAX_TORRENT_SIZE as usize,
+        }
+    }
+}
+
+impl Limits {
+    /// Checks that `buf` starts with a well-formed value within the limits, returning the
+    /// end offset of the value.
+    ///
+    /// Works without recursion, so it is safe to call before parsers which recurse.
+    pub fn check(&self, buf: &[u8]) -> Result<usize> {
+        if buf.len() > self.max_size {
+            bail!(Error::TooLarge(self.max_size as u64));
+        }
+
+        let mut stack = vec![];
+        let mut elements = 0usize;
+        let mut pos = 0;
+        loop {
+            let byte = *buf.get(pos).ok_or(Error::InvalidBencode(pos))?;
+            match stack.last_mut() {
+                Some(&mut Container::List) | Some(&mut Container::Dict { key_next: true })
+                    if byte == b'e' =>
+                {
+                    stack.pop();
+                    pos += 1;
+                }
+                Some(&mut Container::Dict { ref mut key_next }) if *key_next => {
+                    pos = self.string_end(buf, pos)?;
+                    *key_next = false;
+                    continue;
+                }
+                _ => {
+                    elements += 1;
+                    if elements > self.max_elements {
+                        bail!(Error::LimitExceeded {
+                            what: "element count".to_owned(),
+                            limit: self.max_elements,
+                        });
+                    }
+                    match byte {
+                        b'i' => {
+                            pos += buf[pos..]
+                                .iter()
+                                .position(|&b| b == b'e')
+                                .ok_or(Error::InvalidBencode(pos))?
+                                + 1;
+                        }
+                        b'0'..=b'9' => pos = self.string_end(buf, pos)?,
+                        b'l' | b'd' => {
+                            if stack.len() >= self.max_depth {
+                                bail!(Error::LimitExceeded {
+                                    what: "nesting depth".to_owned(),
+                                    limit: self.max_depth,
+                                });
+                            }
+                            stack.push(if byte == b'l' {
+                                Container::List
+                            } else {
+                                Container::Dict { key_next: true }
+                            });
+                            pos += 1;
+                            continue;
+                        }
+                        _ => bail!(Error::InvalidBencode(pos)),
+                    }
+                }
+            }
+
+            // A value is complete
+            match stack.last_mut() {
+                Some(&mut Container::Dict { ref mut key_next }) => *key_next = true,
+                Some(&mut Container::List) => {}
+                None => return Ok(pos),
+            }
+        }
+    }
+
+    /// Returns the end offset of the byte string at `pos`.
+    fn string_end(&self, buf: &[u8], pos: usize) -> Result<usize> {
+        let invalid = || Error::InvalidBencode(pos);
+        let colon = pos
+            + buf[pos..]
+                .iter()
+                .take(21)
+                .position(|&b| b == b':')
+                .ok_or_else(invalid)?;
+        let len: usize = str::from_utf8(&buf[pos..colon])
+            .ok()
+            .and_then(|len| len.parse().ok())
+            .ok_or_else(invalid)?;
+        if len > self.max_string_len {
+            bail!(Error::LimitExceeded {
+                what: "string length".to_owned(),
+                limit: self.max_string_len,
+            });
+        }
+        match (colon + 1).checked_add(len) {
+            Some(end) if end <= buf.len() => Ok(end),
+            _ => bail!(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_check() {
+        let limits = Limits::default();
+        assert_eq!(limits.check(b"d1:ali1ei2eee").unwrap(), 13);
+        assert_eq!(limits.check(b"i1etrailing").unwrap(), 3);
+        assert!(limits.check(b"d1:ae").is_err());
+        assert!(limits.check(b"d1:ai1e").is_err());
+        assert!(limits.check(b"li1e").is_err());
+        assert!(limits.check(b"99999999999:a").is_err());
+
+        let nested = format!("{}{}", "l".repeat(100_000), "e".repeat(100_000));
+        assert!(limits.check(nested.as_bytes()).is_err());
+
+        let limits = Limits {
+            max_size: 100,
+            max_depth: 2,
+            max_elements: 4,
+            max_string_len: 3,
+        };
+        assert!(limits.check(b"llee").is_ok());
+        assert!(limits.check(b"llleee").is_err());
+        assert!(limits.check(b"li1ei2ei3ee").is_ok());
+        assert!(limits.check(b"li1ei2ei3ei4ee").is_err());
+        assert!(limits.check(b"4:abcd").is_err());
+        assert!(limits.check(&[b'l'; 101]).is_err());
+    }
+}
@@ -0,0 +1,448 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+use std::borrow::Cow;
+use std::fmt;
+use std::str::{self, FromStr};
+
+use error::{Error, Result};
+use {to_hex, InfoHash, Torrent, TorrentVersion};
+
+/// Selects which trackers are added to a magnet link.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrackerSelection {
+    /// Don't add any trackers
+    None,
+    /// Add only the first tracker of each tier
+    FirstPerTier,
+    /// Add all trackers in tier order
+    All,
+}
+
+/// Controls which parts of a torrent end up in a magnet link.
+#[derive(Debug, Clone)]
+pub struct MagnetOptions {
+    /// Add the torrent name as `dn`
+    pub display_name: bool,
+    /// Trackers to add as `tr`
+    pub trackers: TrackerSelection,
+    /// Add the webseeds as `ws`
+    pub web_seeds: bool,
+}
+
+impl Default for MagnetOptions {
+    fn default() -> Self {
+        MagnetOptions {
+            display_name: true,
+            trackers: TrackerSelection::All,
+            web_seeds: true,
+        }
+    }
+}
+
+/// A parsed `magnet:` link.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Magnet {
+    info_hashes: Vec<InfoHash>,
+    info_hashes_v2: Vec<[u8; 32]>,
+    display_name: Option<String>,
+    trackers: Vec<String>,
+    web_seeds: Vec<String>,
+    peers: Vec<String>,
+}
+
+impl Magnet {
+    /// Creates the magnet link of a torrent, with the v1 info hash, the v2 info hash for
+    /// v2-only torrents, or both for hybrid torrents.
+    pub fn from_torrent(torrent: &Torrent, options: &MagnetOptions) -> Result<Self> {
+        let mut magnet = Magnet::default();
+        if torrent.version() != TorrentVersion::V2 {
+            magnet.info_hashes.push(torrent.info_hash()?);
+        }
+        magnet.info_hashes_v2.extend(torrent.info_hash_v2()?);
+
+        if options.display_name {
+            magnet.display_name = torrent.info().name().map(Cow::into_owned);
+        }
+
+        let trackers: Vec<&str> = match options.trackers {
+            TrackerSelection::None => vec![],
+            TrackerSelection::FirstPerTier => torrent
+                .tiers()
+                .iter()
+                .map(|tier| tier[0].as_str())
+                .collect(),
+            TrackerSelection::All => torrent.trackers(),
+        };
+        for tracker in trackers {
+            if !magnet.trackers.iter().any(|t| t == tracker) {
+                magnet.trackers.push(tracker.to_owned());
+            }
+        }
+
+        if options.web_seeds {
+            magnet.web_seeds = torrent.url_list().into_iter().map(str::to_owned).collect();
+        }
+
+        Ok(magnet)
+    }
+
+    /// Returns the first info hash of the link, or for v2-only links the v2 info hash
+    /// truncated to 20 bytes as used by trackers and the DHT (BEP 52).
+    pub fn info_hash(&self) -> InfoHash {
+        match (self.info_hashes.first(), self.info_hashes_v2.first()) {
+            (Some(info_hash), _) => *info_hash,
+            (None, Some(info_hash)) => InfoHash::from_bytes(&info_hash[..20]).unwrap(),
+            (None, None) => InfoHash::default(),
+        }
+    }
+
+    /// Returns all v1 info hashes (`urn:btih`) of the link, as a link may carry several
+    /// `xt` parameters.
+    pub fn info_hashes(&self) -> &[InfoHash] {
+        &self.info_hashes
+    }
+
+    /// Returns the SHA-256 v2 info hashes (`urn:btmh`) of the link.
+    pub fn info_hashes_v2(&self) -> &[[u8; 32]] {
+        &self.info_hashes_v2
+    }
+
+    pub fn display_name(&self) -> Option<&str> {
+        self.display_name.as_deref()
+    }
+
+    pub fn trackers(&self) -> &[String] {
+        &self.trackers
+    }
+
+    pub fn web_seeds(&self) -> &[String] {
+        &self.web_seeds
+    }
+
+    /// Returns the peer addresses of the `x.pe` parameters as `host:port`.
+    pub fn peers(&self) -> &[String] {
+        &self.peers
+    }
+}
+
+/// A link with just the info hash.
+impl From<InfoHash> for Magnet {
+    fn from(info_hash: InfoHash) -> Self {
+        Magnet {
+            info_hashes: vec![info_hash],
+            ..Default::default()
+        }
+    }
+}
+
+impl FromStr for Magnet {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::InvalidMagnet(s.to_owned());
+        if !s
+            .get(..8)
+            .is_some_and(|p| p.eq_ignore_ascii_case("magnet:?"))
+        {
+            return Err(invalid());
+        }
+
+        let mut magnet = Magnet::default();
+        for param in s[8..].split('&').filter(|p| !p.is_empty()) {
+            let (key, raw) = match param.find('=') {
+                Some(pos) => (&param[..pos], &param[pos + 1..]),
+                None => continue,
+            };
+            let value = percent_decode(raw);
+            // Keys may carry an index suffix, e.g. `tr.1`
+            let key = if key == "x.pe" {
+                key
+            } else {
+                key.split('.').next().unwrap_or(key)
+            };
+            match key {
+                "xt" if value.len() > 9
+                    && value
+                        .get(..9)
+                        .is_some_and(|p| p.eq_ignore_ascii_case("urn:btih:")) =>
+                {
+                    let info_hash = value[9..].parse().map_err(|_| invalid())?;
+                    if !magnet.info_hashes.contains(&info_hash) {
+                        magnet.info_hashes.push(info_hash);
+                    }
+                }
+                "xt" if value
+                    .get(..9)
+                    .is_some_and(|p| p.eq_ignore_ascii_case("urn:btmh:")) =>
+                {
+                    let info_hash = parse_multihash(&value[9..]).ok_or_else(invalid)?;
+                    if !magnet.info_hashes_v2.contains(&info_hash) {
+                        magnet.info_hashes_v2.push(info_hash);
+                    }
+                }
+                "dn" => magnet.display_name = Some(percent_decode(&raw.replace('+', " "))),
+                "tr" => magnet.trackers.push(value),
+                "ws" => magnet.web_seeds.push(value),
+                "x.pe" => magnet.peers.push(value),
+                _ => {}
+            }
+        }
+
+        if magnet.info_hashes.is_empty() && magnet.info_hashes_v2.is_empty() {
+            return Err(invalid());
+        }
+
+        Ok(magnet)
+    }
+}
+
+impl fmt::Display for Magnet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("magnet:?")?;
+        let v1 = self.info_hashes.iter().map(|h| format!("btih:{}", h));
+        let v2 = self
+            .info_hashes_v2
+            .iter()
+            .map(|h| format!("btmh:1220{}", to_hex(h)));
+        for (i, xt) in v1.chain(v2).enumerate() {
+            if i > 0 {
+                f.write_str("&")?;
+            }
+            write!(f, "xt=urn:{}", xt)?;
+        }
+        if let Some(ref name) = self.display_name {
+            write!(f, "&dn={}", percent_encode(name))?;
+        }
+        for tracker in &self.trackers {
+            write!(f, "&tr={}", percent_encode(tracker))?;
+        }
+        for url in &self.web_seeds {
+            write!(f, "&ws={}", percent_encode(url))?;
+        }
+        for peer in &self.peers {
+            write!(f, "&x.pe={}", percent_encode(peer))?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses the SHA-256 multihash of a `urn:btmh` info hash, `1220` followed by the hex
+/// digest.
+fn parse_multihash(s: &str) -> Option<[u8; 32]> {
+    let hex = s
+        .strip_prefix("1220")
+        .filter(|hex| hex.len() == 64 && hex.bytes().all(|b| b.is_ascii_hexdigit()))?;
+    let mut hash = [0u8; 32];
+    for (i, byte) in hash.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16).ok()?;
+    }
+
+    Some(hash)
+}
+
+pub fn magnet_link(torrent: &Torrent, options: &MagnetOptions) -> Result<String> {
+    Ok(Magnet::from_torrent(torrent, options)?.to_string())
+}
+
+/// Percent-encodes everything except the unreserved characters of RFC 3986.
+pub fn percent_encode(s: &str) -> String {
+    percent_encode_bytes(s.as_bytes())
+}
+
+/// Percent-encodes bytes like `percent_encode`, e.g. the raw info hash of tracker requests.
+pub fn percent_encode_bytes(bytes: &[u8]) -> String {
+    let mut encoded = String::with_capacity(bytes.len());
+    for &byte in bytes {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Decodes `%XX` sequences, leaving malformed ones untouched.
+pub fn percent_decode(s: &str) -> String {
+    let bytes = s.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%'
+            && i + 2 < bytes.len()
+            && bytes[i + 1..i + 3].iter().all(u8::is_ascii_hexdigit)
+        {
+            let hex = str::from_utf8(&bytes[i + 1..i + 3]).unwrap();
+            decoded.push(u8::from_str_radix(hex, 16).unwrap());
+            i += 3;
+            continue;
+        }
+        decoded.push(bytes[i]);
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(feature = "hash")]
+    const TORRENT: &[u8] = b"d8:announce9:http://a/13:announce-listll9:http://a/9:http://b/e\
+l9:http://c/ee4:infod6:lengthi1e4:name5:a b&c12:piece lengthi16384e6:pieces0:e\
+8:url-list9:http://w/e";
+
+    #[test]
+    pub fn test_percent_encode() {
+        assert_eq!(percent_encode("a b&c/ä"), "a%20b%26c%2F%C3%A4");
+    }
+
+    #[test]
+    pub fn test_percent_decode() {
+        assert_eq!(percent_decode("a%20b%26c%2F%C3%A4"), "a b&c/ä");
+        assert_eq!(percent_decode("100%"), "100%");
+        assert_eq!(percent_decode("%zz%4"), "%zz%4");
+        assert_eq!(percent_decode("%+1"), "%+1");
+    }
+
+    #[test]
+    pub fn test_parse() {
+        let magnet: Magnet = "magnet:?xt=urn:btih:b43fd6d3a310c79acb7f4c915a12b9f6b9cc5bbf\
+                              &xt=urn:btmh:1220caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e\
+                              &dn=some+name%2B%21&tr.1=udp%3A%2F%2Fa%3A80\
+                              &tr.2=http%3A%2F%2Fb%2F&ws=http%3A%2F%2Fw%2F&x.pe=1.2.3.4%3A5"
+            .parse()
+            .unwrap();
+        assert_eq!(
+            magnet.info_hash().to_string(),
+            "b43fd6d3a310c79acb7f4c915a12b9f6b9cc5bbf"
+        );
+        assert_eq!(magnet.info_hashes().len(), 1);
+        assert_eq!(
+            to_hex(&magnet.info_hashes_v2()[0]),
+            "caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e"
+        );
+        assert_eq!(magnet.display_name(), Some("some name+!"));
+        assert_eq!(magnet.trackers(), ["udp://a:80", "http://b/"]);
+        assert_eq!(magnet.web_seeds(), ["http://w/"]);
+        assert_eq!(magnet.peers(), ["1.2.3.4:5"]);
+
+        let base32: Magnet = "MAGNET:?xt=urn:btih:WQ75NU5DCDDZVS37JSIVUEVZ6244YW57"
+            .parse()
+            .unwrap();
+        assert_eq!(base32.info_hash(), magnet.info_hash());
+
+        let link = "magnet:?xt=urn:btmh:1220caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e";
+        let v2: Magnet = link.parse().unwrap();
+        assert!(v2.info_hashes().is_empty());
+        assert_eq!(v2.info_hashes_v2(), magnet.info_hashes_v2());
+        assert_eq!(
+            v2.info_hash().to_string(),
+            "caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa"
+        );
+        assert_eq!(v2.to_string(), link);
+    }
+
+    #[test]
+    pub fn test_parse_invalid() {
+        assert!("http://example.com".parse::<Magnet>().is_err());
+        assert!("magnet:?dn=foo".parse::<Magnet>().is_err());
+        assert!("magnet:?xt=urn:btih:abc".parse::<Magnet>().is_err());
+        assert!("magnét:?xt=urn:btih:abc".parse::<Magnet>().is_err());
+        assert!("magnet:?xt=urn:btihéabc".parse::<Magnet>().is_err());
+        assert!("magnet:?xt=urn:btmh:1220abcd".parse::<Magnet>().is_err());
+        assert!("magnet:?xt=urn:btmh:1114caf1e1c30e81cb361b9ee167c4aa64228a7fa4fa9f6105232b28ad099f3a302e"
+            .parse::<Magnet>()
+            .is_err());
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_roundtrip() {
+        let torrent = Torrent::from_buf(TORRENT).unwrap();
+        let link = magnet_link(&torrent, &MagnetOptions::default()).unwrap();
+        let magnet: Magnet = link.parse().unwrap();
+        assert_eq!(magnet.info_hash(), torrent.info_hash().unwrap());
+        assert_eq!(magnet.trackers(), torrent.trackers().as_slice());
+        assert_eq!(magnet.to_string(), link);
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_magnet_link() {
+        let torrent = Torrent::from_buf(TORRENT).unwrap();
+        let hash = torrent.info_hash().unwrap();
+        assert_eq!(
+            magnet_link(&torrent, &MagnetOptions::default()).unwrap(),
+            format!(
+                "magnet:?xt=urn:btih:{}&dn=a%20b%26c&tr=http%3A%2F%2Fa%2F\
+                 &tr=http%3A%2F%2Fb%2F&tr=http%3A%2F%2Fc%2F&ws=http%3A%2F%2Fw%2F",
+                hash
+            )
+        );
+
+        let options = MagnetOptions {
+            display_name: false,
+            trackers: TrackerSelection::FirstPerTier,
+            web_seeds: false,
+        };
+        assert_eq!(
+            magnet_link(&torrent, &options).unwrap(),
+            format!(
+                "magnet:?xt=urn:btih:{}&tr=http%3A%2F%2Fa%2F&tr=http%3A%2F%2Fc%2F",
+                hash
+            )
+        );
+    }
+
+    #[cfg(feature = "hash")]
+    #[test]
+    pub fn test_magnet_link_v2() {
+        let options = MagnetOptions::default();
+        let v2 = Torrent::from_buf(
+            b"d4:infod9:file treed1:ad0:d6:lengthi1eeee12:meta versioni2e4:name1:a\
+12:piece lengthi16384eee",
+        )
+        .unwrap();
+        let hash = to_hex(&v2.info_hash_v2().unwrap().unwrap());
+        let link = magnet_link(&v2, &options).unwrap();
+        assert_eq!(link, format!("magnet:?xt=urn:btmh:1220{}&dn=a", hash));
+        let magnet: Magnet = link.parse().unwrap();
+        assert!(magnet.info_hashes().is_empty());
+        assert_eq!(to_hex(&magnet.info_hashes_v2()[0]), hash);
+
+        let hybrid = Torrent::from_buf(
+            b"d4:infod9:file treed1:ad0:d6:lengthi1eeee6:lengthi1e12:meta versioni2e\
+4:name1:a12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+        )
+        .unwrap();
+        assert_eq!(
+            magnet_link(&hybrid, &options).unwrap(),
+            format!(
+                "magnet:?xt=urn:btih:{}&xt=urn:btmh:1220{}&dn=a",
+                hybrid.info_hash().unwrap(),
+                to_hex(&hybrid.info_hash_v2().unwrap().unwrap())
+            )
+        );
+    }
+}
@@ -18,32 +18,65 @@
 
 #[macro_use]
 extern crate clap;
-extern crate chrono;
+extern crate glob;
 extern crate number_prefix;
 extern crate serde;
 extern crate serde_bencode;
 extern crate serde_bytes;
+#[cfg(any(feature = "json", feature = "yaml"))]
+#[macro_use]
+extern crate serde_derive;
+#[cfg(feature = "json")]
+extern crate serde_json;
+#[cfg(feature = "yaml")]
+extern crate serde_yaml;
 extern crate torrentinfo;
 extern crate yansi;
 #[macro_use]
 extern crate lazy_static;
 
-use std::collections::HashMap;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
 use std::fs::File;
 use std::io::prelude::*;
+use std::io::IsTerminal;
+#[cfg(feature = "net")]
+use std::net::{IpAddr, SocketAddr};
 use std::path::Path;
 use std::process;
 
-use chrono::prelude::*;
-use clap::{App, AppSettings, Arg};
+use clap::{App, AppSettings, Arg, ArgMatches, SubCommand};
 use number_prefix::{binary_prefix, Prefixed, Standalone};
 use serde_bencode::value::Value;
 use yansi::{Paint, Style};
 
-use torrentinfo::Torrent;
+#[cfg(feature = "net")]
+use torrentinfo::dht::{self, LookupOptions};
+#[cfg(feature = "net")]
+use torrentinfo::metadata::{self, FetchOptions};
+#[cfg(feature = "net")]
+use torrentinfo::tracker::{self, AnnounceRequest};
+#[cfg(feature = "net")]
+use torrentinfo::webseed;
+use torrentinfo::{
+    decompress, find_torrents, verify_with, Error, Limits, Magnet, PieceLength, PieceStatus,
+    Progress, Torrent, TorrentBuilder, TorrentVersion, VerifyOptions, MAX_TORRENT_SIZE,
+};
+#[cfg(feature = "net")]
+use torrentinfo::{download_torrent, redact_url, InfoHash};
 
 const VERSION: &str = crate_version!();
 
+#[cfg(feature = "net")]
+const FILENAME_HELP: &str = "The .torrent files or glob patterns, http(s) urls or magnet links";
+#[cfg(not(feature = "net"))]
+const FILENAME_HELP: &str = "The .torrent files or glob patterns or magnet links";
+
+#[cfg(feature = "json")]
+const FORMATS: &[&str] = &["csv", "ndjson"];
+#[cfg(not(feature = "json"))]
+const FORMATS: &[&str] = &["csv"];
+
 lazy_static! {
     static ref S_NUMBER: Style = Style::cyan();
     static ref S_BYTES: Style = Style::red().bold();
@@ -69,6 +102,39 @@ fn main() {
                 .takes_value(false)
                 .conflicts_with_all(&["details", "everything"]),
         )
+        .arg(
+            Arg::with_name("tree")
+                .long("tree")
+                .help("Show the files as a directory tree with the size of every directory")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with("everything"),
+        )
+        .arg(
+            Arg::with_name("sort")
+                .long("sort")
+                .help("Sort the files by name, path or size, largest first")
+                .required(false)
+                .takes_value(true)
+                .possible_values(&["name", "path", "size"]),
+        )
+        .arg(
+            Arg::with_name("reverse")
+                .long("reverse")
+                .help("Reverse the order of the files")
+                .required(false)
+                .takes_value(false),
+        )
+        .arg(
+            Arg::with_name("match")
+                .long("match")
+                .value_name("PATTERN")
+                .help("Only list the files whose path matches the glob pattern")
+                .required(false)
+                .takes_value(true)
+                .multiple(true)
+                .number_of_values(1),
+        )
         .arg(
             Arg::with_name("details")
                 .short("d")
@@ -85,132 +151,1839 @@ fn main() {
                 .required(false)
                 .takes_value(false),
         )
+        .arg(
+            Arg::with_name("max-depth")
+                .long("max-depth")
+                .help("Maximum nesting depth printed by --everything")
+                .required(false)
+                .takes_value(true)
+                .default_value("32"),
+        )
+        .arg(
+            Arg::with_name("magnet")
+                .short("m")
+                .long("magnet")
+                .help("Print the magnet link of the torrent")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["files", "tree", "details", "everything"]),
+        )
+        .arg(
+            Arg::with_name("manifest")
+                .long("manifest")
+                .help("Print offset, size, MD5, SHA-1 and path of every file, tab-separated")
+                .required(false)
+                .takes_value(false)
+                .conflicts_with_all(&["files", "tree", "details", "everything", "magnet"]),
+        )
         .arg(
             Arg::with_name("nocolour")
                 .short("n")
                 .long("nocolour")
                 .help("No Colours")
                 .required(false)
+                .takes_value(false)
+                .global(true),
+        )
+        .arg(
+            Arg::with_name("format")
+                .long("format")
+                .help("Print one row per torrent in this format")
+                .required(false)
+                .takes_value(true)
+                .possible_values(FORMATS)
+                .conflicts_with_all(&[
+                    "files",
+                    "tree",
+                    "details",
+                    "everything",
+                    "magnet",
+                    "manifest",
+                ]),
+        )
+        .arg(
+            Arg::with_name("recursive")
+                .short("r")
+                .long("recursive")
+                .help("Read all .torrent files below this directory")
+                .required(false)
+                .takes_value(true)
+                .value_name("DIR")
+                .multiple(true)
+                .number_of_values(1),
+        )
+        .arg(
+            Arg::with_name("include")
+                .long("include")
+                .help("Only read the torrents below --recursive which match this glob pattern")
+                .required(false)
+                .takes_value(true)
+                .value_name("PATTERN")
+                .multiple(true)
+                .number_of_values(1)
+                .requires("recursive"),
+        )
+        .arg(
+            Arg::with_name("exclude")
+                .long("exclude")
+                .help("Skip the torrents below --recursive which match this glob pattern")
+                .required(false)
+                .takes_value(true)
+                .value_name("PATTERN")
+                .multiple(true)
+                .number_of_values(1)
+                .requires("recursive"),
+        )
+        .arg(
+            Arg::with_name("files-from")
+                .long("files-from")
+                .help("Read the torrents listed in this file, one per line, - for stdin")
+                .required(false)
+                .takes_value(true)
+                .value_name("FILE"),
+        )
+        .arg(
+            Arg::with_name("null")
+                .short("0")
+                .long("null")
+                .help("The --files-from list is NUL-separated, and read from stdin by default")
+                .required(false)
                 .takes_value(false),
         )
-        .arg(Arg::with_name("filename").required(true).takes_value(true));
+        .arg(
+            Arg::with_name("filename")
+                .help(FILENAME_HELP)
+                .required_unless_one(&["recursive", "files-from", "null"])
+                .takes_value(true)
+                .multiple(true),
+        );
 
-    let matches = app.get_matches();
+    #[cfg(feature = "json")]
+    let app = app.arg(
+        Arg::with_name("json")
+            .long("json")
+            .help("Print the torrent as JSON")
+            .required(false)
+            .takes_value(false)
+            .conflicts_with_all(&[
+                "files",
+                "tree",
+                "details",
+                "everything",
+                "magnet",
+                "manifest",
+            ]),
+    );
 
-    let show_files = matches.is_present("files");
-    let show_details = matches.is_present("details");
-    let show_everything = matches.is_present("everything");
-    let filename = matches.value_of("filename").unwrap();
+    #[cfg(feature = "yaml")]
+    let app = app.arg(
+        Arg::with_name("yaml")
+            .long("yaml")
+            .help("Print the torrent as YAML")
+            .required(false)
+            .takes_value(false)
+            .conflicts_with_all(&[
+                "files",
+                "tree",
+                "details",
+                "everything",
+                "magnet",
+                "manifest",
+                "json",
+            ]),
+    );
+
+    let app = app.setting(AppSettings::SubcommandsNegateReqs).subcommand(
+        SubCommand::with_name("create")
+            .about("Creates a torrent from a file or directory")
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .help("The .torrent file to write, <name>.torrent by default")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("announce")
+                    .short("a")
+                    .long("announce")
+                    .help("A tracker tier, with comma-separated urls, may be given multiple times")
+                    .takes_value(true)
+                    .value_name("URL")
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("piece-length")
+                    .short("l")
+                    .long("piece-length")
+                    .help("The piece length in bytes, with an optional K or M suffix, or auto")
+                    .takes_value(true)
+                    .default_value("auto"),
+            )
+            .arg(
+                Arg::with_name("private")
+                    .short("p")
+                    .long("private")
+                    .help("Mark the torrent private"),
+            )
+            .arg(
+                Arg::with_name("comment")
+                    .short("c")
+                    .long("comment")
+                    .help("The comment of the torrent")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("name")
+                    .long("name")
+                    .help("The name of the torrent, the file or directory name by default")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("source")
+                    .short("s")
+                    .long("source")
+                    .help("The source tag, which makes the info hash unique to a tracker")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("version")
+                    .long("version")
+                    .help("The BitTorrent protocol version")
+                    .takes_value(true)
+                    .possible_values(&["v1", "v2", "hybrid"])
+                    .default_value("v1"),
+            )
+            .arg(
+                Arg::with_name("exclude")
+                    .short("x")
+                    .long("exclude")
+                    .help("Leave out files matching this glob pattern")
+                    .takes_value(true)
+                    .value_name("PATTERN")
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .short("t")
+                    .long("threads")
+                    .help("The number of hashing threads, the number of CPUs by default")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("deterministic")
+                    .long("deterministic")
+                    .help("Leave out the creation date and created by"),
+            )
+            .arg(
+                Arg::with_name("force")
+                    .short("f")
+                    .long("force")
+                    .help("Overwrite the output file if it exists"),
+            )
+            .arg(
+                Arg::with_name("path")
+                    .help("The file or directory to create the torrent from")
+                    .required(true)
+                    .takes_value(true),
+            ),
+    );
+
+    let app = app.subcommand(
+        SubCommand::with_name("verify")
+            .about("Checks downloaded data against the torrent, failing unless it is complete")
+            .arg(
+                Arg::with_name("data")
+                    .short("d")
+                    .long("data")
+                    .help("The download directory, which contains the directory of multi-file torrents")
+                    .takes_value(true)
+                    .value_name("DIR")
+                    .default_value("."),
+            )
+            .arg(
+                Arg::with_name("threads")
+                    .short("t")
+                    .long("threads")
+                    .help("The number of hashing threads, the number of CPUs by default")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("v2")
+                    .long("v2")
+                    .help("Check hybrid torrents against the v2 hashes"),
+            )
+            .arg(
+                Arg::with_name("filename")
+                    .help("The .torrent file")
+                    .required(true)
+                    .takes_value(true),
+            ),
+    );
+
+    let app = app.subcommand(
+        SubCommand::with_name("edit")
+            .about("Changes the trackers, comment, private flag or name of a torrent")
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .help("The .torrent file to write, the edited file by default")
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("set-comment")
+                    .long("set-comment")
+                    .help("Set the comment, removing it if empty")
+                    .takes_value(true)
+                    .value_name("COMMENT"),
+            )
+            .arg(
+                Arg::with_name("set-announce")
+                    .long("set-announce")
+                    .help("Replace all trackers by this url")
+                    .takes_value(true)
+                    .value_name("URL"),
+            )
+            .arg(
+                Arg::with_name("add-tracker")
+                    .long("add-tracker")
+                    .help("Add a tracker in a new tier, may be given multiple times")
+                    .takes_value(true)
+                    .value_name("URL")
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("remove-tracker")
+                    .long("remove-tracker")
+                    .help("Remove a tracker from all tiers, may be given multiple times")
+                    .takes_value(true)
+                    .value_name("URL")
+                    .multiple(true)
+                    .number_of_values(1),
+            )
+            .arg(
+                Arg::with_name("private")
+                    .long("private")
+                    .help("Mark the torrent private, which changes the info hash"),
+            )
+            .arg(
+                Arg::with_name("no-private")
+                    .long("no-private")
+                    .help("Mark the torrent public, which changes the info hash")
+                    .conflicts_with("private"),
+            )
+            .arg(
+                Arg::with_name("set-name")
+                    .long("set-name")
+                    .help(
+                        "Rename the file or directory of the torrent, which changes the info hash",
+                    )
+                    .takes_value(true)
+                    .value_name("NAME"),
+            )
+            .arg(
+                Arg::with_name("filename")
+                    .help("The .torrent file")
+                    .required(true)
+                    .takes_value(true),
+            ),
+    );
+
+    let app = app.subcommand(
+        SubCommand::with_name("strip")
+            .about("Writes a copy of a torrent without identifying metadata, all of it by default")
+            .arg(
+                Arg::with_name("output")
+                    .short("o")
+                    .long("output")
+                    .help("The .torrent file to write")
+                    .required(true)
+                    .takes_value(true),
+            )
+            .arg(
+                Arg::with_name("comment")
+                    .long("comment")
+                    .help("Remove the comment"),
+            )
+            .arg(
+                Arg::with_name("created-by")
+                    .long("created-by")
+                    .help("Remove the created by field"),
+            )
+            .arg(
+                Arg::with_name("creation-date")
+                    .long("creation-date")
+                    .help("Remove the creation date"),
+            )
+            .arg(
+                Arg::with_name("webseeds")
+                    .long("webseeds")
+                    .help("Remove the webseeds and HTTP seeds"),
+            )
+            .arg(
+                Arg::with_name("trackers")
+                    .long("trackers")
+                    .help("Remove the trackers"),
+            )
+            .arg(
+                Arg::with_name("filename")
+                    .help("The .torrent file")
+                    .required(true)
+                    .takes_value(true),
+            ),
+    );
+
+    #[cfg(feature = "net")]
+    let app = app
+        .subcommand(
+            SubCommand::with_name("scrape")
+                .about("Requests the number of seeders and leechers from the trackers")
+                .arg(
+                    Arg::with_name("filename")
+                        .help("The .torrent file, its http(s) url or a magnet link")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("announce")
+                .about("Announces to the trackers and prints the peers they return")
+                .arg(
+                    Arg::with_name("port")
+                        .short("p")
+                        .long("port")
+                        .help("The port to announce")
+                        .takes_value(true)
+                        .default_value("6881"),
+                )
+                .arg(
+                    Arg::with_name("filename")
+                        .help("The .torrent file, its http(s) url or a magnet link")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("from-magnet")
+                .about("Downloads the metadata of a magnet link from peers and saves the .torrent")
+                .arg(
+                    Arg::with_name("output")
+                        .short("o")
+                        .long("output")
+                        .help("The .torrent file to write, <info hash>.torrent by default")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("magnet")
+                        .help("The magnet link or info hash")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("webseeds")
+                .about("Checks that the webseeds serve the files of the torrent")
+                .arg(
+                    Arg::with_name("max-files")
+                        .long("max-files")
+                        .help("The maximum number of files checked per webseed")
+                        .takes_value(true)
+                        .default_value("100"),
+                )
+                .arg(
+                    Arg::with_name("filename")
+                        .help("The .torrent file or its http(s) url")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        )
+        .subcommand(
+            SubCommand::with_name("dht")
+                .about("Looks up the peers of the torrent in the DHT")
+                .arg(
+                    Arg::with_name("filename")
+                        .help("The .torrent file, its http(s) url or a magnet link")
+                        .required(true)
+                        .takes_value(true),
+                ),
+        );
+
+    let matches = app.get_matches();
 
     if matches.is_present("nocolour") {
         Paint::disable();
     }
 
-    let mut file = match File::open(filename) {
-        Ok(f) => f,
+    if let Some(matches) = matches.subcommand_matches("create") {
+        create(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("verify") {
+        verify(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("edit") {
+        edit(matches);
+        return;
+    }
+    if let Some(matches) = matches.subcommand_matches("strip") {
+        strip(matches);
+        return;
+    }
+
+    #[cfg(feature = "net")]
+    {
+        if let Some(matches) = matches.subcommand_matches("scrape") {
+            scrape(matches);
+            return;
+        }
+        if let Some(matches) = matches.subcommand_matches("announce") {
+            announce(matches);
+            return;
+        }
+        if let Some(matches) = matches.subcommand_matches("dht") {
+            dht(matches);
+            return;
+        }
+        if let Some(matches) = matches.subcommand_matches("webseeds") {
+            webseeds(matches);
+            return;
+        }
+        if let Some(matches) = matches.subcommand_matches("from-magnet") {
+            from_magnet(matches);
+            return;
+        }
+    }
+
+    let lists_files = ["files", "tree", "details"]
+        .iter()
+        .any(|arg| matches.is_present(arg));
+    if !lists_files {
+        if let Some(arg) = ["sort", "reverse", "match"]
+            .iter()
+            .find(|arg| matches.is_present(arg))
+        {
+            eprintln!(
+                "Application Error: --{} needs --files, --tree or --details",
+                arg
+            );
+            process::exit(1);
+        }
+    }
+
+    let options = Options {
+        files: matches.is_present("files"),
+        tree: matches.is_present("tree"),
+        sort: matches.value_of("sort").map(str::to_owned),
+        reverse: matches.is_present("reverse"),
+        patterns: match_patterns(&matches),
+        details: matches.is_present("details"),
+        everything: matches.is_present("everything"),
+        magnet: matches.is_present("magnet"),
+        manifest: matches.is_present("manifest"),
+        #[cfg(feature = "json")]
+        json: matches.is_present("json"),
+        #[cfg(feature = "yaml")]
+        yaml: matches.is_present("yaml"),
+        max_depth: value_t!(matches, "max-depth", usize).unwrap_or_else(|e| e.exit()),
+    };
+    let filenames = expand_filenames(matches.values_of("filename").unwrap_or_default()).and_then(
+        |mut filenames| {
+            filenames.extend(recursive_filenames(&matches)?);
+            filenames.extend(listed_filenames(&matches)?);
+            Ok(filenames)
+        },
+    );
+    let filenames = match filenames {
+        Ok(filenames) => filenames,
         Err(e) => {
             eprintln!("Application Error: {}", e);
             process::exit(1);
         }
     };
 
-    let indent = "    ";
-    let col_width: u32 = 19;
-    let mut buf: Vec<u8> = vec![];
-    file.read_to_end(&mut buf).unwrap();
+    if let Some(format) = matches.value_of("format") {
+        print_summaries(&filenames, format);
+        return;
+    }
+    if filenames.len() > 1 && options.is_document() {
+        eprintln!(
+            "Application Error: --json and --yaml print a single torrent, use --format {} for several",
+            FORMATS[FORMATS.len() - 1]
+        );
+        process::exit(1);
+    }
 
-    println!(
-        "{}",
-        Paint::new(Path::new(filename).file_name().unwrap().to_str().unwrap()).bold()
-    );
+    let mut failed = false;
+    for (index, filename) in filenames.iter().enumerate() {
+        if index > 0 && options.is_text() {
+            println!();
+        }
+        if let Err(e) = show(filename, &options) {
+            if filenames.len() > 1 {
+                eprintln!("Application Error: {}: {}", filename, e);
+            } else {
+                eprintln!("Application Error: {}", e);
+            }
+            failed = true;
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
 
-    if !show_everything {
-        let torrent = Torrent::from_buf(&buf).unwrap();
-        let info = torrent.info();
+/// What to print about each torrent.
+struct Options {
+    files: bool,
+    tree: bool,
+    sort: Option<String>,
+    reverse: bool,
+    patterns: Vec<glob::Pattern>,
+    details: bool,
+    everything: bool,
+    magnet: bool,
+    manifest: bool,
+    #[cfg(feature = "json")]
+    json: bool,
+    #[cfg(feature = "yaml")]
+    yaml: bool,
+    max_depth: usize,
+}
 
-        if !show_details {
-            if let Some(ref v) = info.name() {
-                print_line("name", &v, &indent, &col_width);
-            }
-            if let Some(ref v) = &torrent.comment() {
-                print_line("comment", &v, &indent, &col_width);
+impl Options {
+    /// Returns whether the output is the sectioned text format rather than one of the
+    /// machine-readable ones.
+    fn is_text(&self) -> bool {
+        !self.is_document() && !self.magnet && !self.manifest
+    }
+
+    /// Returns whether the output is a JSON or YAML document, which can't be concatenated
+    /// for several torrents.
+    fn is_document(&self) -> bool {
+        #[cfg(feature = "json")]
+        {
+            if self.json {
+                return true;
             }
-            if let Some(ref v) = &torrent.announce() {
-                print_line("announce url", &v, &indent, &col_width);
+        }
+        #[cfg(feature = "yaml")]
+        {
+            if self.yaml {
+                return true;
             }
-            if let Some(ref v) = &torrent.created_by() {
-                print_line("created by", &v, &indent, &col_width);
+        }
+        false
+    }
+}
+
+/// Expands the arguments which are glob patterns rather than existing files, for shells
+/// which don't expand them like cmd.exe.
+fn expand_filenames<'a, I: Iterator<Item = &'a str>>(args: I) -> Result<Vec<String>, String> {
+    let mut filenames = vec![];
+    for arg in args {
+        let is_pattern = arg.contains(&['*', '?', '['][..])
+            && !arg.starts_with("magnet:")
+            && !arg.contains("://")
+            && !Path::new(arg).exists();
+        if !is_pattern {
+            filenames.push(arg.to_owned());
+            continue;
+        }
+
+        let paths = glob::glob(arg).map_err(|_| format!("invalid glob pattern: '{}'", arg))?;
+        let count = filenames.len();
+        filenames.extend(
+            paths
+                .filter_map(|path| path.ok())
+                .map(|path| path.display().to_string()),
+        );
+        if filenames.len() == count {
+            return Err(format!("no files match '{}'", arg));
+        }
+    }
+    Ok(filenames)
+}
+
+/// Returns the torrent files below the `--recursive` directories which match the
+/// `--include` and `--exclude` patterns.
+fn recursive_filenames(matches: &ArgMatches) -> Result<Vec<String>, String> {
+    let values = |name| -> Vec<String> {
+        matches
+            .values_of(name)
+            .map_or_else(Vec::new, |values| values.map(str::to_owned).collect())
+    };
+    let (include, exclude) = (values("include"), values("exclude"));
+    let mut filenames = vec![];
+    for dir in values("recursive") {
+        let torrents = find_torrents(&dir, &include, &exclude).map_err(|e| match e {
+            Error::ReadData { ref source, .. } => format!("{}: {}", e, source),
+            e => e.to_string(),
+        })?;
+        if torrents.is_empty() {
+            return Err(format!("no torrents found in '{}'", dir));
+        }
+        filenames.extend(torrents.iter().map(|path| path.display().to_string()));
+    }
+    Ok(filenames)
+}
+
+/// Returns the torrent files listed in the `--files-from` file, separated by line breaks
+/// or by NULs with `--null`.
+fn listed_filenames(matches: &ArgMatches) -> Result<Vec<String>, String> {
+    let null = matches.is_present("null");
+    let list = match matches.value_of("files-from") {
+        Some("-") => None,
+        Some(list) => Some(list),
+        None if null => None,
+        None => return Ok(vec![]),
+    };
+
+    let mut buf = vec![];
+    let read = match list {
+        Some(list) => File::open(list).and_then(|mut file| file.read_to_end(&mut buf)),
+        None => std::io::stdin().read_to_end(&mut buf),
+    };
+    read.map_err(|e| format!("could not read '{}': {}", list.unwrap_or("stdin"), e))?;
+
+    let separator = if null { b'\0' } else { b'\n' };
+    Ok(buf
+        .split(|&b| b == separator)
+        .map(|name| String::from_utf8_lossy(name))
+        .map(|name| {
+            if null {
+                name.into_owned()
+            } else {
+                name.trim_end_matches('\r').to_owned()
             }
-            if let Some(ref v) = &torrent.creation_date() {
-                let date = Utc.timestamp(*v, 0);
-                print_line("created on", &date, &indent, &col_width);
+        })
+        .filter(|name| !name.is_empty())
+        .collect())
+}
+
+fn print_warnings(torrent: &Torrent) {
+    for warning in torrent.check() {
+        eprintln!("Warning: {}", warning);
+    }
+}
+
+/// Prints the torrent file, url or magnet link `filename` as selected by `options`.
+fn show(filename: &str, options: &Options) -> Result<(), Box<dyn std::error::Error>> {
+    let indent = "    ";
+    let col_width: u32 = 19;
+
+    if filename.starts_with("magnet:") {
+        let magnet: Magnet = filename.parse()?;
+        if options.magnet {
+            println!("{}", magnet);
+            return Ok(());
+        }
+        #[cfg(feature = "json")]
+        {
+            if options.json {
+                print_magnet_json(&magnet);
+                return Ok(());
             }
-            if let Some(ref v) = &torrent.encoding() {
-                print_line("encoding", &v, &indent, &col_width);
+        }
+        #[cfg(feature = "yaml")]
+        {
+            if options.yaml {
+                print_magnet_yaml(&magnet);
+                return Ok(());
             }
+        }
+        print_magnet(&magnet, indent, &col_width);
+        return Ok(());
+    }
 
-            let files = torrent.num_files();
-            print_line("num files", &files, &indent, &col_width);
+    let buf = read_input(filename)?;
+    if options.everything {
+        print_header(filename);
+        return print_everything(&buf, indent, options.max_depth);
+    }
 
-            ;
-            let size = match binary_prefix(torrent.total_size() as f64) {
-                Standalone(bytes) => format!("{} bytes", bytes),
-                Prefixed(prefix, n) => format!("{:.2} {}B", n, prefix),
-            };
-            print_line("total size", &S_NUMBER.paint(size), &indent, &col_width);
-            let info_hash_str = match torrent.info_hash() {
-                Ok(info_hash) => torrentinfo::to_hex(&info_hash),
-                Err(e) => format!("could not calculate info hash: {}", e),
-            };
+    let torrent = Torrent::from_buf(&buf)?;
+    if options.magnet {
+        let link = torrent
+            .magnet_link()
+            .map_err(|e| format!("could not create magnet link: {}", e))?;
+        println!("{}", link);
+        return Ok(());
+    }
 
-            print_line("info hash", &info_hash_str, &indent, &col_width);
+    if options.manifest {
+        for entry in torrent.manifest() {
+            println!("{}", entry.to_line());
         }
+        return Ok(());
+    }
 
-        if show_files || show_details {
-            println!("{}{}", indent, S_LABEL.paint("files"));
-            let _files: Vec<torrentinfo::File>;
-            let files = match torrent.files() {
-                Some(f) => f,
-                None => {
-                    let name = info.name().clone().unwrap();
-                    let f = torrentinfo::File::new(torrent.total_size(), vec![name]);
-                    _files = vec![f];
-                    &_files
-                }
-            };
+    #[cfg(feature = "json")]
+    {
+        if options.json {
+            print_warnings(&torrent);
+            println!("{}", torrent.to_json_pretty()?);
+            return Ok(());
+        }
+    }
+    #[cfg(feature = "yaml")]
+    {
+        if options.yaml {
+            print_warnings(&torrent);
+            println!("{}", torrent.to_yaml()?);
+            return Ok(());
+        }
+    }
 
-            for (index, file) in files.iter().enumerate() {
-                println!("{}{}", indent.repeat(2), S_LABEL.paint(index));
-                println!("{}{}", indent.repeat(3), file.path().join("/"));
-                let size = match binary_prefix(*file.length() as f64) {
-                    Standalone(bytes) => format!("{} bytes", bytes),
-                    Prefixed(prefix, n) => format!("{:.2} {}B", n, prefix),
-                };
-                println!("{}{}", indent.repeat(3), S_NUMBER.paint(size));
-            }
+    print_header(filename);
+    let info = torrent.info();
+    print_warnings(&torrent);
+
+    if !options.details {
+        if let Some(v) = torrent.decoded_name() {
+            print_line("name", &v, indent, &col_width);
+        }
+        if let Some(v) = torrent.decoded_comment() {
+            print_line("comment", &v, indent, &col_width);
+        }
+        if let Some(v) = torrent.announce() {
+            print_line("announce url", &v, indent, &col_width);
+        }
+        if let Some(v) = torrent.created_by() {
+            print_line("created by", &v, indent, &col_width);
+        }
+        if let Some(date) = torrent.creation_datetime() {
+            print_line("created on", &date, indent, &col_width);
+        } else if let Some(v) = torrent.creation_date() {
+            print_line("created on", &v, indent, &col_width);
+        }
+        if let Some(v) = torrent.encoding() {
+            print_line("encoding", &v, indent, &col_width);
         }
 
-        if show_details {
-            println!("{}{}", indent, S_LABEL.paint("piece length"));
-            println!("{}{}", indent.repeat(2), &info.piece_length());
-            println!("{}{}", indent, S_LABEL.paint("pieces"));
+        let files = torrent.num_files();
+        print_line("num files", &files, indent, &col_width);
+
+        let size = format_size(torrent.total_size());
+        print_line("total size", &S_NUMBER.paint(size), indent, &col_width);
+        let info_hash_str = match torrent.info_hash() {
+            Ok(info_hash) => info_hash.to_string(),
+            Err(e) => format!("could not calculate info hash: {}", e),
+        };
+
+        print_line("info hash", &info_hash_str, indent, &col_width);
+    }
+
+    if options.files || options.details || options.tree {
+        println!("{}{}", indent, S_LABEL.paint("files"));
+        let mut files = listed_files(&torrent);
+        if !options.patterns.is_empty() {
+            files.retain(|file| {
+                let path = file.path.join("/");
+                options
+                    .patterns
+                    .iter()
+                    .any(|pattern| pattern.matches(&path))
+            });
+        }
+        sort_files(&mut files, options.sort.as_deref(), options.reverse);
+        if options.tree {
+            print_file_tree(&files, indent, options.sort.as_deref(), options.reverse);
+        } else {
+            for file in &files {
+                println!("{}{}", indent.repeat(2), S_LABEL.paint(file.index));
+                println!("{}{}", indent.repeat(3), file.path.join("/"));
+                println!(
+                    "{}{}",
+                    indent.repeat(3),
+                    S_NUMBER.paint(format_size(file.length))
+                );
+            }
+        }
+        if !options.patterns.is_empty() {
+            let matched = files.iter().filter(|file| !file.padding);
+            println!("{}{}", indent, S_LABEL.paint("matched"));
             println!(
-                "{}{}",
+                "{}{} files, {}",
                 indent.repeat(2),
-                S_BYTES.paint(format!("[{} Bytes]", info.pieces().len()))
+                matched.clone().count(),
+                S_NUMBER.paint(format_size(matched.map(|file| file.length).sum()))
             );
-            println!("{}{}", indent, S_LABEL.paint("private"));
-            println!(
-                "{}{}",
-                indent.repeat(2),
-                &info.private().unwrap_or_default()
+        }
+    }
+
+    if options.details {
+        println!("{}{}", indent, S_LABEL.paint("trackers"));
+        for (index, tier) in torrent.tiers().iter().enumerate() {
+            println!("{}{}", indent.repeat(2), S_LABEL.paint(index));
+            for url in tier.iter() {
+                println!("{}{}", indent.repeat(3), url);
+            }
+        }
+        println!("{}{}", indent, S_LABEL.paint("piece length"));
+        println!("{}{}", indent.repeat(2), &info.piece_length());
+        println!("{}{}", indent, S_LABEL.paint("pieces"));
+        println!(
+            "{}{}",
+            indent.repeat(2),
+            S_BYTES.paint(format!("[{} Bytes]", info.pieces().as_bytes().len()))
+        );
+        println!("{}{}", indent, S_LABEL.paint("num pieces"));
+        println!("{}{}", indent.repeat(2), torrent.num_pieces());
+        println!("{}{}", indent, S_LABEL.paint("private"));
+        println!("{}{}", indent.repeat(2), if info.private() { 1 } else { 0 });
+        if let Some(source) = info.source() {
+            println!("{}{}", indent, S_LABEL.paint("source"));
+            println!("{}{}", indent.repeat(2), source);
+        }
+    }
+    Ok(())
+}
+
+/// A file of the `--files` listing.
+struct ListedFile {
+    /// The index of the file in the torrent
+    index: usize,
+    path: Vec<String>,
+    length: u64,
+    padding: bool,
+}
+
+/// Returns the files of the torrent, the files of the file tree for v2-only torrents, or
+/// its name and size for single-file torrents.
+fn listed_files(torrent: &Torrent) -> Vec<ListedFile> {
+    let file_tree = torrent.info().file_tree();
+    match torrent.files() {
+        Some(files) => files
+            .iter()
+            .enumerate()
+            .map(|(index, file)| ListedFile {
+                index,
+                path: torrent
+                    .decoded_path(file)
+                    .into_iter()
+                    .map(|c| c.into_owned())
+                    .collect(),
+                length: file.length(),
+                padding: file.is_padding(),
+            })
+            .collect(),
+        None if !file_tree.is_empty() => file_tree
+            .into_iter()
+            .enumerate()
+            .map(|(index, entry)| ListedFile {
+                index,
+                path: entry.path().to_vec(),
+                length: entry.length(),
+                padding: false,
+            })
+            .collect(),
+        None => vec![ListedFile {
+            index: 0,
+            path: torrent
+                .decoded_name()
+                .into_iter()
+                .map(|name| name.into_owned())
+                .collect(),
+            length: torrent.total_size(),
+            padding: false,
+        }],
+    }
+}
+
+/// Parses the `--match` patterns, exiting on an invalid one.
+fn match_patterns(matches: &ArgMatches) -> Vec<glob::Pattern> {
+    matches
+        .values_of("match")
+        .unwrap_or_default()
+        .map(|pattern| {
+            glob::Pattern::new(pattern).unwrap_or_else(|e| {
+                eprintln!(
+                    "Application Error: invalid glob pattern '{}': {}",
+                    pattern, e
+                );
+                process::exit(1);
+            })
+        })
+        .collect()
+}
+
+/// Sorts the files for `--sort` and `--reverse`, by size largest first.
+fn sort_files(files: &mut [ListedFile], sort: Option<&str>, reverse: bool) {
+    match sort {
+        Some("name") => files.sort_by(|a, b| a.path.last().cmp(&b.path.last())),
+        Some("path") => files.sort_by(|a, b| a.path.cmp(&b.path)),
+        Some("size") => files.sort_by_key(|f| Reverse(f.length)),
+        _ => {}
+    }
+    if reverse {
+        files.reverse();
+    }
+}
+
+/// A directory of the `--tree` listing, with the total size of its files.
+#[derive(Default)]
+struct TreeDir<'a> {
+    size: u64,
+    dirs: BTreeMap<&'a str, TreeDir<'a>>,
+    files: Vec<(&'a str, u64)>,
+}
+
+/// An entry to print, with its nesting depth.
+enum TreeEntry<'a> {
+    Dir(usize, &'a str, &'a TreeDir<'a>),
+    File(usize, &'a str, u64),
+}
+
+/// Prints the files as a directory tree with the size of every directory, leaving out pad
+/// files.
+///
+/// The files of each directory keep the order of `files`, the subdirectories are sorted
+/// like `sort_files` sorts files and listed first.
+fn print_file_tree(files: &[ListedFile], indent: &str, sort: Option<&str>, reverse: bool) {
+    let mut root = TreeDir::default();
+    for file in files.iter().filter(|file| !file.padding) {
+        let (name, dirs) = match file.path.split_last() {
+            Some(split) => split,
+            None => continue,
+        };
+        let mut dir = &mut root;
+        dir.size += file.length;
+        for component in dirs {
+            dir = dir.dirs.entry(component).or_default();
+            dir.size += file.length;
+        }
+        dir.files.push((name, file.length));
+    }
+
+    let order = |dirs: &mut Vec<(&str, &TreeDir)>| {
+        if sort == Some("size") {
+            dirs.sort_by_key(|d| Reverse(d.1.size));
+        }
+        if reverse {
+            dirs.reverse();
+        }
+    };
+    let mut stack = tree_entries(&root, 2, &order);
+    while let Some(entry) = stack.pop() {
+        match entry {
+            TreeEntry::Dir(depth, name, dir) => {
+                println!(
+                    "{}{}/ {}",
+                    indent.repeat(depth),
+                    S_LABEL_ALT.paint(name),
+                    S_NUMBER.paint(format_size(dir.size))
+                );
+                stack.extend(tree_entries(dir, depth + 1, &order));
+            }
+            TreeEntry::File(depth, name, length) => println!(
+                "{}{} {}",
+                indent.repeat(depth),
+                name,
+                S_NUMBER.paint(format_size(length))
+            ),
+        }
+    }
+}
+
+/// Returns the directories in the order of `order` and then the files of `dir`, in reverse
+/// order for use as a stack.
+fn tree_entries<'a, F>(dir: &'a TreeDir<'a>, depth: usize, order: &F) -> Vec<TreeEntry<'a>>
+where
+    F: Fn(&mut Vec<(&'a str, &'a TreeDir<'a>)>),
+{
+    let mut dirs: Vec<(&str, &TreeDir)> = dir.dirs.iter().map(|(&name, dir)| (name, dir)).collect();
+    order(&mut dirs);
+    let dirs = dirs
+        .into_iter()
+        .map(|(name, dir)| TreeEntry::Dir(depth, name, dir));
+    let files = dir
+        .files
+        .iter()
+        .map(|&(name, length)| TreeEntry::File(depth, name, length));
+    let mut entries: Vec<TreeEntry> = dirs.chain(files).collect();
+    entries.reverse();
+    entries
+}
+
+fn format_size(bytes: u64) -> String {
+    match binary_prefix(bytes as f64) {
+        Standalone(bytes) => format!("{} bytes", bytes),
+        Prefixed(prefix, n) => format!("{:.2} {}B", n, prefix),
+    }
+}
+
+fn print_header(filename: &str) {
+    let name = Path::new(filename)
+        .file_name()
+        .map_or_else(|| filename.into(), |name| name.to_string_lossy());
+    println!("{}", Paint::new(name).bold());
+}
+
+fn print_magnet(magnet: &Magnet, indent: &str, col_width: &u32) {
+    println!("{}", Paint::new("magnet link").bold());
+    if let Some(v) = magnet.display_name() {
+        print_line("name", &v, indent, col_width);
+    }
+    for info_hash in magnet.info_hashes() {
+        print_line("info hash", &info_hash, indent, col_width);
+    }
+    for info_hash in magnet.info_hashes_v2() {
+        print_line(
+            "info hash v2",
+            &torrentinfo::to_hex(info_hash),
+            indent,
+            col_width,
+        );
+    }
+    for tracker in magnet.trackers() {
+        print_line("tracker", &tracker, indent, col_width);
+    }
+    for url in magnet.web_seeds() {
+        print_line("webseed", &url, indent, col_width);
+    }
+}
+
+/// A magnet link for `--json` and `--yaml`.
+#[cfg(any(feature = "json", feature = "yaml"))]
+#[derive(Serialize)]
+struct MagnetView<'a> {
+    name: Option<&'a str>,
+    info_hashes: Vec<String>,
+    info_hashes_v2: Vec<String>,
+    trackers: &'a [String],
+    web_seeds: &'a [String],
+}
+
+#[cfg(any(feature = "json", feature = "yaml"))]
+impl<'a> MagnetView<'a> {
+    fn new(magnet: &'a Magnet) -> Self {
+        MagnetView {
+            name: magnet.display_name(),
+            info_hashes: magnet
+                .info_hashes()
+                .iter()
+                .map(|hash| hash.to_hex())
+                .collect(),
+            info_hashes_v2: magnet
+                .info_hashes_v2()
+                .iter()
+                .map(|hash| torrentinfo::to_hex(hash))
+                .collect(),
+            trackers: magnet.trackers(),
+            web_seeds: magnet.web_seeds(),
+        }
+    }
+}
+
+#[cfg(feature = "json")]
+fn print_magnet_json(magnet: &Magnet) {
+    let json = serde_json::to_string_pretty(&MagnetView::new(magnet)).unwrap();
+    println!("{}", json);
+}
+
+#[cfg(feature = "yaml")]
+fn print_magnet_yaml(magnet: &Magnet) {
+    let yaml = serde_yaml::to_string(&MagnetView::new(magnet)).unwrap();
+    println!("{}", yaml);
+}
+
+/// A row of the `--format` output.
+#[cfg_attr(feature = "json", derive(Serialize))]
+struct Summary {
+    path: String,
+    name: Option<String>,
+    info_hash: Option<String>,
+    total_size: u64,
+    num_files: usize,
+    trackers: Vec<String>,
+}
+
+impl Summary {
+    fn new(path: &str, torrent: &Torrent) -> Self {
+        Summary {
+            path: path.to_owned(),
+            name: torrent.decoded_name().map(|name| name.into_owned()),
+            info_hash: torrent.info_hash().ok().map(|hash| hash.to_hex()),
+            total_size: torrent.total_size(),
+            num_files: torrent.num_files(),
+            trackers: torrent
+                .trackers()
+                .iter()
+                .map(|url| url.to_string())
+                .collect(),
+        }
+    }
+
+    /// Returns the summary as a CSV record, with the trackers separated by spaces.
+    fn to_csv(&self) -> String {
+        [
+            csv_field(&self.path),
+            csv_field(self.name.as_deref().unwrap_or_default()),
+            self.info_hash.clone().unwrap_or_default(),
+            self.total_size.to_string(),
+            self.num_files.to_string(),
+            csv_field(&self.trackers.join(" ")),
+        ]
+        .join(",")
+    }
+}
+
+/// Quotes `field` if it contains separators, quotes or line breaks (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+/// Prints a row per torrent in `format`, continuing after torrents which can't be read and
+/// exiting with an error at the end.
+fn print_summaries(filenames: &[String], format: &str) {
+    if format == "csv" {
+        println!("path,name,info_hash,total_size,num_files,trackers");
+    }
+    let mut failed = false;
+    for filename in filenames {
+        let torrent = read_input(filename).and_then(|buf| Ok(Torrent::from_buf(&buf)?));
+        let summary = match torrent {
+            Ok(torrent) => Summary::new(filename, &torrent),
+            Err(e) => {
+                eprintln!("Application Error: {}: {}", filename, e);
+                failed = true;
+                continue;
+            }
+        };
+        match format {
+            #[cfg(feature = "json")]
+            "ndjson" => println!("{}", serde_json::to_string(&summary).unwrap()),
+            _ => println!("{}", summary.to_csv()),
+        }
+    }
+    if failed {
+        process::exit(1);
+    }
+}
+
+fn create(matches: &ArgMatches) {
+    let path = matches.value_of("path").unwrap();
+    let mut builder = TorrentBuilder::new(path)
+        .private(matches.is_present("private"))
+        .deterministic(matches.is_present("deterministic"))
+        .version(match matches.value_of("version") {
+            Some("v2") => TorrentVersion::V2,
+            Some("hybrid") => TorrentVersion::Hybrid,
+            _ => TorrentVersion::V1,
+        });
+
+    let tiers: Vec<Vec<String>> = matches
+        .values_of("announce")
+        .map_or_else(Vec::new, |tiers| {
+            tiers
+                .map(|tier| tier.split(',').map(str::to_owned).collect())
+                .collect()
+        });
+    if let Some(url) = tiers.first().and_then(|tier| tier.first()) {
+        builder = builder.announce(url);
+    }
+    if tiers.len() > 1 || tiers.first().is_some_and(|tier| tier.len() > 1) {
+        builder = builder.announce_list(tiers.clone());
+    }
+    if let Some(comment) = matches.value_of("comment") {
+        builder = builder.comment(comment);
+    }
+    if let Some(name) = matches.value_of("name") {
+        builder = builder.name(name);
+    }
+    if let Some(source) = matches.value_of("source") {
+        builder = builder.source(source);
+    }
+    for pattern in matches.values_of("exclude").unwrap_or_default() {
+        builder = builder.exclude(pattern);
+    }
+    if matches.is_present("threads") {
+        builder = builder.threads(value_t!(matches, "threads", usize).unwrap_or_else(|e| e.exit()));
+    }
+    match parse_piece_length(matches.value_of("piece-length").unwrap()) {
+        Some(piece_length) => builder = builder.piece_length(piece_length),
+        None => {
+            eprintln!("Application Error: invalid piece length");
+            process::exit(1);
+        }
+    }
+
+    let output = matches.value_of("output").map_or_else(
+        || {
+            let name = matches.value_of("name").map_or_else(
+                || {
+                    Path::new(path)
+                        .file_name()
+                        .unwrap_or_default()
+                        .to_string_lossy()
+                },
+                Into::into,
             );
+            format!("{}.torrent", name)
+        },
+        str::to_owned,
+    );
+    if !matches.is_present("force") && Path::new(&output).exists() {
+        eprintln!(
+            "Application Error: '{}' exists, use --force to overwrite it",
+            output
+        );
+        process::exit(1);
+    }
+
+    let mut progress = CreateProgress {
+        visible: std::io::stderr().is_terminal(),
+        ..CreateProgress::default()
+    };
+    let torrent = match builder.build_with_progress(&mut progress) {
+        Ok(torrent) => torrent,
+        Err(Error::Io(e)) => {
+            eprintln!("Application Error: {}: {}", path, e);
+            process::exit(1);
+        }
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    };
+    if let Err(e) = File::create(&output)
+        .map_err(Error::from)
+        .and_then(|file| torrent.write_to(file))
+    {
+        eprintln!("Application Error: could not write '{}': {}", output, e);
+        process::exit(1);
+    }
+    println!("{}", output);
+    if let Ok(info_hash) = torrent.info_hash() {
+        println!("{}", info_hash);
+    }
+}
+
+fn verify(matches: &ArgMatches) {
+    let data = matches.value_of("data").unwrap();
+    let mut options = VerifyOptions {
+        v2: matches.is_present("v2"),
+        ..VerifyOptions::default()
+    };
+    if matches.is_present("threads") {
+        options.threads = value_t!(matches, "threads", usize).unwrap_or_else(|e| e.exit());
+        options.read_ahead = options.threads * 2;
+    }
+    let verification = Torrent::from_file(matches.value_of("filename").unwrap())
+        .and_then(|torrent| verify_with(&torrent, data, &options));
+    let verification = match verification {
+        Ok(verification) => verification,
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let indent = "    ";
+    let col_width: u32 = 19;
+    println!("{}", S_LABEL.paint("files"));
+    for file in &verification.files {
+        let status = if !file.exists() {
+            Paint::red("missing".to_owned())
+        } else if file.is_complete() {
+            Paint::green("complete".to_owned())
+        } else if file.is_short() {
+            Paint::red(format!("short, {:.2}%", file.completeness() * 100.0))
+        } else {
+            Paint::yellow(format!("{:.2}%", file.completeness() * 100.0))
+        };
+        let path = file.path.strip_prefix(data).unwrap_or(&file.path);
+        println!("{}{} {}", indent, path.display(), status);
+    }
+
+    let bad: Vec<usize> = verification
+        .failed_pieces()
+        .iter()
+        .map(|piece| piece.index)
+        .collect();
+    for &(label, status) in &[
+        ("good pieces", PieceStatus::Good),
+        ("bad pieces", PieceStatus::Bad),
+        ("missing pieces", PieceStatus::Missing),
+    ] {
+        let count = S_NUMBER.paint(verification.count(status));
+        print_line(label, &count, "", &col_width);
+    }
+    if !bad.is_empty() {
+        print_line("failed pieces", &piece_ranges(&bad), "", &col_width);
+    }
+    let percent = format!("{:.2}%", verification.percent_complete());
+    print_line("complete", &S_NUMBER.paint(percent), "", &col_width);
+
+    if !verification.is_complete() {
+        process::exit(1);
+    }
+}
+
+fn edit(matches: &ArgMatches) {
+    let filename = matches.value_of("filename").unwrap();
+    let mut torrent = match Torrent::from_file(filename) {
+        Ok(torrent) => torrent,
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    if let Some(comment) = matches.value_of("set-comment") {
+        torrent.set_comment(Some(comment).filter(|comment| !comment.is_empty()));
+    }
+    if let Some(url) = matches.value_of("set-announce") {
+        torrent.set_announce_list(None);
+        torrent.set_announce(Some(url));
+    }
+    for url in matches.values_of("remove-tracker").unwrap_or_default() {
+        if !torrent.remove_tracker(url) {
+            eprintln!("Warning: {} is not a tracker of the torrent", url);
+        }
+    }
+    for url in matches.values_of("add-tracker").unwrap_or_default() {
+        torrent.add_tracker(usize::MAX, url);
+    }
+    if matches.is_present("private") {
+        torrent.info_mut().set_private(true);
+    } else if matches.is_present("no-private") {
+        torrent.info_mut().set_private(false);
+    }
+    if let Some(name) = matches.value_of("set-name") {
+        if name.is_empty() || name == "." || name == ".." || name.contains(&['/', '\\'][..]) {
+            eprintln!("Application Error: invalid name: '{}'", name);
+            process::exit(1);
+        }
+        if torrent.decoded_name().as_deref() != Some(name) {
+            eprintln!(
+                "Warning: renaming changes the info hash, so the torrent joins a new swarm \
+and clients download it into '{}'",
+                name
+            );
+        }
+        torrent.set_name(name);
+    }
+
+    save_edited(&torrent, matches.value_of("output").unwrap_or(filename));
+}
+
+fn strip(matches: &ArgMatches) {
+    let mut torrent = match Torrent::from_file(matches.value_of("filename").unwrap()) {
+        Ok(torrent) => torrent,
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let fields = [
+        "comment",
+        "created-by",
+        "creation-date",
+        "webseeds",
+        "trackers",
+    ];
+    let all = !fields.iter().any(|&field| matches.is_present(field));
+    let strip = |field| all || matches.is_present(field);
+    if strip("comment") {
+        torrent.set_comment(None);
+    }
+    if strip("created-by") {
+        torrent.set_created_by(None);
+    }
+    if strip("creation-date") {
+        torrent.set_creation_date(None);
+    }
+    if strip("webseeds") {
+        torrent.set_url_list(None);
+        torrent.set_httpseeds(None);
+    }
+    if strip("trackers") {
+        torrent.set_announce(None);
+        torrent.set_announce_list(None);
+    }
+
+    save_edited(&torrent, matches.value_of("output").unwrap());
+}
+
+/// Writes an edited torrent to `output`, warning if its info hash changed.
+fn save_edited(torrent: &Torrent, output: &str) {
+    let result = torrent.to_bytes().and_then(|buf| {
+        std::fs::write(output, &buf)?;
+        Ok(buf)
+    });
+    let buf = match result {
+        Ok(buf) => buf,
+        Err(e) => {
+            eprintln!("Application Error: could not write '{}': {}", output, e);
+            process::exit(1);
+        }
+    };
+
+    if torrent.info_hash_changed().unwrap_or_default() {
+        let info_hash = Torrent::from_buf(&buf).and_then(|torrent| torrent.info_hash());
+        if let Ok(info_hash) = info_hash {
+            eprintln!(
+                "Warning: the info hash changed to {}, peers of the original torrent won't \
+find this one",
+                info_hash
+            );
+        }
+    }
+    println!("{}", output);
+}
+
+/// Formats sorted piece indices as comma-separated ranges like `0-3, 7`.
+fn piece_ranges(indices: &[usize]) -> String {
+    let mut ranges: Vec<(usize, usize)> = vec![];
+    for &index in indices {
+        match ranges.last_mut() {
+            Some(&mut (_, ref mut end)) if *end + 1 == index => *end = index,
+            _ => ranges.push((index, index)),
+        }
+    }
+    ranges
+        .iter()
+        .map(|&(start, end)| {
+            if start == end {
+                start.to_string()
+            } else {
+                format!("{}-{}", start, end)
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// Parses `auto` or a length in bytes with an optional `K` or `M` binary suffix.
+fn parse_piece_length(s: &str) -> Option<PieceLength> {
+    if s.eq_ignore_ascii_case("auto") {
+        return Some(PieceLength::Auto);
+    }
+    let (digits, factor) = match s.char_indices().last()? {
+        (i, 'k') | (i, 'K') => (&s[..i], 1024),
+        (i, 'm') | (i, 'M') => (&s[..i], 1024 * 1024),
+        _ => (s, 1),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .map(PieceLength::Fixed)
+}
+
+/// Prints the hashing progress to stderr, if `visible`.
+#[derive(Default)]
+struct CreateProgress {
+    visible: bool,
+    total_pieces: usize,
+    percent: usize,
+}
+
+impl Progress for CreateProgress {
+    fn start(&mut self, _: u64, total_pieces: usize) {
+        self.total_pieces = total_pieces;
+    }
+
+    fn piece(&mut self, pieces_done: usize, _: u64) {
+        let percent = pieces_done * 100 / self.total_pieces.max(1);
+        if self.visible && percent != self.percent {
+            self.percent = percent;
+            eprint!("\rhashing {}%", percent);
         }
+    }
+
+    fn finish(&mut self) {
+        if self.percent > 0 {
+            eprintln!();
+        }
+    }
+}
+
+/// Returns the info hash, the trackers and the content size, if known, of a torrent file or
+/// magnet link.
+#[cfg(feature = "net")]
+fn swarm(filename: &str) -> (InfoHash, Vec<String>, Option<u64>) {
+    let result = if filename.starts_with("magnet:") {
+        filename
+            .parse::<Magnet>()
+            .map(|magnet| (magnet.info_hash(), magnet.trackers().to_vec(), None))
+    } else {
+        let torrent = if is_url(filename) {
+            Torrent::from_url(filename)
+        } else {
+            Torrent::from_file(filename)
+        };
+        torrent.and_then(|t| {
+            Ok((
+                t.info_hash()?,
+                t.trackers().iter().map(|url| url.to_string()).collect(),
+                Some(t.content_size()),
+            ))
+        })
+    };
+    match result {
+        Ok(result) => result,
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+fn scrape(matches: &ArgMatches) {
+    let (info_hash, trackers, _) = swarm(matches.value_of("filename").unwrap());
+    if trackers.is_empty() {
+        eprintln!("Application Error: torrent has no trackers");
+        process::exit(1);
+    }
+
+    let indent = "    ";
+    let col_width: u32 = 19;
+    let mut failed = 0;
+    for url in &trackers {
+        println!("{}", Paint::new(redact_url(url)).bold());
+        match tracker::scrape(url, &[info_hash]) {
+            Ok(results) => {
+                let result = results.get(&info_hash).cloned().unwrap_or_default();
+                print_line(
+                    "seeders",
+                    &S_NUMBER.paint(result.seeders),
+                    indent,
+                    &col_width,
+                );
+                print_line(
+                    "leechers",
+                    &S_NUMBER.paint(result.leechers),
+                    indent,
+                    &col_width,
+                );
+                print_line(
+                    "completed",
+                    &S_NUMBER.paint(result.completed),
+                    indent,
+                    &col_width,
+                );
+            }
+            Err(e) => {
+                failed += 1;
+                print_line("error", &e, indent, &col_width);
+            }
+        }
+    }
+    if failed == trackers.len() {
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "net")]
+fn announce(matches: &ArgMatches) {
+    let port = value_t!(matches, "port", u16).unwrap_or_else(|e| e.exit());
+    let (info_hash, trackers, size) = swarm(matches.value_of("filename").unwrap());
+    if trackers.is_empty() {
+        eprintln!("Application Error: torrent has no trackers");
+        process::exit(1);
+    }
+    let mut request = AnnounceRequest::new(info_hash, port);
+    // Announce as a leecher so trackers return seeders too
+    request.left = size.unwrap_or(1);
+
+    let indent = "    ";
+    let col_width: u32 = 19;
+    let mut failed = 0;
+    for url in &trackers {
+        println!("{}", Paint::new(redact_url(url)).bold());
+        match tracker::announce(url, &request) {
+            Ok(response) => {
+                print_line(
+                    "interval",
+                    &S_NUMBER.paint(response.interval),
+                    indent,
+                    &col_width,
+                );
+                print_line(
+                    "seeders",
+                    &S_NUMBER.paint(response.seeders),
+                    indent,
+                    &col_width,
+                );
+                print_line(
+                    "leechers",
+                    &S_NUMBER.paint(response.leechers),
+                    indent,
+                    &col_width,
+                );
+                print_line(
+                    "peers",
+                    &S_NUMBER.paint(response.peers.len()),
+                    indent,
+                    &col_width,
+                );
+                for peer in &response.peers {
+                    print_line("peer", peer, indent, &col_width);
+                }
+            }
+            Err(e) => {
+                failed += 1;
+                print_line("error", &e, indent, &col_width);
+            }
+        }
+    }
+    if failed == trackers.len() {
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "net")]
+fn dht(matches: &ArgMatches) {
+    let filename = matches.value_of("filename").unwrap();
+    let (info_hash, _, _) = swarm(filename);
+    let mut options = LookupOptions::default();
+    // The nodes of trackerless torrents are tried before the well-known ones
+    if let Ok(torrent) = Torrent::from_file(filename) {
+        for node in torrent.nodes().iter().rev() {
+            let node = match node.host().parse::<IpAddr>() {
+                Ok(ip) => SocketAddr::new(ip, node.port()).to_string(),
+                Err(_) => format!("{}:{}", node.host(), node.port()),
+            };
+            options.bootstrap.insert(0, node);
+        }
+    }
+
+    let lookup = match dht::get_peers(&info_hash, &options) {
+        Ok(lookup) => lookup,
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    };
+    let indent = "";
+    let col_width: u32 = 19;
+    print_line(
+        "queried",
+        &S_NUMBER.paint(lookup.queried),
+        indent,
+        &col_width,
+    );
+    print_line(
+        "responded",
+        &S_NUMBER.paint(lookup.responded),
+        indent,
+        &col_width,
+    );
+    print_line(
+        "peers",
+        &S_NUMBER.paint(lookup.peers.len()),
+        indent,
+        &col_width,
+    );
+    for peer in &lookup.peers {
+        print_line("peer", peer, indent, &col_width);
+    }
+    if lookup.responded == 0 {
+        process::exit(1);
+    }
+}
+
+#[cfg(feature = "net")]
+fn from_magnet(matches: &ArgMatches) {
+    let link = matches.value_of("magnet").unwrap();
+    let magnet = link
+        .parse::<Magnet>()
+        .or_else(|_| link.parse::<InfoHash>().map(Magnet::from));
+    let result = magnet.and_then(|magnet| {
+        let torrent = metadata::fetch(&magnet, &FetchOptions::default())?;
+        let output = matches
+            .value_of("output")
+            .map_or_else(|| format!("{}.torrent", magnet.info_hash()), str::to_owned);
+        torrent.write_to(File::create(&output)?)?;
+        Ok(output)
+    });
+    match result {
+        Ok(output) => println!("{}", output),
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    }
+}
+
+#[cfg(feature = "net")]
+fn webseeds(matches: &ArgMatches) {
+    let max_files = value_t!(matches, "max-files", usize).unwrap_or_else(|e| e.exit());
+    let filename = matches.value_of("filename").unwrap();
+    let torrent = if is_url(filename) {
+        Torrent::from_url(filename)
     } else {
-        print_everything(&buf, indent);
+        Torrent::from_file(filename)
+    };
+    let checks = torrent.and_then(|torrent| webseed::check_webseeds(&torrent, max_files));
+    let checks = match checks {
+        Ok(checks) => checks,
+        Err(e) => {
+            eprintln!("Application Error: {}", e);
+            process::exit(1);
+        }
+    };
+
+    let indent = "    ";
+    for check in &checks {
+        println!("{}", Paint::new(redact_url(&check.url)).bold());
+        for file in &check.files {
+            let status = if file.status.is_ok() {
+                Paint::green(file.status.to_string())
+            } else {
+                Paint::red(file.status.to_string())
+            };
+            if file.path.is_empty() {
+                println!("{}{}", indent, status);
+            } else {
+                println!(
+                    "{}{} {}",
+                    indent,
+                    S_LABEL.paint(file.path.join("/")),
+                    status
+                );
+            }
+        }
+    }
+    if checks.iter().any(|check| !check.is_ok()) {
+        process::exit(1);
     }
 }
 
+/// Reads the torrent file, or downloads it if `filename` is an http or https url, and
+/// decompresses it.
+fn read_input(filename: &str) -> Result<Vec<u8>, Box<dyn std::error::Error>> {
+    #[cfg(feature = "net")]
+    {
+        if is_url(filename) {
+            return Ok(decompress(download_torrent(filename)?, MAX_TORRENT_SIZE)?);
+        }
+    }
+
+    let mut buf = vec![];
+    File::open(filename)?.read_to_end(&mut buf)?;
+    Ok(decompress(buf, MAX_TORRENT_SIZE)?)
+}
+
+#[cfg(feature = "net")]
+fn is_url(filename: &str) -> bool {
+    let lowercase = filename.to_ascii_lowercase();
+    lowercase.starts_with("http://") || lowercase.starts_with("https://")
+}
+
 fn print_line<T: std::fmt::Display>(name: &str, value: &T, indent: &str, col_width: &u32) {
     let n = *col_width as usize - name.len();
     println!(
@@ -222,75 +1995,129 @@ fn print_line<T: std::fmt::Display>(name: &str, value: &T, indent: &str, col_wid
     );
 }
 
-fn print_everything(buf: &[u8], indent: &str) {
-    let bencoded = serde_bencode::from_bytes(buf).expect("could not decode .torrent file");
+fn print_everything(
+    buf: &[u8],
+    indent: &str,
+    max_depth: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    Limits::default()
+        .check(buf)
+        .map_err(|e| format!("could not decode .torrent file: {}", e))?;
+    let bencoded = serde_bencode::from_bytes(buf)
+        .map_err(|e| format!("could not decode .torrent file: {}", e))?;
     match bencoded {
-        Value::Dict(root) => print_dict(&root, indent, 1),
+        Value::Dict(root) => print_tree(&root, indent, max_depth),
         _ => {
             println!("torrent file is not a dict");
-            return;
         }
     }
+    Ok(())
 }
 
 type Dict = HashMap<Vec<u8>, Value>;
 
-fn print_dict(dict: &Dict, indent: &str, depth: usize) {
-    let style = |key| {
-        if depth % 2 == 0 {
-            S_LABEL_ALT.paint(key)
+/// An entry to print, with its nesting depth and its dict key or list index.
+type Entry<'a> = (usize, String, &'a Value);
+
+/// Returns the entries of `dict` sorted by key, in reverse order for use as a stack.
+fn dict_entries(dict: &Dict, depth: usize) -> Vec<Entry<'_>> {
+    let mut entries: Vec<Entry> = dict
+        .iter()
+        .map(|(k, v)| (depth, String::from_utf8_lossy(k).into_owned(), v))
+        .collect();
+    entries.sort_by(|a, b| b.1.cmp(&a.1));
+    entries
+}
+
+/// Prints the values of `root` depth-first without recursion, eliding lists and dicts
+/// nested deeper than `max_depth`.
+fn print_tree(root: &Dict, indent: &str, max_depth: usize) {
+    let mut stack = dict_entries(root, 1);
+    while let Some((depth, key, value)) = stack.pop() {
+        let style = if depth.is_multiple_of(2) {
+            &*S_LABEL_ALT
         } else {
-            S_LABEL.paint(key)
-        }
-    };
-    for (k, v) in dict {
-        let key = String::from_utf8_lossy(k);
-        println!("{}{}", indent.repeat(depth), style(key));
+            &*S_LABEL
+        };
+        println!("{}{}", indent.repeat(depth), style.paint(key));
 
-        match v {
-            Value::Dict(ref d) => print_dict(d, &indent, depth + 1),
-            Value::List(ref l) => print_list(l, &indent, depth + 1),
+        let value_indent = indent.repeat(depth + 1);
+        match *value {
+            Value::Dict(ref d) if depth < max_depth => stack.extend(dict_entries(d, depth + 1)),
+            Value::List(ref l) if depth < max_depth => stack.extend(
+                l.iter()
+                    .enumerate()
+                    .rev()
+                    .map(|(i, v)| (depth + 1, i.to_string(), v)),
+            ),
+            Value::Dict(_) | Value::List(_) => {
+                println!("{}{}", value_indent, S_BYTES.paint("[...]"))
+            }
             Value::Bytes(ref b) => {
                 if b.len() > 80 {
                     println!(
                         "{}{}",
-                        indent.repeat(depth + 1),
+                        value_indent,
                         S_BYTES.paint(format!("[{} Bytes]", b.len()))
                     )
                 } else {
-                    println!("{}{}", indent.repeat(depth + 1), String::from_utf8_lossy(b))
+                    println!("{}{}", value_indent, String::from_utf8_lossy(b))
                 }
             }
-            Value::Int(ref i) => println!("{}{}", indent.repeat(depth + 1), S_NUMBER.paint(i)),
+            Value::Int(ref i) => println!("{}{}", value_indent, S_NUMBER.paint(i)),
         }
     }
 }
 
-fn print_list(list: &[Value], indent: &str, depth: usize) {
-    let style = |key| {
-        if depth % 2 == 0 {
-            S_LABEL_ALT.paint(key)
-        } else {
-            S_LABEL.paint(key)
-        }
-    };
-    for (k, v) in list.iter().enumerate() {
-        println!("{}{}", indent.repeat(depth), style(k));
-        match v {
-            Value::Dict(ref d) => print_dict(d, &indent, depth + 1),
-            Value::List(ref l) => print_list(l, &indent, depth + 1),
-            Value::Bytes(ref b) => {
-                if b.len() > 80 {
-                    println!(
-                        "{}{}",
-                        indent.repeat(depth + 1),
-                        S_BYTES.paint(format!("[{} Bytes]", b.len()))
-                    )
-                } else {
-                    println!("{}{}", indent.repeat(depth + 1), String::from_utf8_lossy(b))
-                }
-            }
-            Value::Int(ref i) => println!("{}{}", indent.repeat(depth + 1), S_NUMBER.paint(i)),
-        }
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(files: &[ListedFile]) -> Vec<String> {
+        files.iter().map(|file| file.path.join("/")).collect()
+    }
+
+    #[test]
+    pub fn test_listed_files() {
+        let v1 = Torrent::from_buf(
+            b"d4:infod5:filesld6:lengthi1e4:pathl1:aeed6:lengthi2e4:pathl1:beee\
+4:name1:d12:piece lengthi16384e6:pieces0:ee",
+        )
+        .unwrap();
+        let mut files = listed_files(&v1);
+        assert_eq!(paths(&files), vec!["a", "b"]);
+        sort_files(&mut files, Some("size"), false);
+        assert_eq!(paths(&files), vec!["b", "a"]);
+
+        let single =
+            Torrent::from_buf(b"d4:infod6:lengthi5e4:name1:a12:piece lengthi16384e6:pieces0:ee")
+                .unwrap();
+        let files = listed_files(&single);
+        assert_eq!(paths(&files), vec!["a"]);
+        assert_eq!(files[0].length, 5);
+
+        let v2 = Torrent::from_buf(
+            b"d4:infod9:file treed1:ad1:cd0:d6:lengthi1eeee1:bd0:d6:lengthi2eeee\
+12:meta versioni2e4:name1:d12:piece lengthi16384eee",
+        )
+        .unwrap();
+        let mut files = listed_files(&v2);
+        assert_eq!(paths(&files), vec!["a/c", "b"]);
+        assert_eq!((files[0].index, files[1].index), (0, 1));
+        assert_eq!(files[1].length, 2);
+        sort_files(&mut files, Some("size"), true);
+        assert_eq!(paths(&files), vec!["a/c", "b"]);
+        sort_files(&mut files, Some("name"), true);
+        assert_eq!(paths(&files), vec!["a/c", "b"]);
+        let pattern = glob::Pattern::new("a/*").unwrap();
+        files.retain(|file| pattern.matches(&file.path.join("/")));
+        assert_eq!(paths(&files), vec!["a/c"]);
+
+        let single_v2 = Torrent::from_buf(
+            b"d4:infod9:file treed1:ad0:d6:lengthi5eeee12:meta versioni2e4:name1:a\
+12:piece lengthi16384eee",
+        )
+        .unwrap();
+        assert_eq!(paths(&listed_files(&single_v2)), vec!["a"]);
     }
 }
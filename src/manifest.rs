@@ -0,0 +1,191 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Lists of the files of torrents with their checksums.
+
+use std::path::{Path, PathBuf};
+
+use sanitize;
+use {to_hex, FileMode, Torrent};
+
+/// A file of a torrent with its position in the content and the checksums the torrent
+/// carries for it, see `Torrent::manifest`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ManifestEntry {
+    /// The decoded path, starting with the torrent name for multi-file torrents
+    pub path: Vec<String>,
+    pub length: u64,
+    /// The offset of the file within the content
+    pub offset: u64,
+    /// The hex MD5 sum of the file, which some old clients add
+    pub md5sum: Option<String>,
+    /// The SHA-1 hash of the file (BEP 47)
+    pub sha1: Option<[u8; 20]>,
+    /// The merkle root of the file in v2 torrents
+    pub pieces_root: Option<Vec<u8>>,
+}
+
+impl ManifestEntry {
+    /// Returns the path joined with `/`.
+    pub fn path_string(&self) -> String {
+        self.path.join("/")
+    }
+
+    /// Returns the path with unsafe components dropped or escaped, as in
+    /// `File::sanitized_path`.
+    pub fn sanitized_path(&self) -> Vec<String> {
+        sanitize::sanitize_path(&self.path)
+    }
+
+    /// Returns the location of the file in the download directory `root`, with the path
+    /// components sanitized so the file can't be outside of `root`.
+    pub fn local_path(&self, root: &Path) -> PathBuf {
+        let mut path = root.to_path_buf();
+        path.extend(self.sanitized_path());
+        path
+    }
+
+    /// Returns the entry as tab-separated offset, length, MD5, SHA-1 and path, with `-` for
+    /// missing checksums.
+    pub fn to_line(&self) -> String {
+        format!(
+            "{}\t{}\t{}\t{}\t{}",
+            self.offset,
+            self.length,
+            self.md5sum.as_deref().unwrap_or("-"),
+            self.sha1
+                .map_or_else(|| "-".to_owned(), |hash| to_hex(&hash)),
+            self.path_string()
+        )
+    }
+}
+
+pub fn entries(torrent: &Torrent) -> Vec<ManifestEntry> {
+    let info = torrent.info();
+    let name = torrent.decoded_name().map(|name| name.into_owned());
+    match info.mode() {
+        Some(FileMode::Single { length, md5sum }) => vec![ManifestEntry {
+            path: name.into_iter().collect(),
+            length: *length,
+            offset: 0,
+            md5sum: md5sum.clone(),
+            sha1: None,
+            pieces_root: None,
+        }],
+        Some(FileMode::Multi { files }) => files
+            .iter()
+            .filter(|file| !file.is_padding())
+            .map(|file| ManifestEntry {
+                path: name
+                    .iter()
+                    .cloned()
+                    .chain(
+                        torrent
+                            .decoded_path(file)
+                            .into_iter()
+                            .map(|c| c.into_owned()),
+                    )
+                    .collect(),
+                length: file.length(),
+                offset: file.offset(),
+                md5sum: file.md5sum().map(str::to_owned),
+                sha1: file.sha1().cloned(),
+                pieces_root: None,
+            })
+            .collect(),
+        None => {
+            let tree = info.file_tree();
+            // The tree of single-file torrents is the file named after the torrent
+            let single =
+                tree.len() == 1 && name.as_deref().is_some_and(|name| tree[0].path() == [name]);
+            let piece_length = info.piece_length().max(1);
+            let mut offset = 0u64;
+            tree.into_iter()
+                .map(|file| {
+                    let path = if single {
+                        file.path().to_vec()
+                    } else {
+                        name.iter().cloned().chain(file.path().to_vec()).collect()
+                    };
+                    let entry = ManifestEntry {
+                        path,
+                        length: file.length(),
+                        offset,
+                        md5sum: None,
+                        sha1: None,
+                        pieces_root: file.pieces_root().map(|root| root.to_vec()),
+                    };
+                    // Every file starts a new piece
+                    let pieces = file.length().div_ceil(piece_length);
+                    offset = offset.saturating_add(pieces.saturating_mul(piece_length));
+                    entry
+                })
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_manifest() {
+        let buf = &b"d4:infod5:filesld6:lengthi1e6:md5sum32:0123456789abcdef0123456789abcdef\
+4:pathl1:aee\
+d4:attr1:p6:lengthi3e4:pathl4:.pad1:3eed6:lengthi2e4:pathl1:b1:c\
+e4:sha120:aaaaaaaaaaaaaaaaaaaaee4:name1:d12:piece lengthi16384e6:pieces0:ee"[..];
+        let manifest = Torrent::from_buf(buf).unwrap().manifest();
+        assert_eq!(manifest.len(), 2);
+        assert_eq!(manifest[0].path, vec!["d", "a"]);
+        assert_eq!(
+            manifest[0].to_line(),
+            "0\t1\t0123456789abcdef0123456789abcdef\t-\td/a"
+        );
+        assert_eq!(manifest[1].offset, 4);
+        assert_eq!(manifest[1].sha1, Some([b'a'; 20]));
+        assert_eq!(manifest[1].path_string(), "d/b/c");
+        assert_eq!(
+            manifest[1].local_path(Path::new("/dl")),
+            Path::new("/dl/d/b/c")
+        );
+
+        let buf = &b"d4:infod6:lengthi5e4:name1:a12:piece lengthi16384e6:pieces0:ee"[..];
+        let manifest = Torrent::from_buf(buf).unwrap().manifest();
+        assert_eq!(manifest[0].to_line(), "0\t5\t-\t-\ta");
+
+        let buf = &b"d4:infod9:file treed1:ad0:d6:lengthi5eeee12:meta versioni2e4:name1:a\
+12:piece lengthi16384eee"[..];
+        let manifest = Torrent::from_buf(buf).unwrap().manifest();
+        assert_eq!(manifest.len(), 1);
+        assert_eq!(manifest[0].to_line(), "0\t5\t-\t-\ta");
+
+        let buf = &b"d4:infod9:file treed1:ad0:d6:lengthi16385eee1:bd0:d6:lengthi0eee\
+1:cd0:d6:lengthi1eeee12:meta versioni2e4:name1:d12:piece lengthi16384eee"[..];
+        let manifest = Torrent::from_buf(buf).unwrap().manifest();
+        let lines: Vec<String> = manifest.iter().map(ManifestEntry::to_line).collect();
+        assert_eq!(
+            lines,
+            vec![
+                "0\t16385\t-\t-\td/a",
+                "32768\t0\t-\t-\td/b",
+                "32768\t1\t-\t-\td/c",
+            ]
+        );
+    }
+}
@@ -0,0 +1,195 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! SHA-256 merkle trees of BitTorrent v2 (BEP 52) and the SHA-1 trees of merkle torrents
+//! (BEP 30).
+
+use hash;
+
+/// The size of the leaf blocks.
+pub const BLOCK_SIZE: usize = 16 * 1024;
+
+pub type Hash = [u8; 32];
+
+pub fn sha256(data: &[u8]) -> Hash {
+    hash::backend().sha256(data)
+}
+
+fn hash_pair(left: &Hash, right: &Hash) -> Hash {
+    let mut buf = [0u8; 64];
+    buf[..32].copy_from_slice(left);
+    buf[32..].copy_from_slice(right);
+    sha256(&buf)
+}
+
+/// Returns the root of a tree with `leaves` zero leaves, `leaves` being a power of two.
+pub fn zero_root(leaves: usize) -> Hash {
+    let mut hash = [0u8; 32];
+    let mut n = 1;
+    while n < leaves {
+        hash = hash_pair(&hash, &hash);
+        n *= 2;
+    }
+
+    hash
+}
+
+/// Calculates the root of the tree over `nodes`, padding them to a power of two with `pad`.
+pub fn root(nodes: &[Hash], pad: Hash) -> Hash {
+    if nodes.is_empty() {
+        return pad;
+    }
+    let mut layer = nodes.to_vec();
+    layer.resize(nodes.len().next_power_of_two(), pad);
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| hash_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    layer[0]
+}
+
+/// Calculates the root of the tree over the 16 KiB blocks of `data`.
+pub fn blocks_root(data: &[u8]) -> Hash {
+    let leaves: Vec<Hash> = data.chunks(BLOCK_SIZE).map(sha256).collect();
+    root(&leaves, [0u8; 32])
+}
+
+/// Extends the root of a subtree with `leaves` leaves to a subtree with `target` leaves,
+/// treating the added leaves as zero.
+pub fn extend_root(mut hash: Hash, leaves: usize, target: usize) -> Hash {
+    let mut n = leaves.next_power_of_two().max(1);
+    while n < target {
+        hash = hash_pair(&hash, &zero_root(n));
+        n *= 2;
+    }
+
+    hash
+}
+
+/// Calculates the `pieces root` of a file from its piece layer.
+pub fn file_root(piece_layer: &[Hash], piece_length: usize) -> Hash {
+    root(piece_layer, zero_root(piece_length / BLOCK_SIZE))
+}
+
+pub type Sha1Hash = [u8; 20];
+
+pub fn sha1(data: &[u8]) -> Sha1Hash {
+    hash::backend().sha1(data)
+}
+
+fn sha1_pair(left: &Sha1Hash, right: &Sha1Hash) -> Sha1Hash {
+    let mut buf = [0u8; 40];
+    buf[..20].copy_from_slice(left);
+    buf[20..].copy_from_slice(right);
+    sha1(&buf)
+}
+
+/// Calculates the root of a BEP 30 tree over the piece hashes `leaves`, padded to a power
+/// of two with zero hashes.
+pub fn sha1_root(leaves: &[Sha1Hash]) -> Sha1Hash {
+    let mut layer = leaves.to_vec();
+    layer.resize(leaves.len().next_power_of_two(), [0u8; 20]);
+    while layer.len() > 1 {
+        layer = layer
+            .chunks(2)
+            .map(|pair| sha1_pair(&pair[0], &pair[1]))
+            .collect();
+    }
+
+    layer.first().cloned().unwrap_or([0u8; 20])
+}
+
+/// Calculates the root from the hash of leaf `index` and the hashes of its uncles, ordered
+/// from the leaf layer up.
+pub fn sha1_proof_root(mut index: usize, leaf: Sha1Hash, uncles: &[Sha1Hash]) -> Sha1Hash {
+    let mut hash = leaf;
+    for uncle in uncles {
+        hash = if index.is_multiple_of(2) {
+            sha1_pair(&hash, uncle)
+        } else {
+            sha1_pair(uncle, &hash)
+        };
+        index /= 2;
+    }
+
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_blocks_root() {
+        let data = vec![1u8; BLOCK_SIZE * 3 + 10];
+        let leaves: Vec<Hash> = data.chunks(BLOCK_SIZE).map(sha256).collect();
+        let expected = hash_pair(
+            &hash_pair(&leaves[0], &leaves[1]),
+            &hash_pair(&leaves[2], &leaves[3]),
+        );
+        assert_eq!(blocks_root(&data), expected);
+        assert_eq!(blocks_root(&data[..100]), sha256(&data[..100]));
+    }
+
+    #[test]
+    pub fn test_extend_root() {
+        let data = vec![7u8; BLOCK_SIZE * 3];
+        let mut leaves: Vec<Hash> = data.chunks(BLOCK_SIZE).map(sha256).collect();
+        leaves.resize(8, [0; 32]);
+        assert_eq!(
+            extend_root(blocks_root(&data), 3, 8),
+            root(&leaves, [0; 32])
+        );
+        assert_eq!(extend_root(blocks_root(&data), 3, 4), blocks_root(&data));
+    }
+
+    #[test]
+    pub fn test_file_root() {
+        let data = vec![3u8; BLOCK_SIZE * 5];
+        let piece_length = BLOCK_SIZE * 2;
+        let layer: Vec<Hash> = data
+            .chunks(piece_length)
+            .map(|piece| {
+                let blocks = piece.len().div_ceil(BLOCK_SIZE);
+                extend_root(blocks_root(piece), blocks, 2)
+            })
+            .collect();
+        assert_eq!(file_root(&layer, piece_length), blocks_root(&data));
+    }
+
+    #[test]
+    pub fn test_sha1_root() {
+        let leaves: Vec<Sha1Hash> = (0..3u8).map(|i| sha1(&[i])).collect();
+        let root = sha1_root(&leaves);
+        assert_eq!(
+            root,
+            sha1_pair(
+                &sha1_pair(&leaves[0], &leaves[1]),
+                &sha1_pair(&leaves[2], &[0; 20])
+            )
+        );
+        assert_eq!(sha1_root(&leaves[..1]), leaves[0]);
+        assert_eq!(
+            sha1_proof_root(2, leaves[2], &[[0; 20], sha1_pair(&leaves[0], &leaves[1])]),
+            root
+        );
+    }
+}
@@ -0,0 +1,502 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Fetching the info dict of magnet links from peers (BEP 9, `net` feature).
+
+use std::collections::HashSet;
+use std::io::{self, Read, Write};
+use std::net::{SocketAddr, TcpStream, ToSocketAddrs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use bencode;
+use dht::{self, LookupOptions};
+use error::{Error, Result};
+use hash;
+use tracker::{self, peer_id, AnnounceRequest};
+use {InfoHash, Magnet, Torrent};
+
+const PROTOCOL: &[u8] = b"\x13BitTorrent protocol";
+const EXTENDED: u8 = 20;
+const EXTENDED_HANDSHAKE: u8 = 0;
+/// The extended message id of `ut_metadata` messages sent to us.
+const UT_METADATA: u8 = 1;
+const METADATA_PIECE_SIZE: usize = 16 * 1024;
+/// The maximum size of peer messages, larger ones end the connection.
+const MAX_MESSAGE_SIZE: usize = 1024 * 1024;
+
+/// The maximum size of an info dict fetched from peers.
+pub const MAX_METADATA_SIZE: usize = 16 * 1024 * 1024;
+/// The maximum duration of fetching the info dict from a single peer.
+pub const MAX_PEER_DURATION: Duration = Duration::from_secs(60);
+
+/// Options of fetching metadata.
+#[derive(Debug, Clone)]
+pub struct FetchOptions {
+    /// Peers to ask in addition to the `x.pe` peers of the magnet link
+    pub peers: Vec<SocketAddr>,
+    /// Ask the trackers of the magnet link for peers
+    pub trackers: bool,
+    /// Look up peers in the DHT, `None` to skip the DHT
+    pub dht: Option<LookupOptions>,
+    /// The timeout of connecting to and reading from a peer
+    pub timeout: Duration,
+    /// The number of peers asked at the same time
+    pub connections: usize,
+    /// The maximum number of peers asked
+    pub max_peers: usize,
+}
+
+impl Default for FetchOptions {
+    fn default() -> Self {
+        FetchOptions {
+            peers: vec![],
+            trackers: true,
+            dht: Some(LookupOptions::default()),
+            timeout: Duration::from_secs(10),
+            connections: 8,
+            max_peers: 100,
+        }
+    }
+}
+
+/// Fetches the info dict of the torrent of a magnet link from its peers and returns the
+/// torrent with the trackers and webseeds of the link.
+///
+/// Peers are taken from the options, the link, its trackers and the DHT, in this order.
+pub fn fetch(magnet: &Magnet, options: &FetchOptions) -> Result<Torrent> {
+    if magnet.info_hashes().is_empty() {
+        bail!(Error::Unsupported(
+            "fetching the metadata of v2-only magnet links".to_owned()
+        ));
+    }
+    let info_hash = magnet.info_hash();
+    let mut peers = options.peers.clone();
+    for peer in magnet.peers() {
+        if let Ok(addrs) = peer.to_socket_addrs() {
+            peers.extend(addrs);
+        }
+    }
+    if options.trackers {
+        let mut request = AnnounceRequest::new(info_hash, 6881);
+        // The size is unknown, but trackers return more peers to leechers
+        request.left = 1;
+        for url in magnet.trackers() {
+            if let Ok(response) = tracker::announce(url, &request) {
+                peers.extend(response.peers);
+            }
+        }
+    }
+    if let Some(ref dht_options) = options.dht {
+        if let Ok(lookup) = dht::get_peers(&info_hash, dht_options) {
+            peers.extend(lookup.peers);
+        }
+    }
+
+    let mut seen = HashSet::new();
+    peers.retain(|peer| seen.insert(*peer));
+    peers.truncate(options.max_peers);
+    if peers.is_empty() {
+        bail!(Error::Peer("no peers found".to_owned()));
+    }
+
+    let info = fetch_from_peers(peers, &info_hash, options)?;
+    torrent_from_info(magnet, &info)
+}
+
+/// Asks the peers concurrently until one sends the info dict.
+fn fetch_from_peers(
+    peers: Vec<SocketAddr>,
+    info_hash: &InfoHash,
+    options: &FetchOptions,
+) -> Result<Vec<u8>> {
+    let num_peers = peers.len();
+    let peers = Arc::new(Mutex::new(peers.into_iter()));
+    let done = Arc::new(AtomicBool::new(false));
+    let (sender, receiver) = mpsc::channel();
+    for _ in 0..options.connections.clamp(1, num_peers) {
+        let (peers, done, sender) = (peers.clone(), done.clone(), sender.clone());
+        let (info_hash, timeout) = (*info_hash, options.timeout);
+        thread::spawn(move || {
+            while !done.load(Ordering::Relaxed) {
+                let peer = match peers.lock().unwrap().next() {
+                    Some(peer) => peer,
+                    None => break,
+                };
+                let deadline = Instant::now() + MAX_PEER_DURATION;
+                let result = fetch_info_until(peer, &info_hash, timeout, deadline, &done);
+                if sender.send(result).is_err() {
+                    break;
+                }
+            }
+        });
+    }
+    drop(sender);
+
+    let mut last_error = None;
+    for result in receiver {
+        match result {
+            Ok(info) => {
+                done.store(true, Ordering::Relaxed);
+                return Ok(info);
+            }
+            Err(e) => last_error = Some(e),
+        }
+    }
+
+    bail!(Error::Peer(format!(
+        "none of {} peers sent the metadata, the last failed with: {}",
+        num_peers,
+        last_error.map_or_else(String::new, |e| e.to_string())
+    )))
+}
+
+/// Returns the torrent of the info dict `info` with the trackers and webseeds of the link.
+fn torrent_from_info(magnet: &Magnet, info: &[u8]) -> Result<Torrent> {
+    let mut buf = b"d4:info".to_vec();
+    buf.extend_from_slice(info);
+    if !magnet.web_seeds().is_empty() {
+        buf.extend_from_slice(b"8:url-listl");
+        for url in magnet.web_seeds() {
+            buf.extend_from_slice(format!("{}:{}", url.len(), url).as_bytes());
+        }
+        buf.push(b'e');
+    }
+    buf.push(b'e');
+
+    let mut torrent = Torrent::from_buf(&buf)?;
+    for (tier, url) in magnet.trackers().iter().enumerate() {
+        torrent.add_tracker(tier, url);
+    }
+
+    Ok(torrent)
+}
+
+fn send_extended(stream: &mut TcpStream, id: u8, payload: &[u8]) -> Result<()> {
+    let mut message = ((payload.len() + 2) as u32).to_be_bytes().to_vec();
+    message.push(EXTENDED);
+    message.push(id);
+    message.extend_from_slice(payload);
+    stream.write_all(&message)?;
+    Ok(())
+}
+
+/// Reads a length-prefixed message, which is empty for keep-alives.
+fn read_message(stream: &mut TcpStream) -> Result<Vec<u8>> {
+    let mut len = [0u8; 4];
+    stream.read_exact(&mut len)?;
+    let len = u32::from_be_bytes(len) as usize;
+    if len > MAX_MESSAGE_SIZE {
+        bail!(Error::Peer(format!(
+            "message of {} bytes is too large",
+            len
+        )));
+    }
+    let mut message = vec![0u8; len];
+    stream.read_exact(&mut message)?;
+    Ok(message)
+}
+
+/// Downloads the info dict from the peer at `addr` with the extension protocol (BEP 10)
+/// and checks it against the info hash, giving up after `MAX_PEER_DURATION`.
+pub fn fetch_info(addr: SocketAddr, info_hash: &InfoHash, timeout: Duration) -> Result<Vec<u8>> {
+    let deadline = Instant::now() + MAX_PEER_DURATION;
+    fetch_info_until(addr, info_hash, timeout, deadline, &AtomicBool::new(false))
+}
+
+/// Like `fetch_info`, giving up at `deadline` or once `stop` is set, so peers which
+/// only send keep-alives or other messages can't hold up the caller.
+fn fetch_info_until(
+    addr: SocketAddr,
+    info_hash: &InfoHash,
+    timeout: Duration,
+    deadline: Instant,
+    stop: &AtomicBool,
+) -> Result<Vec<u8>> {
+    let mut stream = TcpStream::connect_timeout(&addr, timeout)?;
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+
+    let mut handshake = PROTOCOL.to_vec();
+    // The reserved bit of the extension protocol
+    handshake.extend_from_slice(&[0, 0, 0, 0, 0, 0x10, 0, 0]);
+    handshake.extend_from_slice(info_hash.as_bytes());
+    handshake.extend_from_slice(&peer_id());
+    stream.write_all(&handshake)?;
+    let mut reply = [0u8; 68];
+    stream.read_exact(&mut reply)?;
+    if &reply[..20] != PROTOCOL {
+        bail!(Error::Peer("invalid handshake".to_owned()));
+    }
+    if &reply[28..48] != info_hash.as_bytes() {
+        bail!(Error::Peer("peer doesn't have the torrent".to_owned()));
+    }
+    if reply[25] & 0x10 == 0 {
+        bail!(Error::Peer(
+            "peer doesn't support the extension protocol".to_owned()
+        ));
+    }
+    send_extended(
+        &mut stream,
+        EXTENDED_HANDSHAKE,
+        format!("d1:md11:ut_metadatai{}eee", UT_METADATA).as_bytes(),
+    )?;
+
+    let mut metadata: Option<(Vec<u8>, Vec<bool>)> = None;
+    loop {
+        if stop.load(Ordering::Relaxed) {
+            bail!(Error::Peer("cancelled".to_owned()));
+        }
+        let too_late = || Error::Peer("peer didn't send the metadata in time".to_owned());
+        let remaining = deadline.saturating_duration_since(Instant::now());
+        if remaining.is_zero() {
+            bail!(too_late());
+        }
+        stream.set_read_timeout(Some(timeout.min(remaining)))?;
+        let message = match read_message(&mut stream) {
+            Err(Error::Io(ref e))
+                if (e.kind() == io::ErrorKind::WouldBlock
+                    || e.kind() == io::ErrorKind::TimedOut)
+                    && Instant::now() >= deadline =>
+            {
+                bail!(too_late())
+            }
+            message => message?,
+        };
+        if message.len() < 2 || message[0] != EXTENDED {
+            continue;
+        }
+        let payload = &message[2..];
+        match message[1] {
+            EXTENDED_HANDSHAKE if metadata.is_none() => {
+                let id = bencode::dict_value(payload, b"m")
+                    .and_then(|m| bencode::dict_value(m, b"ut_metadata"))
+                    .and_then(bencode::as_uint);
+                let size =
+                    bencode::dict_value(payload, b"metadata_size").and_then(bencode::as_uint);
+                let (id, size) = match (id, size) {
+                    (Some(id), Some(size))
+                        if id > 0 && id < 256 && size > 0 && size <= MAX_METADATA_SIZE as u64 =>
+                    {
+                        (id as u8, size as usize)
+                    }
+                    _ => bail!(Error::Peer("peer doesn't offer the metadata".to_owned())),
+                };
+                let num_pieces = size.div_ceil(METADATA_PIECE_SIZE);
+                for piece in 0..num_pieces {
+                    let request = format!("d8:msg_typei0e5:piecei{}ee", piece);
+                    send_extended(&mut stream, id, request.as_bytes())?;
+                }
+                metadata = Some((vec![0; size], vec![false; num_pieces]));
+            }
+            UT_METADATA => {
+                let (buf, received) = match metadata {
+                    Some((ref mut buf, ref mut received)) => (buf, received),
+                    None => continue,
+                };
+                let dict_end = match bencode::value_end(payload, 0) {
+                    Some(end) => end,
+                    None => bail!(Error::Peer("invalid metadata message".to_owned())),
+                };
+                let dict = &payload[..dict_end];
+                let msg_type = bencode::dict_value(dict, b"msg_type").and_then(bencode::as_uint);
+                let piece = bencode::dict_value(dict, b"piece").and_then(bencode::as_uint);
+                match (msg_type, piece) {
+                    (Some(1), Some(piece)) if (piece as usize) < received.len() => {
+                        let offset = piece as usize * METADATA_PIECE_SIZE;
+                        let len = METADATA_PIECE_SIZE.min(buf.len() - offset);
+                        let data = &payload[dict_end..];
+                        if data.len() != len {
+                            bail!(Error::Peer(format!(
+                                "metadata piece {} has the wrong size",
+                                piece
+                            )));
+                        }
+                        buf[offset..offset + len].copy_from_slice(data);
+                        received[piece as usize] = true;
+                    }
+                    (Some(2), _) => {
+                        bail!(Error::Peer("peer rejected the metadata request".to_owned()))
+                    }
+                    _ => continue,
+                }
+
+                if received.iter().all(|&r| r) {
+                    if hash::backend().sha1(buf)[..] != info_hash.as_bytes()[..] {
+                        bail!(Error::Peer(
+                            "metadata doesn't match the info hash".to_owned()
+                        ));
+                    }
+                    return Ok(buf.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::net::TcpListener;
+
+    /// Runs a peer sending `info` in metadata pieces.
+    fn fake_peer(info_hash: InfoHash, info: Vec<u8>) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut handshake = [0u8; 68];
+                stream.read_exact(&mut handshake).unwrap();
+                let mut reply = PROTOCOL.to_vec();
+                reply.extend_from_slice(&[0, 0, 0, 0, 0, 0x10, 0, 0]);
+                reply.extend_from_slice(info_hash.as_bytes());
+                reply.extend_from_slice(&[b'p'; 20]);
+                stream.write_all(&reply).unwrap();
+                // Have messages before the handshake are skipped
+                stream.write_all(&[0, 0, 0, 5, 4, 0, 0, 0, 0]).unwrap();
+                let handshake = format!("d1:md11:ut_metadatai3ee13:metadata_sizei{}ee", info.len());
+                send_extended(&mut stream, EXTENDED_HANDSHAKE, handshake.as_bytes()).unwrap();
+
+                while let Ok(message) = read_message(&mut stream) {
+                    if message[..2] != [EXTENDED, 3] {
+                        continue;
+                    }
+                    let piece = bencode::dict_value(&message[2..], b"piece")
+                        .and_then(bencode::as_uint)
+                        .unwrap() as usize;
+                    let data = info.chunks(METADATA_PIECE_SIZE).nth(piece).unwrap();
+                    let mut payload = format!(
+                        "d8:msg_typei1e5:piecei{}e10:total_sizei{}ee",
+                        piece,
+                        info.len()
+                    )
+                    .into_bytes();
+                    payload.extend_from_slice(data);
+                    send_extended(&mut stream, UT_METADATA, &payload).unwrap();
+                }
+            }
+        });
+        addr
+    }
+
+    fn info() -> Vec<u8> {
+        // Two metadata pieces
+        let mut info =
+            b"d6:lengthi5e4:name1:a12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaa\
+1:x20000:"
+                .to_vec();
+        info.extend_from_slice(&[b'x'; 20000]);
+        info.push(b'e');
+        info
+    }
+
+    fn info_hash(info: &[u8]) -> InfoHash {
+        InfoHash::from(hash::backend().sha1(info))
+    }
+
+    #[test]
+    pub fn test_fetch_info() {
+        let info = info();
+        let info_hash = info_hash(&info);
+        let peer = fake_peer(info_hash, info.clone());
+        let timeout = Duration::from_secs(5);
+        assert_eq!(fetch_info(peer, &info_hash, timeout).unwrap(), info);
+
+        let wrong = fake_peer(info_hash, b"d4:name1:be".to_vec());
+        match fetch_info(wrong, &info_hash, timeout) {
+            Err(Error::Peer(ref e)) => assert_eq!(e, "metadata doesn't match the info hash"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    /// Runs a peer which only sends keep-alives after the handshake.
+    fn idle_peer(info_hash: InfoHash) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut handshake = [0u8; 68];
+                stream.read_exact(&mut handshake).unwrap();
+                let mut reply = PROTOCOL.to_vec();
+                reply.extend_from_slice(&[0, 0, 0, 0, 0, 0x10, 0, 0]);
+                reply.extend_from_slice(info_hash.as_bytes());
+                reply.extend_from_slice(&[b'p'; 20]);
+                stream.write_all(&reply).unwrap();
+                while stream.write_all(&[0, 0, 0, 0]).is_ok() {
+                    thread::sleep(Duration::from_millis(10));
+                }
+            }
+        });
+        addr
+    }
+
+    #[test]
+    pub fn test_fetch_info_deadline() {
+        let info_hash = info_hash(&info());
+        let peer = idle_peer(info_hash);
+        let timeout = Duration::from_secs(5);
+        let deadline = Instant::now() + Duration::from_millis(200);
+        let stop = AtomicBool::new(false);
+        match fetch_info_until(peer, &info_hash, timeout, deadline, &stop) {
+            Err(Error::Peer(ref e)) => assert_eq!(e, "peer didn't send the metadata in time"),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        let deadline = Instant::now() + MAX_PEER_DURATION;
+        stop.store(true, Ordering::Relaxed);
+        match fetch_info_until(peer, &info_hash, timeout, deadline, &stop) {
+            Err(Error::Peer(ref e)) => assert_eq!(e, "cancelled"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    pub fn test_fetch() {
+        let info = info();
+        let info_hash = info_hash(&info);
+        let peer = fake_peer(info_hash, info);
+        let link = format!(
+            "magnet:?xt=urn:btih:{}&tr=http%3A%2F%2Fa%2F&tr=http%3A%2F%2Fb%2F\
+&ws=http%3A%2F%2Fw%2F&x.pe={}",
+            info_hash, peer
+        );
+        let options = FetchOptions {
+            trackers: false,
+            dht: None,
+            ..FetchOptions::default()
+        };
+        let torrent = fetch(&link.parse().unwrap(), &options).unwrap();
+        assert_eq!(torrent.info_hash().unwrap(), info_hash);
+        assert_eq!(torrent.trackers(), ["http://a/", "http://b/"]);
+        assert_eq!(torrent.url_list(), ["http://w/"]);
+        let torrent = Torrent::from_buf(&torrent.to_bytes().unwrap()).unwrap();
+        assert_eq!(torrent.info_hash().unwrap(), info_hash);
+
+        let magnet = Magnet::from(info_hash);
+        match fetch(&magnet, &options) {
+            Err(Error::Peer(ref e)) => assert_eq!(e, "no peers found"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+}
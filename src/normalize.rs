@@ -0,0 +1,73 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Unicode normalization of decoded names and paths.
+
+use std::borrow::Cow;
+
+use unicode_normalization::{is_nfc, is_nfd, UnicodeNormalization};
+
+/// A Unicode normalization form.
+///
+/// macOS stores file names decomposed (NFD) while most other systems keep them composed
+/// (NFC), so the same name can be encoded differently by torrents created on each.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Normalization {
+    /// Canonical composition
+    Nfc,
+    /// Canonical decomposition
+    Nfd,
+}
+
+impl Normalization {
+    /// Normalizes `s`, borrowing it if it is already normalized.
+    pub fn normalize<'a, S: Into<Cow<'a, str>>>(self, s: S) -> Cow<'a, str> {
+        let s = s.into();
+        match self {
+            Normalization::Nfc if !is_nfc(&s) => Cow::Owned(s.nfc().collect()),
+            Normalization::Nfd if !is_nfd(&s) => Cow::Owned(s.nfd().collect()),
+            _ => s,
+        }
+    }
+
+    /// Normalizes each component of `path`.
+    pub fn normalize_path<'a>(self, path: Vec<Cow<'a, str>>) -> Vec<Cow<'a, str>> {
+        path.into_iter().map(|c| self.normalize(c)).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_normalize() {
+        let composed = "caf\u{e9}";
+        let decomposed = "cafe\u{301}";
+        assert_eq!(Normalization::Nfc.normalize(decomposed), composed);
+        assert_eq!(Normalization::Nfd.normalize(composed), decomposed);
+        assert!(match Normalization::Nfc.normalize(composed) {
+            Cow::Borrowed(_) => true,
+            Cow::Owned(_) => false,
+        });
+        assert_eq!(
+            Normalization::Nfc.normalize_path(vec![decomposed.into(), "a".into()]),
+            vec![composed, "a"]
+        );
+    }
+}
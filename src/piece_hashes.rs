@@ -0,0 +1,86 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+use std::convert::TryFrom;
+
+use to_hex;
+
+/// The length of a SHA-1 piece hash.
+pub const PIECE_HASH_LEN: usize = 20;
+
+/// The concatenated SHA-1 piece hashes of a v1 torrent.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PieceHashes<'a>(&'a [u8]);
+
+impl<'a> PieceHashes<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        PieceHashes(bytes)
+    }
+
+    /// Returns the number of complete hashes.
+    pub fn len(&self) -> usize {
+        self.0.len() / PIECE_HASH_LEN
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Returns the hash of piece `index`.
+    pub fn get(&self, index: usize) -> Option<&'a [u8; 20]> {
+        let start = index.checked_mul(PIECE_HASH_LEN)?;
+        let hash = self.0.get(start..start.checked_add(PIECE_HASH_LEN)?)?;
+        <&[u8; 20]>::try_from(hash).ok()
+    }
+
+    /// Returns the hash of piece `index` as hex string.
+    pub fn hex(&self, index: usize) -> Option<String> {
+        self.get(index).map(|hash| to_hex(hash))
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &'a [u8; 20]> + 'a {
+        self.0
+            .chunks_exact(PIECE_HASH_LEN)
+            .filter_map(|hash| <&[u8; 20]>::try_from(hash).ok())
+    }
+
+    /// Returns the concatenated hashes, including a trailing partial hash.
+    pub fn as_bytes(&self) -> &'a [u8] {
+        self.0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_piece_hashes() {
+        let mut bytes = vec![1u8; 20];
+        bytes.extend_from_slice(&[0xab; 20]);
+        bytes.push(7);
+        let hashes = PieceHashes::new(&bytes);
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes.get(0), Some(&[1u8; 20]));
+        assert_eq!(hashes.get(2), None);
+        assert_eq!(hashes.hex(1), Some("ab".repeat(20)));
+        assert_eq!(hashes.iter().count(), 2);
+        assert_eq!(hashes.as_bytes().len(), 41);
+        assert!(PieceHashes::default().is_empty());
+    }
+}
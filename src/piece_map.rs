@@ -0,0 +1,161 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Mapping of pieces to the files they cover.
+
+use std::ops::Range;
+
+/// A byte range of a file covered by a piece.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileSlice {
+    /// The index of the file
+    pub file: usize,
+    /// The offset of the range within the file
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// Returns the ranges of the files with `lengths` covered by piece `index`.
+///
+/// With `aligned`, every file starts a new piece, as in v2 torrents.
+pub fn file_slices(
+    lengths: &[u64],
+    piece_length: u64,
+    aligned: bool,
+    index: usize,
+) -> Vec<FileSlice> {
+    let mut slices = vec![];
+    if piece_length == 0 {
+        return slices;
+    }
+
+    if aligned {
+        let mut first_piece: u64 = 0;
+        for (file, &length) in lengths.iter().enumerate() {
+            let pieces = length.div_ceil(piece_length);
+            if (index as u64) < first_piece.saturating_add(pieces) {
+                let offset = (index as u64 - first_piece) * piece_length;
+                slices.push(FileSlice {
+                    file,
+                    offset,
+                    length: piece_length.min(length - offset),
+                });
+                break;
+            }
+            first_piece = first_piece.saturating_add(pieces);
+        }
+    } else {
+        let (start, end) = match (index as u64)
+            .checked_mul(piece_length)
+            .and_then(|start| Some((start, start.checked_add(piece_length)?)))
+        {
+            Some(range) => range,
+            None => return slices,
+        };
+        let mut file_start: u64 = 0;
+        for (file, &length) in lengths.iter().enumerate() {
+            let file_end = file_start.saturating_add(length);
+            if length > 0 && file_end > start && file_start < end {
+                let offset = start.saturating_sub(file_start);
+                slices.push(FileSlice {
+                    file,
+                    offset,
+                    length: file_end.min(end) - file_start - offset,
+                });
+            }
+            if file_end >= end {
+                break;
+            }
+            file_start = file_end;
+        }
+    }
+
+    slices
+}
+
+/// Returns the pieces covering file `file` of the files with `lengths`, which are empty for
+/// empty files.
+pub fn file_pieces(lengths: &[u64], piece_length: u64, aligned: bool, file: usize) -> Range<usize> {
+    let length = match lengths.get(file) {
+        Some(&length) if length > 0 && piece_length > 0 => length,
+        _ => return 0..0,
+    };
+
+    if aligned {
+        let first: u64 = lengths[..file]
+            .iter()
+            .map(|l| l.div_ceil(piece_length))
+            .sum();
+        first as usize..(first + length.div_ceil(piece_length)) as usize
+    } else {
+        let offset: u64 = lengths[..file].iter().sum();
+        (offset / piece_length) as usize..((offset + length - 1) / piece_length + 1) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn slice(file: usize, offset: u64, length: u64) -> FileSlice {
+        FileSlice {
+            file,
+            offset,
+            length,
+        }
+    }
+
+    #[test]
+    pub fn test_file_slices() {
+        let lengths = [10, 0, 25, 3];
+        assert_eq!(
+            file_slices(&lengths, 16, false, 0),
+            vec![slice(0, 0, 10), slice(2, 0, 6)]
+        );
+        assert_eq!(file_slices(&lengths, 16, false, 1), vec![slice(2, 6, 16)]);
+        assert_eq!(
+            file_slices(&lengths, 16, false, 2),
+            vec![slice(2, 22, 3), slice(3, 0, 3)]
+        );
+        assert_eq!(file_slices(&lengths, 16, false, 3), vec![]);
+
+        assert_eq!(file_slices(&lengths, 16, true, 0), vec![slice(0, 0, 10)]);
+        assert_eq!(file_slices(&lengths, 16, true, 2), vec![slice(2, 16, 9)]);
+        assert_eq!(file_slices(&lengths, 16, true, 3), vec![slice(3, 0, 3)]);
+        assert_eq!(file_slices(&lengths, 16, true, 4), vec![]);
+
+        assert_eq!(file_slices(&lengths, u64::MAX, false, 1), vec![]);
+        assert_eq!(file_slices(&lengths, 1 << 40, false, usize::MAX), vec![]);
+        assert_eq!(
+            file_slices(&[u64::MAX, u64::MAX], 1, true, 5),
+            vec![slice(0, 5, 1)]
+        );
+    }
+
+    #[test]
+    pub fn test_file_pieces() {
+        let lengths = [10, 0, 25, 3];
+        assert_eq!(file_pieces(&lengths, 16, false, 0), 0..1);
+        assert_eq!(file_pieces(&lengths, 16, false, 1), 0..0);
+        assert_eq!(file_pieces(&lengths, 16, false, 2), 0..3);
+        assert_eq!(file_pieces(&lengths, 16, false, 3), 2..3);
+        assert_eq!(file_pieces(&lengths, 16, true, 2), 1..3);
+        assert_eq!(file_pieces(&lengths, 16, true, 3), 3..4);
+        assert_eq!(file_pieces(&lengths, 16, true, 4), 0..0);
+    }
+}
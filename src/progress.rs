@@ -0,0 +1,43 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+use std::path::Path;
+
+/// Receives progress updates of long-running operations like creating torrents.
+///
+/// All methods are called on the thread which started the operation and do nothing by
+/// default.
+pub trait Progress {
+    /// Called once before any data is read.
+    fn start(&mut self, _total_bytes: u64, _total_pieces: usize) {}
+
+    /// Called when reading of a file starts.
+    fn file(&mut self, _path: &Path) {}
+
+    /// Called after each hashed piece, with the totals so far.
+    fn piece(&mut self, _pieces_done: usize, _bytes_done: u64) {}
+
+    /// Called once after all pieces have been hashed.
+    fn finish(&mut self) {}
+}
+
+/// Ignores all progress updates.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct NoProgress;
+
+impl Progress for NoProgress {}
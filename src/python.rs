@@ -0,0 +1,323 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Python bindings (`python` feature).
+//!
+//! Build the `torrentinfo` extension module with `maturin build`, which enables the
+//! features of `pyproject.toml`. The accessors of `Torrent`, `Info` and `File` are
+//! read-only properties named like their Rust counterparts.
+
+use pyo3::exceptions::{PyOSError, PyValueError};
+use pyo3::prelude::*;
+use pyo3::types::PyBytes;
+
+use error::Error;
+use {File, Info, Torrent};
+
+fn py_error(e: Error) -> PyErr {
+    match e {
+        Error::Io(_) => PyOSError::new_err(e.to_string()),
+        Error::ReadTorrent { ref source, .. } if matches!(**source, Error::Io(_)) => {
+            PyOSError::new_err(e.to_string())
+        }
+        _ => PyValueError::new_err(e.to_string()),
+    }
+}
+
+/// A parsed torrent, exported to Python as `torrentinfo.Torrent`.
+#[pyclass(name = "Torrent", module = "torrentinfo", frozen)]
+pub struct PyTorrent {
+    torrent: Torrent,
+}
+
+#[pymethods]
+impl PyTorrent {
+    /// Parses a torrent from `bytes`.
+    #[new]
+    fn new(buf: &[u8]) -> PyResult<Self> {
+        Torrent::from_buf(buf)
+            .map(|torrent| PyTorrent { torrent })
+            .map_err(py_error)
+    }
+
+    /// Reads and parses the torrent file at `path`.
+    #[staticmethod]
+    fn from_file(path: &str) -> PyResult<Self> {
+        Torrent::from_file(path)
+            .map(|torrent| PyTorrent { torrent })
+            .map_err(py_error)
+    }
+
+    #[getter]
+    fn info(&self) -> PyInfo {
+        PyInfo {
+            info: self.torrent.info().clone(),
+            name: self.torrent.decoded_name().map(|name| name.into_owned()),
+        }
+    }
+
+    /// The hex v1 info hash.
+    #[getter]
+    fn info_hash(&self) -> PyResult<String> {
+        self.torrent
+            .info_hash()
+            .map(|hash| hash.to_hex())
+            .map_err(py_error)
+    }
+
+    #[getter]
+    fn name(&self) -> Option<String> {
+        self.torrent.decoded_name().map(|name| name.into_owned())
+    }
+
+    #[getter]
+    fn announce(&self) -> Option<&str> {
+        self.torrent.announce()
+    }
+
+    /// The tracker tiers, falling back to `announce`.
+    #[getter]
+    fn tiers(&self) -> Vec<Vec<String>> {
+        self.torrent
+            .tiers()
+            .into_iter()
+            .map(|tier| tier.to_vec())
+            .collect()
+    }
+
+    #[getter]
+    fn trackers(&self) -> Vec<&str> {
+        self.torrent.trackers()
+    }
+
+    #[getter]
+    fn comment(&self) -> Option<String> {
+        self.torrent
+            .decoded_comment()
+            .map(|comment| comment.into_owned())
+    }
+
+    #[getter]
+    fn created_by(&self) -> Option<&str> {
+        self.torrent.created_by()
+    }
+
+    /// The creation date as a Unix timestamp.
+    #[getter]
+    fn creation_date(&self) -> Option<i64> {
+        self.torrent.creation_date()
+    }
+
+    #[getter]
+    fn is_private(&self) -> bool {
+        self.torrent.is_private()
+    }
+
+    /// The files of multi-file torrents, `None` for single-file and v2 torrents.
+    #[getter]
+    fn files(&self) -> Option<Vec<PyFile>> {
+        self.torrent.files().map(|files| {
+            files
+                .iter()
+                .map(|file| PyFile {
+                    path: self
+                        .torrent
+                        .decoded_path(file)
+                        .into_iter()
+                        .map(|c| c.into_owned())
+                        .collect(),
+                    file: file.clone(),
+                })
+                .collect()
+        })
+    }
+
+    #[getter]
+    fn num_files(&self) -> usize {
+        self.torrent.num_files()
+    }
+
+    #[getter]
+    fn total_size(&self) -> u64 {
+        self.torrent.total_size()
+    }
+
+    #[getter]
+    fn num_pieces(&self) -> usize {
+        self.torrent.num_pieces()
+    }
+
+    #[getter]
+    fn url_list(&self) -> Vec<&str> {
+        self.torrent.url_list()
+    }
+
+    fn magnet_link(&self) -> PyResult<String> {
+        self.torrent.magnet_link().map_err(py_error)
+    }
+
+    /// Serializes the torrent to bencode.
+    fn to_bytes<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyBytes>> {
+        let buf = self.torrent.to_bytes().map_err(py_error)?;
+        Ok(PyBytes::new(py, &buf))
+    }
+
+    fn __repr__(&self) -> String {
+        format!(
+            "Torrent(name={:?}, info_hash={:?})",
+            self.name().unwrap_or_default(),
+            self.info_hash().unwrap_or_default()
+        )
+    }
+}
+
+/// The info dict of a torrent, exported to Python as `torrentinfo.Info`.
+#[pyclass(name = "Info", module = "torrentinfo", frozen)]
+pub struct PyInfo {
+    info: Info,
+    /// The name decoded with the encoding of the torrent
+    name: Option<String>,
+}
+
+#[pymethods]
+impl PyInfo {
+    #[getter]
+    fn name(&self) -> Option<String> {
+        self.name.clone()
+    }
+
+    #[getter]
+    fn piece_length(&self) -> u64 {
+        self.info.piece_length()
+    }
+
+    /// The SHA-1 piece hashes.
+    #[getter]
+    fn pieces<'py>(&self, py: Python<'py>) -> Vec<Bound<'py, PyBytes>> {
+        self.info
+            .pieces()
+            .iter()
+            .map(|hash| PyBytes::new(py, hash))
+            .collect()
+    }
+
+    #[getter]
+    fn private(&self) -> bool {
+        self.info.private()
+    }
+
+    #[getter]
+    fn source(&self) -> Option<&str> {
+        self.info.source()
+    }
+
+    #[getter]
+    fn meta_version(&self) -> Option<i64> {
+        self.info.meta_version()
+    }
+}
+
+/// A file of a multi-file torrent, exported to Python as `torrentinfo.File`.
+#[pyclass(name = "File", module = "torrentinfo", frozen)]
+pub struct PyFile {
+    file: File,
+    /// The path decoded with the encoding of the torrent
+    path: Vec<String>,
+}
+
+#[pymethods]
+impl PyFile {
+    #[getter]
+    fn path(&self) -> Vec<String> {
+        self.path.clone()
+    }
+
+    #[getter]
+    fn length(&self) -> u64 {
+        self.file.length()
+    }
+
+    #[getter]
+    fn md5sum(&self) -> Option<&str> {
+        self.file.md5sum()
+    }
+
+    #[getter]
+    fn is_padding(&self) -> bool {
+        self.file.is_padding()
+    }
+
+    fn __repr__(&self) -> String {
+        format!("File(path={:?}, length={})", self.path, self.file.length())
+    }
+}
+
+/// The `torrentinfo` Python module.
+#[pymodule]
+#[pyo3(name = "torrentinfo")]
+fn python_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTorrent>()?;
+    m.add_class::<PyInfo>()?;
+    m.add_class::<PyFile>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+    use std::ffi::CString;
+
+    #[test]
+    pub fn test_module() {
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "torrentinfo").unwrap();
+            python_module(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("torrentinfo", module).unwrap();
+            let script = CString::new(
+                r#"
+t = torrentinfo.Torrent(b"d8:announce9:udp://t/a4:infod5:filesld6:lengthi3e4:pathl1:aee"
+    b"d6:lengthi4e4:pathl3:dir1:beee4:name1:t12:piece lengthi16384e"
+    b"6:pieces20:aaaaaaaaaaaaaaaaaaaaee")
+assert t.name == "t" and t.info.name == "t"
+assert len(t.info_hash) == 40
+assert t.trackers == ["udp://t/a"] and t.tiers == [["udp://t/a"]]
+assert t.total_size == 7 and t.num_files == 2 and t.num_pieces == 1
+assert [(f.path, f.length) for f in t.files] == [(["a"], 3), (["dir", "b"], 4)]
+assert t.info.piece_length == 16384 and t.info.pieces == [b"a" * 20]
+assert not t.is_private and t.comment is None
+assert torrentinfo.Torrent(t.to_bytes()).info_hash == t.info_hash
+try:
+    torrentinfo.Torrent(b"le")
+    assert False
+except ValueError:
+    pass
+try:
+    torrentinfo.Torrent.from_file("/nonexistent.torrent")
+    assert False
+except OSError:
+    pass
+"#,
+            )
+            .unwrap();
+            py.run(&script, Some(&globals), None).unwrap();
+        });
+    }
+}
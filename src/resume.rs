@@ -0,0 +1,174 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Fast-resume data for BitTorrent clients.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_bencode::ser;
+use serde_bencode::value::Value;
+
+use error::Result;
+use {PieceStatus, Torrent, TorrentVersion, Verification};
+
+fn bytes<B: Into<Vec<u8>>>(bytes: B) -> Value {
+    Value::Bytes(bytes.into())
+}
+
+fn strings<'a, I: IntoIterator<Item = &'a str>>(strings: I) -> Value {
+    Value::List(strings.into_iter().map(bytes).collect())
+}
+
+/// Creates bencoded libtorrent resume data for a torrent saved in `save_path`, so a client
+/// can start seeding without checking the data again.
+///
+/// Only the good pieces of the `verification` are marked as downloaded, pieces left out
+/// by sampling are not.
+pub fn libtorrent_resume<P: AsRef<Path>>(
+    torrent: &Torrent,
+    verification: &Verification,
+    save_path: P,
+) -> Result<Vec<u8>> {
+    let mut dict = HashMap::new();
+    let mut insert = |key: &str, value| {
+        dict.insert(key.as_bytes().to_vec(), value);
+    };
+    insert("file-format", bytes("libtorrent resume file"));
+    insert("file-version", Value::Int(1));
+    if torrent.version() != TorrentVersion::V2 {
+        insert("info-hash", bytes(&torrent.info_hash()?.as_bytes()[..]));
+    }
+    if let Some(info_hash) = torrent.info_hash_v2()? {
+        insert("info-hash2", bytes(&info_hash[..]));
+    }
+    if let Some(name) = torrent.decoded_name() {
+        insert("name", bytes(name.into_owned()));
+    }
+    insert(
+        "save_path",
+        bytes(save_path.as_ref().to_string_lossy().into_owned()),
+    );
+    // One byte per piece, with the lowest bit set for pieces the client has
+    let pieces: Vec<u8> = verification
+        .pieces
+        .iter()
+        .map(|&s| (s == PieceStatus::Good) as u8)
+        .collect();
+    insert("pieces", bytes(pieces));
+    insert(
+        "trackers",
+        Value::List(
+            torrent
+                .tiers()
+                .into_iter()
+                .map(|tier| strings(tier.iter().map(|url| url.as_str())))
+                .collect(),
+        ),
+    );
+    insert("url-list", strings(torrent.url_list()));
+    insert(
+        "httpseeds",
+        strings(
+            torrent
+                .httpseeds()
+                .unwrap_or_default()
+                .iter()
+                .map(|url| url.as_str()),
+        ),
+    );
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64);
+    insert("added_time", Value::Int(now));
+    if verification.is_complete() {
+        insert("completed_time", Value::Int(now));
+    }
+    for key in &[
+        "total_uploaded",
+        "total_downloaded",
+        "active_time",
+        "seeding_time",
+        "finished_time",
+        "paused",
+        "seed_mode",
+    ] {
+        insert(key, Value::Int(0));
+    }
+    insert("auto_managed", Value::Int(1));
+
+    Ok(ser::to_bytes(&Value::Dict(dict))?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_bencode::de;
+    use std::path::PathBuf;
+
+    fn get<'a>(dict: &'a HashMap<Vec<u8>, Value>, key: &str) -> &'a Value {
+        &dict[key.as_bytes()]
+    }
+
+    #[test]
+    pub fn test_libtorrent_resume() {
+        let buf = &b"d8:announce15:http://tracker/4:infod6:lengthi5e4:name1:a\
+12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let verification = Verification {
+            pieces: vec![PieceStatus::Good],
+            piece_length: 16384,
+            total_size: 5,
+            ..Verification::default()
+        };
+
+        let resume = libtorrent_resume(&torrent, &verification, PathBuf::from("/data")).unwrap();
+        let dict = match de::from_bytes(&resume).unwrap() {
+            Value::Dict(dict) => dict,
+            v => panic!("unexpected value {:?}", v),
+        };
+        assert_eq!(get(&dict, "file-format"), &bytes("libtorrent resume file"));
+        assert_eq!(
+            get(&dict, "info-hash"),
+            &bytes(&torrent.info_hash().unwrap().as_bytes()[..])
+        );
+        assert!(!dict.contains_key(&b"info-hash2"[..]));
+        assert_eq!(get(&dict, "name"), &bytes("a"));
+        assert_eq!(get(&dict, "save_path"), &bytes("/data"));
+        assert_eq!(get(&dict, "pieces"), &bytes(vec![1]));
+        assert_eq!(
+            get(&dict, "trackers"),
+            &Value::List(vec![strings(vec!["http://tracker/"])])
+        );
+        assert!(dict.contains_key(&b"completed_time"[..]));
+
+        let verification = Verification {
+            pieces: vec![PieceStatus::Unchecked],
+            ..verification
+        };
+        let resume = libtorrent_resume(&torrent, &verification, "/data").unwrap();
+        match de::from_bytes(&resume).unwrap() {
+            Value::Dict(dict) => {
+                assert_eq!(get(&dict, "pieces"), &bytes(vec![0]));
+                assert!(!dict.contains_key(&b"completed_time"[..]));
+            }
+            v => panic!("unexpected value {:?}", v),
+        }
+    }
+}
@@ -0,0 +1,349 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! rtorrent session directories.
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde_bencode::de;
+use serde_bencode::value::Value;
+use serde_bytes::ByteBuf;
+
+use client_state::{lossy, timestamp};
+use error::{Error, Result};
+use limits::Limits;
+use magnet::percent_decode;
+use {Bitfield, InfoHash, Torrent};
+
+/// The state of a torrent in rtorrent, stored as `<info hash>.torrent.rtorrent`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct RtorrentState {
+    #[serde(default)]
+    directory: Option<ByteBuf>,
+    #[serde(default)]
+    custom1: Option<ByteBuf>,
+    #[serde(default)]
+    state: Option<i64>,
+    #[serde(default)]
+    complete: Option<i64>,
+    #[serde(default)]
+    chunks_done: Option<u64>,
+    #[serde(default)]
+    chunks_wanted: Option<u64>,
+    #[serde(default)]
+    loaded_file: Option<ByteBuf>,
+    #[serde(default)]
+    tied_to_file: Option<ByteBuf>,
+    #[serde(default)]
+    total_uploaded: Option<u64>,
+    #[serde(default)]
+    #[serde(rename = "timestamp.started")]
+    timestamp_started: Option<i64>,
+    #[serde(default)]
+    #[serde(rename = "timestamp.finished")]
+    timestamp_finished: Option<i64>,
+}
+
+impl RtorrentState {
+    pub fn from_buf(buf: &[u8]) -> Result<Self> {
+        Limits::default().check(buf)?;
+        Ok(de::from_bytes(buf)?)
+    }
+
+    /// Returns the directory of the data, which includes the torrent name for multi-file
+    /// torrents.
+    pub fn directory(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.directory)
+    }
+
+    /// Returns the label set by ruTorrent in `custom1`, percent-decoded.
+    pub fn label(&self) -> Option<String> {
+        lossy(&self.custom1)
+            .filter(|label| !label.is_empty())
+            .map(|label| percent_decode(&label))
+    }
+
+    /// Returns whether the torrent is started rather than stopped.
+    pub fn is_started(&self) -> bool {
+        self.state == Some(1)
+    }
+
+    /// Returns whether all wanted pieces are downloaded.
+    pub fn is_complete(&self) -> bool {
+        self.complete == Some(1)
+    }
+
+    /// Returns the number of downloaded pieces.
+    pub fn chunks_done(&self) -> Option<u64> {
+        self.chunks_done
+    }
+
+    /// Returns the number of pieces of the files selected for download.
+    pub fn chunks_wanted(&self) -> Option<u64> {
+        self.chunks_wanted
+    }
+
+    /// Returns the path the torrent was loaded from.
+    pub fn loaded_file(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.loaded_file)
+    }
+
+    /// Returns the path of the torrent file in the watch directory the torrent is tied to.
+    pub fn tied_to_file(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.tied_to_file).filter(|path| !path.is_empty())
+    }
+
+    pub fn total_uploaded(&self) -> u64 {
+        self.total_uploaded.unwrap_or_default()
+    }
+
+    /// Returns the Unix timestamp the torrent was started at.
+    pub fn started_at(&self) -> Option<i64> {
+        timestamp(self.timestamp_started)
+    }
+
+    /// Returns the Unix timestamp the download finished at.
+    pub fn finished_at(&self) -> Option<i64> {
+        timestamp(self.timestamp_finished)
+    }
+}
+
+/// The resume data of a torrent in rtorrent, stored as
+/// `<info hash>.torrent.libtorrent_resume`.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct RtorrentResume {
+    #[serde(default)]
+    bitfield: Option<Value>,
+    #[serde(default)]
+    files: Vec<RtorrentResumeFile>,
+}
+
+impl RtorrentResume {
+    pub fn from_buf(buf: &[u8]) -> Result<Self> {
+        Limits::default().check(buf)?;
+        Ok(de::from_bytes(buf)?)
+    }
+
+    /// Returns the downloaded pieces of a torrent with `num_pieces` pieces.
+    ///
+    /// rtorrent stores the number of pieces instead of a bitfield if no or all pieces are
+    /// downloaded.
+    pub fn pieces(&self, num_pieces: usize) -> Option<Bitfield> {
+        match self.bitfield {
+            Some(Value::Bytes(ref bytes)) => Bitfield::from_bytes(bytes, num_pieces).ok(),
+            Some(Value::Int(0)) => Some(Bitfield::new(num_pieces)),
+            Some(Value::Int(n)) if n as usize == num_pieces => {
+                Some((0..num_pieces).map(|_| true).collect())
+            }
+            _ => None,
+        }
+    }
+
+    pub fn files(&self) -> &[RtorrentResumeFile] {
+        &self.files
+    }
+}
+
+/// The resume data of a file of a torrent in rtorrent.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct RtorrentResumeFile {
+    #[serde(default)]
+    completed: u64,
+    #[serde(default)]
+    priority: i64,
+    #[serde(default)]
+    mtime: i64,
+}
+
+impl RtorrentResumeFile {
+    /// Returns the number of downloaded pieces of the file.
+    pub fn completed(&self) -> u64 {
+        self.completed
+    }
+
+    /// Returns the priority, 0 for files which are not downloaded.
+    pub fn priority(&self) -> i64 {
+        self.priority
+    }
+
+    /// Returns the modification time of the file when the resume data was saved.
+    pub fn mtime(&self) -> i64 {
+        self.mtime
+    }
+}
+
+/// A torrent of an rtorrent session directory.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RtorrentSession {
+    pub info_hash: InfoHash,
+    /// The copy of the torrent file in the session directory
+    pub torrent_file: PathBuf,
+    pub state: RtorrentState,
+    pub resume: Option<RtorrentResume>,
+}
+
+impl RtorrentSession {
+    /// Reads the torrent file of the session.
+    pub fn torrent(&self) -> Result<Torrent> {
+        Torrent::from_file(&self.torrent_file)
+    }
+}
+
+fn read_session_file(path: &Path) -> Result<Vec<u8>> {
+    fs::read(path).map_err(|e| Error::ReadTorrent {
+        path: path.display().to_string(),
+        source: Box::new(e.into()),
+    })
+}
+
+/// Reads the torrents of an rtorrent session directory, sorted by info hash.
+///
+/// Every `<info hash>.torrent.rtorrent` file is a torrent, with optional resume data in
+/// `<info hash>.torrent.libtorrent_resume`.
+pub fn rtorrent_session<P: AsRef<Path>>(dir: P) -> Result<Vec<RtorrentSession>> {
+    let mut sessions = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let info_hash = match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.ends_with(".torrent.rtorrent") => {
+                match name[..name.len() - ".torrent.rtorrent".len()].parse::<InfoHash>() {
+                    Ok(info_hash) => info_hash,
+                    Err(_) => continue,
+                }
+            }
+            _ => continue,
+        };
+
+        let parse_error = |path: &Path, e: Error| Error::ReadTorrent {
+            path: path.display().to_string(),
+            source: Box::new(e),
+        };
+        let state = RtorrentState::from_buf(&read_session_file(&path)?)
+            .map_err(|e| parse_error(&path, e))?;
+        let torrent_file = path.with_extension("");
+        let resume_path = path.with_extension("libtorrent_resume");
+        let resume = if resume_path.exists() {
+            let resume = RtorrentResume::from_buf(&read_session_file(&resume_path)?)
+                .map_err(|e| parse_error(&resume_path, e))?;
+            Some(resume)
+        } else {
+            None
+        };
+
+        sessions.push(RtorrentSession {
+            info_hash,
+            torrent_file,
+            state,
+            resume,
+        });
+    }
+    sessions.sort_by_key(|session| *session.info_hash.as_bytes());
+
+    Ok(sessions)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    const STATE: &[u8] = b"d11:chunks_donei3e13:chunks_wantedi4e8:completei0e\
+7:custom111:Linux%20ISO9:directory13:/data/foo bar11:loaded_file18:/session/x.torrent\
+5:statei1e18:timestamp.finishedi0e17:timestamp.startedi1500000000e12:tied_to_file0:\
+14:total_uploadedi1024e5:viewsleee";
+
+    #[test]
+    pub fn test_state() {
+        let state = RtorrentState::from_buf(STATE).unwrap();
+        assert_eq!(state.directory().as_deref(), Some("/data/foo bar"));
+        assert_eq!(state.label().as_deref(), Some("Linux ISO"));
+        assert!(state.is_started());
+        assert!(!state.is_complete());
+        assert_eq!(state.chunks_done(), Some(3));
+        assert_eq!(state.chunks_wanted(), Some(4));
+        assert_eq!(state.loaded_file().as_deref(), Some("/session/x.torrent"));
+        assert_eq!(state.tied_to_file(), None);
+        assert_eq!(state.total_uploaded(), 1024);
+        assert_eq!(state.started_at(), Some(1_500_000_000));
+        assert_eq!(state.finished_at(), None);
+
+        assert_eq!(
+            RtorrentState::from_buf(b"de").unwrap(),
+            RtorrentState::default()
+        );
+        assert!(RtorrentState::from_buf(b"le").is_err());
+    }
+
+    #[test]
+    pub fn test_resume() {
+        let resume = RtorrentResume::from_buf(
+            b"d8:bitfield2:\xa0\x005:filesld9:completedi2e5:mtimei17e8:priorityi1eeee",
+        )
+        .unwrap();
+        let pieces = resume.pieces(10).unwrap();
+        assert_eq!(pieces.count_ones(), 2);
+        assert!(pieces.get(0) && pieces.get(2));
+        assert_eq!(resume.files()[0].completed(), 2);
+        assert_eq!(resume.files()[0].priority(), 1);
+        assert_eq!(resume.files()[0].mtime(), 17);
+
+        let complete = RtorrentResume::from_buf(b"d8:bitfieldi10ee").unwrap();
+        assert!(complete.pieces(10).unwrap().is_full());
+        assert_eq!(complete.pieces(11), None);
+        let empty = RtorrentResume::from_buf(b"d8:bitfieldi0ee").unwrap();
+        assert_eq!(empty.pieces(10).unwrap().count_ones(), 0);
+
+        let nested = format!("d8:bitfield{}{}e", "l".repeat(100_000), "e".repeat(100_000));
+        assert!(RtorrentResume::from_buf(nested.as_bytes()).is_err());
+        assert!(RtorrentState::from_buf(nested.as_bytes()).is_err());
+    }
+
+    #[test]
+    pub fn test_session() {
+        let dir = env::temp_dir().join(format!("torrentinfo-rtorrent-{}", ::std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let hash = "b43fd6d3a310c79acb7f4c915a12b9f6b9cc5bbf";
+        fs::write(dir.join(format!("{}.torrent.rtorrent", hash)), STATE).unwrap();
+        fs::write(
+            dir.join(format!("{}.torrent.libtorrent_resume", hash)),
+            b"d8:bitfieldi0ee",
+        )
+        .unwrap();
+        let other = "0000000000000000000000000000000000000000";
+        fs::write(dir.join(format!("{}.torrent.rtorrent", other)), b"de").unwrap();
+        fs::write(dir.join("rtorrent.lock"), b"").unwrap();
+
+        let sessions = rtorrent_session(&dir).unwrap();
+        assert_eq!(sessions.len(), 2);
+        assert_eq!(sessions[0].info_hash.to_string(), other);
+        assert_eq!(sessions[0].resume, None);
+        assert_eq!(sessions[1].info_hash.to_string(), hash);
+        assert_eq!(
+            sessions[1].torrent_file,
+            dir.join(format!("{}.torrent", hash))
+        );
+        assert!(sessions[1].state.is_started());
+        assert!(sessions[1].resume.is_some());
+
+        fs::write(dir.join(format!("{}.torrent.rtorrent", other)), b"le").unwrap();
+        assert!(rtorrent_session(&dir).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
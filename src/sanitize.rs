@@ -0,0 +1,145 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Validation and sanitization of the path components of torrents.
+
+use std::fmt;
+
+/// A path component which is unsafe to use as a file name, e.g. because it could write
+/// outside of the download directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathIssue {
+    /// The component contains a NUL byte
+    Nul,
+    /// The component is empty
+    Empty,
+    /// The component is an absolute path or starts with a drive letter
+    Absolute,
+    /// The component is `..`
+    ParentDir,
+    /// The component is `.`
+    CurrentDir,
+    /// The component contains a path separator
+    Separator,
+}
+
+impl fmt::Display for PathIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match *self {
+            PathIssue::Nul => "a NUL byte",
+            PathIssue::Empty => "an empty component",
+            PathIssue::Absolute => "an absolute component",
+            PathIssue::ParentDir => "a '..' component",
+            PathIssue::CurrentDir => "a '.' component",
+            PathIssue::Separator => "a path separator",
+        };
+        f.write_str(s)
+    }
+}
+
+fn has_drive(component: &str) -> bool {
+    let bytes = component.as_bytes();
+    bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':'
+}
+
+/// Returns the first issue of `component`.
+pub fn check_component(component: &str) -> Option<PathIssue> {
+    if component.contains('\0') {
+        Some(PathIssue::Nul)
+    } else if component.is_empty() {
+        Some(PathIssue::Empty)
+    } else if component.starts_with(['/', '\\']) || has_drive(component) {
+        Some(PathIssue::Absolute)
+    } else if component == ".." {
+        Some(PathIssue::ParentDir)
+    } else if component == "." {
+        Some(PathIssue::CurrentDir)
+    } else if component.contains(['/', '\\']) {
+        Some(PathIssue::Separator)
+    } else {
+        None
+    }
+}
+
+/// Returns the first issue of the components of `path`.
+pub fn check_path<S: AsRef<str>>(path: &[S]) -> Option<PathIssue> {
+    path.iter()
+        .filter_map(|c| check_component(c.as_ref()))
+        .next()
+}
+
+/// Makes `component` safe to use as a file name, replacing NUL bytes, separators and drive
+/// colons with `_`, or returns `None` for components to drop.
+pub fn sanitize_component(component: &str) -> Option<String> {
+    match component {
+        "" | "." | ".." => None,
+        _ => {
+            let mut sanitized = component.replace(['\0', '/', '\\'], "_");
+            if has_drive(&sanitized) {
+                sanitized.replace_range(1..2, "_");
+            }
+            Some(sanitized)
+        }
+    }
+}
+
+/// Sanitizes the components of `path` with `sanitize_component`, substituting `_` if none
+/// remain, so the path never names the directory it is joined to.
+pub fn sanitize_path<S: AsRef<str>>(path: &[S]) -> Vec<String> {
+    let sanitized: Vec<String> = path
+        .iter()
+        .filter_map(|c| sanitize_component(c.as_ref()))
+        .collect();
+    if sanitized.is_empty() {
+        vec!["_".to_owned()]
+    } else {
+        sanitized
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_check_component() {
+        assert_eq!(check_component("a.txt"), None);
+        assert_eq!(check_component("..a"), None);
+        assert_eq!(check_component("a\0"), Some(PathIssue::Nul));
+        assert_eq!(check_component(""), Some(PathIssue::Empty));
+        assert_eq!(check_component("/etc"), Some(PathIssue::Absolute));
+        assert_eq!(check_component("C:"), Some(PathIssue::Absolute));
+        assert_eq!(check_component(".."), Some(PathIssue::ParentDir));
+        assert_eq!(check_component("."), Some(PathIssue::CurrentDir));
+        assert_eq!(check_component("a/../b"), Some(PathIssue::Separator));
+        assert_eq!(check_path(&["a", "..", ""]), Some(PathIssue::ParentDir));
+    }
+
+    #[test]
+    pub fn test_sanitize_component() {
+        assert_eq!(sanitize_component(".."), None);
+        assert_eq!(sanitize_component(""), None);
+        assert_eq!(sanitize_component("a/b\\c"), Some("a_b_c".to_owned()));
+        assert_eq!(sanitize_component("C:x"), Some("C_x".to_owned()));
+        assert_eq!(sanitize_component("/etc"), Some("_etc".to_owned()));
+
+        assert_eq!(sanitize_path(&["..", "a"]), vec!["a"]);
+        assert_eq!(sanitize_path(&["..", "."]), vec!["_"]);
+        assert_eq!(sanitize_path::<&str>(&[]), vec!["_"]);
+    }
+}
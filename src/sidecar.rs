@@ -0,0 +1,159 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! `.sfv` and `.md5` checksum files for the content of torrents.
+
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+
+use crc32fast::Hasher;
+use md5::{Digest, Md5};
+
+use error::{Error, Result};
+use {to_hex, ManifestEntry, Torrent};
+
+/// Reads the file of `entry` below `root` in chunks, passing each to `update`.
+fn read_file<F: FnMut(&[u8])>(entry: &ManifestEntry, root: &Path, mut update: F) -> Result<()> {
+    let path = entry.local_path(root);
+    let read_error = |source| Error::ReadData {
+        path: path.display().to_string(),
+        source,
+    };
+    let mut file = fs::File::open(&path).map_err(read_error)?;
+    let mut buf = vec![0u8; 64 * 1024];
+    loop {
+        let n = file.read(&mut buf).map_err(read_error)?;
+        if n == 0 {
+            return Ok(());
+        }
+        update(&buf[..n]);
+    }
+}
+
+/// Creates an `.sfv` file with the CRC32 checksums of the files in the download directory
+/// `root`, listed with their sanitized paths.
+pub fn sfv(torrent: &Torrent, root: &Path) -> Result<String> {
+    let mut sfv = String::from("; Generated by torrentinfo\n");
+    for entry in torrent.manifest() {
+        let mut hasher = Hasher::new();
+        read_file(&entry, root, |data| hasher.update(data))?;
+        sfv.push_str(&format!(
+            "{} {:08X}\n",
+            entry.sanitized_path().join("/"),
+            hasher.finalize()
+        ));
+    }
+
+    Ok(sfv)
+}
+
+/// Creates an `.md5` file in the format of `md5sum`, using the MD5 sums of the torrent and
+/// computing the missing ones from the files in the download directory `root`, listed
+/// with their sanitized paths.
+pub fn md5sums(torrent: &Torrent, root: &Path) -> Result<String> {
+    let mut md5sums = String::new();
+    for entry in torrent.manifest() {
+        let md5sum = match entry.md5sum {
+            Some(ref md5sum) => md5sum.to_ascii_lowercase(),
+            None => {
+                let mut hasher = Md5::default();
+                read_file(&entry, root, |data| hasher.input(data))?;
+                to_hex(&hasher.result())
+            }
+        };
+        md5sums.push_str(&format!(
+            "{}  {}\n",
+            md5sum,
+            entry.sanitized_path().join("/")
+        ));
+    }
+
+    Ok(md5sums)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+
+    #[test]
+    pub fn test_sidecar_files() {
+        let root = env::temp_dir().join(format!("torrentinfo-sidecar-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("d")).unwrap();
+        fs::write(root.join("d/a"), b"123456789").unwrap();
+        fs::write(root.join("d/b"), b"").unwrap();
+
+        let buf = &b"d4:infod5:filesld6:lengthi9e4:pathl1:aeed6:lengthi0e4:pathl1:beee\
+4:name1:d12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            sfv(&torrent, &root).unwrap(),
+            "; Generated by torrentinfo\nd/a CBF43926\nd/b 00000000\n"
+        );
+        assert_eq!(
+            md5sums(&torrent, &root).unwrap(),
+            "25f9e794323b453885f5181f1b624d0b  d/a\nd41d8cd98f00b204e9800998ecf8427e  d/b\n"
+        );
+
+        // The MD5 sum of the torrent is used without reading the file
+        let buf = &b"d4:infod6:lengthi1e6:md5sum32:0123456789ABCDEF0123456789ABCDEF\
+4:name1:x12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            md5sums(&torrent, &root).unwrap(),
+            "0123456789abcdef0123456789abcdef  x\n"
+        );
+        match sfv(&torrent, &root) {
+            Err(Error::ReadData { ref path, .. }) => assert!(path.ends_with('x')),
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        // The files are listed with the paths they were read from
+        fs::create_dir_all(root.join("e")).unwrap();
+        fs::write(root.join("e/c"), b"123456789").unwrap();
+        let buf = &b"d4:infod5:filesld6:lengthi9e4:pathl2:..1:ceee4:name1:e\
+12:piece lengthi16384e6:pieces0:ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            sfv(&torrent, &root).unwrap(),
+            "; Generated by torrentinfo\ne/c CBF43926\n"
+        );
+        assert_eq!(
+            md5sums(&torrent, &root).unwrap(),
+            "25f9e794323b453885f5181f1b624d0b  e/c\n"
+        );
+
+        // The file tree of single-file v2 torrents is the file named after the torrent
+        fs::write(root.join("f"), b"123456789").unwrap();
+        let buf = &b"d4:infod9:file treed1:fd0:d6:lengthi9eeee12:meta versioni2e4:name1:f\
+12:piece lengthi16384eee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        assert_eq!(
+            sfv(&torrent, &root).unwrap(),
+            "; Generated by torrentinfo\nf CBF43926\n"
+        );
+        assert_eq!(
+            md5sums(&torrent, &root).unwrap(),
+            "25f9e794323b453885f5181f1b624d0b  f\n"
+        );
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,96 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Torrent signatures (BEP 35).
+
+use std::collections::BTreeMap;
+
+use serde_bencode::ser;
+use serde_bencode::value::Value;
+use serde_bytes::ByteBuf;
+
+use error::Result;
+
+/// A signature of the info dict, stored under the signer's name in the `signatures` dict.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize, Serialize)]
+#[non_exhaustive]
+pub struct Signature {
+    #[serde(default)]
+    certificate: Option<ByteBuf>,
+    #[serde(default)]
+    info: Option<Value>,
+    signature: ByteBuf,
+    #[serde(flatten)]
+    extra: BTreeMap<String, Value>,
+}
+
+impl Signature {
+    /// Returns the DER encoded X.509 certificate of the signer, if embedded.
+    pub fn certificate(&self) -> Option<&[u8]> {
+        self.certificate.as_ref().map(|c| &c[..])
+    }
+
+    /// Returns the additional signed data of the signer.
+    pub fn info(&self) -> Option<&Value> {
+        self.info.as_ref()
+    }
+
+    pub fn signature(&self) -> &[u8] {
+        &self.signature
+    }
+
+    /// Returns the signed data, the bencoded `info` dict of the torrent followed by the
+    /// bencoded `info` of this signature.
+    pub fn signed_data(&self, torrent_info: &[u8]) -> Result<Vec<u8>> {
+        let mut data = torrent_info.to_vec();
+        if let Some(ref info) = self.info {
+            data.extend(ser::to_bytes(info)?);
+        }
+
+        Ok(data)
+    }
+
+    /// Verifies the RSA signature with SHA-1 of `torrent_info` with the DER encoded
+    /// `public_key`, either a SubjectPublicKeyInfo or a PKCS#1 key.
+    #[cfg(feature = "signatures")]
+    pub fn verify(&self, torrent_info: &[u8], public_key: &[u8]) -> Result<bool> {
+        use rsa::pkcs1::DecodeRsaPublicKey;
+        use rsa::pkcs8::DecodePublicKey;
+        use rsa::{Pkcs1v15Sign, RsaPublicKey};
+
+        use error::Error;
+        use hash;
+
+        /// The DER encoded DigestInfo prefix of SHA-1 hashes (RFC 8017).
+        const SHA1_PREFIX: &[u8] = &[
+            0x30, 0x21, 0x30, 0x09, 0x06, 0x05, 0x2b, 0x0e, 0x03, 0x02, 0x1a, 0x05, 0x00, 0x04,
+            0x14,
+        ];
+
+        let key = RsaPublicKey::from_public_key_der(public_key)
+            .or_else(|_| RsaPublicKey::from_pkcs1_der(public_key))
+            .map_err(|_| Error::InvalidPublicKey)?;
+        let hash = hash::backend().sha1(&self.signed_data(torrent_info)?);
+        let scheme = Pkcs1v15Sign {
+            hash_len: Some(hash.len()),
+            prefix: SHA1_PREFIX.into(),
+        };
+
+        Ok(key.verify(scheme, &hash, &self.signature).is_ok())
+    }
+}
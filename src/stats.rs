@@ -0,0 +1,154 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Statistics about the files of torrents.
+
+use std::collections::BTreeMap;
+
+use {ManifestEntry, Torrent};
+
+/// The number and total size of a group of files.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SizeStats {
+    pub files: usize,
+    pub size: u64,
+}
+
+impl SizeStats {
+    fn add(&mut self, length: u64) {
+        self.files += 1;
+        self.size = self.size.saturating_add(length);
+    }
+}
+
+/// Statistics about the content files of a torrent, leaving out pad files, see
+/// `Torrent::stats`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct TorrentStats {
+    pub num_pieces: usize,
+    pub num_files: usize,
+    pub total_size: u64,
+    /// The average file size, rounded down
+    pub average_file_size: u64,
+    /// The first of the largest files
+    pub largest_file: Option<ManifestEntry>,
+    /// The first of the smallest files
+    pub smallest_file: Option<ManifestEntry>,
+    /// The files by lowercase file extension, with `""` for files without extension
+    pub extensions: BTreeMap<String, SizeStats>,
+    /// The files by the directory below the torrent root they are in, leaving out the
+    /// files directly in the root
+    pub directories: BTreeMap<String, SizeStats>,
+    /// The largest number of path components below the torrent root
+    pub max_depth: usize,
+}
+
+/// Returns the lowercase extension of the file `name`, ignoring the dot of hidden files.
+fn extension(name: &str) -> String {
+    match name.rfind('.') {
+        Some(pos) if pos > 0 => name[pos + 1..].to_lowercase(),
+        _ => String::new(),
+    }
+}
+
+pub fn stats(torrent: &Torrent) -> TorrentStats {
+    let manifest = torrent.manifest();
+    // The paths of multi-file torrents start with the torrent name
+    let skip = if torrent.files().is_some() || torrent.info().mode().is_none() {
+        1
+    } else {
+        0
+    };
+
+    let mut stats = TorrentStats {
+        num_pieces: torrent.num_pieces(),
+        num_files: manifest.len(),
+        ..Default::default()
+    };
+    for entry in &manifest {
+        stats.total_size = stats.total_size.saturating_add(entry.length);
+        if stats
+            .largest_file
+            .as_ref()
+            .is_none_or(|f| entry.length > f.length)
+        {
+            stats.largest_file = Some(entry.clone());
+        }
+        if stats
+            .smallest_file
+            .as_ref()
+            .is_none_or(|f| entry.length < f.length)
+        {
+            stats.smallest_file = Some(entry.clone());
+        }
+
+        let path = entry.path.get(skip..).unwrap_or_default();
+        if let Some(name) = path.last() {
+            stats
+                .extensions
+                .entry(extension(name))
+                .or_default()
+                .add(entry.length);
+        }
+        if path.len() > 1 {
+            stats
+                .directories
+                .entry(path[0].clone())
+                .or_default()
+                .add(entry.length);
+        }
+        stats.max_depth = stats.max_depth.max(path.len());
+    }
+    if !manifest.is_empty() {
+        stats.average_file_size = stats.total_size / manifest.len() as u64;
+    }
+
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_stats() {
+        let buf = &b"d4:infod5:filesld6:lengthi1e4:pathl5:a.TXTeed6:lengthi3e4:pathl1:b5:c.txte\
+ed6:lengthi8e4:pathl1:b1:d7:.hiddeneed4:attr1:p6:lengthi100e4:pathl4:.pad3:100eee\
+4:name1:n12:piece lengthi16384e6:pieces0:ee"[..];
+        let stats = Torrent::from_buf(buf).unwrap().stats();
+        assert_eq!(stats.num_pieces, 1);
+        assert_eq!(stats.num_files, 3);
+        assert_eq!(stats.total_size, 12);
+        assert_eq!(stats.average_file_size, 4);
+        assert_eq!(stats.largest_file.unwrap().path_string(), "n/b/d/.hidden");
+        assert_eq!(stats.smallest_file.unwrap().path_string(), "n/a.TXT");
+        assert_eq!(stats.extensions["txt"], SizeStats { files: 2, size: 4 });
+        assert_eq!(stats.extensions[""], SizeStats { files: 1, size: 8 });
+        assert_eq!(stats.directories.len(), 1);
+        assert_eq!(stats.directories["b"], SizeStats { files: 2, size: 11 });
+        assert_eq!(stats.max_depth, 3);
+
+        let buf = &b"d4:infod6:lengthi5e4:name5:a.mkv12:piece lengthi16384e6:pieces0:ee"[..];
+        let stats = Torrent::from_buf(buf).unwrap().stats();
+        assert_eq!(stats.extensions["mkv"], SizeStats { files: 1, size: 5 });
+        assert!(stats.directories.is_empty());
+        assert_eq!(stats.max_depth, 1);
+
+        assert_eq!(Torrent::default().stats().average_file_size, 0);
+    }
+}
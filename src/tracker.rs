@@ -0,0 +1,455 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Tracker clients (`net` feature).
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_bencode::de;
+use serde_bencode::value::Value;
+
+use error::{Error, Result};
+use http;
+use limits::Limits;
+use magnet::percent_encode_bytes;
+use udp_tracker::{self, Connection};
+use {InfoHash, TrackerUrl};
+
+/// The maximum size of tracker responses.
+const MAX_RESPONSE_SIZE: u64 = 4 * 1024 * 1024;
+
+/// The swarm statistics of a torrent reported by a tracker.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub struct ScrapeResult {
+    /// The peers with the complete torrent
+    pub seeders: u64,
+    /// The peers still downloading
+    pub leechers: u64,
+    /// The number of completed downloads
+    pub completed: u64,
+}
+
+/// The event of an announce.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum AnnounceEvent {
+    /// A regular announce
+    #[default]
+    None = 0,
+    Completed = 1,
+    Started = 2,
+    Stopped = 3,
+}
+
+/// The parameters of an announce.
+#[derive(Debug, Clone)]
+pub struct AnnounceRequest {
+    pub info_hash: InfoHash,
+    pub peer_id: [u8; 20],
+    /// The port the client listens on
+    pub port: u16,
+    pub uploaded: u64,
+    pub downloaded: u64,
+    /// The number of bytes left to download
+    pub left: u64,
+    pub event: AnnounceEvent,
+    /// The number of peers wanted, `None` for the default of the tracker
+    pub num_want: Option<u32>,
+}
+
+impl AnnounceRequest {
+    /// Creates a request with a random peer id, announcing nothing downloaded or left.
+    pub fn new(info_hash: InfoHash, port: u16) -> Self {
+        AnnounceRequest {
+            info_hash,
+            peer_id: peer_id(),
+            port,
+            uploaded: 0,
+            downloaded: 0,
+            left: 0,
+            event: AnnounceEvent::None,
+            num_want: None,
+        }
+    }
+}
+
+/// The response to an announce.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct AnnounceResponse {
+    /// The seconds to wait before the next announce
+    pub interval: u64,
+    pub seeders: u64,
+    pub leechers: u64,
+    pub peers: Vec<SocketAddr>,
+}
+
+/// Returns a random Azureus-style peer id with the crate version, e.g. `-TI0100-`.
+pub(crate) fn peer_id() -> [u8; 20] {
+    let mut peer_id = *b"-TI0000-000000000000";
+    let version = env!("CARGO_PKG_VERSION").replace('.', "");
+    for (i, b) in version.bytes().take(4).enumerate() {
+        peer_id[3 + i] = b;
+    }
+    for chunk in peer_id[8..].chunks_mut(4) {
+        let random = random_u32();
+        for (i, b) in chunk.iter_mut().enumerate() {
+            *b = b'0' + (random >> (i * 8)) as u8 % 10;
+        }
+    }
+
+    peer_id
+}
+
+/// Returns a random number for transaction and peer ids, which needn't be unpredictable.
+pub(crate) fn random_u32() -> u32 {
+    static COUNTER: AtomicUsize = AtomicUsize::new(0);
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.subsec_nanos());
+    let count = COUNTER.fetch_add(1, Ordering::Relaxed) as u32;
+    (nanos ^ process::id().rotate_left(16) ^ count.rotate_left(24)).wrapping_mul(0x9e37_79b9)
+}
+
+/// Returns the scrape url of an HTTP tracker following the convention of replacing the
+/// `announce` at the start of the last path segment with `scrape`.
+///
+/// Returns `None` for trackers which don't support scraping.
+pub fn scrape_url(announce: &str) -> Option<String> {
+    let slash = announce.rfind('/')?;
+    let (base, segment) = announce.split_at(slash + 1);
+    if !segment.starts_with("announce") || base.ends_with("//") {
+        return None;
+    }
+
+    Some(format!("{}scrape{}", base, &segment["announce".len()..]))
+}
+
+/// Requests the swarm statistics of the torrents with `info_hashes` from the tracker with
+/// the announce url `announce`.
+///
+/// Torrents unknown to the tracker are missing from the result. UDP requests are retried
+/// with growing timeouts.
+pub fn scrape(announce: &str, info_hashes: &[InfoHash]) -> Result<HashMap<InfoHash, ScrapeResult>> {
+    let tracker: TrackerUrl = announce.parse()?;
+    match tracker.scheme() {
+        "http" | "https" => http_scrape(announce, info_hashes),
+        "udp" => udp_connect(&tracker)?.scrape(info_hashes),
+        scheme => bail!(Error::Unsupported(format!("scraping {} trackers", scheme))),
+    }
+}
+
+/// Announces to the tracker with the announce url `announce`.
+pub fn announce(announce: &str, request: &AnnounceRequest) -> Result<AnnounceResponse> {
+    let tracker: TrackerUrl = announce.parse()?;
+    match tracker.scheme() {
+        "http" | "https" => http_announce(announce, request),
+        "udp" => udp_connect(&tracker)?.announce(request),
+        scheme => bail!(Error::Unsupported(format!(
+            "announcing to {} trackers",
+            scheme
+        ))),
+    }
+}
+
+fn udp_connect(tracker: &TrackerUrl) -> Result<Connection> {
+    let port = match tracker.port() {
+        Some(port) => port,
+        None => bail!(Error::Tracker(format!("{} has no port", tracker.host()))),
+    };
+    Connection::connect(tracker.host(), port, udp_tracker::Timeouts::default())
+}
+
+fn http_scrape(
+    announce: &str,
+    info_hashes: &[InfoHash],
+) -> Result<HashMap<InfoHash, ScrapeResult>> {
+    let mut url = scrape_url(announce)
+        .ok_or_else(|| Error::Unsupported("scraping this tracker".to_owned()))?;
+    for (i, info_hash) in info_hashes.iter().enumerate() {
+        let separator = if i == 0 && !url.contains('?') {
+            '?'
+        } else {
+            '&'
+        };
+        url.push(separator);
+        url.push_str("info_hash=");
+        url.push_str(&percent_encode_bytes(info_hash.as_bytes()));
+    }
+
+    parse_scrape(&http::get(&url, MAX_RESPONSE_SIZE)?)
+}
+
+fn http_announce(announce: &str, request: &AnnounceRequest) -> Result<AnnounceResponse> {
+    parse_announce(&http::get(
+        &announce_url(announce, request),
+        MAX_RESPONSE_SIZE,
+    )?)
+}
+
+/// Returns the announce url with the query of `request`, asking for compact peers.
+fn announce_url(announce: &str, request: &AnnounceRequest) -> String {
+    let mut url = announce.to_owned();
+    url.push(if url.contains('?') { '&' } else { '?' });
+    url.push_str(&format!(
+        "info_hash={}&peer_id={}&port={}&uploaded={}&downloaded={}&left={}&compact=1",
+        percent_encode_bytes(request.info_hash.as_bytes()),
+        percent_encode_bytes(&request.peer_id),
+        request.port,
+        request.uploaded,
+        request.downloaded,
+        request.left
+    ));
+    let event = match request.event {
+        AnnounceEvent::None => None,
+        AnnounceEvent::Completed => Some("completed"),
+        AnnounceEvent::Started => Some("started"),
+        AnnounceEvent::Stopped => Some("stopped"),
+    };
+    if let Some(event) = event {
+        url.push_str("&event=");
+        url.push_str(event);
+    }
+    if let Some(num_want) = request.num_want {
+        url.push_str(&format!("&numwant={}", num_want));
+    }
+
+    url
+}
+
+/// Parses compact IPv4 peers (BEP 23), 4 address and 2 port bytes each.
+pub fn compact_peers(buf: &[u8]) -> Vec<SocketAddr> {
+    buf.chunks_exact(6)
+        .map(|peer| {
+            let ip = Ipv4Addr::new(peer[0], peer[1], peer[2], peer[3]);
+            SocketAddr::new(ip.into(), u16::from_be_bytes([peer[4], peer[5]]))
+        })
+        .collect()
+}
+
+/// Parses compact IPv6 peers (BEP 7), 16 address and 2 port bytes each.
+pub fn compact_peers6(buf: &[u8]) -> Vec<SocketAddr> {
+    buf.chunks_exact(18)
+        .map(|peer| {
+            let mut ip = [0u8; 16];
+            ip.copy_from_slice(&peer[..16]);
+            SocketAddr::new(
+                Ipv6Addr::from(ip).into(),
+                u16::from_be_bytes([peer[16], peer[17]]),
+            )
+        })
+        .collect()
+}
+
+/// Parses an announce response with compact or dictionary peer lists.
+///
+/// Peers given by host name instead of address are skipped.
+fn parse_announce(buf: &[u8]) -> Result<AnnounceResponse> {
+    let dict = response_dict(buf)?;
+    let mut peers = match dict.get(&b"peers"[..]) {
+        Some(Value::Bytes(buf)) => compact_peers(buf),
+        Some(Value::List(list)) => list
+            .iter()
+            .filter_map(|peer| match peer {
+                Value::Dict(peer) => {
+                    let ip = match peer.get(&b"ip"[..]) {
+                        Some(Value::Bytes(ip)) => String::from_utf8_lossy(ip).parse::<IpAddr>(),
+                        _ => return None,
+                    };
+                    match (ip, peer.get(&b"port"[..])) {
+                        (Ok(ip), Some(&Value::Int(port))) if port > 0 && port <= 0xffff => {
+                            Some(SocketAddr::new(ip, port as u16))
+                        }
+                        _ => None,
+                    }
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => vec![],
+    };
+    if let Some(Value::Bytes(buf)) = dict.get(&b"peers6"[..]) {
+        peers.extend(compact_peers6(buf));
+    }
+
+    Ok(AnnounceResponse {
+        interval: uint(&dict, "interval"),
+        seeders: uint(&dict, "complete"),
+        leechers: uint(&dict, "incomplete"),
+        peers,
+    })
+}
+
+/// Returns the dict of a tracker response, failing with its `failure reason`.
+pub(crate) fn response_dict(buf: &[u8]) -> Result<HashMap<Vec<u8>, Value>> {
+    Limits::default().check(buf)?;
+    let dict = match de::from_bytes(buf) {
+        Ok(Value::Dict(dict)) => dict,
+        _ => bail!(Error::Tracker("invalid response".to_owned())),
+    };
+    if let Some(Value::Bytes(reason)) = dict.get(&b"failure reason"[..]) {
+        bail!(Error::Tracker(String::from_utf8_lossy(reason).into_owned()));
+    }
+
+    Ok(dict)
+}
+
+/// Returns a non-negative integer of a dict, 0 if it is missing.
+pub(crate) fn uint(dict: &HashMap<Vec<u8>, Value>, key: &str) -> u64 {
+    match dict.get(key.as_bytes()) {
+        Some(&Value::Int(n)) if n > 0 => n as u64,
+        _ => 0,
+    }
+}
+
+fn parse_scrape(buf: &[u8]) -> Result<HashMap<InfoHash, ScrapeResult>> {
+    let dict = response_dict(buf)?;
+    let files = match dict.get(&b"files"[..]) {
+        Some(Value::Dict(files)) => files,
+        _ => bail!(Error::Tracker(
+            "missing files in scrape response".to_owned()
+        )),
+    };
+
+    let mut results = HashMap::new();
+    for (info_hash, stats) in files {
+        if let (Ok(info_hash), Value::Dict(stats)) = (InfoHash::from_bytes(info_hash), stats) {
+            results.insert(
+                info_hash,
+                ScrapeResult {
+                    seeders: uint(stats, "complete"),
+                    leechers: uint(stats, "incomplete"),
+                    completed: uint(stats, "downloaded"),
+                },
+            );
+        }
+    }
+
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_announce_request() {
+        let request = AnnounceRequest::new(InfoHash::from([1; 20]), 6881);
+        assert!(request.peer_id.starts_with(b"-TI"));
+        assert!(request.peer_id[8..].iter().all(u8::is_ascii_digit));
+        let other = AnnounceRequest::new(InfoHash::from([1; 20]), 6881);
+        assert_ne!(request.peer_id, other.peer_id);
+    }
+
+    #[test]
+    pub fn test_announce_url() {
+        let mut request = AnnounceRequest::new(InfoHash::from([0xab; 20]), 6881);
+        request.peer_id = *b"-TI0100-123456789012";
+        request.left = 1000;
+        request.event = AnnounceEvent::Started;
+        request.num_want = Some(50);
+        let expected = format!(
+            "http://t.example/announce?passkey=x&info_hash={}&peer_id=-TI0100-123456789012\
+&port=6881&uploaded=0&downloaded=0&left=1000&compact=1&event=started&numwant=50",
+            "%AB".repeat(20)
+        );
+        assert_eq!(
+            announce_url("http://t.example/announce?passkey=x", &request),
+            expected
+        );
+    }
+
+    #[test]
+    pub fn test_parse_announce() {
+        let buf = b"d8:completei3e10:incompletei4e8:intervali1800e\
+5:peers12:\x7f\x00\x00\x01\x1a\xe1\x0a\x00\x00\x02\x00\x50\
+6:peers618:\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x00\x01\x1a\xe1e";
+        let response = parse_announce(buf).unwrap();
+        assert_eq!(response.interval, 1800);
+        assert_eq!(response.seeders, 3);
+        assert_eq!(response.leechers, 4);
+        assert_eq!(
+            response.peers,
+            vec![
+                "127.0.0.1:6881".parse::<SocketAddr>().unwrap(),
+                "10.0.0.2:80".parse().unwrap(),
+                "[::1]:6881".parse().unwrap(),
+            ]
+        );
+
+        let buf = b"d8:intervali60e5:peersld2:ip9:127.0.0.17:peer id20:aaaaaaaaaaaaaaaaaaaa\
+4:porti6881eed2:ip3:::14:porti80eed2:ip9:t.example4:porti1eeee";
+        let response = parse_announce(buf).unwrap();
+        assert_eq!(
+            response.peers,
+            vec![
+                "127.0.0.1:6881".parse::<SocketAddr>().unwrap(),
+                "[::1]:80".parse().unwrap(),
+            ]
+        );
+
+        assert!(parse_announce(b"d14:failure reason4:nopee").is_err());
+    }
+
+    #[test]
+    pub fn test_scrape_url() {
+        assert_eq!(
+            scrape_url("http://t.example/announce").as_deref(),
+            Some("http://t.example/scrape")
+        );
+        assert_eq!(
+            scrape_url("https://t.example/x/announce.php?passkey=abc").as_deref(),
+            Some("https://t.example/x/scrape.php?passkey=abc")
+        );
+        assert_eq!(scrape_url("http://t.example/a"), None);
+        assert_eq!(scrape_url("http://t.example/x/announce/y"), None);
+        assert_eq!(scrape_url("http://announce"), None);
+    }
+
+    #[test]
+    pub fn test_parse_scrape() {
+        let buf = b"d5:filesd20:aaaaaaaaaaaaaaaaaaaad8:completei5e10:downloadedi50e\
+10:incompletei10ee20:bbbbbbbbbbbbbbbbbbbbd8:completei-1eeee";
+        let results = parse_scrape(buf).unwrap();
+        assert_eq!(
+            results[&InfoHash::from([b'a'; 20])],
+            ScrapeResult {
+                seeders: 5,
+                leechers: 10,
+                completed: 50,
+            }
+        );
+        assert_eq!(
+            results[&InfoHash::from([b'b'; 20])],
+            ScrapeResult::default()
+        );
+
+        match parse_scrape(b"d14:failure reason9:forbiddene") {
+            Err(Error::Tracker(ref reason)) => assert_eq!(reason, "forbidden"),
+            r => panic!("unexpected result {:?}", r),
+        }
+        assert!(parse_scrape(b"de").is_err());
+        assert!(parse_scrape(b"<html>").is_err());
+
+        let nested = format!("{}{}", "l".repeat(100_000), "e".repeat(100_000));
+        assert!(parse_scrape(nested.as_bytes()).is_err());
+        assert!(parse_announce(nested.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,207 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Transmission resume files.
+
+use std::borrow::Cow;
+
+use serde_bencode::de;
+use serde_bytes::ByteBuf;
+
+use client_state::{lossy, timestamp};
+use error::Result;
+use limits::Limits;
+use Bitfield;
+
+/// The download progress of a Transmission resume file.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+struct Progress {
+    /// `all` if complete, written by Transmission 2
+    #[serde(default)]
+    have: Option<ByteBuf>,
+    /// A bitfield of the pieces, or `all` or `none`
+    #[serde(default)]
+    pieces: Option<ByteBuf>,
+    /// A bitfield of the 16 KiB blocks, or `all` or `none`
+    #[serde(default)]
+    blocks: Option<ByteBuf>,
+}
+
+/// The state of a torrent in Transmission, stored in the `resume` directory as
+/// `<info hash>.resume`, or `<name>.<info hash prefix>.resume` before Transmission 4.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct TransmissionResume {
+    #[serde(default)]
+    destination: Option<ByteBuf>,
+    #[serde(default)]
+    #[serde(rename = "incomplete-dir")]
+    incomplete_dir: Option<ByteBuf>,
+    #[serde(default)]
+    name: Option<ByteBuf>,
+    #[serde(default)]
+    labels: Vec<ByteBuf>,
+    #[serde(default)]
+    paused: Option<i64>,
+    #[serde(default)]
+    downloaded: Option<u64>,
+    #[serde(default)]
+    uploaded: Option<u64>,
+    #[serde(default)]
+    #[serde(rename = "added-date")]
+    added_date: Option<i64>,
+    #[serde(default)]
+    #[serde(rename = "done-date")]
+    done_date: Option<i64>,
+    #[serde(default)]
+    #[serde(rename = "activity-date")]
+    activity_date: Option<i64>,
+    #[serde(default)]
+    progress: Progress,
+}
+
+impl TransmissionResume {
+    pub fn from_buf(buf: &[u8]) -> Result<Self> {
+        Limits::default().check(buf)?;
+        Ok(de::from_bytes(buf)?)
+    }
+
+    /// Returns the directory the data is saved in once complete.
+    pub fn destination(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.destination)
+    }
+
+    /// Returns the directory of incomplete data, if Transmission keeps it apart.
+    pub fn incomplete_dir(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.incomplete_dir)
+    }
+
+    /// Returns the name of the torrent, which differs from the name in the torrent after
+    /// renaming.
+    pub fn name(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.name)
+    }
+
+    /// Returns the labels, written by Transmission 3 and later.
+    pub fn labels(&self) -> Vec<Cow<'_, str>> {
+        self.labels
+            .iter()
+            .map(|label| String::from_utf8_lossy(label))
+            .collect()
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused.is_some_and(|paused| paused != 0)
+    }
+
+    /// Returns the number of downloaded bytes.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded.unwrap_or_default()
+    }
+
+    /// Returns the number of uploaded bytes.
+    pub fn uploaded(&self) -> u64 {
+        self.uploaded.unwrap_or_default()
+    }
+
+    /// Returns the Unix timestamp the torrent was added at.
+    pub fn added_date(&self) -> Option<i64> {
+        timestamp(self.added_date)
+    }
+
+    /// Returns the Unix timestamp the download finished at.
+    pub fn done_date(&self) -> Option<i64> {
+        timestamp(self.done_date)
+    }
+
+    /// Returns the Unix timestamp of the last upload or download.
+    pub fn activity_date(&self) -> Option<i64> {
+        timestamp(self.activity_date)
+    }
+
+    /// Returns whether all pieces are downloaded.
+    pub fn is_complete(&self) -> bool {
+        let progress = &self.progress;
+        [&progress.have, &progress.pieces, &progress.blocks]
+            .iter()
+            .any(|value| value.as_ref().is_some_and(|v| &v[..] == b"all"))
+    }
+
+    /// Returns the downloaded pieces of a torrent with `num_pieces` pieces.
+    ///
+    /// Only Transmission 3 and later store pieces, older versions store blocks, so
+    /// the pieces are known only if the torrent is complete.
+    pub fn pieces(&self, num_pieces: usize) -> Option<Bitfield> {
+        if self.is_complete() {
+            return Some((0..num_pieces).map(|_| true).collect());
+        }
+        match self.progress.pieces {
+            Some(ref pieces) if &pieces[..] == b"none" => Some(Bitfield::new(num_pieces)),
+            Some(ref pieces) => Bitfield::from_bytes(pieces, num_pieces).ok(),
+            None => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    pub fn test_resume() {
+        let resume = TransmissionResume::from_buf(
+            b"d13:activity-datei1600000100e10:added-datei1600000000e11:destination9:/data/foo\
+9:done-datei0e10:downloadedi2048e14:incomplete-dir12:/data/.part/6:labelsl3:iso5:linuxe\
+4:name3:foo6:pausedi1e8:progressd6:pieces2:\xc0\x00e8:uploadedi512ee",
+        )
+        .unwrap();
+        assert_eq!(resume.destination().as_deref(), Some("/data/foo"));
+        assert_eq!(resume.incomplete_dir().as_deref(), Some("/data/.part/"));
+        assert_eq!(resume.name().as_deref(), Some("foo"));
+        assert_eq!(resume.labels(), ["iso", "linux"]);
+        assert!(resume.is_paused());
+        assert_eq!(resume.downloaded(), 2048);
+        assert_eq!(resume.uploaded(), 512);
+        assert_eq!(resume.added_date(), Some(1_600_000_000));
+        assert_eq!(resume.done_date(), None);
+        assert_eq!(resume.activity_date(), Some(1_600_000_100));
+        assert!(!resume.is_complete());
+        assert_eq!(resume.pieces(10).unwrap().count_ones(), 2);
+    }
+
+    #[test]
+    pub fn test_progress() {
+        let complete = TransmissionResume::from_buf(b"d8:progressd4:have3:allee").unwrap();
+        assert!(complete.is_complete());
+        assert!(complete.pieces(3).unwrap().is_full());
+
+        let none = TransmissionResume::from_buf(b"d8:progressd6:pieces4:noneee").unwrap();
+        assert_eq!(none.pieces(3).unwrap().count_ones(), 0);
+
+        let blocks = TransmissionResume::from_buf(b"d8:progressd6:blocks1:\x80ee").unwrap();
+        assert!(!blocks.is_complete());
+        assert_eq!(blocks.pieces(3), None);
+
+        assert_eq!(
+            TransmissionResume::from_buf(b"de").unwrap(),
+            TransmissionResume::default()
+        );
+
+        let nested = format!("d1:x{}{}e", "l".repeat(100_000), "e".repeat(100_000));
+        assert!(TransmissionResume::from_buf(nested.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,336 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! The UDP tracker protocol (BEP 15).
+
+use std::collections::HashMap;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs, UdpSocket};
+use std::time::{Duration, Instant};
+
+use error::{Error, Result};
+use tracker::{
+    compact_peers, compact_peers6, random_u32, AnnounceRequest, AnnounceResponse, ScrapeResult,
+};
+use InfoHash;
+
+const PROTOCOL_ID: u64 = 0x417_2710_1980;
+const CONNECT: u32 = 0;
+const ANNOUNCE: u32 = 1;
+const SCRAPE: u32 = 2;
+const ERROR: u32 = 3;
+
+/// The info hashes per scrape request, which keeps requests below common MTUs.
+const MAX_SCRAPE_HASHES: usize = 70;
+
+/// Timeouts of UDP requests, which start at `timeout` and double with each retry.
+#[derive(Debug, Clone, Copy)]
+pub struct Timeouts {
+    pub timeout: Duration,
+    pub retries: u32,
+}
+
+impl Default for Timeouts {
+    fn default() -> Self {
+        Timeouts {
+            timeout: Duration::from_secs(3),
+            retries: 3,
+        }
+    }
+}
+
+fn u32_at(buf: &[u8], offset: usize) -> u32 {
+    let mut bytes = [0u8; 4];
+    bytes.copy_from_slice(&buf[offset..offset + 4]);
+    u32::from_be_bytes(bytes)
+}
+
+/// A connection to a UDP tracker.
+pub struct Connection {
+    socket: UdpSocket,
+    id: u64,
+    timeouts: Timeouts,
+}
+
+impl Connection {
+    /// Connects to the tracker at `host:port`.
+    pub fn connect(host: &str, port: u16, timeouts: Timeouts) -> Result<Self> {
+        let addr = (host.trim_matches(|c| c == '[' || c == ']'), port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(|| Error::Tracker(format!("could not resolve {}", host)))?;
+        let local: SocketAddr = if addr.is_ipv4() {
+            "0.0.0.0:0".parse().unwrap()
+        } else {
+            "[::]:0".parse().unwrap()
+        };
+        let socket = UdpSocket::bind(local)?;
+        socket.connect(addr)?;
+        let mut connection = Connection {
+            socket,
+            id: PROTOCOL_ID,
+            timeouts,
+        };
+
+        let response = connection.request(CONNECT, &[])?;
+        if response.len() < 8 {
+            bail!(Error::Tracker("invalid connect response".to_owned()));
+        }
+        let mut id = [0u8; 8];
+        id.copy_from_slice(&response[..8]);
+        connection.id = u64::from_be_bytes(id);
+
+        Ok(connection)
+    }
+
+    /// Sends a request with retries, returning the response after the action and
+    /// transaction id.
+    fn request(&mut self, action: u32, payload: &[u8]) -> Result<Vec<u8>> {
+        let transaction = random_u32();
+        let mut request = Vec::with_capacity(16 + payload.len());
+        request.extend_from_slice(&self.id.to_be_bytes());
+        request.extend_from_slice(&action.to_be_bytes());
+        request.extend_from_slice(&transaction.to_be_bytes());
+        request.extend_from_slice(payload);
+
+        let mut buf = vec![0u8; 65536];
+        let mut timeout = self.timeouts.timeout;
+        for _ in 0..=self.timeouts.retries {
+            self.socket.send(&request)?;
+            // Ignored responses don't extend the timeout of the attempt
+            let deadline = Instant::now() + timeout;
+            loop {
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                self.socket.set_read_timeout(Some(remaining))?;
+                let n = match self.socket.recv(&mut buf) {
+                    Ok(n) => n,
+                    Err(ref e)
+                        if e.kind() == io::ErrorKind::WouldBlock
+                            || e.kind() == io::ErrorKind::TimedOut =>
+                    {
+                        break
+                    }
+                    Err(e) => bail!(e),
+                };
+                // Responses to earlier attempts or other requests are ignored
+                if n < 8 || u32_at(&buf, 4) != transaction {
+                    continue;
+                }
+                match u32_at(&buf, 0) {
+                    ERROR => bail!(Error::Tracker(
+                        String::from_utf8_lossy(&buf[8..n]).into_owned()
+                    )),
+                    a if a == action => return Ok(buf[8..n].to_vec()),
+                    _ => bail!(Error::Tracker("unexpected response action".to_owned())),
+                }
+            }
+            timeout *= 2;
+        }
+
+        bail!(Error::Tracker("timed out".to_owned()))
+    }
+
+    /// Scrapes the tracker, splitting large requests.
+    pub fn scrape(&mut self, info_hashes: &[InfoHash]) -> Result<HashMap<InfoHash, ScrapeResult>> {
+        let mut results = HashMap::new();
+        for chunk in info_hashes.chunks(MAX_SCRAPE_HASHES) {
+            let payload: Vec<u8> = chunk.iter().flat_map(|h| h.as_bytes().to_vec()).collect();
+            let response = self.request(SCRAPE, &payload)?;
+            for (info_hash, stats) in chunk.iter().zip(response.chunks_exact(12)) {
+                results.insert(
+                    *info_hash,
+                    ScrapeResult {
+                        seeders: u32_at(stats, 0).into(),
+                        completed: u32_at(stats, 4).into(),
+                        leechers: u32_at(stats, 8).into(),
+                    },
+                );
+            }
+        }
+
+        Ok(results)
+    }
+
+    /// Announces to the tracker.
+    pub fn announce(&mut self, request: &AnnounceRequest) -> Result<AnnounceResponse> {
+        let mut payload = Vec::with_capacity(82);
+        payload.extend_from_slice(request.info_hash.as_bytes());
+        payload.extend_from_slice(&request.peer_id);
+        payload.extend_from_slice(&request.downloaded.to_be_bytes());
+        payload.extend_from_slice(&request.left.to_be_bytes());
+        payload.extend_from_slice(&request.uploaded.to_be_bytes());
+        payload.extend_from_slice(&(request.event as u32).to_be_bytes());
+        payload.extend_from_slice(&0u32.to_be_bytes());
+        payload.extend_from_slice(&random_u32().to_be_bytes());
+        payload.extend_from_slice(&request.num_want.map_or(-1, |n| n as i32).to_be_bytes());
+        payload.extend_from_slice(&request.port.to_be_bytes());
+
+        let response = self.request(ANNOUNCE, &payload)?;
+        if response.len() < 12 {
+            bail!(Error::Tracker("invalid announce response".to_owned()));
+        }
+        // Trackers reached over IPv6 return IPv6 peers
+        let peers = if self.socket.peer_addr()?.is_ipv6() {
+            compact_peers6(&response[12..])
+        } else {
+            compact_peers(&response[12..])
+        };
+
+        Ok(AnnounceResponse {
+            interval: u32_at(&response, 0).into(),
+            leechers: u32_at(&response, 4).into(),
+            seeders: u32_at(&response, 8).into(),
+            peers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+
+    /// Answers requests like a tracker, dropping the first request of each kind.
+    fn fake_tracker() -> u16 {
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            let mut seen = vec![];
+            loop {
+                let (n, from) = socket.recv_from(&mut buf).unwrap();
+                let action = u32_at(&buf, 8);
+                if !seen.contains(&action) {
+                    seen.push(action);
+                    continue;
+                }
+                let mut response = buf[8..16].to_vec();
+                match action {
+                    CONNECT => response.extend_from_slice(&42u64.to_be_bytes()),
+                    SCRAPE => {
+                        assert_eq!(&buf[..8], &42u64.to_be_bytes());
+                        for i in 0..(n - 16) / 20 {
+                            for value in &[5u32 + i as u32, 50, 10] {
+                                response.extend_from_slice(&value.to_be_bytes());
+                            }
+                        }
+                    }
+                    ANNOUNCE => {
+                        assert_eq!(n, 98);
+                        for value in &[1800u32, 3, 7] {
+                            response.extend_from_slice(&value.to_be_bytes());
+                        }
+                        response.extend_from_slice(&[10, 0, 0, 1, 0x1a, 0xe1]);
+                    }
+                    _ => {
+                        response[..4].copy_from_slice(&ERROR.to_be_bytes());
+                        response.extend_from_slice(b"unknown action");
+                    }
+                }
+                socket.send_to(&response, from).unwrap();
+            }
+        });
+        port
+    }
+
+    #[test]
+    pub fn test_udp_tracker() {
+        let port = fake_tracker();
+        let timeouts = Timeouts {
+            timeout: Duration::from_millis(100),
+            retries: 2,
+        };
+        let mut connection = Connection::connect("127.0.0.1", port, timeouts).unwrap();
+        assert_eq!(connection.id, 42);
+
+        let hashes = [InfoHash::from([1; 20]), InfoHash::from([2; 20])];
+        let results = connection.scrape(&hashes).unwrap();
+        assert_eq!(
+            results[&hashes[1]],
+            ScrapeResult {
+                seeders: 6,
+                leechers: 10,
+                completed: 50,
+            }
+        );
+
+        let response = connection
+            .announce(&AnnounceRequest::new(hashes[0], 6881))
+            .unwrap();
+        assert_eq!(response.interval, 1800);
+        assert_eq!((response.seeders, response.leechers), (7, 3));
+        assert_eq!(response.peers, vec!["10.0.0.1:6881".parse().unwrap()]);
+
+        match connection.request(7, &[]) {
+            Err(Error::Tracker(ref e)) => assert_eq!(e, "unknown action"),
+            r => panic!("unexpected result {:?}", r),
+        }
+    }
+
+    #[test]
+    pub fn test_mismatched_transactions() {
+        // Answers each request with a stream of responses to other transactions
+        let tracker = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = tracker.local_addr().unwrap().port();
+        thread::spawn(move || {
+            let mut buf = [0u8; 1024];
+            let (_, from) = tracker.recv_from(&mut buf).unwrap();
+            let mut response = buf[8..16].to_vec();
+            response[4] ^= 0xff;
+            response.extend_from_slice(&42u64.to_be_bytes());
+            for _ in 0..500 {
+                if tracker.send_to(&response, from).is_err() {
+                    break;
+                }
+                thread::sleep(Duration::from_millis(5));
+            }
+        });
+
+        let start = Instant::now();
+        match Connection::connect(
+            "127.0.0.1",
+            port,
+            Timeouts {
+                timeout: Duration::from_millis(100),
+                retries: 1,
+            },
+        ) {
+            Err(Error::Tracker(ref e)) => assert_eq!(e, "timed out"),
+            r => panic!("unexpected result {:?}", r.map(|c| c.id)),
+        }
+        assert!(start.elapsed() < Duration::from_secs(1));
+    }
+
+    #[test]
+    pub fn test_udp_timeout() {
+        // Nothing answers on the port of a bound socket which never reads
+        let socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let port = socket.local_addr().unwrap().port();
+        let timeouts = Timeouts {
+            timeout: Duration::from_millis(10),
+            retries: 1,
+        };
+        match Connection::connect("127.0.0.1", port, timeouts) {
+            Err(Error::Tracker(ref e)) => assert_eq!(e, "timed out"),
+            r => panic!("unexpected result {:?}", r.map(|c| c.id)),
+        }
+    }
+}
@@ -0,0 +1,252 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! uTorrent and BitTorrent mainline `resume.dat` files.
+
+use std::borrow::Cow;
+use std::fs;
+use std::path::Path;
+
+use serde_bencode::de;
+use serde_bencode::value::Value;
+use serde_bytes::ByteBuf;
+
+use bencode;
+use client_state::{lossy, timestamp};
+use error::{Error, Result};
+use limits::Limits;
+use {Bitfield, InfoHash};
+
+/// The state of a torrent in uTorrent, stored in `resume.dat` under the path of its
+/// torrent file.
+#[derive(Debug, Clone, PartialEq, Default, Deserialize)]
+pub struct UtorrentResume {
+    #[serde(default)]
+    caption: Option<ByteBuf>,
+    #[serde(default)]
+    path: Option<ByteBuf>,
+    #[serde(default)]
+    label: Option<ByteBuf>,
+    #[serde(default)]
+    labels: Vec<ByteBuf>,
+    #[serde(default)]
+    info: Option<ByteBuf>,
+    #[serde(default)]
+    started: Option<i64>,
+    #[serde(default)]
+    have: Option<ByteBuf>,
+    #[serde(default)]
+    prio: Option<ByteBuf>,
+    #[serde(default)]
+    targets: Vec<Vec<Value>>,
+    #[serde(default)]
+    downloaded: Option<u64>,
+    #[serde(default)]
+    uploaded: Option<u64>,
+    #[serde(default)]
+    added_on: Option<i64>,
+    #[serde(default)]
+    completed_on: Option<i64>,
+}
+
+impl UtorrentResume {
+    pub fn from_buf(buf: &[u8]) -> Result<Self> {
+        Limits::default().check(buf)?;
+        Ok(de::from_bytes(buf)?)
+    }
+
+    /// Returns the name shown in uTorrent, which differs from the name in the torrent
+    /// after renaming.
+    pub fn caption(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.caption)
+    }
+
+    /// Returns the save path, which is the file itself for single-file torrents and
+    /// includes the torrent name for multi-file torrents.
+    pub fn path(&self) -> Option<Cow<'_, str>> {
+        lossy(&self.path)
+    }
+
+    /// Returns the labels, of which uTorrent 2 stores only one in `label`.
+    pub fn labels(&self) -> Vec<Cow<'_, str>> {
+        let mut labels: Vec<Cow<str>> = self
+            .labels
+            .iter()
+            .map(|label| String::from_utf8_lossy(label))
+            .collect();
+        if let Some(label) = lossy(&self.label).filter(|label| !label.is_empty()) {
+            if !labels.contains(&label) {
+                labels.insert(0, label);
+            }
+        }
+        labels
+    }
+
+    /// Returns the info hash, if stored.
+    pub fn info_hash(&self) -> Option<InfoHash> {
+        self.info
+            .as_ref()
+            .and_then(|info| InfoHash::from_bytes(info).ok())
+    }
+
+    /// Returns whether the torrent is started rather than stopped or paused.
+    pub fn is_started(&self) -> bool {
+        self.started.is_some_and(|started| started != 0)
+    }
+
+    /// Returns whether the download finished.
+    pub fn is_complete(&self) -> bool {
+        self.completed_on().is_some()
+    }
+
+    /// Returns the downloaded pieces of a torrent with `num_pieces` pieces.
+    pub fn pieces(&self, num_pieces: usize) -> Option<Bitfield> {
+        self.have
+            .as_ref()
+            .and_then(|have| Bitfield::from_bytes(have, num_pieces).ok())
+    }
+
+    /// Returns the priorities of the files, 0 for files which are not downloaded.
+    pub fn priorities(&self) -> &[u8] {
+        self.prio.as_ref().map_or(&[], |prio| &prio[..])
+    }
+
+    /// Returns the files saved outside of the save path, by their index in the torrent.
+    pub fn targets(&self) -> Vec<(usize, Cow<'_, str>)> {
+        // `[index, path]` lists
+        self.targets
+            .iter()
+            .filter_map(|target| match target[..] {
+                [Value::Int(index), Value::Bytes(ref path)] if index >= 0 => {
+                    Some((index as usize, String::from_utf8_lossy(path)))
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Returns the number of downloaded bytes.
+    pub fn downloaded(&self) -> u64 {
+        self.downloaded.unwrap_or_default()
+    }
+
+    /// Returns the number of uploaded bytes.
+    pub fn uploaded(&self) -> u64 {
+        self.uploaded.unwrap_or_default()
+    }
+
+    /// Returns the Unix timestamp the torrent was added at.
+    pub fn added_on(&self) -> Option<i64> {
+        timestamp(self.added_on)
+    }
+
+    /// Returns the Unix timestamp the download finished at.
+    pub fn completed_on(&self) -> Option<i64> {
+        timestamp(self.completed_on)
+    }
+}
+
+/// A torrent of a uTorrent `resume.dat` file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct UtorrentTorrent {
+    /// The path of the torrent file, relative to the directory of `resume.dat` unless
+    /// absolute
+    pub torrent_file: String,
+    pub resume: UtorrentResume,
+}
+
+/// Parses the torrents of a uTorrent `resume.dat` file, in the order of the file.
+///
+/// Entries which aren't torrents, like `.fileguard` and `rec`, are skipped, torrents are
+/// the dicts stored under the path of a `.torrent` file.
+pub fn parse_utorrent_resume(buf: &[u8]) -> Result<Vec<UtorrentTorrent>> {
+    if buf.first() != Some(&b'd') {
+        bail!(Error::InvalidBencode(0));
+    }
+
+    let mut torrents = vec![];
+    let mut pos = 1;
+    while *buf.get(pos).ok_or(Error::InvalidBencode(pos))? != b'e' {
+        let (key, key_end) = bencode::string_at(buf, pos).ok_or(Error::InvalidBencode(pos))?;
+        let value_end = bencode::value_end(buf, key_end).ok_or(Error::InvalidBencode(key_end))?;
+        let is_torrent = key.len() > 8 && key[key.len() - 8..].eq_ignore_ascii_case(b".torrent");
+        if is_torrent && buf[key_end] == b'd' {
+            torrents.push(UtorrentTorrent {
+                torrent_file: String::from_utf8_lossy(key).into_owned(),
+                resume: UtorrentResume::from_buf(&buf[key_end..value_end])?,
+            });
+        }
+        pos = value_end;
+    }
+
+    Ok(torrents)
+}
+
+/// Reads the torrents of a uTorrent `resume.dat` file.
+pub fn utorrent_resume<P: AsRef<Path>>(path: P) -> Result<Vec<UtorrentTorrent>> {
+    let path = path.as_ref();
+    let read_error = |e: Error| Error::ReadTorrent {
+        path: path.display().to_string(),
+        source: Box::new(e),
+    };
+    let buf = fs::read(path).map_err(|e| read_error(e.into()))?;
+    parse_utorrent_resume(&buf).map_err(read_error)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RESUME_DAT: &[u8] = b"d10:.fileguard40:0000000000000000000000000000000000000000\
+11:foo.torrentd8:added_oni1500000000e7:caption3:foo12:completed_oni0e10:downloadedi2048e\
+4:have2:\xc0\x004:info20:aaaaaaaaaaaaaaaaaaaa5:label3:iso6:labelsl5:linuxe4:path9:/data/foo\
+4:prio2:\x08\x007:startedi2e7:targetslli1e4:/x/bee8:uploadedi512ee\
+3:recd3:foo3:baree";
+
+    #[test]
+    pub fn test_resume_dat() {
+        let torrents = parse_utorrent_resume(RESUME_DAT).unwrap();
+        assert_eq!(torrents.len(), 1);
+        assert_eq!(torrents[0].torrent_file, "foo.torrent");
+        let resume = &torrents[0].resume;
+        assert_eq!(resume.caption().as_deref(), Some("foo"));
+        assert_eq!(resume.path().as_deref(), Some("/data/foo"));
+        assert_eq!(resume.labels(), ["iso", "linux"]);
+        assert_eq!(resume.info_hash(), Some(InfoHash::from([b'a'; 20])));
+        assert!(resume.is_started());
+        assert!(!resume.is_complete());
+        assert_eq!(resume.pieces(10).unwrap().count_ones(), 2);
+        assert_eq!(resume.priorities(), [8, 0]);
+        assert_eq!(resume.targets(), [(1, "/x/b".into())]);
+        assert_eq!(resume.downloaded(), 2048);
+        assert_eq!(resume.uploaded(), 512);
+        assert_eq!(resume.added_on(), Some(1_500_000_000));
+        assert_eq!(resume.completed_on(), None);
+
+        assert!(parse_utorrent_resume(b"de").unwrap().is_empty());
+        assert!(parse_utorrent_resume(b"le").is_err());
+        assert!(parse_utorrent_resume(b"d3:foo").is_err());
+
+        let nested = format!(
+            "d5:x.torrentd7:targets{}{}ee",
+            "l".repeat(100_000),
+            "e".repeat(100_000)
+        );
+        assert!(parse_utorrent_resume(nested.as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,1099 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Verification of downloaded data against the piece hashes of torrents.
+
+use std::collections::{BTreeSet, HashMap};
+use std::fs;
+use std::io::{self, Read, Seek, SeekFrom};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Mutex};
+use std::thread;
+
+use bitfield::Bitfield;
+use error::{Error, Result};
+use merkle;
+use sanitize;
+use {to_hex, FileMode, Torrent, TorrentVersion};
+
+/// The largest piece length which is verified, as every piece read is held in memory.
+const MAX_PIECE_LENGTH: u64 = 256 * 1024 * 1024;
+
+/// The result of checking a piece against its hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PieceStatus {
+    /// The data matches the hash
+    Good,
+    /// The data doesn't match the hash
+    Bad,
+    /// A file of the piece is missing or too short
+    Missing,
+    /// The piece wasn't part of the sample, see `Sampling`
+    Unchecked,
+}
+
+/// The completeness of a file, see `Verification`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileStatus {
+    /// The location of the file in the download directory
+    pub path: PathBuf,
+    pub length: u64,
+    /// The offset of the file in the content, including pad files
+    pub offset: u64,
+    /// The size of the file on disk, `None` if it doesn't exist
+    pub size: Option<u64>,
+    /// The pieces covering the file
+    pub pieces: Range<usize>,
+    /// The number of good pieces covering the file
+    pub good_pieces: usize,
+}
+
+impl FileStatus {
+    pub fn exists(&self) -> bool {
+        self.size.is_some()
+    }
+
+    /// Returns whether the file is shorter than its length in the torrent.
+    pub fn is_short(&self) -> bool {
+        self.size.is_some_and(|size| size < self.length)
+    }
+
+    /// Returns whether the file exists and all of its pieces are good.
+    pub fn is_complete(&self) -> bool {
+        self.exists() && self.good_pieces == self.pieces.len()
+    }
+
+    /// Returns the share of good pieces between 0 and 1, which is 1 for existing empty
+    /// files.
+    pub fn completeness(&self) -> f64 {
+        match self.pieces.len() {
+            0 if self.exists() => 1.0,
+            0 => 0.0,
+            n => self.good_pieces as f64 / n as f64,
+        }
+    }
+}
+
+/// A byte range of a file, see `FailedPiece`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileRange {
+    pub path: PathBuf,
+    /// The offset of the range in the file
+    pub offset: u64,
+    pub length: u64,
+}
+
+/// A bad or missing piece with the byte ranges of the files it covers, which need to be
+/// downloaded or copied again.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedPiece {
+    pub index: usize,
+    pub status: PieceStatus,
+    /// The ranges in content order, leaving out pad files
+    pub ranges: Vec<FileRange>,
+}
+
+/// The result of `verify`.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Verification {
+    /// The status of every piece with a hash
+    pub pieces: Vec<PieceStatus>,
+    /// The status of the files, leaving out pad files
+    pub files: Vec<FileStatus>,
+    pub piece_length: u64,
+    /// The size of the content including pad files
+    pub total_size: u64,
+}
+
+impl Verification {
+    /// Returns the number of pieces with `status`.
+    pub fn count(&self, status: PieceStatus) -> usize {
+        self.pieces.iter().filter(|&&s| s == status).count()
+    }
+
+    /// Returns whether all pieces are good.
+    pub fn is_complete(&self) -> bool {
+        self.pieces.iter().all(|&s| s == PieceStatus::Good)
+    }
+
+    /// Returns the share of the content in good pieces between 0 and 1.
+    pub fn completeness(&self) -> f64 {
+        if self.total_size == 0 {
+            return 1.0;
+        }
+        let good: u64 = self
+            .pieces
+            .iter()
+            .enumerate()
+            .filter(|&(_, &s)| s == PieceStatus::Good)
+            .map(|(i, _)| {
+                let start = i as u64 * self.piece_length;
+                self.piece_length.min(self.total_size.saturating_sub(start))
+            })
+            .sum();
+        good as f64 / self.total_size as f64
+    }
+
+    /// Returns the completeness in percent.
+    pub fn percent_complete(&self) -> f64 {
+        self.completeness() * 100.0
+    }
+
+    /// Returns the bad and missing pieces with the byte ranges of the affected files.
+    pub fn failed_pieces(&self) -> Vec<FailedPiece> {
+        self.pieces
+            .iter()
+            .enumerate()
+            .filter(|&(_, &s)| s == PieceStatus::Bad || s == PieceStatus::Missing)
+            .map(|(index, &status)| {
+                let start = index as u64 * self.piece_length;
+                let end = start.saturating_add(self.piece_length);
+                let first = self
+                    .files
+                    .partition_point(|file| file.offset + file.length <= start);
+                let ranges = self.files[first..]
+                    .iter()
+                    .take_while(|file| file.offset < end)
+                    .filter(|file| file.length > 0)
+                    .map(|file| {
+                        let offset = start.saturating_sub(file.offset);
+                        FileRange {
+                            path: file.path.clone(),
+                            offset,
+                            length: (end - file.offset).min(file.length) - offset,
+                        }
+                    })
+                    .collect();
+                FailedPiece {
+                    index,
+                    status,
+                    ranges,
+                }
+            })
+            .collect()
+    }
+
+    /// Returns the bitfield of the good pieces.
+    pub fn bitfield(&self) -> Bitfield {
+        self.pieces
+            .iter()
+            .map(|&s| s == PieceStatus::Good)
+            .collect()
+    }
+
+    /// Returns whether all checked pieces are good, which for a sample means the data is
+    /// probably intact.
+    pub fn is_intact(&self) -> bool {
+        self.pieces
+            .iter()
+            .all(|&s| s == PieceStatus::Good || s == PieceStatus::Unchecked)
+    }
+}
+
+/// Selects the pieces checked by `verify_with`.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Sampling {
+    /// Check every piece
+    #[default]
+    All,
+    /// Check `count` pieces each at the start, the middle and the end
+    HeadMiddleTail(usize),
+    /// Check random pieces, enough to find damage to at least the share `damaged` of the
+    /// pieces with probability `confidence`, e.g. 0.01 and 0.99 check up to 459 pieces
+    Random {
+        confidence: f64,
+        damaged: f64,
+        /// Seeds the selection, the same seed selects the same pieces
+        seed: u64,
+    },
+}
+
+impl Sampling {
+    /// Returns the sorted indices of the selected pieces.
+    fn select(&self, num_pieces: usize) -> Vec<usize> {
+        let selected: BTreeSet<usize> = match *self {
+            Sampling::All => return (0..num_pieces).collect(),
+            Sampling::HeadMiddleTail(count) => {
+                let count = count.min(num_pieces);
+                let middle = num_pieces.saturating_sub(count) / 2;
+                (0..count)
+                    .chain(middle..middle + count)
+                    .chain(num_pieces.saturating_sub(count)..num_pieces)
+                    .filter(|&i| i < num_pieces)
+                    .collect()
+            }
+            Sampling::Random {
+                confidence,
+                damaged,
+                seed,
+            } => {
+                let count = if confidence >= 1.0 || damaged <= 0.0 {
+                    num_pieces
+                } else if confidence <= 0.0 {
+                    0
+                } else {
+                    let count = ((1.0 - confidence).ln() / (1.0 - damaged.min(1.0)).ln()).ceil();
+                    (count.max(1.0) as usize).min(num_pieces)
+                };
+                // xorshift64*, the quality is plenty for picking pieces
+                let mut state = seed | 1;
+                let mut selected = BTreeSet::new();
+                while selected.len() < count {
+                    state ^= state >> 12;
+                    state ^= state << 25;
+                    state ^= state >> 27;
+                    let random = state.wrapping_mul(0x2545_f491_4f6c_dd1d);
+                    selected.insert((random % num_pieces as u64) as usize);
+                }
+                selected
+            }
+        };
+        selected.into_iter().collect()
+    }
+}
+
+/// A file of the content, or a pad file which is read as zeros.
+pub(crate) struct LocalFile {
+    pub path: Option<PathBuf>,
+    pub length: u64,
+}
+
+/// A piece to check, at `offset` in the content of the `LocalFile`s.
+pub(crate) struct PieceCheck {
+    offset: u64,
+    length: u64,
+    hash: Vec<u8>,
+    /// `None` for SHA-1 hashes, else the merkle root of the blocks extended to this many
+    /// leaves
+    blocks: Option<usize>,
+}
+
+impl PieceCheck {
+    fn hash(&self, data: &[u8]) -> Vec<u8> {
+        match self.blocks {
+            None => merkle::sha1(data).to_vec(),
+            Some(target) => {
+                let blocks = data.len().div_ceil(merkle::BLOCK_SIZE);
+                merkle::extend_root(merkle::blocks_root(data), blocks, target).to_vec()
+            }
+        }
+    }
+}
+
+/// Returns the directory or file named after the torrent below `root`.
+fn base_path(torrent: &Torrent, root: &Path) -> PathBuf {
+    let name = torrent
+        .decoded_name()
+        .and_then(|name| sanitize::sanitize_component(&name));
+    let mut base = root.to_path_buf();
+    base.extend(name);
+    base
+}
+
+/// Returns the path of the file at `path` in the torrent if it's in `paths`, relative to
+/// `root`.
+fn remapped(
+    paths: &HashMap<Vec<String>, PathBuf>,
+    root: &Path,
+    path: &[String],
+) -> Option<PathBuf> {
+    paths.get(path).map(|local| root.join(local))
+}
+
+/// Returns the files of a v1 torrent below the download directory `root` and the pieces
+/// to check.
+pub(crate) fn v1_files(
+    torrent: &Torrent,
+    root: &Path,
+    paths: &HashMap<Vec<String>, PathBuf>,
+) -> Result<(Vec<LocalFile>, Vec<PieceCheck>)> {
+    if torrent.info().is_merkle() {
+        bail!(Error::Unsupported("verifying merkle torrents".to_owned()));
+    }
+    let base = base_path(torrent, root);
+    let files: Vec<LocalFile> = match torrent.info().mode() {
+        Some(FileMode::Single { length, .. }) => {
+            let name: Vec<String> = torrent
+                .decoded_name()
+                .map(|n| n.into_owned())
+                .into_iter()
+                .collect();
+            vec![LocalFile {
+                path: Some(remapped(paths, root, &name).unwrap_or(base)),
+                length: *length,
+            }]
+        }
+        Some(FileMode::Multi { files }) => files
+            .iter()
+            .map(|file| LocalFile {
+                path: if file.is_padding() {
+                    None
+                } else {
+                    let torrent_path: Vec<String> =
+                        file.path().into_iter().map(|c| c.into_owned()).collect();
+                    remapped(paths, root, &torrent_path).or_else(|| {
+                        let mut path = base.clone();
+                        path.extend(file.sanitized_path());
+                        Some(path)
+                    })
+                },
+                length: file.length(),
+            })
+            .collect(),
+        None => vec![],
+    };
+
+    let piece_length = torrent.info().piece_length();
+    let total_size: u64 = files.iter().map(|file| file.length).sum();
+    let checks = torrent
+        .info()
+        .pieces()
+        .iter()
+        .enumerate()
+        .map(|(index, hash)| {
+            let offset = index as u64 * piece_length;
+            PieceCheck {
+                offset,
+                length: piece_length.min(total_size.saturating_sub(offset)),
+                hash: hash.to_vec(),
+                blocks: None,
+            }
+        })
+        .collect();
+
+    Ok((files, checks))
+}
+
+/// Returns the files of a v2 or hybrid torrent below the download directory `root` and
+/// the pieces to check against the `piece layers`.
+///
+/// Every file starts a new piece, so the files are followed by pad files as in hybrid
+/// torrents, making the piece indices match those of the v1 pieces.
+pub(crate) fn v2_files(
+    torrent: &Torrent,
+    root: &Path,
+    paths: &HashMap<Vec<String>, PathBuf>,
+) -> Result<(Vec<LocalFile>, Vec<PieceCheck>)> {
+    let piece_length = torrent.info().piece_length();
+    if piece_length < merkle::BLOCK_SIZE as u64 || !piece_length.is_power_of_two() {
+        bail!(Error::InvalidPieceLength(piece_length as usize));
+    }
+    let base = base_path(torrent, root);
+    let name = torrent.decoded_name();
+    let tree = torrent.info().file_tree();
+    let single = tree.len() == 1 && name.is_some_and(|name| tree[0].path() == [&*name]);
+    let layers = torrent.piece_layers();
+    let blocks_per_piece = (piece_length / merkle::BLOCK_SIZE as u64) as usize;
+
+    let mut files = vec![];
+    let mut checks = vec![];
+    let mut offset = 0u64;
+    for (i, entry) in tree.iter().enumerate() {
+        let path = remapped(paths, root, entry.path()).unwrap_or_else(|| {
+            let mut path = base.clone();
+            if !single {
+                path.extend(sanitize::sanitize_path(entry.path()));
+            }
+            path
+        });
+        files.push(LocalFile {
+            path: Some(path),
+            length: entry.length(),
+        });
+
+        let num_pieces = entry.length().div_ceil(piece_length);
+        let root = entry.pieces_root().unwrap_or_default();
+        let invalid = || Error::InvalidPieceLayer(to_hex(root));
+        let hashes: Vec<&[u8]> = match num_pieces {
+            0 => vec![],
+            1 => vec![root],
+            _ => {
+                let layer = layers.get(root).ok_or_else(invalid)?;
+                let hashes: Vec<merkle::Hash> = layer
+                    .chunks(32)
+                    .map(|chunk| {
+                        let mut hash = [0u8; 32];
+                        hash.copy_from_slice(chunk);
+                        hash
+                    })
+                    .collect();
+                if layer.len() as u64 != num_pieces * 32
+                    || merkle::file_root(&hashes, piece_length as usize)[..] != *root
+                {
+                    bail!(invalid());
+                }
+                layer.chunks(32).collect()
+            }
+        };
+        for (j, hash) in hashes.into_iter().enumerate() {
+            let start = j as u64 * piece_length;
+            checks.push(PieceCheck {
+                offset: offset + start,
+                length: piece_length.min(entry.length() - start),
+                hash: hash.to_vec(),
+                blocks: Some(if num_pieces == 1 { 0 } else { blocks_per_piece }),
+            });
+        }
+
+        offset += entry.length();
+        let remainder = entry.length() % piece_length;
+        if remainder > 0 && i + 1 < tree.len() {
+            files.push(LocalFile {
+                path: None,
+                length: piece_length - remainder,
+            });
+            offset += piece_length - remainder;
+        }
+    }
+
+    Ok((files, checks))
+}
+
+/// Opens and reads the content files for `verify_with_reader`, so content on network
+/// mounts, in object storage or inside archives can be verified.
+///
+/// The paths passed to the methods are those of the files in the download directory.
+pub trait PieceReader: Sync {
+    type File;
+
+    /// Opens the file at `path`, returning `None` if it doesn't exist.
+    fn open(&self, path: &Path) -> io::Result<Option<Self::File>>;
+
+    /// Reads from `file` at `offset` into `buf`, returning the number of bytes read, which
+    /// is 0 at the end of the file.
+    fn read_at(&self, file: &mut Self::File, offset: u64, buf: &mut [u8]) -> io::Result<usize>;
+
+    /// Returns the size of the file at `path`, `None` if it doesn't exist.
+    fn size(&self, path: &Path) -> io::Result<Option<u64>>;
+}
+
+/// Reads the content from the file system with `std::fs`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FsReader;
+
+impl PieceReader for FsReader {
+    type File = fs::File;
+
+    fn open(&self, path: &Path) -> io::Result<Option<fs::File>> {
+        match fs::File::open(path) {
+            Ok(file) => Ok(Some(file)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn read_at(&self, file: &mut fs::File, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+        file.seek(SeekFrom::Start(offset))?;
+        file.read(buf)
+    }
+
+    fn size(&self, path: &Path) -> io::Result<Option<u64>> {
+        match fs::metadata(path) {
+            Ok(ref metadata) if metadata.is_file() => Ok(Some(metadata.len())),
+            Ok(_) => Ok(None),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+enum State<F> {
+    Unopened,
+    Open(F),
+    Missing,
+}
+
+/// Reads the concatenated content of the files, filling in zeros for pad files and for
+/// missing data.
+pub(crate) struct ContentReader<'a, R: PieceReader + 'a> {
+    reader: &'a R,
+    files: &'a [LocalFile],
+    index: usize,
+    pos: u64,
+    offset: u64,
+    state: State<R::File>,
+}
+
+impl<'a, R: PieceReader> ContentReader<'a, R> {
+    pub fn new(reader: &'a R, files: &'a [LocalFile]) -> Self {
+        ContentReader {
+            reader,
+            files,
+            index: 0,
+            pos: 0,
+            offset: 0,
+            state: State::Unopened,
+        }
+    }
+
+    /// Moves to `offset` in the content.
+    pub fn seek(&mut self, offset: u64) {
+        if offset == self.offset {
+            return;
+        }
+        let mut start = 0u64;
+        self.index = self.files.len();
+        self.pos = 0;
+        for (index, file) in self.files.iter().enumerate() {
+            if offset < start + file.length {
+                self.index = index;
+                self.pos = offset - start;
+                break;
+            }
+            start += file.length;
+        }
+        self.offset = offset;
+        self.state = State::Unopened;
+    }
+
+    /// Reads the next `length` bytes into `buf`, or less at the end of the content,
+    /// returning whether all of them were present.
+    pub fn read(&mut self, buf: &mut Vec<u8>, mut length: u64) -> Result<bool> {
+        buf.clear();
+        let mut complete = true;
+        while length > 0 && self.index < self.files.len() {
+            let file = &self.files[self.index];
+            let n = length.min(file.length - self.pos);
+            let start = buf.len();
+            buf.resize(start + n as usize, 0);
+            if let Some(ref path) = file.path {
+                complete &= self.read_file(path, &mut buf[start..])?;
+            }
+            self.pos += n;
+            self.offset += n;
+            length -= n;
+            if self.pos == file.length {
+                self.index += 1;
+                self.pos = 0;
+                self.state = State::Unopened;
+            }
+        }
+
+        Ok(complete)
+    }
+
+    fn read_file(&mut self, path: &Path, buf: &mut [u8]) -> Result<bool> {
+        let read_error = |source| Error::ReadData {
+            path: path.display().to_string(),
+            source,
+        };
+        if let State::Unopened = self.state {
+            self.state = match self.reader.open(path).map_err(read_error)? {
+                Some(file) => State::Open(file),
+                None => State::Missing,
+            };
+        }
+        let file = match self.state {
+            State::Open(ref mut file) => file,
+            _ => return Ok(false),
+        };
+        let mut done = 0;
+        while done < buf.len() {
+            match self
+                .reader
+                .read_at(file, self.pos + done as u64, &mut buf[done..])
+            {
+                Ok(0) => {
+                    self.state = State::Missing;
+                    return Ok(false);
+                }
+                Ok(n) => done += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => bail!(read_error(e)),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+/// Returns the pieces covering each of the files.
+fn file_pieces(files: &[LocalFile], piece_length: u64) -> Vec<Range<usize>> {
+    let mut offset = 0u64;
+    files
+        .iter()
+        .map(|file| {
+            let start = offset;
+            offset = offset.saturating_add(file.length);
+            if file.length == 0 {
+                return 0..0;
+            }
+            (start / piece_length) as usize..((offset - 1) / piece_length + 1) as usize
+        })
+        .collect()
+}
+
+/// Creates the report from the status of the pieces.
+pub(crate) fn verification<R: PieceReader>(
+    reader: &R,
+    files: &[LocalFile],
+    piece_length: u64,
+    pieces: Vec<PieceStatus>,
+) -> Verification {
+    let ranges = file_pieces(files, piece_length);
+    let total_size = files.iter().map(|file| file.length).sum();
+    let mut offset = 0u64;
+    let files = files
+        .iter()
+        .zip(ranges)
+        .filter_map(|(file, range)| {
+            offset += file.length;
+            let path = file.path.clone()?;
+            Some(FileStatus {
+                offset: offset - file.length,
+                size: reader.size(&path).ok().and_then(|size| size),
+                good_pieces: pieces
+                    .get(range.clone())
+                    .unwrap_or_default()
+                    .iter()
+                    .filter(|&&s| s == PieceStatus::Good)
+                    .count(),
+                path,
+                length: file.length,
+                pieces: range,
+            })
+        })
+        .collect();
+
+    Verification {
+        pieces,
+        files,
+        piece_length,
+        total_size,
+    }
+}
+
+/// Controls how `verify_with` reads and hashes the data.
+#[derive(Debug, Clone)]
+pub struct VerifyOptions {
+    /// The number of hashing threads, which defaults to the number of CPUs
+    pub threads: usize,
+    /// The number of pieces read ahead of the hashing threads, which defaults to two per
+    /// thread
+    pub read_ahead: usize,
+    /// The pieces to check, by default all of them
+    pub sampling: Sampling,
+    /// Checks hybrid torrents against the v2 hashes instead of the v1 hashes, v2 torrents
+    /// are always checked against the v2 hashes
+    pub v2: bool,
+    /// Maps the paths of files in the torrent to the paths of renamed or moved files,
+    /// relative to the download directory. The paths in the torrent leave out the torrent
+    /// name, except for single-file torrents where it is the only component.
+    pub paths: HashMap<Vec<String>, PathBuf>,
+}
+
+impl Default for VerifyOptions {
+    fn default() -> Self {
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        VerifyOptions {
+            threads,
+            read_ahead: threads * 2,
+            sampling: Sampling::All,
+            v2: false,
+            paths: HashMap::new(),
+        }
+    }
+}
+
+/// Checks the data of a torrent in the download directory `root` against its piece hashes,
+/// using the default `VerifyOptions`.
+///
+/// v2 torrents are checked against the `pieces root` of each file, hashing the 16 KiB
+/// blocks of the pieces, and the piece layers are checked against the roots.
+///
+/// Multi-file torrents are expected in a directory named after the torrent, with the
+/// paths sanitized as in `File::sanitized_path`.
+pub fn verify<P: AsRef<Path>>(torrent: &Torrent, root: P) -> Result<Verification> {
+    verify_with(torrent, root, &VerifyOptions::default())
+}
+
+/// Checks the data of a torrent like `verify`.
+///
+/// The pieces are read by one thread and hashed by `options.threads` workers, so reading
+/// overlaps with hashing.
+pub fn verify_with<P: AsRef<Path>>(
+    torrent: &Torrent,
+    root: P,
+    options: &VerifyOptions,
+) -> Result<Verification> {
+    verify_with_reader(torrent, root, options, &FsReader)
+}
+
+/// Checks the data of a torrent like `verify_with`, reading it with `reader`.
+pub fn verify_with_reader<P: AsRef<Path>, R: PieceReader>(
+    torrent: &Torrent,
+    root: P,
+    options: &VerifyOptions,
+    reader: &R,
+) -> Result<Verification> {
+    let piece_length = torrent.info().piece_length();
+    if piece_length == 0 || piece_length > MAX_PIECE_LENGTH {
+        bail!(Error::InvalidPieceLength(piece_length as usize));
+    }
+    let (files, checks) = match torrent.version() {
+        TorrentVersion::V2 => v2_files(torrent, root.as_ref(), &options.paths)?,
+        TorrentVersion::Hybrid if options.v2 => v2_files(torrent, root.as_ref(), &options.paths)?,
+        _ => v1_files(torrent, root.as_ref(), &options.paths)?,
+    };
+
+    let (piece_tx, piece_rx) =
+        mpsc::sync_channel::<(usize, Option<Vec<u8>>)>(options.read_ahead.max(1));
+    let piece_rx = Mutex::new(piece_rx);
+    let (hash_tx, hash_rx) = mpsc::channel();
+
+    thread::scope(|scope| {
+        let checks = &checks;
+        for _ in 0..options.threads.max(1) {
+            let piece_rx = &piece_rx;
+            let hash_tx = hash_tx.clone();
+            scope.spawn(move || loop {
+                let piece = piece_rx.lock().unwrap().recv();
+                match piece {
+                    Ok((index, data)) => {
+                        let hash = data.map(|data| checks[index].hash(&data));
+                        if hash_tx.send((index, hash)).is_err() {
+                            break;
+                        }
+                    }
+                    Err(_) => break,
+                }
+            });
+        }
+        drop(hash_tx);
+
+        let files = &files;
+        let selected = options.sampling.select(checks.len());
+        let content = scope.spawn(move || -> Result<()> {
+            let mut content = ContentReader::new(reader, files);
+            for index in selected {
+                let check = &checks[index];
+                content.seek(check.offset);
+                let mut buf = Vec::with_capacity(check.length as usize);
+                let complete = content.read(&mut buf, check.length)?;
+                // The workers only hang up on panic, which surfaces when the scope ends
+                let _ = piece_tx.send((index, if complete { Some(buf) } else { None }));
+            }
+            Ok(())
+        });
+
+        let mut pieces = vec![PieceStatus::Unchecked; checks.len()];
+        for (index, hash) in hash_rx {
+            pieces[index] = match hash {
+                None => PieceStatus::Missing,
+                Some(ref hash) if *hash == checks[index].hash => PieceStatus::Good,
+                Some(_) => PieceStatus::Bad,
+            };
+        }
+
+        content.join().expect("piece reading thread panicked")?;
+        Ok(verification(reader, files, piece_length, pieces))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::env;
+    use TorrentBuilder;
+
+    #[test]
+    pub fn test_sampling() {
+        assert_eq!(Sampling::All.select(3), vec![0, 1, 2]);
+        assert_eq!(
+            Sampling::HeadMiddleTail(2).select(10),
+            vec![0, 1, 4, 5, 8, 9]
+        );
+        assert_eq!(Sampling::HeadMiddleTail(2).select(3), vec![0, 1, 2]);
+        assert!(Sampling::HeadMiddleTail(2).select(0).is_empty());
+        assert_eq!(
+            Sampling::HeadMiddleTail(usize::MAX).select(3),
+            vec![0, 1, 2]
+        );
+        let random = |confidence, damaged, seed| Sampling::Random {
+            confidence,
+            damaged,
+            seed,
+        };
+        assert_eq!(random(0.99, 0.01, 7).select(10000).len(), 459);
+        assert_eq!(
+            random(0.99, 0.01, 7).select(10000),
+            random(0.99, 0.01, 7).select(10000)
+        );
+        assert_ne!(
+            random(0.99, 0.01, 7).select(10000),
+            random(0.99, 0.01, 8).select(10000)
+        );
+        assert_eq!(random(0.99, 0.01, 7).select(100).len(), 100);
+        assert_eq!(random(1.0, 0.01, 7).select(5).len(), 5);
+        assert_eq!(random(0.0, 0.01, 7).select(5).len(), 0);
+    }
+
+    #[test]
+    pub fn test_verify() {
+        let root = env::temp_dir().join(format!("torrentinfo-verify-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("d")).unwrap();
+        for &(name, len) in &[("a", 20000), ("b", 30000), ("c", 10000)] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+            fs::write(root.join("d").join(name), data).unwrap();
+        }
+        let torrent = TorrentBuilder::new(root.join("d"))
+            .piece_length(16384)
+            .build()
+            .unwrap();
+
+        let result = verify(&torrent, &root).unwrap();
+        assert!(result.is_complete());
+        assert_eq!(result.count(PieceStatus::Good), 4);
+        assert_eq!(result.percent_complete(), 100.0);
+        assert!(result.files.iter().all(FileStatus::is_complete));
+        let options = VerifyOptions {
+            sampling: Sampling::Random {
+                confidence: 0.5,
+                damaged: 0.5,
+                seed: 1,
+            },
+            ..VerifyOptions::default()
+        };
+        let result = verify_with(&torrent, &root, &options).unwrap();
+        assert_eq!(result.count(PieceStatus::Good), 1);
+        assert!(result.is_intact() && !result.is_complete());
+
+        let mut data = fs::read(root.join("d/a")).unwrap();
+        data[0] ^= 1;
+        fs::write(root.join("d/a"), data).unwrap();
+        fs::remove_file(root.join("d/c")).unwrap();
+
+        let result = verify(&torrent, &root).unwrap();
+        assert_eq!(
+            result.pieces,
+            vec![
+                PieceStatus::Bad,
+                PieceStatus::Good,
+                PieceStatus::Good,
+                PieceStatus::Missing
+            ]
+        );
+        assert_eq!(result.bitfield().as_bytes(), &[0x60]);
+        let range = |path: &str, offset, length| FileRange {
+            path: root.join(path),
+            offset,
+            length,
+        };
+        assert_eq!(
+            result.failed_pieces(),
+            vec![
+                FailedPiece {
+                    index: 0,
+                    status: PieceStatus::Bad,
+                    ranges: vec![range("d/a", 0, 16384)],
+                },
+                FailedPiece {
+                    index: 3,
+                    status: PieceStatus::Missing,
+                    ranges: vec![range("d/b", 29152, 848), range("d/c", 0, 10000)],
+                },
+            ]
+        );
+        let files: Vec<_> = result
+            .files
+            .iter()
+            .map(|f| (f.exists(), f.pieces.clone(), f.good_pieces))
+            .collect();
+        assert_eq!(
+            files,
+            vec![(true, 0..2, 1), (true, 1..4, 2), (false, 3..4, 0)]
+        );
+        assert_eq!(result.files[0].completeness(), 0.5);
+        assert_eq!(result.completeness(), 32768.0 / 60000.0);
+        assert_eq!(result.files[0].path, root.join("d/a"));
+
+        // Renamed files are found with the remapped paths
+        fs::create_dir_all(root.join("moved")).unwrap();
+        fs::rename(root.join("d/b"), root.join("moved/b2")).unwrap();
+        let mut options = VerifyOptions::default();
+        options
+            .paths
+            .insert(vec!["b".to_owned()], PathBuf::from("moved/b2"));
+        let moved = verify_with(&torrent, &root, &options).unwrap();
+        assert_eq!(moved.pieces, result.pieces);
+        assert_eq!(moved.files[1].path, root.join("moved/b2"));
+        assert!(!verify(&torrent, &root).unwrap().files[1].exists());
+        fs::rename(root.join("moved/b2"), root.join("d/b")).unwrap();
+
+        // A truncated file makes its pieces missing
+        fs::write(root.join("d/b"), b"").unwrap();
+        let result = verify(&torrent, &root).unwrap();
+        assert_eq!(result.count(PieceStatus::Missing), 3);
+        assert!(result.files[1].is_short());
+        assert_eq!(result.files[1].size, Some(0));
+        assert_eq!(result.percent_complete(), 0.0);
+
+        for threads in 1..4 {
+            let options = VerifyOptions {
+                threads,
+                read_ahead: 1,
+                sampling: Sampling::All,
+                v2: false,
+                paths: HashMap::new(),
+            };
+            assert_eq!(verify_with(&torrent, &root, &options).unwrap(), result);
+        }
+
+        let options = VerifyOptions {
+            sampling: Sampling::HeadMiddleTail(1),
+            ..VerifyOptions::default()
+        };
+        let result = verify_with(&torrent, &root, &options).unwrap();
+        assert_eq!(result.pieces[2], PieceStatus::Unchecked);
+        assert_eq!(result.count(PieceStatus::Missing), 2);
+        assert!(!result.is_intact());
+
+        let mut torrent = torrent;
+        torrent.info.piece_length = 1 << 60;
+        match verify(&torrent, &root) {
+            Err(Error::InvalidPieceLength(_)) => {}
+            r => panic!("unexpected result {:?}", r.map(|_| ())),
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    pub fn test_verify_v2() {
+        let root = env::temp_dir().join(format!("torrentinfo-verify-v2-{}", ::std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        fs::create_dir_all(root.join("d")).unwrap();
+        for &(name, len) in &[("a", 40000), ("b", 1000), ("c", 0)] {
+            let data: Vec<u8> = (0..len).map(|i| (i * 7 % 251) as u8).collect();
+            fs::write(root.join("d").join(name), data).unwrap();
+        }
+        let build = |version| {
+            TorrentBuilder::new(root.join("d"))
+                .piece_length(16384)
+                .version(version)
+                .build()
+                .unwrap()
+        };
+        let v2 = build(TorrentVersion::V2);
+        let hybrid = build(TorrentVersion::Hybrid);
+        let options = VerifyOptions {
+            v2: true,
+            ..VerifyOptions::default()
+        };
+
+        let result = verify(&v2, &root).unwrap();
+        assert_eq!(result.pieces.len(), 4);
+        assert!(result.is_complete());
+        assert!(result.files.iter().all(FileStatus::is_complete));
+        assert_eq!(verify_with(&hybrid, &root, &options).unwrap(), result);
+
+        let mut data = fs::read(root.join("d/a")).unwrap();
+        let last = data.len() - 1;
+        data[last] ^= 1;
+        fs::write(root.join("d/a"), data).unwrap();
+        fs::remove_file(root.join("d/b")).unwrap();
+
+        let result = verify(&v2, &root).unwrap();
+        assert_eq!(
+            result.pieces,
+            vec![
+                PieceStatus::Good,
+                PieceStatus::Good,
+                PieceStatus::Bad,
+                PieceStatus::Missing
+            ]
+        );
+        assert_eq!(verify_with(&hybrid, &root, &options).unwrap(), result);
+        assert_eq!(verify(&hybrid, &root).unwrap().pieces, result.pieces);
+        let files: Vec<_> = result
+            .files
+            .iter()
+            .map(|f| (f.exists(), f.pieces.clone(), f.good_pieces))
+            .collect();
+        assert_eq!(
+            files,
+            vec![(true, 0..3, 2), (false, 3..4, 0), (true, 0..0, 0)]
+        );
+
+        // A single file is stored under the name of the torrent
+        let single = TorrentBuilder::new(root.join("d/a"))
+            .piece_length(16384)
+            .version(TorrentVersion::V2)
+            .build()
+            .unwrap();
+        let result = verify(&single, root.join("d")).unwrap();
+        assert_eq!(result.files[0].path, root.join("d/a"));
+        assert!(result.is_complete());
+
+        // The piece layers are checked against the pieces root
+        let mut torrent = v2.clone();
+        torrent.piece_layers = None;
+        match verify(&torrent, &root) {
+            Err(Error::InvalidPieceLayer(_)) => {}
+            r => panic!("unexpected result {:?}", r),
+        }
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    /// Serves files from memory
+    struct MemoryReader(HashMap<PathBuf, Vec<u8>>);
+
+    impl PieceReader for MemoryReader {
+        type File = Vec<u8>;
+
+        fn open(&self, path: &Path) -> io::Result<Option<Vec<u8>>> {
+            Ok(self.0.get(path).cloned())
+        }
+
+        fn read_at(&self, file: &mut Vec<u8>, offset: u64, buf: &mut [u8]) -> io::Result<usize> {
+            let data = file.get(offset as usize..).unwrap_or_default();
+            let n = data.len().min(buf.len());
+            buf[..n].copy_from_slice(&data[..n]);
+            Ok(n)
+        }
+
+        fn size(&self, path: &Path) -> io::Result<Option<u64>> {
+            Ok(self.0.get(path).map(|file| file.len() as u64))
+        }
+    }
+
+    #[test]
+    pub fn test_verify_with_reader() {
+        let buf = &b"d4:infod5:filesld6:lengthi3e4:pathl1:aeed6:lengthi2e4:pathl1:beee\
+4:name1:d12:piece lengthi16384e6:pieces20:\x03\xde\x6c\x57\x0b\xfe\x24\xbf\xc3\x28\
+\xcc\xd7\xca\x46\xb7\x6e\xad\xaf\x43\x34ee"[..];
+        let torrent = Torrent::from_buf(buf).unwrap();
+        let mut files = HashMap::new();
+        files.insert(PathBuf::from("/data/d/a"), b"abc".to_vec());
+        files.insert(PathBuf::from("/data/d/b"), b"de".to_vec());
+        let mut reader = MemoryReader(files);
+        let options = VerifyOptions::default();
+
+        let result = verify_with_reader(&torrent, "/data", &options, &reader).unwrap();
+        assert!(result.is_complete());
+        assert_eq!(result.files[1].size, Some(2));
+
+        reader.0.insert(PathBuf::from("/data/d/b"), b"d".to_vec());
+        let result = verify_with_reader(&torrent, "/data", &options, &reader).unwrap();
+        assert_eq!(result.pieces, vec![PieceStatus::Missing]);
+        assert!(result.files[1].is_short());
+    }
+}
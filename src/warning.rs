@@ -0,0 +1,87 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+use std::fmt;
+
+use sanitize::PathIssue;
+
+/// An inconsistency of a torrent which doesn't prevent using it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Warning {
+    /// The number of piece hashes doesn't match the size of the content
+    PieceCountMismatch { expected: usize, actual: usize },
+    /// The length of `pieces` is not a multiple of 20 bytes
+    TruncatedPieces(usize),
+    /// The torrent name is unsafe to use as a file name
+    UnsafeName(PathIssue),
+    /// The path of the file at the index is unsafe to use, see `File::sanitized_path`
+    UnsafePath { file: usize, issue: PathIssue },
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Warning::PieceCountMismatch { expected, actual } => write!(
+                f,
+                "the content needs {} pieces, but there are {} piece hashes",
+                expected, actual
+            ),
+            Warning::TruncatedPieces(len) => write!(
+                f,
+                "the pieces are {} bytes long, which is not a multiple of 20",
+                len
+            ),
+            Warning::UnsafeName(issue) => write!(f, "the name contains {}", issue),
+            Warning::UnsafePath { file, issue } => {
+                write!(f, "the path of file {} contains {}", file, issue)
+            }
+        }
+    }
+}
+
+/// An oddity found while parsing a torrent, see `Torrent::from_buf_with_warnings`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ParseWarning {
+    /// A key which appears more than once in a dict, by its dotted path
+    DuplicateKey(String),
+    /// A key which is not modeled by this crate, by its dotted path
+    UnknownKey(String),
+    /// The creation date is negative or after the year 9999, often a timestamp in
+    /// milliseconds
+    InvalidCreationDate(i64),
+    /// The piece length is not a power of two
+    PieceLengthNotPowerOfTwo(u64),
+    /// A DHT node which is not a `[host, port]` list, by its index, see `Torrent::nodes`
+    InvalidNode(usize),
+}
+
+impl fmt::Display for ParseWarning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ParseWarning::DuplicateKey(ref key) => write!(f, "duplicate key '{}'", key),
+            ParseWarning::UnknownKey(ref key) => write!(f, "unknown key '{}'", key),
+            ParseWarning::InvalidCreationDate(date) => {
+                write!(f, "invalid creation date {}", date)
+            }
+            ParseWarning::PieceLengthNotPowerOfTwo(piece_length) => {
+                write!(f, "the piece length {} is not a power of two", piece_length)
+            }
+            ParseWarning::InvalidNode(index) => write!(f, "invalid DHT node {}", index),
+        }
+    }
+}
@@ -0,0 +1,160 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! JavaScript bindings (`wasm` feature).
+//!
+//! Build for `wasm32-unknown-unknown` without the default features, as `chrono` doesn't
+//! support the target, e.g. with
+//! `wasm-pack build --target web -- --no-default-features --features wasm,encoding`.
+//! The `net`, `mmap`, `async` and `signatures` features are unsupported on the target.
+
+use js_sys::{Array, Error as JsError, JSON};
+use wasm_bindgen::prelude::*;
+
+use error::Error;
+use {decompress, Torrent, MAX_TORRENT_SIZE};
+
+fn js_error(e: Error) -> JsValue {
+    JsError::new(&e.to_string()).into()
+}
+
+/// A parsed torrent, exported to JavaScript as `Torrent`.
+#[wasm_bindgen(js_name = Torrent)]
+pub struct WasmTorrent {
+    torrent: Torrent,
+}
+
+#[wasm_bindgen(js_class = Torrent)]
+impl WasmTorrent {
+    /// Parses a torrent from the bytes of a `Uint8Array`, decompressing gzip and zstd
+    /// torrents with the `gzip` and `zstd` features.
+    #[wasm_bindgen(constructor)]
+    pub fn new(bytes: &[u8]) -> Result<WasmTorrent, JsValue> {
+        decompress(bytes.to_vec(), MAX_TORRENT_SIZE)
+            .and_then(|buf| Torrent::from_buf(&buf))
+            .map(|torrent| WasmTorrent { torrent })
+            .map_err(js_error)
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn name(&self) -> Option<String> {
+        self.torrent.decoded_name().map(|name| name.into_owned())
+    }
+
+    /// The hex v1 info hash.
+    #[wasm_bindgen(getter, js_name = infoHash)]
+    pub fn info_hash(&self) -> Result<String, JsValue> {
+        self.torrent
+            .info_hash()
+            .map(|hash| hash.to_hex())
+            .map_err(js_error)
+    }
+
+    /// The total size in bytes, as a number instead of a `BigInt`.
+    #[wasm_bindgen(getter, js_name = totalSize)]
+    pub fn total_size(&self) -> f64 {
+        self.torrent.total_size() as f64
+    }
+
+    #[wasm_bindgen(getter, js_name = pieceLength)]
+    pub fn piece_length(&self) -> f64 {
+        self.torrent.info().piece_length() as f64
+    }
+
+    #[wasm_bindgen(getter, js_name = numPieces)]
+    pub fn num_pieces(&self) -> usize {
+        self.torrent.num_pieces()
+    }
+
+    #[wasm_bindgen(getter, js_name = numFiles)]
+    pub fn num_files(&self) -> usize {
+        self.torrent.num_files()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn private(&self) -> bool {
+        self.torrent.is_private()
+    }
+
+    #[wasm_bindgen(getter)]
+    pub fn comment(&self) -> Option<String> {
+        self.torrent
+            .decoded_comment()
+            .map(|comment| comment.into_owned())
+    }
+
+    /// The tracker urls of all tiers, as an array of strings.
+    #[wasm_bindgen(getter)]
+    pub fn trackers(&self) -> Array {
+        self.torrent
+            .trackers()
+            .into_iter()
+            .map(JsValue::from_str)
+            .collect()
+    }
+
+    #[wasm_bindgen(js_name = magnetLink)]
+    pub fn magnet_link(&self) -> Result<String, JsValue> {
+        self.torrent.magnet_link().map_err(js_error)
+    }
+
+    /// Returns the torrent as a plain object, see `Torrent::json_view`, which
+    /// `JSON.stringify` uses as well.
+    #[wasm_bindgen(js_name = toJSON)]
+    pub fn to_json(&self) -> Result<JsValue, JsValue> {
+        JSON::parse(&self.torrent.to_json().map_err(js_error)?)
+    }
+}
+
+/// Parses a torrent from the bytes of a `Uint8Array` into a plain object, see
+/// `Torrent::json_view`.
+#[wasm_bindgen(js_name = parseTorrent)]
+pub fn parse_torrent(bytes: &[u8]) -> Result<JsValue, JsValue> {
+    WasmTorrent::new(bytes)?.to_json()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const TORRENT: &[u8] = b"d8:announce9:udp://t/a4:infod6:lengthi5e4:name1:a\
+12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee";
+
+    // Only accessors which don't create JavaScript values run outside of wasm32
+    #[test]
+    pub fn test_torrent() {
+        let torrent = WasmTorrent::new(TORRENT).ok().unwrap();
+        assert_eq!(torrent.name().as_deref(), Some("a"));
+        assert_eq!(
+            torrent.info_hash().ok(),
+            Some(
+                Torrent::from_buf(TORRENT)
+                    .unwrap()
+                    .info_hash()
+                    .unwrap()
+                    .to_hex()
+            )
+        );
+        assert_eq!(torrent.total_size(), 5.0);
+        assert_eq!(torrent.piece_length(), 16384.0);
+        assert_eq!(torrent.num_pieces(), 1);
+        assert_eq!(torrent.num_files(), 1);
+        assert!(!torrent.private());
+        assert_eq!(torrent.comment(), None);
+    }
+}
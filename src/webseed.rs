@@ -0,0 +1,345 @@
+/*
+ * torrentinfo, A torrent file parser
+ * Copyright (C) 2018  Daniel Müller
+ *
+ * This program is free software: you can redistribute it and/or modify
+ * it under the terms of the GNU General Public License as published by
+ * the Free Software Foundation, either version 3 of the License, or
+ * (at your option) any later version.
+ *
+ * This program is distributed in the hope that it will be useful,
+ * but WITHOUT ANY WARRANTY; without even the implied warranty of
+ * MERCHANTABILITY or FITNESS FOR A PARTICULAR PURPOSE.  See the
+ * GNU General Public License for more details.
+ *
+ * You should have received a copy of the GNU General Public License
+ * along with this program.  If not, see <http://www.gnu.org/licenses/>
+ */
+
+//! Availability checks of `url-list` (BEP 19) and `httpseeds` (BEP 17) webseeds (`net`
+//! feature).
+
+use std::fmt;
+
+use ureq;
+
+use error::Result;
+use http;
+use magnet::{percent_encode, percent_encode_bytes};
+use Torrent;
+
+/// The status of a file on a webseed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SeedStatus {
+    /// The file exists with the expected size
+    Ok,
+    /// The file exists, but the server didn't report its size
+    UnknownSize,
+    /// The file exists with a different size
+    SizeMismatch { expected: u64, actual: u64 },
+    /// The server answered with an HTTP error status
+    Status(u16),
+    /// The request failed
+    Failed(String),
+}
+
+impl SeedStatus {
+    /// Returns whether the file is available, which it may be if its size is unknown.
+    pub fn is_ok(&self) -> bool {
+        matches!(*self, SeedStatus::Ok | SeedStatus::UnknownSize)
+    }
+}
+
+impl fmt::Display for SeedStatus {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            SeedStatus::Ok => write!(f, "ok"),
+            SeedStatus::UnknownSize => write!(f, "ok, unknown size"),
+            SeedStatus::SizeMismatch { expected, actual } => {
+                write!(f, "size is {} instead of {}", actual, expected)
+            }
+            SeedStatus::Status(status) => write!(f, "status {}", status),
+            SeedStatus::Failed(ref e) => write!(f, "{}", e),
+        }
+    }
+}
+
+/// The kind of a webseed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WebseedKind {
+    /// A `url-list` entry serving the files (BEP 19)
+    UrlList,
+    /// An `httpseeds` entry serving pieces (BEP 17)
+    HttpSeed,
+}
+
+/// The status of a file or, for `httpseeds`, the first piece on a webseed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FileCheck {
+    /// The path of the file in the torrent, empty for the pieces of `httpseeds`
+    pub path: Vec<String>,
+    pub url: String,
+    pub status: SeedStatus,
+}
+
+/// The result of checking a webseed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WebseedCheck {
+    pub url: String,
+    pub kind: WebseedKind,
+    pub files: Vec<FileCheck>,
+}
+
+impl WebseedCheck {
+    /// Returns whether all checked files are available.
+    pub fn is_ok(&self) -> bool {
+        self.files.iter().all(|file| file.status.is_ok())
+    }
+}
+
+/// Checks all webseeds of the torrent, at most the first `max_files` files of each
+/// `url-list` webseed.
+pub fn check_webseeds(torrent: &Torrent, max_files: usize) -> Result<Vec<WebseedCheck>> {
+    let mut checks: Vec<WebseedCheck> = torrent
+        .url_list()
+        .into_iter()
+        .map(|url| check_url_list(torrent, url, max_files))
+        .collect();
+    for url in torrent.httpseeds().unwrap_or_default() {
+        checks.push(check_httpseed(torrent, url)?);
+    }
+
+    Ok(checks)
+}
+
+/// Checks that the files of the torrent exist with their sizes on a `url-list` webseed,
+/// skipping pad files.
+pub fn check_url_list(torrent: &Torrent, url: &str, max_files: usize) -> WebseedCheck {
+    let name = torrent.info().name().unwrap_or_default().into_owned();
+    let files: Vec<(Vec<String>, u64)> = match torrent.files() {
+        Some(files) => files
+            .iter()
+            .filter(|file| !file.is_padding())
+            .map(|file| {
+                let path = file.path().into_iter().map(|c| c.into_owned()).collect();
+                (path, file.length())
+            })
+            .collect(),
+        None if torrent.num_files() > 1 => torrent
+            .info()
+            .file_tree()
+            .into_iter()
+            .map(|entry| (entry.path().to_vec(), entry.length()))
+            .collect(),
+        None => vec![(vec![], torrent.total_size())],
+    };
+    let multi_file = files.iter().any(|(path, _)| !path.is_empty());
+
+    let files = files
+        .into_iter()
+        .take(max_files)
+        .map(|(path, length)| {
+            let file_url = file_url(url, &name, &path, multi_file);
+            let status = match remote_size(&file_url) {
+                Ok(Some(size)) if size == length => SeedStatus::Ok,
+                Ok(Some(size)) => SeedStatus::SizeMismatch {
+                    expected: length,
+                    actual: size,
+                },
+                Ok(None) => SeedStatus::UnknownSize,
+                Err(status) => status,
+            };
+            FileCheck {
+                path,
+                url: file_url,
+                status,
+            }
+        })
+        .collect();
+
+    WebseedCheck {
+        url: url.to_owned(),
+        kind: WebseedKind::UrlList,
+        files,
+    }
+}
+
+/// Checks that an `httpseeds` webseed serves the first byte of the first piece.
+pub fn check_httpseed(torrent: &Torrent, url: &str) -> Result<WebseedCheck> {
+    let request = format!(
+        "{}{}info_hash={}&piece=0&ranges=0-0",
+        url,
+        if url.contains('?') { '&' } else { '?' },
+        percent_encode_bytes(torrent.info_hash()?.as_bytes())
+    );
+    let status = match http::agent().get(&request).call() {
+        Ok(_) => SeedStatus::Ok,
+        Err(e) => error_status(e),
+    };
+
+    Ok(WebseedCheck {
+        url: url.to_owned(),
+        kind: WebseedKind::HttpSeed,
+        files: vec![FileCheck {
+            path: vec![],
+            url: request,
+            status,
+        }],
+    })
+}
+
+/// Returns the url of a file on a `url-list` webseed.
+///
+/// Urls ending with a slash are directories containing the file or directory of the
+/// torrent, other urls of single-file torrents point to the file itself.
+fn file_url(url: &str, name: &str, path: &[String], multi_file: bool) -> String {
+    if !multi_file && !url.ends_with('/') {
+        return url.to_owned();
+    }
+
+    let mut file_url = url.to_owned();
+    if !file_url.ends_with('/') {
+        file_url.push('/');
+    }
+    file_url.push_str(&percent_encode(name));
+    for component in path {
+        file_url.push('/');
+        file_url.push_str(&percent_encode(component));
+    }
+    file_url
+}
+
+fn error_status(e: ureq::Error) -> SeedStatus {
+    match e {
+        ureq::Error::Status(status, _) => SeedStatus::Status(status),
+        ureq::Error::Transport(e) => SeedStatus::Failed(e.kind().to_string()),
+    }
+}
+
+/// Returns the size of the file at `url` with a HEAD request, falling back to a request
+/// of the first byte for servers which don't support HEAD or don't report the size.
+fn remote_size(url: &str) -> ::std::result::Result<Option<u64>, SeedStatus> {
+    let agent = http::agent();
+    match agent.head(url).call() {
+        Ok(response) => {
+            if let Some(size) = response
+                .header("Content-Length")
+                .and_then(|l| l.parse().ok())
+            {
+                return Ok(Some(size));
+            }
+        }
+        Err(ureq::Error::Status(405, _)) | Err(ureq::Error::Status(501, _)) => {}
+        Err(e) => return Err(error_status(e)),
+    }
+
+    let response = agent
+        .get(url)
+        .set("Range", "bytes=0-0")
+        .call()
+        .map_err(error_status)?;
+    let size = if response.status() == 206 {
+        // `Content-Range: bytes 0-0/size`, the size may be `*`
+        response
+            .header("Content-Range")
+            .and_then(|range| range.rsplit('/').next())
+            .and_then(|size| size.parse().ok())
+    } else {
+        response
+            .header("Content-Length")
+            .and_then(|l| l.parse().ok())
+    };
+
+    Ok(size)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    #[test]
+    pub fn test_file_url() {
+        let path = vec!["dir".to_owned(), "a b.txt".to_owned()];
+        assert_eq!(
+            file_url("http://w/x/", "name", &path, true),
+            "http://w/x/name/dir/a%20b.txt"
+        );
+        assert_eq!(
+            file_url("http://w/x", "name", &path, true),
+            "http://w/x/name/dir/a%20b.txt"
+        );
+        assert_eq!(
+            file_url("http://w/f.iso", "f.iso", &[], false),
+            "http://w/f.iso"
+        );
+        assert_eq!(file_url("http://w/", "f.iso", &[], false), "http://w/f.iso");
+    }
+
+    /// Runs a server answering HEAD requests for `/t/a` with 3 bytes, GET range requests
+    /// for `/t/b` with 5 bytes and everything else with 404.
+    fn server() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                let mut stream = stream.unwrap();
+                let mut request = String::new();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                reader.read_line(&mut request).unwrap();
+                let mut line = String::new();
+                while reader.read_line(&mut line).unwrap() > 2 {
+                    line.clear();
+                }
+                let response = match request.split(' ').take(2).collect::<Vec<_>>()[..] {
+                    ["HEAD", "/t/a"] => "HTTP/1.1 200 OK\r\nContent-Length: 3\r\n\r\n",
+                    ["HEAD", "/t/b"] => {
+                        "HTTP/1.1 405 Method Not Allowed\r\n\
+Content-Length: 0\r\n\r\n"
+                    }
+                    ["GET", "/t/b"] => {
+                        "HTTP/1.1 206 Partial Content\r\nContent-Length: 1\r\n\
+Content-Range: bytes 0-0/5\r\n\r\nx"
+                    }
+                    _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n",
+                };
+                stream.write_all(response.as_bytes()).unwrap();
+            }
+        });
+        format!("http://{}/", addr)
+    }
+
+    #[test]
+    pub fn test_check_url_list() {
+        let url = server();
+        let buf = format!(
+            "d8:url-list{}:{}4:infod5:filesld6:lengthi3e4:pathl1:aeed6:lengthi4e4:pathl1:beed\
+6:lengthi1e4:pathl1:ceee4:name1:t12:piece lengthi16384e6:pieces20:aaaaaaaaaaaaaaaaaaaaee",
+            url.len(),
+            url
+        );
+        let torrent = Torrent::from_buf(buf.as_bytes()).unwrap();
+        let checks = check_webseeds(&torrent, 10).unwrap();
+        assert_eq!(checks.len(), 1);
+        let statuses: Vec<_> = checks[0].files.iter().map(|f| f.status.clone()).collect();
+        assert_eq!(
+            statuses,
+            vec![
+                SeedStatus::Ok,
+                SeedStatus::SizeMismatch {
+                    expected: 4,
+                    actual: 5
+                },
+                SeedStatus::Status(404),
+            ]
+        );
+        assert_eq!(checks[0].files[0].url, format!("{}t/a", url));
+        assert!(!checks[0].is_ok());
+
+        let check = check_url_list(&torrent, &url, 1);
+        assert_eq!(check.files.len(), 1);
+        assert!(check.is_ok());
+    }
+}